@@ -0,0 +1,88 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, LitStr, parse_macro_input, spanned::Spanned};
+
+/// implements `web_render_rs::Vertex` for a `#[repr(C)]` struct with named fields
+///
+/// each field becomes an attribute named after the field, which can be changed with
+/// `#[vertex(name = "a_pos")]`, and integer fields can be normalized with `#[vertex(normalized)]`
+#[proc_macro_derive(Vertex, attributes(vertex))]
+pub fn derive_vertex(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match vertex(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn vertex(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let ident = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(input.generics.span(), "`Vertex` can't be derived for generic structs"));
+    }
+
+    let mut is_repr_c = false;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("repr")) {
+        attr.parse_nested_meta(|meta| {
+            is_repr_c |= meta.path.is_ident("C");
+            Ok(())
+        })?;
+    }
+    if !is_repr_c {
+        return Err(syn::Error::new(Span::call_site(), "`Vertex` can only be derived for `#[repr(C)]` structs"));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new(ident.span(), "`Vertex` can only be derived for structs with named fields")),
+        },
+        _ => return Err(syn::Error::new(ident.span(), "`Vertex` can only be derived for structs")),
+    };
+
+    let mut attributes = Vec::new();
+    let mut field_types = Vec::new();
+    for field in fields {
+        let field_ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let mut name = LitStr::new(&field_ident.to_string(), field_ident.span());
+        let mut normalized = false;
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("vertex")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    name = meta.value()?.parse()?;
+                    Ok(())
+                } else if meta.path.is_ident("normalized") {
+                    normalized = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `name = \"...\"` or `normalized`"))
+                }
+            })?;
+        }
+
+        attributes.push(quote! {
+            ::web_render_rs::AttributeDesc {
+                name: #name,
+                components: <#ty as ::web_render_rs::AttributeFormat>::COMPONENTS,
+                attribute_type: <#ty as ::web_render_rs::AttributeFormat>::TYPE,
+                normalized: #normalized,
+                offset: ::core::mem::offset_of!(#ident, #field_ident),
+            }
+        });
+        field_types.push(ty);
+    }
+
+    Ok(quote! {
+        // SAFETY: the struct is `#[repr(C)]`, the assertion below rules out padding and every field
+        // implements `AttributeFormat`, which is only implemented for plain old data
+        unsafe impl ::web_render_rs::Vertex for #ident {
+            const LAYOUT: &'static [::web_render_rs::AttributeDesc] = &[#(#attributes),*];
+        }
+        const _: () = ::core::assert!(
+            ::core::mem::size_of::<#ident>() == 0 #(+ ::core::mem::size_of::<#field_types>())*,
+            "`Vertex` structs can't contain padding, reorder the fields or add explicit padding fields",
+        );
+    })
+}
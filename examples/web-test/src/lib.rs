@@ -2,7 +2,7 @@ use wasm_bindgen::prelude::*;
 use web_sys::WebGl2RenderingContext;
 extern crate console_error_panic_hook;
 extern crate web_render_rs;
-use web_render_rs::{Renderer, UpdateInfo, RenderInfo};
+use web_render_rs::{Renderer, UpdateInfo, RenderInfo, LoopMode, OwnedBuffer, Color};
 
 #[wasm_bindgen(start)]
 pub fn start() -> Result<(), JsValue> {
@@ -12,24 +12,27 @@ pub fn start() -> Result<(), JsValue> {
     let canvas = document.get_element_by_id("canvas").unwrap();
     let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
 
-    let renderer = Renderer::from_canvas(canvas)?
-        .with_on_resize(|_state, (x, y)| {
-            web_sys::console::log_3(&"canvas size: ".into(), &x.into(), &y.into());
-            // could use to lower resolution:
-            (x/*  / 10 */, y/*  / 10 */)
-        }).unwrap()
-        .with_on_render(on_render).unwrap()
-        .with_on_update(on_update).unwrap()
-        .with_shaders(include_str!("vert_shader.glsl"), include_str!("frag_shader.glsl")).unwrap()
-        .with_on_event("keydown", on_keydown)?
-        .with_on_event("click", on_click)?;
-
     let state = State {
         x: 1.0,
         y: 0.0,
         most_recent_key: String::new(),
     };
-    renderer.start(state, 60, 0.1);
+
+    let renderer = Renderer::builder()
+        .with_on_resize(|_state, _css_size, (x, y)| {
+            web_sys::console::log_3(&"canvas size: ".into(), &x.into(), &y.into());
+            // could use to lower resolution:
+            (x/*  / 10 */, y/*  / 10 */)
+        })
+        .with_on_render(on_render)
+        .with_clear_color(Color::BLACK)
+        .with_on_update(on_update)
+        .with_shaders(include_str!("vert_shader.glsl"), include_str!("frag_shader.glsl"))
+        .with_on_event("keydown", on_keydown)
+        .with_on_event("click", on_click)
+        .build(canvas, state, LoopMode { updates_per_second: 60, max_frame_time: 0.1 })?;
+
+    renderer.run();
     Ok(())
 }
 
@@ -67,7 +70,8 @@ fn on_render(render_info: RenderInfo<State>) {
 
     let vertices: [f32; 9] = [render_info.state.x, render_info.state.y, 0.0, 1.0, -1.0, 0.0, 0.0, 1.0, 0.0];
 
-    let buffer = context.create_buffer().unwrap();
+    // deleted at the end of the frame when it is dropped
+    let buffer = OwnedBuffer::new(context).unwrap();
     context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&buffer));
 
     // Note that `Float32Array::view` is somewhat dangerous (hence the
@@ -91,9 +95,6 @@ fn on_render(render_info: RenderInfo<State>) {
     context.vertex_attrib_pointer_with_i32(0, 3, WebGl2RenderingContext::FLOAT, false, 0, 0);
     context.enable_vertex_attrib_array(0);
 
-    context.clear_color(0.0, 0.0, 0.0, 1.0);
-    context.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
-
     context.draw_arrays(
         WebGl2RenderingContext::TRIANGLES,
         0,
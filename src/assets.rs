@@ -0,0 +1,238 @@
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, ImageBitmap, Response, WebGl2RenderingContext};
+use js_sys::{Array, ArrayBuffer, Uint8Array};
+use std::cell::{Ref, RefCell};
+use std::collections::HashMap;
+use std::future::Future;
+use std::rc::Rc;
+
+use crate::{RendererError, Texture2d, ColorSpace, SamplerOptions};
+#[cfg(feature = "gltf")]
+use crate::GltfModel;
+
+/// something requested from `Assets`, which is shared by everything that requested the same url
+pub struct Asset<T> {
+    url: String,
+    state: RefCell<AssetState<T>>,
+}
+
+enum AssetState<T> {
+    Loading,
+    Loaded(T),
+    Failed(RendererError),
+}
+
+impl<T> Asset<T> {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+    /// the asset if it has finished loading, `None` while it is loading or if it failed to
+    pub fn get(&self) -> Option<Ref<'_, T>> {
+        Ref::filter_map(self.state.borrow(), |state| match state {
+            AssetState::Loaded(asset) => Some(asset),
+            _ => None,
+        }).ok()
+    }
+    pub fn is_loading(&self) -> bool {
+        matches!(*self.state.borrow(), AssetState::Loading)
+    }
+    pub fn is_loaded(&self) -> bool {
+        matches!(*self.state.borrow(), AssetState::Loaded(_))
+    }
+    pub fn is_failed(&self) -> bool {
+        matches!(*self.state.borrow(), AssetState::Failed(_))
+    }
+    /// why the asset failed to load, if it did
+    pub fn error(&self) -> Option<Ref<'_, RendererError>> {
+        Ref::filter_map(self.state.borrow(), |state| match state {
+            AssetState::Failed(error) => Some(error),
+            _ => None,
+        }).ok()
+    }
+}
+
+/// a cache of textures and byte blobs keyed by their url, so that each is only downloaded (and
+/// uploaded to the gpu) once however many times it is requested:
+/// ```ignore
+/// // in `with_state_init`
+/// let mut assets = Assets::new(init_info.context());
+/// state.grass = Some(assets.texture("textures/grass.png"));
+/// state.assets = Some(assets);
+/// ...
+/// // in `on_render`
+/// if let Some(grass) = state.grass.as_ref().and_then(|grass| grass.get()) {
+///     grass.bind(0);
+/// }
+/// ```
+///
+/// the downloads run in the background (with `spawn_local`) and finish between frames
+pub struct Assets {
+    context: WebGl2RenderingContext,
+    textures: HashMap<String, Rc<Asset<Texture2d>>>,
+    blobs: HashMap<String, Rc<Asset<Vec<u8>>>>,
+    #[cfg(feature = "gltf")]
+    models: HashMap<String, Rc<Asset<GltfModel>>>,
+}
+
+impl Assets {
+    pub fn new(context: &WebGl2RenderingContext) -> Self {
+        Assets {
+            context: context.clone(),
+            textures: HashMap::new(),
+            blobs: HashMap::new(),
+            #[cfg(feature = "gltf")]
+            models: HashMap::new(),
+        }
+    }
+
+    /// the sRGB, linearly filtered and mipmapped texture decoded from the image at `url`, see `texture_with`
+    pub fn texture(&mut self, url: &str) -> Rc<Asset<Texture2d>> {
+        self.texture_with(url, ColorSpace::Srgb, &SamplerOptions::default())
+    }
+
+    /// the texture decoded from the image at `url`, which starts downloading the first time it is requested
+    ///
+    /// `color_space` and `sampler` are only used by the first request for `url`
+    pub fn texture_with(&mut self, url: &str, color_space: ColorSpace, sampler: &SamplerOptions) -> Rc<Asset<Texture2d>> {
+        let context = self.context.clone();
+        let sampler = *sampler;
+        let owned_url = url.to_owned();
+        request(&mut self.textures, url, async move {
+            fetch_texture(&context, &owned_url, color_space, &sampler).await
+        })
+    }
+
+    /// the bytes of the file at `url`, which starts downloading the first time it is requested
+    pub fn bytes(&mut self, url: &str) -> Rc<Asset<Vec<u8>>> {
+        let owned_url = url.to_owned();
+        request(&mut self.blobs, url, async move { fetch_bytes(&owned_url).await })
+    }
+
+    /// the gltf (or glb) model at `url` with its buffers and textures, which starts downloading the
+    /// first time it is requested, see `GltfModel::load`
+    #[cfg(feature = "gltf")]
+    pub fn gltf(&mut self, url: &str) -> Rc<Asset<GltfModel>> {
+        let context = self.context.clone();
+        let owned_url = url.to_owned();
+        request(&mut self.models, url, async move { GltfModel::load(&context, &owned_url).await })
+    }
+
+    /// removes the asset at `url` from the cache, returns whether it was cached
+    ///
+    /// the memory (including gpu memory for textures) is freed once the last `Rc` of the asset is dropped
+    pub fn evict(&mut self, url: &str) -> bool {
+        [
+            self.textures.remove(url).is_some(),
+            self.blobs.remove(url).is_some(),
+            #[cfg(feature = "gltf")]
+            self.models.remove(url).is_some(),
+        ].contains(&true)
+    }
+    /// removes every asset from the cache, see `evict`
+    pub fn evict_all(&mut self) {
+        self.textures.clear();
+        self.blobs.clear();
+        #[cfg(feature = "gltf")]
+        self.models.clear();
+    }
+
+    /// the number of cached assets that have finished loading successfully, eg. for a progress bar
+    /// alongside `total_count`
+    pub fn loaded_count(&self) -> usize {
+        [
+            count(&self.textures, Asset::is_loaded),
+            count(&self.blobs, Asset::is_loaded),
+            #[cfg(feature = "gltf")]
+            count(&self.models, Asset::is_loaded),
+        ].iter().sum()
+    }
+    /// the number of cached assets that failed to load
+    pub fn failed_count(&self) -> usize {
+        [
+            count(&self.textures, Asset::is_failed),
+            count(&self.blobs, Asset::is_failed),
+            #[cfg(feature = "gltf")]
+            count(&self.models, Asset::is_failed),
+        ].iter().sum()
+    }
+    /// the number of cached assets, whether or not they have finished loading
+    pub fn total_count(&self) -> usize {
+        [
+            self.textures.len(),
+            self.blobs.len(),
+            #[cfg(feature = "gltf")]
+            self.models.len(),
+        ].iter().sum()
+    }
+}
+
+fn count<T>(cache: &HashMap<String, Rc<Asset<T>>>, f: impl Fn(&Asset<T>) -> bool) -> usize {
+    cache.values().filter(|asset| f(asset)).count()
+}
+
+/// returns the cached asset for `url` or starts loading it with `load` (which is dropped without being
+/// polled if the asset is cached)
+fn request<T: 'static>(cache: &mut HashMap<String, Rc<Asset<T>>>, url: &str, load: impl Future<Output = Result<T, RendererError>> + 'static) -> Rc<Asset<T>> {
+    if let Some(asset) = cache.get(url) {
+        return asset.clone();
+    }
+    let asset = Rc::new(Asset {
+        url: url.to_owned(),
+        state: RefCell::new(AssetState::Loading),
+    });
+    cache.insert(url.to_owned(), asset.clone());
+
+    let loading = asset.clone();
+    wasm_bindgen_futures::spawn_local(async move {
+        let state = match load.await {
+            Ok(loaded) => AssetState::Loaded(loaded),
+            Err(error) => AssetState::Failed(error),
+        };
+        *loading.state.borrow_mut() = state;
+    });
+    asset
+}
+
+/// downloads and decodes the image at `url` into a texture, see `Texture2d::from_image_bitmap`
+pub(crate) async fn fetch_texture(context: &WebGl2RenderingContext, url: &str, color_space: ColorSpace, sampler: &SamplerOptions) -> Result<Texture2d, RendererError> {
+    let blob: Blob = JsFuture::from(fetch(url).await?.blob()?).await?.unchecked_into();
+    texture_from_blob(context, &blob, color_space, sampler).await
+}
+
+/// decodes an encoded image (eg. a png) into a texture, see `Texture2d::from_image_bitmap`
+#[cfg_attr(not(feature = "gltf"), allow(dead_code))]
+pub(crate) async fn texture_from_bytes(context: &WebGl2RenderingContext, bytes: &[u8], color_space: ColorSpace, sampler: &SamplerOptions) -> Result<Texture2d, RendererError> {
+    let blob = Blob::new_with_u8_array_sequence(&Array::of1(&Uint8Array::from(bytes)))?;
+    texture_from_blob(context, &blob, color_space, sampler).await
+}
+
+async fn texture_from_blob(context: &WebGl2RenderingContext, blob: &Blob, color_space: ColorSpace, sampler: &SamplerOptions) -> Result<Texture2d, RendererError> {
+    let window = web_sys::window().ok_or(RendererError::Unsupported("window"))?;
+    let image: ImageBitmap = JsFuture::from(window.create_image_bitmap_with_blob(blob)?).await?.unchecked_into();
+    let texture = Texture2d::from_image_bitmap(context, &image, color_space, sampler);
+    image.close();
+    texture
+}
+
+/// downloads the bytes of the file at `url`
+pub(crate) async fn fetch_bytes(url: &str) -> Result<Vec<u8>, RendererError> {
+    let buffer: ArrayBuffer = JsFuture::from(fetch(url).await?.array_buffer()?).await?.unchecked_into();
+    Ok(Uint8Array::new(&buffer).to_vec())
+}
+
+/// downloads the text of the file at `url`
+pub(crate) async fn fetch_text(url: &str) -> Result<String, RendererError> {
+    let text = JsFuture::from(fetch(url).await?.text()?).await?;
+    text.as_string().ok_or_else(|| RendererError::Parse("text", format!("`{}` isn't text", url)))
+}
+
+/// fetches `url`, erroring if the response isn't ok
+async fn fetch(url: &str) -> Result<Response, RendererError> {
+    let window = web_sys::window().ok_or(RendererError::Unsupported("window"))?;
+    let response: Response = JsFuture::from(window.fetch_with_str(url)).await?.unchecked_into();
+    if !response.ok() {
+        return Err(RendererError::Fetch(url.to_owned(), response.status()));
+    }
+    Ok(response)
+}
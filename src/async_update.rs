@@ -0,0 +1,82 @@
+use std::cell::{OnceCell, RefCell};
+use std::future::Future;
+use std::rc::Rc;
+
+use crate::{Renderer, RendererError};
+
+pub(crate) type OnUpdateAsync<S> = Box<dyn Fn(AsyncUpdateInfo<S>)>;
+
+/// the information given to an async `on_update`, see `Renderer::with_on_update_async`
+///
+/// unlike `UpdateInfo` this doesn't borrow the renderer (the future outlives the update it was started
+/// in), so the state is only accessible through `with_state`, which must not be held across an `.await`
+pub struct AsyncUpdateInfo<S: 'static> {
+    state: Rc<OnceCell<RefCell<S>>>,
+    fixed_time_step: f64,
+    number_of_updates: u32,
+}
+
+impl<S> AsyncUpdateInfo<S> {
+    /// calls `f` with the state
+    ///
+    /// panics if called from within another `with_state`
+    pub fn with_state<R>(&self, f: impl FnOnce(&mut S) -> R) -> R {
+        f(&mut self.state.get().unwrap().borrow_mut())
+    }
+    pub fn fixed_time_step(&self) -> f64 {
+        self.fixed_time_step
+    }
+    /// the number of updates before the one that started this future
+    pub fn number_of_updates(&self) -> u32 {
+        self.number_of_updates
+    }
+}
+
+impl<S> Renderer<S> {
+    /// adds an async `on_update` function whose future is started (with `spawn_local`) every update,
+    /// after `on_update` if both are set
+    ///
+    /// the game loop does not wait for the future so it may complete any number of updates (or
+    /// frames) later and several may be running at once, eg:
+    /// ```ignore
+    /// renderer.with_on_update_async(|update_info: AsyncUpdateInfo<State>| async move {
+    ///     if !update_info.with_state(|state| state.wants_level) { return }
+    ///     let level = fetch_level().await;
+    ///     update_info.with_state(|state| state.level = Some(level));
+    /// })?
+    /// ```
+    ///
+    /// returns self for chaining
+    ///
+    /// errors if the async `on_update` has already been set
+    pub fn with_on_update_async<F, Fut>(self, on_update_async: F) -> Result<Self, RendererError>
+        where F: Fn(AsyncUpdateInfo<S>) -> Fut + 'static,
+              Fut: Future<Output = ()> + 'static,
+    {
+        let on_update_async: OnUpdateAsync<S> = Box::new(move |update_info| wasm_bindgen_futures::spawn_local(on_update_async(update_info)));
+        self.on_update_async.set(on_update_async).map_err(|_| RendererError::AlreadySet("on_update_async"))?;
+        Ok(self)
+    }
+
+    /// always errors with `RendererError::AsyncRender`
+    ///
+    /// a render has to be finished by the end of the frame, but the game loop can't wait for a future
+    /// so the canvas would be presented before it completed (or with half of it drawn). Instead, do the
+    /// async work in `with_on_update_async`, store the result in the state and render it in `on_render`
+    pub fn with_on_render_async<F, Fut>(self, _on_render_async: F) -> Result<Self, RendererError>
+        where F: Fn(AsyncUpdateInfo<S>) -> Fut + 'static,
+              Fut: Future<Output = ()> + 'static,
+    {
+        Err(RendererError::AsyncRender)
+    }
+
+    pub(crate) fn update_async(&self) {
+        if let Some(on_update_async) = self.on_update_async.get() {
+            on_update_async(AsyncUpdateInfo {
+                state: self.state.clone(),
+                fixed_time_step: self.fixed_time_step,
+                number_of_updates: self.number_of_updates,
+            });
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::WebGl2RenderingContext;
+use js_sys::{Array, Object, Reflect};
+use std::collections::HashMap;
+
+use crate::{RendererError, Texture2d, ColorSpace, SamplerOptions};
+
+type Gl = WebGl2RenderingContext;
+
+/// where an image is in a `TextureAtlas`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasRegion {
+    /// `[min_u, min_v, max_u, max_v]` in texture coordinates (with `v` going up like the rows of `Texture2d::from_rgba8`)
+    pub uv_rect: [f32; 4],
+    /// the size of the image in pixels
+    pub pixel_size: (u32, u32),
+}
+
+struct AtlasImage {
+    name: String,
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+/// packs images into a `TextureAtlas`, created with `TextureAtlas::builder`
+///
+/// ```ignore
+/// let atlas = TextureAtlas::builder()
+///     .image("player", 32, 32, &player_pixels)
+///     .image("coin", 16, 16, &coin_pixels)
+///     .padding(2)
+///     .build(context, ColorSpace::Srgb, &SamplerOptions::default())?;
+/// let coin = atlas.region("coin").unwrap();
+/// ```
+pub struct TextureAtlasBuilder {
+    images: Vec<AtlasImage>,
+    padding: u32,
+    extrude: bool,
+}
+
+impl Default for TextureAtlasBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextureAtlasBuilder {
+    pub fn new() -> Self {
+        TextureAtlasBuilder {
+            images: Vec::new(),
+            padding: 1,
+            extrude: true,
+        }
+    }
+
+    /// adds an image of tightly packed rgba bytes (4 per pixel, rows from the bottom up like
+    /// `Texture2d::from_rgba8`), replacing any previous image with the same name
+    ///
+    /// returns self for chaining
+    pub fn image(mut self, name: &str, width: u32, height: u32, data: &[u8]) -> Self {
+        self.images.retain(|image| image.name != name);
+        self.images.push(AtlasImage { name: name.to_owned(), width, height, data: data.to_vec() });
+        self
+    }
+
+    /// the gap in pixels around each image, `1` by default, so that filtering (and mipmapping) at the
+    /// edge of a region doesn't bleed in the neighbouring images
+    ///
+    /// returns self for chaining
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// whether the padding is filled by repeating the edge pixels of each image (rather than left
+    /// transparent), `true` by default, which avoids seams between tiles that are drawn next to each other
+    ///
+    /// returns self for chaining
+    pub fn extrude(mut self, extrude: bool) -> Self {
+        self.extrude = extrude;
+        self
+    }
+
+    /// packs the images onto shelves (tallest first) and uploads the atlas
+    ///
+    /// errors if an image's data is the wrong length, the atlas would be larger than `MAX_TEXTURE_SIZE`
+    /// or the texture could not be created
+    pub fn build(&self, context: &WebGl2RenderingContext, color_space: ColorSpace, sampler: &SamplerOptions) -> Result<TextureAtlas, RendererError> {
+        for image in &self.images {
+            if image.data.len() != image.width as usize * image.height as usize * 4 {
+                return Err(RendererError::Texture(format!(
+                    "atlas image `{}` has {} bytes but is {}x{}", image.name, image.data.len(), image.width, image.height,
+                )));
+            }
+        }
+        let max_size = context.get_parameter(Gl::MAX_TEXTURE_SIZE).ok()
+            .and_then(|value| value.as_f64())
+            .unwrap_or(4096.0) as u32;
+
+        let padding = self.padding;
+        let cell = |image: &AtlasImage| (image.width + padding * 2, image.height + padding * 2);
+        let area: u64 = self.images.iter().map(|image| { let (w, h) = cell(image); w as u64 * h as u64 }).sum();
+        let widest = self.images.iter().map(|image| cell(image).0).max().unwrap_or(1);
+        let width = ((area as f64).sqrt().ceil() as u32).max(widest).next_power_of_two();
+        if width > max_size {
+            return Err(RendererError::Texture(format!("the atlas would be wider than MAX_TEXTURE_SIZE ({})", max_size)));
+        }
+
+        // shelf packing: images are placed left to right along a shelf as tall as its first (tallest)
+        // image, starting a new shelf above when the row is full
+        let mut order: Vec<&AtlasImage> = self.images.iter().collect();
+        order.sort_by(|a, b| b.height.cmp(&a.height).then(b.width.cmp(&a.width)));
+        let mut placements = Vec::with_capacity(order.len());
+        let (mut x, mut shelf_y, mut shelf_height) = (0, 0, 0);
+        for image in order {
+            let (cell_width, cell_height) = cell(image);
+            if x + cell_width > width {
+                shelf_y += shelf_height;
+                (x, shelf_height) = (0, 0);
+            }
+            placements.push((image, x + padding, shelf_y + padding));
+            x += cell_width;
+            shelf_height = shelf_height.max(cell_height);
+        }
+        let height = (shelf_y + shelf_height).max(1);
+        if height > max_size {
+            return Err(RendererError::Texture(format!("the atlas would be taller than MAX_TEXTURE_SIZE ({})", max_size)));
+        }
+
+        let mut pixels = vec![0; width as usize * height as usize * 4];
+        let mut regions = HashMap::with_capacity(placements.len());
+        for (image, x, y) in placements {
+            blit_image(&mut pixels, width, image, x, y, if self.extrude { padding } else { 0 });
+            regions.insert(image.name.clone(), AtlasRegion {
+                uv_rect: [
+                    x as f32 / width as f32,
+                    y as f32 / height as f32,
+                    (x + image.width) as f32 / width as f32,
+                    (y + image.height) as f32 / height as f32,
+                ],
+                pixel_size: (image.width, image.height),
+            });
+        }
+
+        Ok(TextureAtlas {
+            texture: Texture2d::from_rgba8(context, width, height, &pixels, color_space, sampler)?,
+            regions,
+        })
+    }
+}
+
+/// copies `image` into `pixels` (which is `width` pixels wide) at `(x, y)`, repeating its edge pixels
+/// `extrude` pixels outwards
+fn blit_image(pixels: &mut [u8], width: u32, image: &AtlasImage, x: u32, y: u32, extrude: u32) {
+    if image.width == 0 || image.height == 0 {
+        return;
+    }
+    let extrude = extrude as i64;
+    for dst_y in -extrude..image.height as i64 + extrude {
+        let src_y = dst_y.clamp(0, image.height as i64 - 1) as usize;
+        for dst_x in -extrude..image.width as i64 + extrude {
+            let src_x = dst_x.clamp(0, image.width as i64 - 1) as usize;
+            let src = (src_y * image.width as usize + src_x) * 4;
+            let dst = ((y as i64 + dst_y) as usize * width as usize + (x as i64 + dst_x) as usize) * 4;
+            pixels[dst..dst + 4].copy_from_slice(&image.data[src..src + 4]);
+        }
+    }
+}
+
+/// many images packed into one texture so that sprites using different images can be drawn in one
+/// batch, built at runtime with `TextureAtlas::builder` or loaded from a pre-baked atlas with `from_json`
+pub struct TextureAtlas {
+    texture: Texture2d,
+    regions: HashMap<String, AtlasRegion>,
+}
+
+impl TextureAtlas {
+    pub fn builder() -> TextureAtlasBuilder {
+        TextureAtlasBuilder::new()
+    }
+
+    /// reads the regions of a pre-baked atlas from a TexturePacker-style json description, whose
+    /// `frames` is either an object of `name: { frame: { x, y, w, h } }` or an array of
+    /// `{ filename, frame: { x, y, w, h } }`, with `texture` being the atlas image (loaded with
+    /// `Texture2d::from_image_bitmap` so that the frames' top-left origin lines up)
+    ///
+    /// errors if the json can't be parsed, is missing `frames` or has a rotated frame, which isn't supported
+    pub fn from_json(json: &str, texture: Texture2d) -> Result<TextureAtlas, RendererError> {
+        let error = |msg: String| RendererError::Parse("atlas json", msg);
+        let root = js_sys::JSON::parse(json).map_err(|_| error("invalid json".to_owned()))?;
+        let frames = get(&root, "frames").filter(JsValue::is_object).ok_or_else(|| error("missing `frames`".to_owned()))?;
+
+        let entries: Vec<(String, JsValue)> = if Array::is_array(&frames) {
+            Array::from(&frames).iter()
+                .map(|frame| {
+                    let name = get(&frame, "filename").and_then(|name| name.as_string()).ok_or_else(|| error("a frame is missing `filename`".to_owned()))?;
+                    Ok((name, frame))
+                })
+                .collect::<Result<_, RendererError>>()?
+        } else {
+            Object::entries(frames.unchecked_ref()).iter()
+                .map(|entry| {
+                    let entry = Array::from(&entry);
+                    (entry.get(0).as_string().unwrap_or_default(), entry.get(1))
+                })
+                .collect()
+        };
+
+        let (width, height) = (texture.width() as f32, texture.height() as f32);
+        let mut regions = HashMap::with_capacity(entries.len());
+        for (name, frame) in entries {
+            if get(&frame, "rotated").and_then(|rotated| rotated.as_bool()) == Some(true) {
+                return Err(error(format!("frame `{}` is rotated", name)));
+            }
+            let rect = get(&frame, "frame").ok_or_else(|| error(format!("frame `{}` is missing `frame`", name)))?;
+            let number = |key| get(&rect, key).and_then(|value| value.as_f64()).ok_or_else(|| error(format!("frame `{}` is missing `frame.{}`", name, key)));
+            let (x, y, w, h) = (number("x")?, number("y")?, number("w")?, number("h")?);
+            regions.insert(name, AtlasRegion {
+                // the frames have a top-left origin but the texture was flipped
+                uv_rect: [
+                    x as f32 / width,
+                    1.0 - (y + h) as f32 / height,
+                    (x + w) as f32 / width,
+                    1.0 - y as f32 / height,
+                ],
+                pixel_size: (w as u32, h as u32),
+            });
+        }
+        Ok(TextureAtlas { texture, regions })
+    }
+
+    /// downloads a pre-baked atlas, see `from_json`
+    ///
+    /// errors if either download fails or the json is invalid
+    #[cfg(feature = "assets")]
+    pub async fn load(context: &WebGl2RenderingContext, json_url: &str, image_url: &str, color_space: ColorSpace, sampler: &SamplerOptions) -> Result<TextureAtlas, RendererError> {
+        let json = crate::assets::fetch_text(json_url).await?;
+        let texture = crate::assets::fetch_texture(context, image_url, color_space, sampler).await?;
+        Self::from_json(&json, texture)
+    }
+
+    /// where the image `name` is in the atlas
+    pub fn region(&self, name: &str) -> Option<AtlasRegion> {
+        self.regions.get(name).copied()
+    }
+    /// every image in the atlas with its region, in no particular order
+    pub fn regions(&self) -> impl Iterator<Item = (&str, AtlasRegion)> {
+        self.regions.iter().map(|(name, region)| (name.as_str(), *region))
+    }
+    pub fn texture(&self) -> &Texture2d {
+        &self.texture
+    }
+}
+
+/// `object[key]` if it is defined
+pub(crate) fn get(object: &JsValue, key: &str) -> Option<JsValue> {
+    Reflect::get(object, &JsValue::from_str(key)).ok().filter(|value| !value.is_undefined())
+}
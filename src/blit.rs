@@ -0,0 +1,143 @@
+use wasm_bindgen::JsCast;
+use web_sys::{WebGl2RenderingContext, WebGlProgram, WebGlSampler, WebGlUniformLocation, WebGlVertexArrayObject};
+
+use crate::{MagFilter, Rect, RenderInfo, RendererError, Sampler, SamplerOptions, Texture2d, compile_shader, link_program};
+use crate::stats;
+use crate::viewport::get_box;
+
+type Gl = WebGl2RenderingContext;
+
+const BLIT_VERTEX_SHADER: &str = "\
+#version 300 es
+// left, bottom, right and top
+uniform vec4 u_dst;
+uniform vec4 u_src;
+out vec2 v_uv;
+
+void main() {
+    // (0, 0), (1, 0), (0, 1) and (1, 1) as a triangle strip
+    vec2 corner = vec2(gl_VertexID & 1, gl_VertexID >> 1);
+    v_uv = mix(u_src.xy, u_src.zw, corner);
+    gl_Position = vec4(mix(u_dst.xy, u_dst.zw, corner), 0.0, 1.0);
+}
+";
+
+const BLIT_FRAGMENT_SHADER: &str = "\
+#version 300 es
+precision mediump float;
+uniform sampler2D u_texture;
+in vec2 v_uv;
+out vec4 color;
+
+void main() {
+    color = texture(u_texture, v_uv);
+}
+";
+
+/// the program and samplers of `RenderInfo::blit`, created the first time something is blitted
+pub(crate) struct BlitProgram {
+    context: WebGl2RenderingContext,
+    program: WebGlProgram,
+    dst_location: Option<WebGlUniformLocation>,
+    src_location: Option<WebGlUniformLocation>,
+    texture_location: Option<WebGlUniformLocation>,
+    nearest: Sampler,
+    linear: Sampler,
+}
+
+impl BlitProgram {
+    fn new(context: &WebGl2RenderingContext) -> Result<BlitProgram, RendererError> {
+        let vert_shader = compile_shader(context, Gl::VERTEX_SHADER, BLIT_VERTEX_SHADER)?;
+        let frag_shader = compile_shader(context, Gl::FRAGMENT_SHADER, BLIT_FRAGMENT_SHADER)?;
+        let program = link_program(context, &vert_shader, &frag_shader);
+        context.delete_shader(Some(&vert_shader));
+        context.delete_shader(Some(&frag_shader));
+        let program = program?;
+        Ok(BlitProgram {
+            context: context.clone(),
+            dst_location: context.get_uniform_location(&program, "u_dst"),
+            src_location: context.get_uniform_location(&program, "u_src"),
+            texture_location: context.get_uniform_location(&program, "u_texture"),
+            program,
+            nearest: Sampler::new(context, &SamplerOptions::nearest())?,
+            // without mipmaps so that textures without them can be blitted
+            linear: Sampler::new(context, &SamplerOptions::default())?,
+        })
+    }
+}
+
+impl Drop for BlitProgram {
+    fn drop(&mut self) {
+        self.context.delete_program(Some(&self.program));
+    }
+}
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// draws the `src_rect` pixels of `texture` (or all of it) stretched over `dst_rect`, like
+    /// `CanvasRenderingContext2D.drawImage`, eg. `MagFilter::Nearest` for pixel art:
+    /// ```ignore
+    /// render_info.blit(&sheet, Some(Rect::new(16, 0, 16, 16)), Rect::new(100, 100, 64, 64), MagFilter::Nearest)?;
+    /// ```
+    ///
+    /// both rects have a top-left origin, `src_rect` in the texture's pixels and `dst_rect` in the
+    /// current viewport's. The current blend mode is used and the previously bound program and vertex
+    /// array object are rebound afterwards
+    ///
+    /// errors if the blit program could not be created
+    pub fn blit(&mut self, texture: &Texture2d, src_rect: Option<Rect>, dst_rect: Rect, filter: MagFilter) -> Result<(), RendererError> {
+        let context = (*self.renderer.context).clone();
+        if self.renderer.blit_program.is_none() {
+            self.renderer.blit_program = Some(BlitProgram::new(&context)?);
+        }
+        let unit = self.bind_texture(texture);
+        let Some(empty_vertex_array) = self.renderer.empty_vertex_array.get_or_init(|| context.create_vertex_array()) else {
+            return Err(RendererError::Create("vertex array"));
+        };
+        let Some(blit) = &self.renderer.blit_program else { return Ok(()) };
+
+        let [_, _, viewport_width, viewport_height] = get_box(&context, Gl::VIEWPORT).map(|value| value.max(1) as f32);
+        let dst = [
+            dst_rect.x as f32 / viewport_width * 2.0 - 1.0,
+            1.0 - (dst_rect.y + dst_rect.height) as f32 / viewport_height * 2.0,
+            (dst_rect.x + dst_rect.width) as f32 / viewport_width * 2.0 - 1.0,
+            1.0 - dst_rect.y as f32 / viewport_height * 2.0,
+        ];
+        let (width, height) = (texture.width().max(1) as f32, texture.height().max(1) as f32);
+        let src_rect = src_rect.unwrap_or(Rect::new(0, 0, width as i32, height as i32));
+        // textures are bottom up so the top of the rect is at the top of the texture
+        let src = [
+            src_rect.x as f32 / width,
+            1.0 - (src_rect.y + src_rect.height) as f32 / height,
+            (src_rect.x + src_rect.width) as f32 / width,
+            1.0 - src_rect.y as f32 / height,
+        ];
+
+        let previous_program = context.get_parameter(Gl::CURRENT_PROGRAM).ok()
+            .and_then(|value| value.dyn_into::<WebGlProgram>().ok());
+        let previous_vertex_array = context.get_parameter(Gl::VERTEX_ARRAY_BINDING).ok()
+            .and_then(|value| value.dyn_into::<WebGlVertexArrayObject>().ok());
+        context.active_texture(Gl::TEXTURE0 + unit);
+        let previous_sampler = context.get_parameter(Gl::SAMPLER_BINDING).ok()
+            .and_then(|value| value.dyn_into::<WebGlSampler>().ok());
+
+        context.use_program(Some(&blit.program));
+        context.uniform4fv_with_f32_array(blit.dst_location.as_ref(), &dst);
+        context.uniform4fv_with_f32_array(blit.src_location.as_ref(), &src);
+        context.uniform1i(blit.texture_location.as_ref(), unit as i32);
+        match filter {
+            MagFilter::Nearest => blit.nearest.bind(unit),
+            MagFilter::Linear => blit.linear.bind(unit),
+        }
+        context.bind_vertex_array(Some(empty_vertex_array));
+        context.draw_arrays(Gl::TRIANGLE_STRIP, 0, 4);
+        stats::count(|stats| {
+            stats.draw_calls += 1;
+            stats.triangles += 2;
+        });
+
+        context.bind_sampler(unit, previous_sampler.as_ref());
+        context.bind_vertex_array(previous_vertex_array.as_ref());
+        context.use_program(previous_program.as_ref());
+        Ok(())
+    }
+}
@@ -0,0 +1,483 @@
+use web_sys::HtmlCanvasElement;
+
+use crate::{Renderer, RendererError, LogLevel, BuiltinUniforms, PointerEventType, UpdateInfo, RenderInfo, ShaderConfig, PixelRatio, ResizePolicy, OnUpdate, OnResize, OnResizeEntry, OnEvent, OnRawEvent};
+use crate::resize::ResizeCallback;
+use crate::init::OnStateInit;
+use crate::console::log_at;
+use crate::error_boundary::OnError;
+use crate::pointer::OnPointer;
+#[cfg(feature = "clipboard")]
+use crate::clipboard::OnPaste;
+#[cfg(feature = "drop")]
+use crate::drop_image::OnDropImage;
+#[cfg(feature = "futures")]
+use crate::loading::{AssetFuture, OnLoadingProgress, OnLoadingError};
+#[cfg(feature = "futures")]
+use std::future::Future;
+use crate::fullscreen::{FULLSCREEN_VERTEX_SHADER, render_fullscreen};
+
+/// how often `on_update` is called and how much lag the game loop will catch up on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoopMode {
+    pub updates_per_second: u32,
+    /// the maximum time (in seconds) that a single frame is allowed to account for
+    pub max_frame_time: f64,
+}
+
+/// configures a `Renderer` before it is constructed
+///
+/// all configuration is infallible and is only validated in `build`:
+/// ```ignore
+/// let renderer = Renderer::builder()
+///     .with_shaders(vert_shader, frag_shader)
+///     .with_on_update(on_update)
+///     .with_on_render(on_render)
+///     .with_on_event("keydown", on_keydown)
+///     .build(canvas, state, LoopMode { updates_per_second: 60, max_frame_time: 0.1 })?;
+/// renderer.run();
+/// ```
+pub struct RendererBuilder<S>
+    where S: 'static
+{
+    /// the first misconfiguration found by a setter, returned by `build`
+    error: Option<RendererError>,
+    shaders: Option<(String, String, ShaderConfig)>,
+    named_shaders: Vec<(String, String, String)>,
+    builtin_uniforms: Option<BuiltinUniforms>,
+    clear_color: Option<[f32; 4]>,
+    clear_depth: Option<f32>,
+    clear_stencil: Option<i32>,
+    #[cfg(feature = "log")]
+    log_to_console: bool,
+    log_level: LogLevel,
+
+    on_state_init: Option<OnStateInit<S>>,
+    on_update: Option<fn(UpdateInfo<S>)>,
+    on_render: Option<fn(RenderInfo<S>)>,
+    on_error: Option<OnError>,
+    on_unload: Option<fn(&mut S)>,
+    snapshot_state: Option<fn(&S) -> S>,
+    update_systems: Vec<(&'static str, i32, OnUpdate<S>)>,
+    on_resize: Option<ResizeCallback<S>>,
+    on_resize_during_update: bool,
+    pixel_ratio: PixelRatio,
+    resolution_scale: f64,
+    resize_policy: ResizePolicy,
+
+    event_listeners: Vec<(&'static str, Listener<S>)>,
+
+    #[cfg(feature = "futures")]
+    assets: Vec<AssetFuture<S>>,
+    #[cfg(feature = "futures")]
+    on_loading_progress: Option<OnLoadingProgress<S>>,
+    #[cfg(feature = "futures")]
+    on_loading_error: Option<OnLoadingError<S>>,
+    #[cfg(feature = "futures")]
+    loading_timeout: Option<f64>,
+}
+
+enum Listener<S> {
+    Event(OnEvent<S>, i32),
+    Passive(OnEvent<S>),
+    Raw(OnRawEvent<S>),
+    Pointer(PointerEventType, OnPointer<S>),
+    #[cfg(feature = "clipboard")]
+    Paste(OnPaste<S>),
+    #[cfg(feature = "drop")]
+    DropImage(OnDropImage<S>),
+}
+
+impl<S> Default for RendererBuilder<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> RendererBuilder<S> {
+    pub fn new() -> Self {
+        RendererBuilder {
+            error: None,
+            shaders: None,
+            named_shaders: Vec::new(),
+            builtin_uniforms: None,
+            clear_color: None,
+            clear_depth: None,
+            clear_stencil: None,
+            #[cfg(feature = "log")]
+            log_to_console: false,
+            log_level: LogLevel::default(),
+
+            on_state_init: None,
+            on_update: None,
+            on_render: None,
+            on_error: None,
+            on_unload: None,
+            snapshot_state: None,
+            update_systems: Vec::new(),
+            on_resize: None,
+            on_resize_during_update: false,
+            pixel_ratio: PixelRatio::default(),
+            resolution_scale: 1.0,
+            resize_policy: ResizePolicy::Stretch,
+
+            event_listeners: Vec::new(),
+
+            #[cfg(feature = "futures")]
+            assets: Vec::new(),
+            #[cfg(feature = "futures")]
+            on_loading_progress: None,
+            #[cfg(feature = "futures")]
+            on_loading_error: None,
+            #[cfg(feature = "futures")]
+            loading_timeout: None,
+        }
+    }
+
+    /// sets the shaders that will be linked to a program and attached to the context in `build`
+    ///
+    /// replaces any previously set shaders
+    pub fn with_shaders(self, vert_shader: &str, frag_shader: &str) -> Self {
+        self.with_shaders_configured(vert_shader, frag_shader, ShaderConfig::default())
+    }
+
+    /// like `with_shaders` but preprocesses both shaders according to `config`, see `Renderer::with_shaders_configured`
+    ///
+    /// replaces any previously set shaders
+    pub fn with_shaders_configured(mut self, vert_shader: &str, frag_shader: &str, config: ShaderConfig) -> Self {
+        self.shaders = Some((vert_shader.to_owned(), frag_shader.to_owned(), config));
+        self
+    }
+
+    /// sets a fragment shader to be drawn over the whole canvas every frame, with `FULLSCREEN_VERTEX_SHADER`
+    /// as its vertex shader and the built-in uniforms (`u_time`, `u_resolution` and so on unless other
+    /// names are set with `with_builtin_uniforms`), for quick demos:
+    /// ```ignore
+    /// Renderer::builder()
+    ///     .with_fragment_shader(frag_shader)
+    ///     .build(canvas, (), LoopMode { updates_per_second: 60, max_frame_time: 0.1 })?
+    ///     .run();
+    /// ```
+    /// the fragment shader can read `in vec2 v_uv`, an `on_update` can still be added for interactive
+    /// uniforms and an `on_render` added afterwards replaces the one that draws the fullscreen triangle
+    ///
+    /// replaces any previously set shaders, errors from compiling the shader are returned by `build`, as
+    /// is `RendererError::AlreadySet("on_render")` if an `on_render` has already been set
+    pub fn with_fragment_shader(mut self, frag_shader: &str) -> Self {
+        self.builtin_uniforms.get_or_insert_with(BuiltinUniforms::default);
+        if self.on_render.is_some() {
+            self.error.get_or_insert(RendererError::AlreadySet("on_render"));
+        }
+        self.on_render = Some(render_fullscreen);
+        self.with_shaders(FULLSCREEN_VERTEX_SHADER, frag_shader)
+    }
+
+    /// adds shaders that will be linked to a program that can be switched to by name, see `Renderer::with_named_shader`
+    pub fn with_named_shader(mut self, name: &str, vert_shader: &str, frag_shader: &str) -> Self {
+        self.named_shaders.push((name.to_owned(), vert_shader.to_owned(), frag_shader.to_owned()));
+        self
+    }
+
+    /// sets the named built-in uniforms before each `on_render`, see `Renderer::with_builtin_uniforms`
+    ///
+    /// replaces any previously set names
+    pub fn with_builtin_uniforms(mut self, names: BuiltinUniforms) -> Self {
+        self.builtin_uniforms = Some(names);
+        self
+    }
+
+    /// clears the color buffer to `color` before each `on_render`, see `Renderer::with_clear_color`
+    pub fn with_clear_color(mut self, color: impl Into<[f32; 4]>) -> Self {
+        self.clear_color = Some(color.into());
+        self
+    }
+
+    /// clears the depth buffer to `depth` before each `on_render`, see `Renderer::with_clear_depth`
+    pub fn with_clear_depth(mut self, depth: f32) -> Self {
+        self.clear_depth = Some(depth);
+        self
+    }
+
+    /// clears the stencil buffer to `stencil` before each `on_render`, see `Renderer::with_clear_stencil`
+    pub fn with_clear_stencil(mut self, stencil: i32) -> Self {
+        self.clear_stencil = Some(stencil);
+        self
+    }
+
+    /// sets `ConsoleLogger` as the global logger when the game loop starts, see `Renderer::with_log_to_console`
+    #[cfg(feature = "log")]
+    pub fn with_log_to_console(mut self) -> Self {
+        self.log_to_console = true;
+        self
+    }
+
+    /// sets how much the renderer logs to the browser console, see `Renderer::with_log_level`
+    pub fn with_log_level(mut self, log_level: LogLevel) -> Self {
+        self.log_level = log_level;
+        self
+    }
+
+    /// sets a function that is called once with the state when the game loop is started, see `Renderer::with_state_init`
+    ///
+    /// replaces any previously set state init function
+    pub fn with_state_init(mut self, on_state_init: OnStateInit<S>) -> Self {
+        self.on_state_init = Some(on_state_init);
+        self
+    }
+
+    /// sets an `on_update` function that is called `updates_per_second` times per second
+    ///
+    /// replaces any previously set `on_update`
+    pub fn with_on_update(mut self, on_update: fn(UpdateInfo<S>)) -> Self {
+        self.on_update = Some(on_update);
+        self
+    }
+
+    /// sets an `on_render` function that is called as often as is allowed by the web page
+    ///
+    /// replaces any previously set `on_render`
+    pub fn with_on_render(mut self, on_render: fn(RenderInfo<S>)) -> Self {
+        self.on_render = Some(on_render);
+        self
+    }
+
+    /// sets an error boundary around each frame's callbacks, see `Renderer::with_on_error`
+    ///
+    /// replaces any previously set `on_error`
+    pub fn with_on_error(mut self, on_error: OnError) -> Self {
+        self.on_error = Some(on_error);
+        self
+    }
+
+    /// calls `on_unload` with the state when the page unloads, see `Renderer::with_on_unload`
+    ///
+    /// replaces any previously set `on_unload`
+    pub fn with_on_unload(mut self, on_unload: fn(&mut S)) -> Self {
+        self.on_unload = Some(on_unload);
+        self
+    }
+
+    /// keeps a clone of the state from before the latest update, see `Renderer::with_sub_tick_state`
+    pub fn with_sub_tick_state(mut self) -> Self where S: Clone {
+        self.snapshot_state = Some(S::clone);
+        self
+    }
+
+    /// adds an update system, see `Renderer::with_update_system`
+    pub fn with_update_system(mut self, name: &'static str, priority: i32, system: fn(UpdateInfo<S>)) -> Self {
+        self.update_systems.push((name, priority, system));
+        self
+    }
+
+    /// sets an 'on_resize' event listener (that also optionally mutates the size), see `Renderer::with_on_resize`
+    ///
+    /// replaces any previously set `on_resize` or `on_resize_entry`
+    pub fn with_on_resize(mut self, on_resize: OnResize<S>) -> Self {
+        self.on_resize = Some(ResizeCallback::Size(on_resize));
+        self
+    }
+
+    /// sets an 'on_resize' event listener that receives the size reported by the canvas's `ResizeObserver`,
+    /// see `Renderer::with_on_resize_entry`
+    ///
+    /// replaces any previously set `on_resize` or `on_resize_entry`
+    pub fn with_on_resize_entry(mut self, on_resize_entry: OnResizeEntry<S>) -> Self {
+        self.on_resize = Some(ResizeCallback::Entry(on_resize_entry));
+        self
+    }
+
+    /// sets whether the canvas's size is checked before each update tick, see `Renderer::with_on_resize_during_update`
+    pub fn with_on_resize_during_update(mut self, on_resize_during_update: bool) -> Self {
+        self.on_resize_during_update = on_resize_during_update;
+        self
+    }
+
+    /// sets how many drawing-buffer pixels are used per css pixel (`PixelRatio::Auto` by default)
+    pub fn with_pixel_ratio(mut self, pixel_ratio: PixelRatio) -> Self {
+        self.pixel_ratio = pixel_ratio;
+        self
+    }
+
+    /// sets what the size of the drawing buffer is multiplied by (`1.0` by default), see `Renderer::with_resolution_scale`
+    pub fn with_resolution_scale(mut self, scale: f64) -> Self {
+        self.resolution_scale = scale;
+        self
+    }
+
+    /// sets how the viewport fills the drawing buffer (`ResizePolicy::Stretch` by default), see `Renderer::with_resize_policy`
+    pub fn with_resize_policy(mut self, resize_policy: ResizePolicy) -> Self {
+        self.resize_policy = resize_policy;
+        self
+    }
+
+    /// adds a custom event listener, see `Renderer::with_on_event`
+    pub fn with_on_event(self, event_type: &'static str, on_event: OnEvent<S>) -> Self {
+        self.with_on_event_with_priority(event_type, 0, on_event)
+    }
+
+    /// adds a custom event listener with a priority, see `Renderer::with_on_event_with_priority`
+    pub fn with_on_event_with_priority(mut self, event_type: &'static str, priority: i32, on_event: OnEvent<S>) -> Self {
+        self.event_listeners.push((event_type, Listener::Event(on_event, priority)));
+        self
+    }
+
+    /// adds a passive custom event listener, see `Renderer::with_on_passive_event`
+    pub fn with_on_passive_event(mut self, event_type: &'static str, on_event: OnEvent<S>) -> Self {
+        self.event_listeners.push((event_type, Listener::Passive(on_event)));
+        self
+    }
+
+    /// adds a custom event listener that receives the raw value, see `Renderer::with_on_raw_event`
+    pub fn with_on_raw_event(mut self, event_type: &'static str, on_raw_event: OnRawEvent<S>) -> Self {
+        self.event_listeners.push((event_type, Listener::Raw(on_raw_event)));
+        self
+    }
+
+    /// adds an asset that is loaded before the game loop starts updating, see `Renderer::with_asset`
+    #[cfg(feature = "futures")]
+    pub fn with_asset<F, Fut>(mut self, asset: Fut) -> Self
+        where F: FnOnce(&mut S) + 'static,
+              Fut: Future<Output = Result<F, RendererError>> + 'static,
+    {
+        self.assets.push(Box::pin(async move {
+            asset.await.map(|store| Box::new(store) as Box<dyn FnOnce(&mut S)>)
+        }));
+        self
+    }
+
+    /// sets a function that is called every frame while assets are loading, see `Renderer::with_on_loading_progress`
+    ///
+    /// replaces any previously set loading progress function
+    #[cfg(feature = "futures")]
+    pub fn with_on_loading_progress(mut self, on_loading_progress: OnLoadingProgress<S>) -> Self {
+        self.on_loading_progress = Some(on_loading_progress);
+        self
+    }
+
+    /// sets a function that is called when an asset fails to load, see `Renderer::with_on_loading_error`
+    ///
+    /// replaces any previously set loading error function
+    #[cfg(feature = "futures")]
+    pub fn with_on_loading_error(mut self, on_loading_error: OnLoadingError<S>) -> Self {
+        self.on_loading_error = Some(on_loading_error);
+        self
+    }
+
+    /// stops waiting for assets after `seconds`, see `Renderer::with_loading_timeout`
+    #[cfg(feature = "futures")]
+    pub fn with_loading_timeout(mut self, seconds: f64) -> Self {
+        self.loading_timeout = Some(seconds);
+        self
+    }
+
+    /// adds a pointer event listener, see `Renderer::with_on_pointer`
+    pub fn with_on_pointer(mut self, event_type: PointerEventType, on_pointer: OnPointer<S>) -> Self {
+        self.event_listeners.push((event_type.event_type(), Listener::Pointer(event_type, on_pointer)));
+        self
+    }
+
+    /// adds a `paste` event listener that receives the pasted text, see `Renderer::with_on_paste`
+    #[cfg(feature = "clipboard")]
+    pub fn with_on_paste(mut self, on_paste: OnPaste<S>) -> Self {
+        self.event_listeners.push(("paste", Listener::Paste(on_paste)));
+        self
+    }
+
+    /// loads image files dropped on the canvas into textures, see `Renderer::with_on_drop_image`
+    #[cfg(feature = "drop")]
+    pub fn with_on_drop_image(mut self, on_drop_image: OnDropImage<S>) -> Self {
+        self.event_listeners.push(("drop", Listener::DropImage(on_drop_image)));
+        self
+    }
+
+    /// constructs a renderer from the canvas, compiling shaders and adding event listeners
+    ///
+    /// call `run` on the result to start the game loop
+    pub fn build(self, canvas: HtmlCanvasElement, state: S, loop_mode: LoopMode) -> Result<Renderer<S>, RendererError> {
+        self.build_with_optional_canvas(Some(canvas), state, loop_mode)
+    }
+
+    /// like `build` but creates a headless renderer if `canvas` is `None`, see `Renderer::new_with_optional_canvas`
+    ///
+    /// errors if `canvas` is `None` and event listeners have been added or a setter was misused (eg.
+    /// `with_fragment_shader` after `with_on_render`)
+    pub fn build_with_optional_canvas(self, canvas: Option<HtmlCanvasElement>, state: S, loop_mode: LoopMode) -> Result<Renderer<S>, RendererError> {
+        if let Some(error) = self.error {
+            log_at(self.log_level, LogLevel::Errors, || format!("creating the renderer: {}", error));
+            return Err(error);
+        }
+        let mut renderer = Renderer::create_with_optional_canvas(canvas)
+            .inspect_err(|err| log_at(self.log_level, LogLevel::Errors, || format!("creating the renderer: {}", err)))?
+            .with_log_level(self.log_level);
+
+        if let Some((vert_shader, frag_shader, config)) = self.shaders {
+            renderer = renderer.with_shaders_configured(&vert_shader, &frag_shader, config)?;
+        }
+        for (name, vert_shader, frag_shader) in self.named_shaders {
+            renderer = renderer.with_named_shader(&name, &vert_shader, &frag_shader)?;
+        }
+        if let Some(names) = self.builtin_uniforms {
+            renderer = renderer.with_builtin_uniforms(names);
+        }
+        renderer.clear_color = self.clear_color;
+        renderer.clear_depth = self.clear_depth;
+        renderer.clear_stencil = self.clear_stencil;
+        #[cfg(feature = "log")]
+        if self.log_to_console {
+            renderer = renderer.with_log_to_console();
+        }
+        if let Some(on_state_init) = self.on_state_init {
+            renderer = renderer.with_state_init(on_state_init);
+        }
+        if let Some(on_update) = self.on_update {
+            renderer = renderer.with_on_update(on_update)?;
+        }
+        if let Some(on_render) = self.on_render {
+            renderer = renderer.with_on_render(on_render)?;
+        }
+        if let Some(on_error) = self.on_error {
+            renderer = renderer.with_on_error(on_error)?;
+        }
+        if let Some(on_unload) = self.on_unload {
+            renderer = renderer.with_on_unload(on_unload)?;
+        }
+        renderer.snapshot_state = self.snapshot_state;
+        for (name, priority, system) in self.update_systems {
+            renderer = renderer.with_update_system(name, priority, system);
+        }
+        match self.on_resize {
+            Some(ResizeCallback::Size(on_resize)) => renderer = renderer.with_on_resize(on_resize)?,
+            Some(ResizeCallback::Entry(on_resize_entry)) => renderer = renderer.with_on_resize_entry(on_resize_entry)?,
+            None => {}
+        }
+        renderer = renderer
+            .with_on_resize_during_update(self.on_resize_during_update)
+            .with_pixel_ratio(self.pixel_ratio)
+            .with_resolution_scale(self.resolution_scale)
+            .with_resize_policy(self.resize_policy);
+        for (event_type, listener) in self.event_listeners {
+            renderer = match listener {
+                Listener::Event(on_event, priority) => renderer.with_on_event_with_priority(event_type, priority, on_event)?,
+                Listener::Passive(on_event) => renderer.with_on_passive_event(event_type, on_event)?,
+                Listener::Raw(on_raw_event) => renderer.with_on_raw_event(event_type, on_raw_event)?,
+                Listener::Pointer(pointer_event_type, on_pointer) => renderer.with_on_pointer(pointer_event_type, on_pointer)?,
+                #[cfg(feature = "clipboard")]
+                Listener::Paste(on_paste) => renderer.with_on_paste(on_paste)?,
+                #[cfg(feature = "drop")]
+                Listener::DropImage(on_drop_image) => renderer.with_on_drop_image(on_drop_image)?,
+            };
+        }
+
+        #[cfg(feature = "futures")]
+        {
+            for asset in self.assets {
+                renderer.loading.push(asset);
+            }
+            renderer.loading.on_progress = self.on_loading_progress;
+            renderer.loading.on_error = self.on_loading_error;
+            renderer.loading.timeout = self.loading_timeout;
+        }
+
+        renderer.set_state(state, loop_mode);
+        Ok(renderer)
+    }
+}
@@ -0,0 +1,206 @@
+use wasm_bindgen::{JsCast, closure::Closure};
+use web_sys::{HtmlCanvasElement, MouseEvent, WebGl2RenderingContext, WebGlProgram, WebGlUniformLocation};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::{Renderer, active_uniforms};
+
+type Gl = WebGl2RenderingContext;
+type MouseCallback = Closure<dyn Fn(MouseEvent)>;
+
+/// the names of the uniforms set by `Renderer::with_builtin_uniforms`, a uniform is skipped if the
+/// program in use doesn't have an active uniform with its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuiltinUniforms {
+    /// `float`: the running time in seconds
+    pub time: &'static str,
+    /// `float`: the time in seconds that the frame accounted for
+    pub delta: &'static str,
+    /// `vec2` (or `vec3` with a z of 1): the drawing-buffer size in pixels
+    pub resolution: &'static str,
+    /// `vec2`: the mouse position in drawing-buffer pixels (with a bottom-left origin like `gl_FragCoord`),
+    /// `vec3`: the position and the pressed buttons as a bitmask (see `MouseEvent.buttons`) or
+    /// `vec4`: the position and the position of the last click, negated while no button is pressed (like shadertoy)
+    pub mouse: &'static str,
+    /// `int` (or `float`): the number of frames rendered before this one
+    pub frame: &'static str,
+}
+
+impl Default for BuiltinUniforms {
+    /// `u_time`, `u_delta`, `u_resolution`, `u_mouse` and `u_frame`
+    fn default() -> Self {
+        BuiltinUniforms {
+            time: "u_time",
+            delta: "u_delta",
+            resolution: "u_resolution",
+            mouse: "u_mouse",
+            frame: "u_frame",
+        }
+    }
+}
+
+impl BuiltinUniforms {
+    /// the names used by shadertoy: `iTime`, `iTimeDelta`, `iResolution`, `iMouse` and `iFrame`
+    pub fn shadertoy() -> Self {
+        BuiltinUniforms {
+            time: "iTime",
+            delta: "iTimeDelta",
+            resolution: "iResolution",
+            mouse: "iMouse",
+            frame: "iFrame",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Builtin {
+    Time,
+    Delta,
+    Resolution,
+    Mouse,
+    Frame,
+}
+
+/// the mouse position and buttons in drawing-buffer pixels with a bottom-left origin
+#[derive(Debug, Clone, Copy, Default)]
+struct Mouse {
+    x: f32,
+    y: f32,
+    click_x: f32,
+    click_y: f32,
+    buttons: u16,
+}
+
+/// the state of `Renderer::with_builtin_uniforms`
+pub(crate) struct Builtins {
+    names: BuiltinUniforms,
+    mouse: Rc<Cell<Mouse>>,
+    canvas: Option<Rc<HtmlCanvasElement>>,
+    /// removed from `canvas` when dropped
+    mouse_listeners: Vec<(&'static str, MouseCallback)>,
+    /// the active builtins of each program (by the name of the shader, `None` for the one linked by
+    /// `with_shaders`) with their location and type
+    locations: HashMap<Option<String>, Vec<(Builtin, WebGlUniformLocation, u32)>>,
+}
+
+impl Drop for Builtins {
+    fn drop(&mut self) {
+        if let Some(canvas) = &self.canvas {
+            for (event_type, closure) in &self.mouse_listeners {
+                let _ = canvas.remove_event_listener_with_callback(event_type, closure.as_ref().unchecked_ref());
+            }
+        }
+    }
+}
+
+impl Builtins {
+    /// forgets the uniforms found in the programs, eg. after they are replaced
+    pub(crate) fn clear_locations(&mut self) {
+        self.locations.clear();
+    }
+}
+
+impl<S> Renderer<S> {
+    /// sets the uniforms named in `names` (eg. `BuiltinUniforms::default()` for `u_time`, `u_resolution`
+    /// and so on, or `BuiltinUniforms::shadertoy()`) on the program in use before each `on_render`, for
+    /// programs linked by `with_shaders` or `with_named_shader`. Uniforms that the program doesn't have
+    /// are skipped, so only the ones the shader needs have to be declared:
+    /// ```ignore
+    /// uniform float u_time;
+    /// uniform vec2 u_resolution;
+    /// ...
+    /// vec2 uv = gl_FragCoord.xy / u_resolution;
+    /// ```
+    ///
+    /// the mouse is tracked with listeners on the canvas so stays at `(0, 0)` for headless renderers
+    ///
+    /// returns self for chaining
+    pub fn with_builtin_uniforms(mut self, names: BuiltinUniforms) -> Self {
+        let mouse = Rc::new(Cell::new(Mouse::default()));
+        let mut mouse_listeners = Vec::new();
+        if let Some(canvas) = &self.canvas {
+            for event_type in ["mousemove", "mousedown", "mouseup"] {
+                let rc_canvas = canvas.clone();
+                let rc_mouse = mouse.clone();
+                let closure = MouseCallback::new(move |event: MouseEvent| {
+                    // from css pixels with a top-left origin
+                    let scale_x = rc_canvas.width() as f32 / rc_canvas.client_width().max(1) as f32;
+                    let scale_y = rc_canvas.height() as f32 / rc_canvas.client_height().max(1) as f32;
+                    let x = event.offset_x() as f32 * scale_x;
+                    let y = rc_canvas.height() as f32 - event.offset_y() as f32 * scale_y;
+
+                    let mut mouse = rc_mouse.get();
+                    mouse.x = x;
+                    mouse.y = y;
+                    mouse.buttons = event.buttons();
+                    if event.type_() == "mousedown" {
+                        mouse.click_x = x;
+                        mouse.click_y = y;
+                    }
+                    rc_mouse.set(mouse);
+                });
+                if canvas.add_event_listener_with_callback(event_type, closure.as_ref().unchecked_ref()).is_ok() {
+                    mouse_listeners.push((event_type, closure));
+                }
+            }
+        }
+
+        self.builtin_uniforms = Some(Builtins {
+            names,
+            mouse,
+            canvas: self.canvas.clone(),
+            mouse_listeners,
+            locations: HashMap::new(),
+        });
+        self
+    }
+
+    pub(crate) fn set_builtin_uniforms(&mut self) {
+        let Some(builtins) = &mut self.builtin_uniforms else { return };
+        let program = match &self.current_shader {
+            Some(name) => self.named_shaders.get(name).map(|shader| &shader.program),
+            None => self.program.as_ref(),
+        };
+        let Some(program) = program else { return };
+        let context = &self.context;
+        let locations = builtins.locations
+            .entry(self.current_shader.clone())
+            .or_insert_with(|| find_builtins(context, program, &builtins.names));
+
+        let (width, height) = (context.drawing_buffer_width() as f32, context.drawing_buffer_height() as f32);
+        let mouse = builtins.mouse.get();
+        for (builtin, location, gl_type) in locations.iter() {
+            let location = Some(location);
+            match (builtin, *gl_type) {
+                (Builtin::Time, _) => context.uniform1f(location, self.running_time as f32),
+                (Builtin::Delta, _) => context.uniform1f(location, self.frame_time as f32),
+                (Builtin::Frame, Gl::INT) => context.uniform1i(location, self.number_of_renders as i32),
+                (Builtin::Frame, _) => context.uniform1f(location, self.number_of_renders as f32),
+                (Builtin::Resolution, Gl::FLOAT_VEC3) => context.uniform3f(location, width, height, 1.0),
+                (Builtin::Resolution, _) => context.uniform2f(location, width, height),
+                (Builtin::Mouse, Gl::FLOAT_VEC3) => context.uniform3f(location, mouse.x, mouse.y, mouse.buttons as f32),
+                (Builtin::Mouse, Gl::FLOAT_VEC4) => {
+                    let sign = if mouse.buttons != 0 { 1.0 } else { -1.0 };
+                    context.uniform4f(location, mouse.x, mouse.y, mouse.click_x * sign, mouse.click_y * sign)
+                }
+                (Builtin::Mouse, _) => context.uniform2f(location, mouse.x, mouse.y),
+            }
+        }
+    }
+}
+
+/// the builtins that are active in `program`, with their location and type
+fn find_builtins(context: &WebGl2RenderingContext, program: &WebGlProgram, names: &BuiltinUniforms) -> Vec<(Builtin, WebGlUniformLocation, u32)> {
+    let builtins = [
+        (Builtin::Time, names.time),
+        (Builtin::Delta, names.delta),
+        (Builtin::Resolution, names.resolution),
+        (Builtin::Mouse, names.mouse),
+        (Builtin::Frame, names.frame),
+    ];
+    active_uniforms(context, program).into_iter().filter_map(|info| {
+        let &(builtin, _) = builtins.iter().find(|(_, name)| *name == info.name)?;
+        Some((builtin, info.location?, info.gl_type))
+    }).collect()
+}
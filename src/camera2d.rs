@@ -0,0 +1,124 @@
+use web_sys::{WebGl2RenderingContext, WebGlUniformLocation};
+
+use crate::{Uniform, UniformValue, RenderInfo};
+
+/// a 2d camera looking at `position` with `y` going up, whose `view_proj` is uploaded like any other
+/// matrix and passed to `SpriteBatch::draw`, `TileMap::draw_with_camera` or `Draw2d::set_transform`:
+/// ```ignore
+/// // in `on_update`, keeping the previous camera to interpolate from
+/// state.previous_camera = state.camera;
+/// state.camera.position = state.player_position;
+/// ...
+/// // in `on_render`
+/// let mut camera = state.previous_camera.lerp(&state.camera, render_info.blending_factor() as f32);
+/// camera.fit(&render_info);
+/// render_info.set_uniform_cached("u_view_proj", &camera)?;
+/// sprites.draw(&mut render_info, camera.view_proj())?;
+/// ```
+///
+/// screen positions are in drawing buffer pixels with a top-left origin, so css positions (eg. a
+/// pointer's `offset_x`) need scaling by the drawing buffer's size over the canvas's css size first
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera2d {
+    /// the world position at the center of the view
+    pub position: [f32; 2],
+    /// the screen pixels per world unit
+    pub zoom: f32,
+    /// the counter-clockwise rotation of the camera in radians, which turns the world clockwise on screen
+    pub rotation: f32,
+    viewport_size: (u32, u32),
+}
+
+impl Default for Camera2d {
+    fn default() -> Self {
+        Self::new([0.0, 0.0], 1.0)
+    }
+}
+
+impl Camera2d {
+    pub fn new(position: [f32; 2], zoom: f32) -> Self {
+        Camera2d { position, zoom, rotation: 0.0, viewport_size: (1, 1) }
+    }
+
+    /// sets the size in pixels of what the camera is drawn to, eg. from `on_resize`
+    pub fn set_viewport_size(&mut self, (width, height): (u32, u32)) {
+        self.viewport_size = (width.max(1), height.max(1));
+    }
+    pub fn viewport_size(&self) -> (u32, u32) {
+        self.viewport_size
+    }
+    /// sets the viewport size to the canvas's (drawing buffer's) size
+    pub fn fit<S>(&mut self, render_info: &RenderInfo<S>) {
+        self.set_viewport_size(render_info.canvas_size());
+    }
+
+    /// `self` moved towards `other` by `t` (eg. `blending_factor()`), taking the shorter way around for
+    /// the rotation and keeping `other`'s viewport size
+    pub fn lerp(&self, other: &Camera2d, t: f32) -> Camera2d {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        let turn = (other.rotation - self.rotation + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+        Camera2d {
+            position: [lerp(self.position[0], other.position[0]), lerp(self.position[1], other.position[1])],
+            zoom: lerp(self.zoom, other.zoom),
+            rotation: self.rotation + turn * t,
+            viewport_size: other.viewport_size,
+        }
+    }
+
+    /// the (column-major) matrix from world positions to clip space
+    pub fn view_proj(&self) -> [[f32; 4]; 4] {
+        let (width, height) = self.viewport_size;
+        let (sx, sy) = (2.0 * self.zoom / width as f32, 2.0 * self.zoom / height as f32);
+        let (sin, cos) = (-self.rotation).sin_cos();
+        let [x, y] = self.position;
+        [
+            [sx * cos, sy * sin, 0.0, 0.0],
+            [-sx * sin, sy * cos, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [sx * (sin * y - cos * x), -sy * (sin * x + cos * y), 0.0, 1.0],
+        ]
+    }
+
+    /// the world position under `screen` (in drawing buffer pixels with a top-left origin)
+    pub fn screen_to_world(&self, screen: [f32; 2]) -> [f32; 2] {
+        let (width, height) = self.viewport_size;
+        let dx = (screen[0] - width as f32 * 0.5) / self.zoom;
+        let dy = (height as f32 * 0.5 - screen[1]) / self.zoom;
+        let (sin, cos) = self.rotation.sin_cos();
+        [self.position[0] + cos * dx - sin * dy, self.position[1] + sin * dx + cos * dy]
+    }
+
+    /// where `world` is on screen (in drawing buffer pixels with a top-left origin)
+    pub fn world_to_screen(&self, world: [f32; 2]) -> [f32; 2] {
+        let (width, height) = self.viewport_size;
+        let (dx, dy) = (world[0] - self.position[0], world[1] - self.position[1]);
+        let (sin, cos) = (-self.rotation).sin_cos();
+        [
+            width as f32 * 0.5 + (cos * dx - sin * dy) * self.zoom,
+            height as f32 * 0.5 - (sin * dx + cos * dy) * self.zoom,
+        ]
+    }
+
+    /// `[min_x, min_y, max_x, max_y]` of the world that is visible (the bounds of the rotated view),
+    /// eg. for culling
+    pub fn visible_rect(&self) -> [f32; 4] {
+        let (width, height) = (self.viewport_size.0 as f32, self.viewport_size.1 as f32);
+        let corners = [[0.0, 0.0], [width, 0.0], [0.0, height], [width, height]].map(|corner| self.screen_to_world(corner));
+        corners.iter().fold([f32::INFINITY, f32::INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY], |[min_x, min_y, max_x, max_y], [x, y]| {
+            [min_x.min(*x), min_y.min(*y), max_x.max(*x), max_y.max(*y)]
+        })
+    }
+}
+
+impl From<Camera2d> for UniformValue {
+    fn from(camera: Camera2d) -> Self {
+        UniformValue::Mat4(camera.view_proj().as_flattened().try_into().unwrap())
+    }
+}
+
+/// uploads `view_proj` to a `mat4` uniform
+impl Uniform for Camera2d {
+    fn upload(&self, context: &WebGl2RenderingContext, location: Option<&WebGlUniformLocation>) {
+        context.uniform_matrix4fv_with_f32_array(location, false, self.view_proj().as_flattened());
+    }
+}
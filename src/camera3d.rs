@@ -0,0 +1,371 @@
+use std::f32::consts::FRAC_PI_2;
+
+use web_sys::{WebGl2RenderingContext, WebGlUniformLocation};
+
+use crate::{Uniform, UniformValue, RenderInfo, PointerSnapshot};
+
+/// how close the pitch of the controllers can get to straight up or down, past which the view would flip
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+/// a perspective camera at `position` looking at `target`, whose `view_proj` is uploaded like any other
+/// matrix:
+/// ```ignore
+/// // in `on_render`
+/// state.camera.fit(&render_info);
+/// render_info.set_uniform_cached("u_view_proj", &state.camera)?;
+/// ```
+///
+/// the aspect ratio comes from the drawing buffer's size (via `fit` or `set_viewport_size`), so it
+/// follows whatever size an `on_resize` override chose rather than the canvas's css size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera3d {
+    pub position: [f32; 3],
+    pub target: [f32; 3],
+    pub up: [f32; 3],
+    /// the vertical field of view in radians
+    pub fov_y: f32,
+    pub near: f32,
+    pub far: f32,
+    aspect: f32,
+}
+
+impl Default for Camera3d {
+    fn default() -> Self {
+        Self::new([0.0, 0.0, 5.0], [0.0; 3])
+    }
+}
+
+impl Camera3d {
+    /// a camera with `y` up, a 60 degree vertical field of view and clip planes at `0.1` and `1000.0`
+    pub fn new(position: [f32; 3], target: [f32; 3]) -> Self {
+        Camera3d {
+            position,
+            target,
+            up: [0.0, 1.0, 0.0],
+            fov_y: 60f32.to_radians(),
+            near: 0.1,
+            far: 1000.0,
+            aspect: 1.0,
+        }
+    }
+
+    /// sets the aspect ratio from the size in pixels of what the camera is drawn to, eg. from `on_resize`
+    pub fn set_viewport_size(&mut self, (width, height): (u32, u32)) {
+        self.aspect = width.max(1) as f32 / height.max(1) as f32;
+    }
+    /// the width over the height of the viewport
+    pub fn aspect(&self) -> f32 {
+        self.aspect
+    }
+    /// sets the viewport size to the canvas's (drawing buffer's) size
+    pub fn fit<S>(&mut self, render_info: &RenderInfo<S>) {
+        self.set_viewport_size(render_info.canvas_size());
+    }
+
+    /// the unit vector from `position` towards `target`
+    pub fn forward(&self) -> [f32; 3] {
+        normalize(sub(self.target, self.position))
+    }
+    /// the unit vector to the right of the view
+    pub fn right(&self) -> [f32; 3] {
+        normalize(cross(self.forward(), self.up))
+    }
+
+    /// the (column-major) matrix from world space to view space, which looks down `-z`
+    pub fn view(&self) -> [[f32; 4]; 4] {
+        let forward = self.forward();
+        let right = normalize(cross(forward, self.up));
+        let up = cross(right, forward);
+        let eye = self.position;
+        [
+            [right[0], up[0], -forward[0], 0.0],
+            [right[1], up[1], -forward[1], 0.0],
+            [right[2], up[2], -forward[2], 0.0],
+            [-dot(right, eye), -dot(up, eye), dot(forward, eye), 1.0],
+        ]
+    }
+
+    /// the (column-major) perspective matrix from view space to clip space
+    pub fn projection(&self) -> [[f32; 4]; 4] {
+        let focal_length = 1.0 / (self.fov_y * 0.5).tan();
+        let (near, far) = (self.near, self.far);
+        [
+            [focal_length / self.aspect, 0.0, 0.0, 0.0],
+            [0.0, focal_length, 0.0, 0.0],
+            [0.0, 0.0, (far + near) / (near - far), -1.0],
+            [0.0, 0.0, 2.0 * far * near / (near - far), 0.0],
+        ]
+    }
+
+    /// `projection` times `view`
+    pub fn view_proj(&self) -> [[f32; 4]; 4] {
+        multiply(self.projection(), self.view())
+    }
+    /// the inverse of `view_proj`, from clip space back to world space, eg. for `unproject`
+    pub fn view_proj_inverse(&self) -> [[f32; 4]; 4] {
+        invert(self.view_proj())
+    }
+
+    /// the world space ray through `pos` from the near plane, where `pos` has a top-left origin and is
+    /// in the same units as `canvas_size`, so a pointer's css position (eg. `PointerSnapshot::x` and
+    /// `y`) can be used as is with the canvas's css size (`canvas_css_size`):
+    /// ```ignore
+    /// let ray = state.camera.ray_from_screen((pointer.x, pointer.y), update_info.canvas_css_size());
+    /// if let Some(t) = ray.intersect_plane([0.0; 3], [0.0, 1.0, 0.0]) {
+    ///     state.cursor = ray.at(t);
+    /// }
+    /// ```
+    ///
+    /// this assumes the drawing buffer has the same aspect ratio as the canvas
+    pub fn ray_from_screen(&self, pos: (f64, f64), canvas_size: (f64, f64)) -> Ray {
+        let ndc_x = (pos.0 / canvas_size.0.max(1.0) * 2.0 - 1.0) as f32;
+        let ndc_y = (1.0 - pos.1 / canvas_size.1.max(1.0) * 2.0) as f32;
+        let inverse = self.view_proj_inverse();
+        let unproject = |ndc_z: f32| {
+            let clip = [ndc_x, ndc_y, ndc_z, 1.0];
+            let world: [f32; 4] = std::array::from_fn(|row| (0..4).map(|i| inverse[i][row] * clip[i]).sum());
+            [world[0] / world[3], world[1] / world[3], world[2] / world[3]]
+        };
+        let (near, far) = (unproject(-1.0), unproject(1.0));
+        Ray { origin: near, dir: normalize(sub(far, near)) }
+    }
+}
+
+/// a half-line from `origin` along `dir` (a unit vector), see `Camera3d::ray_from_screen`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: [f32; 3],
+    pub dir: [f32; 3],
+}
+
+impl Ray {
+    /// the point `t` units along the ray
+    pub fn at(&self, t: f32) -> [f32; 3] {
+        add(self.origin, self.dir.map(|component| component * t))
+    }
+
+    /// how far along the ray it hits the plane through `point` with the normal `normal`, `None` if it
+    /// is parallel to the plane or the plane is behind it
+    pub fn intersect_plane(&self, point: [f32; 3], normal: [f32; 3]) -> Option<f32> {
+        let denominator = dot(self.dir, normal);
+        if denominator.abs() < f32::EPSILON {
+            return None;
+        }
+        let t = dot(sub(point, self.origin), normal) / denominator;
+        (t >= 0.0).then_some(t)
+    }
+
+    /// how far along the ray it enters the axis-aligned box from `min` to `max` (`0.0` if it starts
+    /// inside), `None` if it misses or the box is behind it
+    pub fn intersect_aabb(&self, min: [f32; 3], max: [f32; 3]) -> Option<f32> {
+        let (mut t_min, mut t_max) = (0.0f32, f32::INFINITY);
+        for axis in 0..3 {
+            // dividing by zero gives infinities, which leave the range alone if the origin is between
+            // the slab's planes and empty it otherwise
+            let inverse = 1.0 / self.dir[axis];
+            let t0 = (min[axis] - self.origin[axis]) * inverse;
+            let t1 = (max[axis] - self.origin[axis]) * inverse;
+            t_min = t_min.max(t0.min(t1));
+            t_max = t_max.min(t0.max(t1));
+        }
+        (t_min <= t_max).then_some(t_min)
+    }
+}
+
+impl From<Camera3d> for UniformValue {
+    fn from(camera: Camera3d) -> Self {
+        UniformValue::Mat4(camera.view_proj().as_flattened().try_into().unwrap())
+    }
+}
+
+/// uploads `view_proj` to a `mat4` uniform
+impl Uniform for Camera3d {
+    fn upload(&self, context: &WebGl2RenderingContext, location: Option<&WebGlUniformLocation>) {
+        context.uniform_matrix4fv_with_f32_array(location, false, self.view_proj().as_flattened());
+    }
+}
+
+/// `view_proj`
+#[cfg(feature = "glam")]
+impl From<Camera3d> for glam::Mat4 {
+    fn from(camera: Camera3d) -> Self {
+        glam::Mat4::from_cols_array_2d(&camera.view_proj())
+    }
+}
+
+/// `view_proj`
+#[cfg(feature = "nalgebra")]
+impl From<Camera3d> for nalgebra::Matrix4<f32> {
+    fn from(camera: Camera3d) -> Self {
+        nalgebra::Matrix4::from(camera.view_proj())
+    }
+}
+
+/// moves a `Camera3d` around its target, dragging a pointer rotates and the wheel dollies in and out:
+/// ```ignore
+/// renderer = renderer.with_pointer_tracking()?;
+/// ...
+/// // in `on_update`
+/// let (pointers, wheel_delta) = (update_info.pointers().to_vec(), update_info.wheel_delta());
+/// let state = &mut *update_info.state;
+/// state.orbit.update(&mut state.camera, &pointers, wheel_delta);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrbitController {
+    /// the rotation around `y` in radians
+    pub yaw: f32,
+    /// the angle above the horizontal in radians
+    pub pitch: f32,
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// radians per css pixel dragged
+    pub rotate_speed: f32,
+    /// the distance is scaled by `exp(zoom_speed)` per pixel scrolled
+    pub zoom_speed: f32,
+    /// the id and position of the pointer that is dragging
+    drag: Option<(i32, f64, f64)>,
+}
+
+impl OrbitController {
+    /// a controller that starts from `camera`'s current position around its target
+    pub fn from_camera(camera: &Camera3d) -> Self {
+        let offset = sub(camera.position, camera.target);
+        let distance = length(offset).max(f32::EPSILON);
+        OrbitController {
+            yaw: offset[0].atan2(offset[2]),
+            pitch: (offset[1] / distance).clamp(-1.0, 1.0).asin(),
+            distance,
+            min_distance: 0.1,
+            max_distance: f32::INFINITY,
+            rotate_speed: 0.005,
+            zoom_speed: 0.001,
+            drag: None,
+        }
+    }
+
+    /// applies this update's input and moves `camera` to match, keeping its target
+    pub fn update(&mut self, camera: &mut Camera3d, pointers: &[PointerSnapshot], wheel_delta: f64) {
+        let pressed = pointers.iter().find(|pointer| pointer.pressure > 0.0);
+        match (pressed, self.drag) {
+            (Some(pointer), Some((id, x, y))) if pointer.id == id => {
+                self.yaw -= (pointer.x - x) as f32 * self.rotate_speed;
+                self.pitch = (self.pitch + (pointer.y - y) as f32 * self.rotate_speed).clamp(-MAX_PITCH, MAX_PITCH);
+            }
+            _ => {}
+        }
+        self.drag = pressed.map(|pointer| (pointer.id, pointer.x, pointer.y));
+        self.distance = (self.distance * (wheel_delta as f32 * self.zoom_speed).exp()).clamp(self.min_distance, self.max_distance);
+
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        let offset = [cos_pitch * sin_yaw, sin_pitch, cos_pitch * cos_yaw].map(|component| component * self.distance);
+        camera.position = add(camera.target, offset);
+    }
+}
+
+/// moves a `Camera3d` like a first person fly camera, `KeyW`/`KeyS` move forwards and backwards,
+/// `KeyA`/`KeyD` strafe, `Space`/`ShiftLeft` move up and down and the arrow keys look around:
+/// ```ignore
+/// renderer = renderer.with_keyboard_tracking()?;
+/// ...
+/// // in `on_update`
+/// let (keys, dt) = (update_info.keys_down().clone(), update_info.fixed_time_step() as f32);
+/// let state = &mut *update_info.state;
+/// state.fly.update(&mut state.camera, |code| keys.contains(code), dt);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlyController {
+    /// the rotation around `y` in radians, `0` looks down `-z`
+    pub yaw: f32,
+    /// the angle above the horizontal in radians
+    pub pitch: f32,
+    /// units per second
+    pub speed: f32,
+    /// radians per second
+    pub look_speed: f32,
+}
+
+impl FlyController {
+    /// a controller that starts looking the way `camera` is
+    pub fn from_camera(camera: &Camera3d) -> Self {
+        let forward = camera.forward();
+        FlyController {
+            yaw: (-forward[0]).atan2(-forward[2]),
+            pitch: forward[1].clamp(-1.0, 1.0).asin(),
+            speed: 5.0,
+            look_speed: 1.5,
+        }
+    }
+
+    /// applies `dt` seconds of the held keys (by `KeyboardEvent.code`) to `camera`, keeping its
+    /// target one unit in front of it
+    pub fn update(&mut self, camera: &mut Camera3d, is_key_down: impl Fn(&str) -> bool, dt: f32) {
+        let axis = |positive: &str, negative: &str| is_key_down(positive) as i32 as f32 - is_key_down(negative) as i32 as f32;
+        self.yaw += axis("ArrowLeft", "ArrowRight") * self.look_speed * dt;
+        self.pitch = (self.pitch + axis("ArrowUp", "ArrowDown") * self.look_speed * dt).clamp(-MAX_PITCH, MAX_PITCH);
+
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        let forward = [-cos_pitch * sin_yaw, sin_pitch, -cos_pitch * cos_yaw];
+        let right = [cos_yaw, 0.0, -sin_yaw];
+        let distance = self.speed * dt;
+        let (forwards, sideways, upwards) = (axis("KeyW", "KeyS"), axis("KeyD", "KeyA"), axis("Space", "ShiftLeft"));
+        let movement: [f32; 3] = std::array::from_fn(|i| {
+            (forward[i] * forwards + right[i] * sideways + camera.up[i] * upwards) * distance
+        });
+        camera.position = add(camera.position, movement);
+        camera.target = add(camera.position, forward);
+    }
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+fn length(a: [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
+/// `a` times `b` for (column-major) matrices
+pub(crate) fn multiply(a: [[f32; 4]; 4], b: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    std::array::from_fn(|column| std::array::from_fn(|row| {
+        (0..4).map(|i| a[i][row] * b[column][i]).sum()
+    }))
+}
+/// the inverse of a (column-major) matrix by its adjugate, or all zeroes if it is singular
+pub(crate) fn invert(m: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    let m = m.as_flattened();
+    let mut inverse = [0.0f32; 16];
+    inverse[0] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15] + m[9] * m[7] * m[14] + m[13] * m[6] * m[11] - m[13] * m[7] * m[10];
+    inverse[4] = -m[4] * m[10] * m[15] + m[4] * m[11] * m[14] + m[8] * m[6] * m[15] - m[8] * m[7] * m[14] - m[12] * m[6] * m[11] + m[12] * m[7] * m[10];
+    inverse[8] = m[4] * m[9] * m[15] - m[4] * m[11] * m[13] - m[8] * m[5] * m[15] + m[8] * m[7] * m[13] + m[12] * m[5] * m[11] - m[12] * m[7] * m[9];
+    inverse[12] = -m[4] * m[9] * m[14] + m[4] * m[10] * m[13] + m[8] * m[5] * m[14] - m[8] * m[6] * m[13] - m[12] * m[5] * m[10] + m[12] * m[6] * m[9];
+    inverse[1] = -m[1] * m[10] * m[15] + m[1] * m[11] * m[14] + m[9] * m[2] * m[15] - m[9] * m[3] * m[14] - m[13] * m[2] * m[11] + m[13] * m[3] * m[10];
+    inverse[5] = m[0] * m[10] * m[15] - m[0] * m[11] * m[14] - m[8] * m[2] * m[15] + m[8] * m[3] * m[14] + m[12] * m[2] * m[11] - m[12] * m[3] * m[10];
+    inverse[9] = -m[0] * m[9] * m[15] + m[0] * m[11] * m[13] + m[8] * m[1] * m[15] - m[8] * m[3] * m[13] - m[12] * m[1] * m[11] + m[12] * m[3] * m[9];
+    inverse[13] = m[0] * m[9] * m[14] - m[0] * m[10] * m[13] - m[8] * m[1] * m[14] + m[8] * m[2] * m[13] + m[12] * m[1] * m[10] - m[12] * m[2] * m[9];
+    inverse[2] = m[1] * m[6] * m[15] - m[1] * m[7] * m[14] - m[5] * m[2] * m[15] + m[5] * m[3] * m[14] + m[13] * m[2] * m[7] - m[13] * m[3] * m[6];
+    inverse[6] = -m[0] * m[6] * m[15] + m[0] * m[7] * m[14] + m[4] * m[2] * m[15] - m[4] * m[3] * m[14] - m[12] * m[2] * m[7] + m[12] * m[3] * m[6];
+    inverse[10] = m[0] * m[5] * m[15] - m[0] * m[7] * m[13] - m[4] * m[1] * m[15] + m[4] * m[3] * m[13] + m[12] * m[1] * m[7] - m[12] * m[3] * m[5];
+    inverse[14] = -m[0] * m[5] * m[14] + m[0] * m[6] * m[13] + m[4] * m[1] * m[14] - m[4] * m[2] * m[13] - m[12] * m[1] * m[6] + m[12] * m[2] * m[5];
+    inverse[3] = -m[1] * m[6] * m[11] + m[1] * m[7] * m[10] + m[5] * m[2] * m[11] - m[5] * m[3] * m[10] - m[9] * m[2] * m[7] + m[9] * m[3] * m[6];
+    inverse[7] = m[0] * m[6] * m[11] - m[0] * m[7] * m[10] - m[4] * m[2] * m[11] + m[4] * m[3] * m[10] + m[8] * m[2] * m[7] - m[8] * m[3] * m[6];
+    inverse[11] = -m[0] * m[5] * m[11] + m[0] * m[7] * m[9] + m[4] * m[1] * m[11] - m[4] * m[3] * m[9] - m[8] * m[1] * m[7] + m[8] * m[3] * m[5];
+    inverse[15] = m[0] * m[5] * m[10] - m[0] * m[6] * m[9] - m[4] * m[1] * m[10] + m[4] * m[2] * m[9] + m[8] * m[1] * m[6] - m[8] * m[2] * m[5];
+
+    let determinant = m[0] * inverse[0] + m[1] * inverse[4] + m[2] * inverse[8] + m[3] * inverse[12];
+    let scale = if determinant == 0.0 { 0.0 } else { 1.0 / determinant };
+    std::array::from_fn(|column| std::array::from_fn(|row| inverse[column * 4 + row] * scale))
+}
+fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let length = length(a);
+    if length == 0.0 { a } else { a.map(|component| component / length) }
+}
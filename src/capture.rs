@@ -0,0 +1,117 @@
+use wasm_bindgen::{JsCast, closure::Closure};
+use web_sys::{Blob, BlobEvent, MediaRecorder, MediaStream, RecordingState};
+
+use crate::{Renderer, RenderInfo, RendererError};
+
+impl<S> Renderer<S> {
+    /// a stream of the canvas's contents at up to `fps` frames per second (`0.0` for a frame only when
+    /// `MediaStreamTrack.requestFrame` is called), eg. for a `MediaRecorder` or a `<video>`
+    ///
+    /// errors if the renderer is headless or the context wasn't created with `preserveDrawingBuffer`,
+    /// which can be done by calling `getContext("webgl2", { preserveDrawingBuffer: true })` on the canvas
+    /// before creating the renderer
+    pub fn canvas_capture_stream(&self, fps: f64) -> Result<MediaStream, RendererError> {
+        let canvas = self.canvas.as_ref().ok_or(RendererError::Headless)?;
+        let preserve_drawing_buffer = self.context.get_context_attributes()
+            .and_then(|attributes| attributes.get_preserve_drawing_buffer())
+            .unwrap_or(false);
+        if !preserve_drawing_buffer {
+            return Err(RendererError::ContextAttribute("preserveDrawingBuffer"));
+        }
+        Ok(canvas.capture_stream_with_frame_request_rate(fps)?)
+    }
+
+    /// stops the recorders started with `RenderInfo::start_recording`, called when the game loop exits
+    pub(crate) fn stop_recorders(&mut self) {
+        for recorder in self.recorders.drain(..) {
+            if recorder.state() != RecordingState::Inactive {
+                let _ = recorder.stop();
+            }
+        }
+    }
+}
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// starts recording the canvas at up to `fps` frames per second (see `Renderer::canvas_capture_stream`),
+    /// the recording is delivered to the function set with `MediaRecorderHandle::on_data_available`:
+    /// ```ignore
+    /// let mut recorder = render_info.start_recording(60.0)?;
+    /// recorder.on_data_available(|blob| download(blob, "session.webm"));
+    /// state.recorder = Some(recorder);
+    /// ...
+    /// state.recorder.take().unwrap().stop()?;
+    /// ```
+    ///
+    /// the recorder is stopped when the game loop exits
+    ///
+    /// errors if the canvas can't be captured or the browser can't record it
+    pub fn start_recording(&mut self, fps: f64) -> Result<MediaRecorderHandle, RendererError> {
+        let stream = self.renderer.canvas_capture_stream(fps)?;
+        let recorder = MediaRecorder::new_with_media_stream(&stream)?;
+        recorder.start()?;
+        self.renderer.recorders.retain(|recorder| recorder.state() != RecordingState::Inactive);
+        self.renderer.recorders.push(recorder.clone());
+        Ok(MediaRecorderHandle {
+            recorder,
+            on_data_available: None,
+        })
+    }
+}
+
+/// a `MediaRecorder` of the canvas, see `RenderInfo::start_recording`
+///
+/// stops recording when dropped
+pub struct MediaRecorderHandle {
+    recorder: MediaRecorder,
+    on_data_available: Option<Closure<dyn Fn(BlobEvent)>>,
+}
+
+impl MediaRecorderHandle {
+    /// starts recording again after `stop`, starting a new recording
+    pub fn start(&self) -> Result<(), RendererError> {
+        Ok(self.recorder.start()?)
+    }
+    /// stops recording, after which the recording is delivered to `on_data_available`
+    pub fn stop(&self) -> Result<(), RendererError> {
+        Ok(self.recorder.stop()?)
+    }
+    /// pauses recording without ending the recording
+    pub fn pause(&self) -> Result<(), RendererError> {
+        Ok(self.recorder.pause()?)
+    }
+    pub fn resume(&self) -> Result<(), RendererError> {
+        Ok(self.recorder.resume()?)
+    }
+    pub fn is_recording(&self) -> bool {
+        self.recorder.state() == RecordingState::Recording
+    }
+
+    /// sets a function that receives the recording (as a `Blob` of eg. webm video) when it is stopped
+    ///
+    /// replaces any previously set function
+    pub fn on_data_available(&mut self, on_data_available: fn(Blob)) {
+        let closure = Closure::<dyn Fn(BlobEvent)>::new(move |event: BlobEvent| {
+            if let Some(blob) = event.data() {
+                on_data_available(blob);
+            }
+        });
+        self.recorder.set_ondataavailable(Some(closure.as_ref().unchecked_ref()));
+        self.on_data_available = Some(closure);
+    }
+
+    pub fn recorder(&self) -> &MediaRecorder {
+        &self.recorder
+    }
+}
+
+impl Drop for MediaRecorderHandle {
+    fn drop(&mut self) {
+        if self.recorder.state() != RecordingState::Inactive {
+            let _ = self.recorder.stop();
+        }
+        // the recording is delivered after `stop` returns so the closure has to outlive the handle
+        if let Some(closure) = self.on_data_available.take() {
+            closure.forget();
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use web_sys::WebGl2RenderingContext;
+
+use crate::{Renderer, RenderInfo};
+
+type Gl = WebGl2RenderingContext;
+
+impl<S> Renderer<S> {
+    /// clears the color buffer to `color` at the start of every render, before `on_render`, instead
+    /// of clearing it in `on_render`
+    ///
+    /// returns self for chaining
+    pub fn with_clear_color(mut self, color: impl Into<[f32; 4]>) -> Self {
+        self.clear_color = Some(color.into());
+        self
+    }
+
+    /// clears the depth buffer to `depth` (eg. `1.0`) at the start of every render, see `with_clear_color`
+    ///
+    /// returns self for chaining
+    pub fn with_clear_depth(mut self, depth: f32) -> Self {
+        self.clear_depth = Some(depth);
+        self
+    }
+
+    /// clears the stencil buffer to `stencil` at the start of every render, see `with_clear_color`
+    ///
+    /// returns self for chaining
+    pub fn with_clear_stencil(mut self, stencil: i32) -> Self {
+        self.clear_stencil = Some(stencil);
+        self
+    }
+
+    /// clears the buffers that have a clear value set, with a single `clear`
+    pub(crate) fn clear_persistent(&self) {
+        let context = &self.context;
+        let mut mask = 0;
+        if let Some([red, green, blue, alpha]) = self.clear_color {
+            context.clear_color(red, green, blue, alpha);
+            mask |= Gl::COLOR_BUFFER_BIT;
+        }
+        if let Some(depth) = self.clear_depth {
+            context.clear_depth(depth);
+            mask |= Gl::DEPTH_BUFFER_BIT;
+        }
+        if let Some(stencil) = self.clear_stencil {
+            context.clear_stencil(stencil);
+            mask |= Gl::STENCIL_BUFFER_BIT;
+        }
+        if mask != 0 {
+            context.clear(mask);
+        }
+    }
+}
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// changes (or with `None`, stops) the clearing of the color buffer at the start of each render
+    /// from the next frame on, see `Renderer::with_clear_color`
+    pub fn set_clear_color_persistent(&mut self, color: Option<[f32; 4]>) {
+        self.renderer.clear_color = color;
+    }
+    /// changes (or with `None`, stops) the clearing of the depth buffer at the start of each render
+    /// from the next frame on, see `Renderer::with_clear_depth`
+    pub fn set_clear_depth_persistent(&mut self, depth: Option<f32>) {
+        self.renderer.clear_depth = depth;
+    }
+}
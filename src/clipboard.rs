@@ -0,0 +1,68 @@
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::ClipboardEvent;
+use js_sys::Reflect;
+use std::future::Future;
+
+use crate::{LogLevel, Renderer, RenderInfo, RendererError};
+
+pub(crate) type OnPaste<S> = fn(&mut S, String);
+
+impl<S> Renderer<S> {
+    /// adds a `paste` event listener that receives the pasted plain text, eg. for a word puzzle:
+    /// ```ignore
+    /// renderer = renderer.with_on_paste(|state, text| state.guess.push_str(text.trim()))?;
+    /// ```
+    /// pastes without any plain text are ignored
+    ///
+    /// the canvas only receives `paste` events when it is focused, so it needs a `tabindex` attribute
+    ///
+    /// returns self for chaining
+    ///
+    /// errors if the event listener could not be added or the renderer is headless
+    pub fn with_on_paste(self, on_paste: OnPaste<S>) -> Result<Self, RendererError> {
+        self.add_event_listener("paste", false, 0, Box::new(move |state, event| {
+            let text = event.dyn_ref::<ClipboardEvent>()
+                .and_then(ClipboardEvent::clipboard_data)
+                .and_then(|data| data.get_data("text/plain").ok())
+                .filter(|text| !text.is_empty());
+            if let Some(text) = text {
+                on_paste(state, text);
+            }
+        }))
+    }
+}
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// writes `text` to the clipboard, the returned future (eg. for `spawn_local`) resolves once it has
+    /// been written
+    ///
+    /// browsers only allow writing shortly after the user has interacted with the page (eg. clicked),
+    /// so a warning is logged if there hasn't been a recent interaction
+    ///
+    /// the future errors if there is no window or the browser rejects the write
+    pub fn write_to_clipboard(&self, text: &str) -> impl Future<Output = Result<(), RendererError>> + 'static {
+        let promise = web_sys::window().map(|window| {
+            let navigator = window.navigator();
+            if !has_user_activation(&navigator) {
+                self.renderer.log(LogLevel::Warnings, || String::from("writing to the clipboard without a recent user interaction, which browsers usually reject"));
+            }
+            navigator.clipboard().write_text(text)
+        });
+        async move {
+            JsFuture::from(promise.ok_or(RendererError::Unsupported("window"))?).await?;
+            Ok(())
+        }
+    }
+}
+
+/// whether the page has transient user activation (`navigator.userActivation.isActive`), assumed to
+/// be true in browsers that don't support `userActivation`
+fn has_user_activation(navigator: &web_sys::Navigator) -> bool {
+    Reflect::get(navigator, &JsValue::from_str("userActivation"))
+        .ok()
+        .filter(|user_activation| !user_activation.is_undefined())
+        .and_then(|user_activation| Reflect::get(&user_activation, &JsValue::from_str("isActive")).ok())
+        .and_then(|is_active| is_active.as_bool())
+        .unwrap_or(true)
+}
@@ -0,0 +1,133 @@
+use web_sys::{WebGl2RenderingContext, WebGlUniformLocation};
+
+use crate::{RendererError, Uniform, UniformValue};
+
+/// an rgba color with components in `[0, 1]`, accepted anywhere a `[f32; 4]` color is:
+/// ```ignore
+/// renderer = renderer.with_clear_color(Color::from_hex("#1e1e2e")?);
+/// draw.rect(10.0, 10.0, 50.0, 50.0, Color::RED.lerp(Color::BLUE, t));
+/// ```
+///
+/// the components aren't tied to a color space, colors written in css or picked from an image are
+/// usually sRGB and need `to_linear` before being blended or lit in linear space
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const TRANSPARENT: Color = Color::new(0.0, 0.0, 0.0, 0.0);
+    pub const BLACK: Color = Color::rgb(0.0, 0.0, 0.0);
+    pub const WHITE: Color = Color::rgb(1.0, 1.0, 1.0);
+    pub const GRAY: Color = Color::rgb(0.5, 0.5, 0.5);
+    pub const RED: Color = Color::rgb(1.0, 0.0, 0.0);
+    pub const GREEN: Color = Color::rgb(0.0, 1.0, 0.0);
+    pub const BLUE: Color = Color::rgb(0.0, 0.0, 1.0);
+    pub const YELLOW: Color = Color::rgb(1.0, 1.0, 0.0);
+    pub const CYAN: Color = Color::rgb(0.0, 1.0, 1.0);
+    pub const MAGENTA: Color = Color::rgb(1.0, 0.0, 1.0);
+
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Color { r, g, b, a }
+    }
+    /// an opaque color
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Color::new(r, g, b, 1.0)
+    }
+    /// an opaque color from bytes
+    pub const fn rgb8(r: u8, g: u8, b: u8) -> Self {
+        Color::rgba8(r, g, b, 255)
+    }
+    pub const fn rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Color::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0)
+    }
+
+    /// parses `#rrggbb` or `#rrggbbaa` (the `#` is optional)
+    ///
+    /// errors if `hex` isn't 6 or 8 hex digits
+    pub fn from_hex(hex: &str) -> Result<Self, RendererError> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if !(digits.len() == 6 || digits.len() == 8) || !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(RendererError::Parse("hex color", format!("expected `#rrggbb` or `#rrggbbaa` but got `{}`", hex)));
+        }
+        // every byte is an ascii hex digit so every pair is a valid slice and number
+        let byte = |i: usize| u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).unwrap();
+        let alpha = if digits.len() == 8 { byte(3) } else { 255 };
+        Ok(Color::rgba8(byte(0), byte(1), byte(2), alpha))
+    }
+
+    /// an opaque color from its hue (in degrees), saturation and lightness (both in `[0, 1]`)
+    pub fn hsl(hue: f32, saturation: f32, lightness: f32) -> Self {
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        let hue = hue.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let (r, g, b) = match hue as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = lightness - chroma * 0.5;
+        Color::rgb(r + m, g + m, b + m)
+    }
+
+    /// the same color with alpha `a`
+    pub const fn with_alpha(self, a: f32) -> Self {
+        Color::new(self.r, self.g, self.b, a)
+    }
+
+    /// decodes sRGB components to linear ones, leaving alpha as it is
+    pub fn to_linear(self) -> Self {
+        let decode = |c: f32| if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) };
+        Color::new(decode(self.r), decode(self.g), decode(self.b), self.a)
+    }
+    /// encodes linear components to sRGB ones, leaving alpha as it is
+    pub fn to_srgb(self) -> Self {
+        let encode = |c: f32| if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+        Color::new(encode(self.r), encode(self.g), encode(self.b), self.a)
+    }
+
+    /// the color multiplied by its alpha, for `BlendMode::PremultipliedAlpha`
+    pub fn premultiplied(self) -> Self {
+        Color::new(self.r * self.a, self.g * self.a, self.b * self.a, self.a)
+    }
+
+    /// `self` moved towards `other` by `t`, component-wise
+    pub fn lerp(self, other: Color, t: f32) -> Self {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        Color::new(lerp(self.r, other.r), lerp(self.g, other.g), lerp(self.b, other.b), lerp(self.a, other.a))
+    }
+
+    pub const fn to_array(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from([r, g, b, a]: [f32; 4]) -> Self {
+        Color::new(r, g, b, a)
+    }
+}
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        color.to_array()
+    }
+}
+
+impl From<Color> for UniformValue {
+    fn from(color: Color) -> Self {
+        UniformValue::Vec4(color.to_array())
+    }
+}
+
+/// uploads to a `vec4` uniform
+impl Uniform for Color {
+    fn upload(&self, context: &WebGl2RenderingContext, location: Option<&WebGlUniformLocation>) {
+        UniformValue::from(*self).upload(context, location)
+    }
+}
@@ -0,0 +1,197 @@
+use web_sys::WebGl2RenderingContext;
+
+use crate::{has_extension, RendererError, SamplerOptions, Texture2d};
+
+/// the block footprint of an ASTC compressed texture, each block is 16 bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AstcBlockSize {
+    B4x4,
+    B5x4,
+    B5x5,
+    B6x5,
+    B6x6,
+    B8x5,
+    B8x6,
+    B8x8,
+    B10x5,
+    B10x6,
+    B10x8,
+    B10x10,
+    B12x10,
+    B12x12,
+}
+
+impl AstcBlockSize {
+    pub const ALL: [AstcBlockSize; 14] = [
+        AstcBlockSize::B4x4, AstcBlockSize::B5x4, AstcBlockSize::B5x5, AstcBlockSize::B6x5,
+        AstcBlockSize::B6x6, AstcBlockSize::B8x5, AstcBlockSize::B8x6, AstcBlockSize::B8x8,
+        AstcBlockSize::B10x5, AstcBlockSize::B10x6, AstcBlockSize::B10x8, AstcBlockSize::B10x10,
+        AstcBlockSize::B12x10, AstcBlockSize::B12x12,
+    ];
+
+    /// the width and height of a block in texels
+    pub fn dimensions(self) -> (u32, u32) {
+        match self {
+            AstcBlockSize::B4x4 => (4, 4),
+            AstcBlockSize::B5x4 => (5, 4),
+            AstcBlockSize::B5x5 => (5, 5),
+            AstcBlockSize::B6x5 => (6, 5),
+            AstcBlockSize::B6x6 => (6, 6),
+            AstcBlockSize::B8x5 => (8, 5),
+            AstcBlockSize::B8x6 => (8, 6),
+            AstcBlockSize::B8x8 => (8, 8),
+            AstcBlockSize::B10x5 => (10, 5),
+            AstcBlockSize::B10x6 => (10, 6),
+            AstcBlockSize::B10x8 => (10, 8),
+            AstcBlockSize::B10x10 => (10, 10),
+            AstcBlockSize::B12x10 => (12, 10),
+            AstcBlockSize::B12x12 => (12, 12),
+        }
+    }
+
+    /// the `COMPRESSED_RGBA_ASTC_*_KHR` constant
+    fn to_gl(self) -> u32 {
+        // the constants are contiguous in the same order as the variants
+        0x93B0 + Self::ALL.iter().position(|&size| size == self).unwrap() as u32
+    }
+}
+
+/// a block compressed texture format, each of which requires a webgl extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressedFormat {
+    /// `COMPRESSED_RGB_S3TC_DXT1_EXT` (BC1 without alpha), 8 bytes per 4x4 block
+    S3tcDxt1Rgb,
+    /// `COMPRESSED_RGBA_S3TC_DXT1_EXT` (BC1 with 1-bit alpha), 8 bytes per 4x4 block
+    S3tcDxt1Rgba,
+    /// `COMPRESSED_RGBA_S3TC_DXT3_EXT` (BC2), 16 bytes per 4x4 block
+    S3tcDxt3Rgba,
+    /// `COMPRESSED_RGBA_S3TC_DXT5_EXT` (BC3), 16 bytes per 4x4 block
+    S3tcDxt5Rgba,
+    /// `COMPRESSED_RGB8_ETC2`, 8 bytes per 4x4 block
+    Etc2Rgb8,
+    /// `COMPRESSED_RGBA8_ETC2_EAC`, 16 bytes per 4x4 block
+    Etc2Rgba8,
+    /// `COMPRESSED_RGBA_ASTC_*_KHR`, 16 bytes per block
+    Astc(AstcBlockSize),
+}
+
+impl CompressedFormat {
+    pub fn to_gl(self) -> u32 {
+        match self {
+            CompressedFormat::S3tcDxt1Rgb => 0x83F0,
+            CompressedFormat::S3tcDxt1Rgba => 0x83F1,
+            CompressedFormat::S3tcDxt3Rgba => 0x83F2,
+            CompressedFormat::S3tcDxt5Rgba => 0x83F3,
+            CompressedFormat::Etc2Rgb8 => 0x9274,
+            CompressedFormat::Etc2Rgba8 => 0x9278,
+            CompressedFormat::Astc(block_size) => block_size.to_gl(),
+        }
+    }
+
+    /// the name of the webgl extension that provides this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            CompressedFormat::S3tcDxt1Rgb
+            | CompressedFormat::S3tcDxt1Rgba
+            | CompressedFormat::S3tcDxt3Rgba
+            | CompressedFormat::S3tcDxt5Rgba => "WEBGL_compressed_texture_s3tc",
+            CompressedFormat::Etc2Rgb8 | CompressedFormat::Etc2Rgba8 => "WEBGL_compressed_texture_etc",
+            CompressedFormat::Astc(_) => "WEBGL_compressed_texture_astc",
+        }
+    }
+
+    /// the width and height of a block in texels
+    pub fn block_dimensions(self) -> (u32, u32) {
+        match self {
+            CompressedFormat::Astc(block_size) => block_size.dimensions(),
+            _ => (4, 4),
+        }
+    }
+
+    pub fn bytes_per_block(self) -> usize {
+        match self {
+            CompressedFormat::S3tcDxt1Rgb
+            | CompressedFormat::S3tcDxt1Rgba
+            | CompressedFormat::Etc2Rgb8 => 8,
+            _ => 16,
+        }
+    }
+
+    /// the number of bytes that a `width`x`height` image (or mip level) takes up in this format
+    pub fn byte_len(self, width: u32, height: u32) -> usize {
+        let (block_width, block_height) = self.block_dimensions();
+        let blocks_x = width.div_ceil(block_width) as usize;
+        let blocks_y = height.div_ceil(block_height) as usize;
+        blocks_x * blocks_y * self.bytes_per_block()
+    }
+
+    /// whether `context` supports (and has now enabled) this format
+    pub fn is_supported(self, context: &WebGl2RenderingContext) -> bool {
+        has_extension(context, self.extension())
+    }
+
+    /// all formats supported by `context`, useful for picking which pre-compressed asset variant to download
+    pub fn supported(context: &WebGl2RenderingContext) -> Vec<CompressedFormat> {
+        let mut formats = vec![
+            CompressedFormat::S3tcDxt1Rgb,
+            CompressedFormat::S3tcDxt1Rgba,
+            CompressedFormat::S3tcDxt3Rgba,
+            CompressedFormat::S3tcDxt5Rgba,
+            CompressedFormat::Etc2Rgb8,
+            CompressedFormat::Etc2Rgba8,
+        ];
+        formats.extend(AstcBlockSize::ALL.map(CompressedFormat::Astc));
+        formats.retain(|format| format.is_supported(context));
+        formats
+    }
+}
+
+/// the family of compressed formats to download assets in, see `TextureCompression::best_available`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureCompression {
+    /// `WEBGL_compressed_texture_astc`, mostly on mobile
+    Astc,
+    /// `WEBGL_compressed_texture_etc`, mostly on mobile
+    Etc2,
+    /// `WEBGL_compressed_texture_s3tc`, mostly on desktop
+    S3tc,
+    /// no compressed formats are supported so textures must be uncompressed
+    None,
+}
+
+impl TextureCompression {
+    /// the best supported family for `context`, preferring ASTC then ETC2 then S3TC
+    pub fn best_available(context: &WebGl2RenderingContext) -> TextureCompression {
+        if CompressedFormat::Astc(AstcBlockSize::B4x4).is_supported(context) {
+            TextureCompression::Astc
+        } else if CompressedFormat::Etc2Rgba8.is_supported(context) {
+            TextureCompression::Etc2
+        } else if CompressedFormat::S3tcDxt5Rgba.is_supported(context) {
+            TextureCompression::S3tc
+        } else {
+            TextureCompression::None
+        }
+    }
+}
+
+/// uploads a single level `COMPRESSED_RGB8_ETC2` texture with the default sampler
+///
+/// errors like `Texture2d::from_compressed`
+pub fn upload_texture_etc2_rgb(context: &WebGl2RenderingContext, width: u32, height: u32, data: &[u8]) -> Result<Texture2d, RendererError> {
+    Texture2d::from_compressed(context, CompressedFormat::Etc2Rgb8, width, height, &[data], &SamplerOptions::default())
+}
+
+/// uploads a single level `COMPRESSED_RGBA8_ETC2_EAC` texture with the default sampler
+///
+/// errors like `Texture2d::from_compressed`
+pub fn upload_texture_etc2_rgba(context: &WebGl2RenderingContext, width: u32, height: u32, data: &[u8]) -> Result<Texture2d, RendererError> {
+    Texture2d::from_compressed(context, CompressedFormat::Etc2Rgba8, width, height, &[data], &SamplerOptions::default())
+}
+
+/// uploads a single level `COMPRESSED_RGBA_ASTC_*_KHR` texture with the default sampler, enabling
+/// `WEBGL_compressed_texture_astc`
+///
+/// errors like `Texture2d::from_compressed`, eg. if the extension is unsupported
+pub fn upload_texture_astc(context: &WebGl2RenderingContext, block_size: AstcBlockSize, width: u32, height: u32, data: &[u8]) -> Result<Texture2d, RendererError> {
+    Texture2d::from_compressed(context, CompressedFormat::Astc(block_size), width, height, &[data], &SamplerOptions::default())
+}
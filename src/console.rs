@@ -0,0 +1,128 @@
+#[cfg(feature = "log")]
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use crate::Renderer;
+
+/// `console.log`s a `format!`ted message, eg. for debug output from callbacks:
+/// ```ignore
+/// console_log!("frame {}: {:?}", update_info.number_of_updates(), state.player);
+/// ```
+#[macro_export]
+macro_rules! console_log {
+    ($($arg:tt)*) => {
+        $crate::__console::log_1(&::std::format!($($arg)*).into())
+    };
+}
+
+#[doc(hidden)]
+pub use web_sys::console as __console;
+
+/// how much the renderer itself logs to the browser console, see `Renderer::with_log_level`
+///
+/// each level includes the ones before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    None,
+    /// shader compilation failures, context creation errors and errors that can't be returned
+    Errors,
+    /// things that are likely mistakes, eg. starting without `on_render`, and losing the context
+    Warnings,
+    /// every frame, resize and event dispatch
+    Verbose,
+}
+
+/// `Warnings` in debug builds and `None` in release builds
+impl Default for LogLevel {
+    fn default() -> Self {
+        if cfg!(debug_assertions) { LogLevel::Warnings } else { LogLevel::None }
+    }
+}
+
+/// logs the message from `message` if `log_level` includes `level`, to `console.error` for errors,
+/// `console.warn` for warnings and `console.log` otherwise
+pub(crate) fn log_at(log_level: LogLevel, level: LogLevel, message: impl FnOnce() -> String) {
+    if level == LogLevel::None || log_level < level {
+        return;
+    }
+    let message = format!("web-render-rs: {}", message()).into();
+    match level {
+        LogLevel::Errors => web_sys::console::error_1(&message),
+        LogLevel::Warnings => web_sys::console::warn_1(&message),
+        _ => web_sys::console::log_1(&message),
+    }
+}
+
+impl<S> Renderer<S> {
+    /// sets how much the renderer logs to the browser console, `LogLevel::default()` to begin with
+    ///
+    /// returns self for chaining
+    pub fn with_log_level(self, log_level: LogLevel) -> Self {
+        self.log_level.set(log_level);
+        self
+    }
+
+    /// logs the message from `message` if the log level includes `level`
+    pub(crate) fn log(&self, level: LogLevel, message: impl FnOnce() -> String) {
+        log_at(self.log_level.get(), level, message)
+    }
+}
+
+/// a `log` logger that forwards errors to `console.error`, warnings to `console.warn`, info to
+/// `console.log` and debug and trace to `console.debug`, see `Renderer::with_log_to_console`
+#[cfg(feature = "log")]
+pub struct ConsoleLogger;
+
+#[cfg(feature = "log")]
+static CONSOLE_LOGGER: ConsoleLogger = ConsoleLogger;
+
+#[cfg(feature = "log")]
+impl ConsoleLogger {
+    /// sets the console logger as the global logger and logs everything
+    ///
+    /// errors if a global logger has already been set
+    pub fn init() -> Result<(), SetLoggerError> {
+        log::set_logger(&CONSOLE_LOGGER)?;
+        log::set_max_level(LevelFilter::Trace);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "log")]
+impl Log for ConsoleLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let message = format!("[{}] {}", record.target(), record.args()).into();
+        match record.level() {
+            Level::Error => web_sys::console::error_1(&message),
+            Level::Warn => web_sys::console::warn_1(&message),
+            Level::Info => web_sys::console::log_1(&message),
+            Level::Debug | Level::Trace => web_sys::console::debug_1(&message),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(feature = "log")]
+impl<S> Renderer<S> {
+    /// sets `ConsoleLogger` as the global logger when the game loop starts, so that `log::info!` etc.
+    /// end up in the browser console
+    ///
+    /// does nothing if another global logger has already been set by then
+    ///
+    /// returns self for chaining
+    pub fn with_log_to_console(mut self) -> Self {
+        self.log_to_console = true;
+        self
+    }
+
+    /// sets the global logger if `with_log_to_console` was called
+    pub(crate) fn init_logger(&self) {
+        if self.log_to_console {
+            let _ = ConsoleLogger::init();
+        }
+    }
+}
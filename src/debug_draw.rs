@@ -0,0 +1,232 @@
+use wasm_bindgen::JsCast;
+use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlProgram, WebGlUniformLocation, WebGlVertexArrayObject};
+
+use crate::{Camera3d, Color, RenderInfo, RendererError, compile_shader, link_program};
+use crate::stats;
+
+type Gl = WebGl2RenderingContext;
+
+const DEBUG_VERTEX_SHADER: &str = "\
+#version 300 es
+layout(location = 0) in vec3 a_position;
+layout(location = 1) in vec4 a_color;
+uniform mat4 u_view_proj;
+out vec4 v_color;
+
+void main() {
+    v_color = a_color;
+    gl_Position = u_view_proj * vec4(a_position, 1.0);
+}
+";
+
+const DEBUG_FRAGMENT_SHADER: &str = "\
+#version 300 es
+precision mediump float;
+in vec4 v_color;
+out vec4 color;
+
+void main() {
+    color = v_color;
+}
+";
+
+/// the floats per vertex: a position and a color
+const VERTEX_FLOATS: usize = 7;
+
+/// the segments of each circle of a `sphere`
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// one pixel wide 3d lines for visualising things while debugging, eg. a ground grid, collision
+/// volumes and the axes of a transform, all drawn with a single draw call:
+/// ```ignore
+/// let mut debug = DebugDraw::new(context)?;
+/// ...
+/// // in `on_render`
+/// debug.grid(&camera, 1.0, 50.0)
+///     .axes([0.0; 3], 1.0)
+///     .aabb(player.min, player.max, Color::GREEN)
+///     .sphere(target.center, target.radius, Color::RED);
+/// debug.draw(&mut render_info, camera.view_proj());
+/// ```
+///
+/// only available with the `debug-draw` feature, so that release builds can compile it (and the calls
+/// to it, behind `#[cfg(feature = "debug-draw")]`) out
+pub struct DebugDraw {
+    context: WebGl2RenderingContext,
+    program: WebGlProgram,
+    view_proj_location: Option<WebGlUniformLocation>,
+    vertex_array: WebGlVertexArrayObject,
+    buffer: WebGlBuffer,
+    vertices: Vec<f32>,
+}
+
+impl DebugDraw {
+    /// errors if the program, buffer or vertex array object could not be created
+    pub fn new(context: &WebGl2RenderingContext) -> Result<DebugDraw, RendererError> {
+        let vert_shader = compile_shader(context, Gl::VERTEX_SHADER, DEBUG_VERTEX_SHADER)?;
+        let frag_shader = compile_shader(context, Gl::FRAGMENT_SHADER, DEBUG_FRAGMENT_SHADER)?;
+        let program = link_program(context, &vert_shader, &frag_shader);
+        context.delete_shader(Some(&vert_shader));
+        context.delete_shader(Some(&frag_shader));
+        let program = program?;
+
+        let buffer = context.create_buffer().ok_or(RendererError::Create("buffer"))?;
+        let vertex_array = context.create_vertex_array().ok_or(RendererError::Create("vertex array"))?;
+        let previous = context.get_parameter(Gl::VERTEX_ARRAY_BINDING).ok()
+            .and_then(|value| value.dyn_into::<WebGlVertexArrayObject>().ok());
+        context.bind_vertex_array(Some(&vertex_array));
+        context.bind_buffer(Gl::ARRAY_BUFFER, Some(&buffer));
+        let stride = (VERTEX_FLOATS * size_of::<f32>()) as i32;
+        context.enable_vertex_attrib_array(0);
+        context.vertex_attrib_pointer_with_i32(0, 3, Gl::FLOAT, false, stride, 0);
+        context.enable_vertex_attrib_array(1);
+        context.vertex_attrib_pointer_with_i32(1, 4, Gl::FLOAT, false, stride, 3 * size_of::<f32>() as i32);
+        context.bind_vertex_array(previous.as_ref());
+
+        Ok(DebugDraw {
+            context: context.clone(),
+            view_proj_location: context.get_uniform_location(&program, "u_view_proj"),
+            program,
+            vertex_array,
+            buffer,
+            vertices: Vec::new(),
+        })
+    }
+
+    /// a line from `from` to `to`
+    ///
+    /// returns self for chaining
+    pub fn line(&mut self, from: [f32; 3], to: [f32; 3], color: impl Into<[f32; 4]>) -> &mut Self {
+        let color = color.into();
+        for point in [from, to] {
+            self.vertices.extend_from_slice(&point);
+            self.vertices.extend_from_slice(&color);
+        }
+        self
+    }
+
+    /// a grid on the ground (`y = 0`) with lines every `spacing` units out to `extent` units from the
+    /// point below the camera, which it follows so that it looks infinite. Every tenth line is
+    /// brighter and the lines through the origin are the colors of the x (red) and z (blue) axes
+    ///
+    /// returns self for chaining
+    pub fn grid(&mut self, camera: &Camera3d, spacing: f32, extent: f32) -> &mut Self {
+        if spacing <= 0.0 || extent <= 0.0 {
+            return self;
+        }
+        let lines = (extent / spacing).ceil() as i64;
+        let (center_x, center_z) = ((camera.position[0] / spacing).round() as i64, (camera.position[2] / spacing).round() as i64);
+        let (min_x, max_x) = ((center_x - lines) as f32 * spacing, (center_x + lines) as f32 * spacing);
+        let (min_z, max_z) = ((center_z - lines) as f32 * spacing, (center_z + lines) as f32 * spacing);
+        let line_color = |index: i64, axis: Color| match index {
+            0 => axis,
+            _ if index % 10 == 0 => Color::GRAY,
+            _ => Color::new(0.3, 0.3, 0.3, 1.0),
+        };
+        for index in center_x - lines..=center_x + lines {
+            let x = index as f32 * spacing;
+            self.line([x, 0.0, min_z], [x, 0.0, max_z], line_color(index, Color::BLUE));
+        }
+        for index in center_z - lines..=center_z + lines {
+            let z = index as f32 * spacing;
+            self.line([min_x, 0.0, z], [max_x, 0.0, z], line_color(index, Color::RED));
+        }
+        self
+    }
+
+    /// the x (red), y (green) and z (blue) axes from `origin`, each `length` long
+    ///
+    /// returns self for chaining
+    pub fn axes(&mut self, origin: [f32; 3], length: f32) -> &mut Self {
+        for (axis, color) in [Color::RED, Color::GREEN, Color::BLUE].into_iter().enumerate() {
+            let mut end = origin;
+            end[axis] += length;
+            self.line(origin, end, color);
+        }
+        self
+    }
+
+    /// the edges of an axis-aligned box
+    ///
+    /// returns self for chaining
+    pub fn aabb(&mut self, min: [f32; 3], max: [f32; 3], color: impl Into<[f32; 4]>) -> &mut Self {
+        let color = color.into();
+        let corner = |i: usize| [if i & 1 == 0 { min[0] } else { max[0] }, if i & 2 == 0 { min[1] } else { max[1] }, if i & 4 == 0 { min[2] } else { max[2] }];
+        // each corner is joined to the corners that differ from it along one axis
+        for i in 0..8 {
+            for axis_bit in [1, 2, 4] {
+                if i & axis_bit == 0 {
+                    self.line(corner(i), corner(i | axis_bit), color);
+                }
+            }
+        }
+        self
+    }
+
+    /// a sphere as three circles around its center, one in each axis plane
+    ///
+    /// returns self for chaining
+    pub fn sphere(&mut self, center: [f32; 3], radius: f32, color: impl Into<[f32; 4]>) -> &mut Self {
+        let color = color.into();
+        for (a, b) in [(0, 1), (1, 2), (0, 2)] {
+            let point = |segment: usize| {
+                let angle = segment as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+                let mut point = center;
+                point[a] += radius * angle.cos();
+                point[b] += radius * angle.sin();
+                point
+            };
+            for segment in 0..CIRCLE_SEGMENTS {
+                self.line(point(segment), point(segment + 1), color);
+            }
+        }
+        self
+    }
+
+    /// removes every line without drawing them
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    /// draws (and then removes) every line added since the previous draw, with `view_proj` being the
+    /// (column-major) view-projection matrix, eg. `Camera3d::view_proj`
+    ///
+    /// the lines use the current pipeline state (eg. the depth test) and the previously bound program
+    /// and vertex array object are rebound afterwards
+    pub fn draw<S>(&mut self, render_info: &mut RenderInfo<S>, view_proj: [[f32; 4]; 4]) {
+        let vertex_count = self.vertices.len() / VERTEX_FLOATS;
+        if vertex_count == 0 {
+            return;
+        }
+
+        let context = &self.context;
+        let previous_program = context.get_parameter(Gl::CURRENT_PROGRAM).ok()
+            .and_then(|value| value.dyn_into::<WebGlProgram>().ok());
+        let previous_vertex_array = context.get_parameter(Gl::VERTEX_ARRAY_BINDING).ok()
+            .and_then(|value| value.dyn_into::<WebGlVertexArrayObject>().ok());
+
+        context.bind_buffer(Gl::ARRAY_BUFFER, Some(&self.buffer));
+        // SAFETY: the view is only used to upload the vertices, without allocating, before they are next modified
+        unsafe {
+            context.buffer_data_with_array_buffer_view(Gl::ARRAY_BUFFER, &js_sys::Float32Array::view(&self.vertices), Gl::STREAM_DRAW);
+        }
+        context.use_program(Some(&self.program));
+        context.uniform_matrix4fv_with_f32_array(self.view_proj_location.as_ref(), false, view_proj.as_flattened());
+        context.bind_vertex_array(Some(&self.vertex_array));
+        context.draw_arrays(Gl::LINES, 0, vertex_count as i32);
+        render_info.note_draw_call(0);
+        stats::count(|stats| stats.program_switches += 1);
+
+        context.bind_vertex_array(previous_vertex_array.as_ref());
+        context.use_program(previous_program.as_ref());
+        self.vertices.clear();
+    }
+}
+
+impl Drop for DebugDraw {
+    fn drop(&mut self) {
+        self.context.delete_program(Some(&self.program));
+        self.context.delete_buffer(Some(&self.buffer));
+        self.context.delete_vertex_array(Some(&self.vertex_array));
+    }
+}
@@ -0,0 +1,173 @@
+use std::f32::consts::TAU;
+
+use crate::{LogLevel, RenderInfo, Renderer, RendererError, SpriteBatch, Texture2d, ColorSpace, SamplerOptions};
+
+/// the number of triangles in a circle
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// the batch behind `RenderInfo::draw2d`, created by its first call
+pub(crate) struct Draw2dState {
+    batch: SpriteBatch,
+    /// a 1x1 white texture so that untextured shapes can be in the same batch as textured ones
+    white: Texture2d,
+    /// `None` for canvas pixels
+    transform: Option<[[f32; 4]; 4]>,
+}
+
+impl Draw2dState {
+    /// the transform or, for canvas pixels, `ortho_pixels`
+    fn view_proj(&self, ortho_pixels: [f32; 16]) -> [[f32; 4]; 4] {
+        self.transform.unwrap_or_else(|| std::array::from_fn(|column| std::array::from_fn(|row| ortho_pixels[column * 4 + row])))
+    }
+}
+
+/// immediate-mode 2d drawing from `on_render`, see `RenderInfo::draw2d`
+///
+/// the shapes are batched and drawn together (one draw call per run of shapes with the same texture)
+/// when the transform changes, on `flush` or after `on_render` returns
+pub struct Draw2d<'r, 'a, S: 'static> {
+    render_info: &'r mut RenderInfo<'a, S>,
+}
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// immediate-mode drawing of rectangles, circles, lines and textures without setting up any
+    /// buffers or shaders:
+    /// ```ignore
+    /// let mut draw = render_info.draw2d()?;
+    /// draw.rect(10.0, 10.0, 200.0, 40.0, [0.2, 0.2, 0.2, 1.0]);
+    /// draw.circle(300.0, 200.0, 25.0, [1.0, 0.0, 0.0, 1.0]);
+    /// draw.line([0.0, 0.0], [100.0, 100.0], 2.0, [1.0; 4]);
+    /// draw.texture(&state.logo, [400.0, 10.0, 64.0, 64.0]);
+    /// ```
+    ///
+    /// coordinates are in canvas (drawing buffer) pixels with a top-left origin unless a transform is
+    /// set with `Draw2d::set_transform`. Everything drawn is flushed after `on_render` returns (into
+    /// whatever framebuffer is bound then) and the transform is reset for the next frame. Blending is
+    /// left as it is, see `set_blend_mode`
+    ///
+    /// errors if the batch's program, buffer or texture could not be created (on the first call)
+    pub fn draw2d(&mut self) -> Result<Draw2d<'_, 'a, S>, RendererError> {
+        if self.renderer.draw2d.is_none() {
+            let context = &self.renderer.context;
+            self.renderer.draw2d = Some(Draw2dState {
+                batch: SpriteBatch::new(context)?,
+                white: Texture2d::from_rgba8(context, 1, 1, &[255; 4], ColorSpace::Linear, &SamplerOptions::nearest())?,
+                transform: None,
+            });
+        }
+        Ok(Draw2d { render_info: self })
+    }
+}
+
+impl<'r, 'a, S> Draw2d<'r, 'a, S> {
+    fn state(&mut self) -> &mut Draw2dState {
+        self.render_info.renderer.draw2d.as_mut().unwrap()
+    }
+
+    /// a filled rectangle with its top-left at `(x, y)` (its min corner with a transform)
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: impl Into<[f32; 4]>) -> &mut Self {
+        let state = self.state();
+        state.batch.quad(state.white.texture(), [x, y, width, height], [0.0; 4], color);
+        self
+    }
+
+    /// a filled circle centered on `(x, y)`
+    pub fn circle(&mut self, x: f32, y: f32, radius: f32, color: impl Into<[f32; 4]>) -> &mut Self {
+        let color = color.into();
+        let state = self.state();
+        let point = |i: usize| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * TAU;
+            [x + radius * angle.cos(), y + radius * angle.sin(), 0.0, 0.0]
+        };
+        for i in 0..CIRCLE_SEGMENTS {
+            state.batch.triangle(state.white.texture(), [[x, y, 0.0, 0.0], point(i), point(i + 1)], color);
+        }
+        self
+    }
+
+    /// a line from `a` to `b` that is `width` across, with square ends at `a` and `b`
+    pub fn line(&mut self, a: [f32; 2], b: [f32; 2], width: f32, color: impl Into<[f32; 4]>) -> &mut Self {
+        let color = color.into();
+        let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+        let length = (dx * dx + dy * dy).sqrt();
+        if length == 0.0 {
+            return self;
+        }
+        let (nx, ny) = (-dy / length * width * 0.5, dx / length * width * 0.5);
+        let corners = [
+            [a[0] + nx, a[1] + ny, 0.0, 0.0],
+            [a[0] - nx, a[1] - ny, 0.0, 0.0],
+            [b[0] - nx, b[1] - ny, 0.0, 0.0],
+            [b[0] + nx, b[1] + ny, 0.0, 0.0],
+        ];
+        let state = self.state();
+        state.batch.triangle(state.white.texture(), [corners[0], corners[1], corners[2]], color);
+        state.batch.triangle(state.white.texture(), [corners[0], corners[2], corners[3]], color);
+        self
+    }
+
+    /// the whole of `texture` stretched over `rect` (`[x, y, width, height]`), upright either way
+    pub fn texture(&mut self, texture: &Texture2d, rect: [f32; 4]) -> &mut Self {
+        self.texture_tinted(texture, rect, [1.0; 4])
+    }
+
+    /// like `texture` but multiplied by `color`
+    pub fn texture_tinted(&mut self, texture: &Texture2d, rect: [f32; 4], color: impl Into<[f32; 4]>) -> &mut Self {
+        let state = self.state();
+        // textures are bottom up so with `y` going down the top of the rect is the top of the texture
+        let uv_rect = if state.transform.is_none() { [0.0, 1.0, 1.0, 0.0] } else { [0.0, 0.0, 1.0, 1.0] };
+        state.batch.quad(texture.texture(), rect, uv_rect, color);
+        self
+    }
+
+    /// routes the following shapes through `view_proj` (eg. `Camera2d::view_proj`, with `y` going up) instead of canvas pixels, or back to canvas pixels with `None`, flushing the
+    /// shapes drawn so far if it changes
+    ///
+    /// errors if flushing fails
+    pub fn set_transform(&mut self, view_proj: Option<[[f32; 4]; 4]>) -> Result<&mut Self, RendererError> {
+        if self.state().transform != view_proj {
+            self.flush()?;
+            self.state().transform = view_proj;
+        }
+        Ok(self)
+    }
+
+    /// draws the shapes drawn so far, eg. before drawing something else on top of them
+    ///
+    /// errors if the batch's draw fails
+    pub fn flush(&mut self) -> Result<&mut Self, RendererError> {
+        flush(self.render_info)?;
+        Ok(self)
+    }
+}
+
+/// draws the shapes in the renderer's `Draw2dState`
+fn flush<S>(render_info: &mut RenderInfo<S>) -> Result<(), RendererError> {
+    let Some(mut state) = render_info.renderer.draw2d.take() else { return Ok(()) };
+    let result = if state.batch.is_empty() {
+        Ok(())
+    } else {
+        let view_proj = state.view_proj(render_info.ortho_pixels());
+        state.batch.draw(render_info, view_proj)
+    };
+    render_info.renderer.draw2d = Some(state);
+    result
+}
+
+impl<S> Renderer<S> {
+    /// draws anything left in `RenderInfo::draw2d`'s batch and resets its transform, after `on_render`
+    pub(crate) fn flush_draw2d(&mut self) {
+        if self.draw2d.as_ref().is_none_or(|state| state.batch.is_empty() && state.transform.is_none()) {
+            return;
+        }
+        let state = self.state.clone();
+        let mut state = state.get().unwrap().borrow_mut();
+        let mut render_info = RenderInfo { state: &mut *state, renderer: self };
+        if let Err(err) = flush(&mut render_info) {
+            self.log(LogLevel::Errors, || format!("drawing draw2d's shapes: {}", err));
+        }
+        if let Some(draw2d) = &mut self.draw2d {
+            draw2d.transform = None;
+        }
+    }
+}
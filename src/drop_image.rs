@@ -0,0 +1,77 @@
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{DragEvent, File, ImageBitmap};
+
+use crate::{LogLevel, Renderer, RendererError, Texture2d, ColorSpace, SamplerOptions};
+use crate::console::log_at;
+
+pub(crate) type OnDropImage<S> = fn(&mut S, File, LoadedTexture);
+
+/// an image file that was dropped on the canvas, see `Renderer::with_on_drop_image`
+pub struct LoadedTexture {
+    /// the decoded image as an sRGB, linearly filtered and mipmapped texture
+    pub texture: Texture2d,
+    pub width: u32,
+    pub height: u32,
+    /// the file's name
+    pub name: String,
+}
+
+impl<S> Renderer<S> {
+    /// loads image files that are dropped on the canvas into textures and passes them to `on_drop_image`,
+    /// eg. for previewing textures in a tool:
+    /// ```ignore
+    /// renderer = renderer.with_on_drop_image(|state, _file, loaded| state.preview = Some(loaded.texture))?;
+    /// ```
+    /// files that aren't images are ignored, images are decoded in the background (with `spawn_local`)
+    /// and errors decoding them are logged (see `with_log_level`)
+    ///
+    /// also prevents the default of `dragover` so that the browser allows dropping on the canvas (and
+    /// of `drop` so that it doesn't open the file instead)
+    ///
+    /// returns self for chaining
+    ///
+    /// errors if the event listeners could not be added or the renderer is headless
+    pub fn with_on_drop_image(self, on_drop_image: OnDropImage<S>) -> Result<Self, RendererError> {
+        let rc_state = self.state.clone();
+        let context = self.context.clone();
+        let log_level = self.log_level.clone();
+        self.add_event_listener("dragover", false, 0, Box::new(|_, event| {
+            if let Some(event) = event.dyn_ref::<DragEvent>() {
+                event.prevent_default();
+            }
+        }))?.add_event_listener("drop", false, 0, Box::new(move |_, event| {
+            let Some(event) = event.dyn_ref::<DragEvent>() else { return };
+            event.prevent_default();
+            let Some(files) = event.data_transfer().and_then(|data_transfer| data_transfer.files()) else { return };
+            for file in (0..files.length()).filter_map(|i| files.get(i)) {
+                if !file.type_().starts_with("image/") { continue }
+                let rc_state = rc_state.clone();
+                let context = context.clone();
+                let log_level = log_level.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    match load_image_file(&context, &file).await {
+                        Ok(loaded) => if let Some(state) = rc_state.get() {
+                            on_drop_image(&mut state.borrow_mut(), file, loaded);
+                        },
+                        Err(error) => log_at(log_level.get(), LogLevel::Errors, || format!("failed to load dropped image `{}`: {}", file.name(), error)),
+                    }
+                });
+            }
+        }))
+    }
+}
+
+async fn load_image_file(context: &web_sys::WebGl2RenderingContext, file: &File) -> Result<LoadedTexture, RendererError> {
+    let window = web_sys::window().ok_or(RendererError::Unsupported("window"))?;
+    let image: ImageBitmap = JsFuture::from(window.create_image_bitmap_with_blob(file)?).await?.unchecked_into();
+    let texture = Texture2d::from_image_bitmap(context, &image, ColorSpace::Srgb, &SamplerOptions::default());
+    image.close();
+    let texture = texture?;
+    Ok(LoadedTexture {
+        width: texture.width(),
+        height: texture.height(),
+        name: file.name(),
+        texture,
+    })
+}
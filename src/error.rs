@@ -0,0 +1,99 @@
+use wasm_bindgen::{JsCast, JsValue};
+use std::fmt;
+
+/// the error type returned by all fallible renderer operations
+#[derive(Debug)]
+pub enum RendererError {
+    /// an exception thrown by a browser api
+    Js(JsValue),
+    /// the vertex shader failed to compile, contains the info log
+    VertexShader(String),
+    /// the fragment shader failed to compile, contains the info log
+    FragmentShader(String),
+    /// the program failed to link, contains the info log
+    Link(String),
+    /// a callback that can only be set once has already been set, contains the callback's name
+    AlreadySet(&'static str),
+    /// a webgl object could not be created, contains the object's kind (eg. "texture")
+    Create(&'static str),
+    /// a texture was given invalid dimensions or data
+    Texture(String),
+    /// a required webgl extension is not supported, contains the extension's name
+    MissingExtension(&'static str),
+    /// a framebuffer could not be completed
+    Framebuffer(String),
+    /// the operation requires a canvas element but the renderer is headless
+    Headless,
+    /// a browser api is not supported, contains the api's name
+    Unsupported(&'static str),
+    /// `Renderer::with_on_render_async` was called, which isn't possible as renders can't be awaited
+    AsyncRender,
+    /// a named item could not be found, contains the item's kind (eg. "uniform block") and name
+    Unknown(&'static str, String),
+    /// a request didn't succeed, contains the url and the http status
+    Fetch(String, u16),
+    /// an operation took too long, contains what it was
+    Timeout(&'static str),
+    /// the context wasn't created with an attribute that the operation needs, contains the attribute's name
+    ContextAttribute(&'static str),
+    /// a file couldn't be parsed, contains what kind of file it was (eg. "atlas json") and why
+    Parse(&'static str, String),
+    /// the browser couldn't create a webgl2 context, either because it doesn't support webgl2 or
+    /// because it is disabled (eg. by a blocklisted gpu driver)
+    WebGl2Unavailable,
+    /// the canvas returned a context that isn't a `WebGL2RenderingContext`
+    ContextCastFailed,
+    /// a callback panicked, contains the panic's message, see `Renderer::with_on_error`
+    Panic(String),
+    /// there is no program in use to set uniforms of, see `RenderInfo::set_uniform_cached`
+    NoProgram,
+}
+
+impl fmt::Display for RendererError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RendererError::Js(value) => write!(f, "javascript error: {:?}", value),
+            RendererError::VertexShader(log) => write!(f, "vertex shader: {}", log),
+            RendererError::FragmentShader(log) => write!(f, "fragment shader: {}", log),
+            RendererError::Link(log) => write!(f, "program linking: {}", log),
+            RendererError::AlreadySet(name) => write!(f, "`{}` has already been set", name),
+            RendererError::Create(kind) => write!(f, "unable to create {} object", kind),
+            RendererError::Texture(msg) => write!(f, "texture: {}", msg),
+            RendererError::MissingExtension(name) => write!(f, "the `{}` extension is not supported", name),
+            RendererError::Framebuffer(msg) => write!(f, "framebuffer: {}", msg),
+            RendererError::Headless => write!(f, "the renderer is headless (has no canvas element)"),
+            RendererError::Unsupported(name) => write!(f, "`{}` is not supported by this browser", name),
+            RendererError::AsyncRender => write!(f, "on_render can't be async as the frame would be presented before the future completed, do the async work in on_update_async and render its result from the state instead"),
+            RendererError::Unknown(kind, name) => write!(f, "unknown {} `{}`", kind, name),
+            RendererError::Fetch(url, status) => write!(f, "fetching `{}` failed with status {}", url, status),
+            RendererError::Timeout(what) => write!(f, "timed out {}", what),
+            RendererError::ContextAttribute(name) => write!(f, "the context was not created with `{}`", name),
+            RendererError::Parse(kind, msg) => write!(f, "parsing {}: {}", kind, msg),
+            RendererError::WebGl2Unavailable => write!(f, "WebGL2 is not supported in your browser, check that it is up to date and that hardware acceleration is enabled"),
+            RendererError::ContextCastFailed => write!(f, "the canvas' webgl2 context is not a WebGL2RenderingContext"),
+            RendererError::Panic(message) => write!(f, "a callback panicked: {}", message),
+            RendererError::NoProgram => write!(f, "no program is in use, link one with `with_shaders` or choose one with `use_shader`"),
+        }
+    }
+}
+
+impl std::error::Error for RendererError {}
+
+impl From<JsValue> for RendererError {
+    fn from(value: JsValue) -> Self {
+        RendererError::Js(value)
+    }
+}
+
+/// converts to a javascript `Error` with the error's message, so that returning a `RendererError`
+/// from a `#[wasm_bindgen]` function with `?` throws something readable
+///
+/// errors thrown by browser apis are passed through unchanged if they are already an `Error`
+impl From<RendererError> for JsValue {
+    fn from(error: RendererError) -> Self {
+        match error {
+            RendererError::Js(value) if value.is_instance_of::<js_sys::Error>() => value,
+            error => js_sys::Error::new(&error.to_string()).into(),
+        }
+    }
+}
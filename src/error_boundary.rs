@@ -0,0 +1,104 @@
+use wasm_bindgen::{JsCast, JsValue, closure::Closure};
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::rc::Rc;
+
+use crate::{LogLevel, Renderer, RendererError};
+
+pub(crate) type OnError = fn(RendererError) -> ErrorAction;
+
+/// what the game loop does after `on_error` has handled an error, see `Renderer::with_on_error`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorAction {
+    /// keeps running as if nothing happened
+    Continue,
+    /// stops updating and rendering (but keeps the loop and event listeners alive) until the canvas
+    /// is next pressed
+    Pause,
+    /// exits the game loop
+    Exit,
+}
+
+/// the message of a panic's payload
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload.downcast_ref::<&str>().map_or_else(|| String::from("unknown panic"), |message| message.to_string()),
+    }
+}
+
+impl<S> Renderer<S> {
+    /// sets an error boundary around each frame's callbacks (`on_update`, the update systems and
+    /// `on_render`, outside of webxr sessions), so that a panic or javascript exception escaping them
+    /// is passed to `on_error` instead of silently killing the loop:
+    /// ```ignore
+    /// renderer = renderer.with_on_error(|err| {
+    ///     web_sys::console::error_1(&err.to_string().into());
+    ///     if matches!(err, RendererError::Panic(_)) { ErrorAction::Pause } else { ErrorAction::Exit }
+    /// })?;
+    /// ```
+    ///
+    /// panics are only caught (as `RendererError::Panic`) when they unwind, wasm defaults to
+    /// `panic = "abort"` where a panic is a trap, which is caught like an exception (as
+    /// `RendererError::Js`). An exception or trap leaves the state borrowed by the callback that was
+    /// running so the loop always exits after `on_error` handles one
+    ///
+    /// returns self for chaining
+    ///
+    /// errors if `on_error` has already been set or the listener that resumes a pause could not be added
+    pub fn with_on_error(mut self, on_error: OnError) -> Result<Self, RendererError> {
+        self.on_error.set(on_error).map_err(|_| RendererError::AlreadySet("on_error"))?;
+        if !self.is_headless() {
+            let paused = self.paused.clone();
+            self = self.add_event_listener("pointerdown", false, i32::MIN, Box::new(move |_, _| paused.set(false)))?;
+        }
+        Ok(self)
+    }
+
+    /// whether the loop is paused by `ErrorAction::Pause`
+    pub fn is_paused(&self) -> bool {
+        self.paused.get()
+    }
+
+    /// runs `f` in the error boundary if `on_error` is set, otherwise just runs it
+    pub(crate) fn run_guarded(&mut self, f: fn(&mut Self)) {
+        let Some(&on_error) = self.on_error.get() else { return f(self) };
+
+        let renderer: *mut Self = self;
+        let panic = Rc::new(RefCell::new(None));
+        let rc_panic = panic.clone();
+        let ran = Rc::new(Cell::new(false));
+        let rc_ran = ran.clone();
+        // `call0` catches javascript exceptions (and traps), panics have to be caught before they
+        // unwind into javascript
+        let closure = Closure::<dyn FnMut()>::new(move || {
+            if rc_ran.replace(true) {
+                return;
+            }
+            // SAFETY: the closure is only called once, synchronously, while `self` is mutably borrowed
+            // by `run_guarded` and not otherwise used
+            if let Err(payload) = catch_unwind(AssertUnwindSafe(|| f(unsafe { &mut *renderer }))) {
+                *rc_panic.borrow_mut() = Some(panic_message(payload));
+            }
+        });
+        let thrown = closure.as_ref().unchecked_ref::<js_sys::Function>().call0(&JsValue::UNDEFINED).err();
+        drop(closure);
+
+        let error = match (panic.take(), thrown) {
+            (Some(message), _) => RendererError::Panic(message),
+            (None, Some(exception)) => RendererError::Js(exception),
+            (None, None) => return,
+        };
+        let state_poisoned = self.state.get().is_some_and(|state| state.try_borrow_mut().is_err());
+        match on_error(error) {
+            _ if state_poisoned => {
+                self.log(LogLevel::Errors, || String::from("exiting the game loop as the state is still borrowed by the callback that failed"));
+                self.exit = true;
+            }
+            ErrorAction::Continue => {}
+            ErrorAction::Pause => self.paused.set(true),
+            ErrorAction::Exit => self.exit = true,
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use web_sys::WebGl2RenderingContext;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::PipelineState;
+
+const MAX_TEXTURE_MAX_ANISOTROPY_EXT: u32 = 0x84FF;
+
+/// what has been queried about (or set on) a context
+#[derive(Default)]
+pub(crate) struct ContextCache {
+    extensions: HashMap<String, bool>,
+    /// `None` until queried, then `Some(None)` if anisotropic filtering is unsupported
+    max_anisotropy: Option<Option<f32>>,
+    /// the state last applied with `PipelineState::apply`, `None` if it is unknown
+    pub(crate) pipeline_state: Option<PipelineState>,
+}
+
+thread_local! {
+    /// the cache for each context that has been queried (there is usually only one), removed by
+    /// `evict_cache` when its renderer is dropped
+    static CACHES: RefCell<Vec<(WebGl2RenderingContext, ContextCache)>> = const { RefCell::new(Vec::new()) };
+}
+
+pub(crate) fn with_cache<R>(context: &WebGl2RenderingContext, f: impl FnOnce(&mut ContextCache) -> R) -> R {
+    CACHES.with(|caches| {
+        let mut caches = caches.borrow_mut();
+        let index = match caches.iter().position(|(cached_context, _)| cached_context == context) {
+            Some(index) => index,
+            None => {
+                caches.push((context.clone(), ContextCache::default()));
+                caches.len() - 1
+            }
+        };
+        f(&mut caches[index].1)
+    })
+}
+
+/// forgets the cache of `context` so that the context itself isn't kept alive, called when the
+/// renderer that owns it is dropped
+pub(crate) fn evict_cache(context: &WebGl2RenderingContext) {
+    CACHES.with(|caches| caches.borrow_mut().retain(|(cached_context, _)| cached_context != context));
+}
+
+/// returns whether the extension `name` is supported by `context`, enabling it if it is
+/// (webgl extensions must be enabled with `getExtension` before their constants are accepted)
+///
+/// results are cached per context so this is cheap to call repeatedly
+pub fn has_extension(context: &WebGl2RenderingContext, name: &str) -> bool {
+    with_cache(context, |cache| {
+        *cache.extensions
+            .entry(name.to_owned())
+            .or_insert_with(|| matches!(context.get_extension(name), Ok(Some(_))))
+    })
+}
+
+/// the maximum anisotropy supported by `EXT_texture_filter_anisotropic`, or `None` if it is unsupported
+///
+/// cached per context like `has_extension`
+pub(crate) fn max_anisotropy(context: &WebGl2RenderingContext) -> Option<f32> {
+    if !has_extension(context, "EXT_texture_filter_anisotropic") {
+        return None;
+    }
+    with_cache(context, |cache| {
+        *cache.max_anisotropy.get_or_insert_with(|| {
+            context.get_parameter(MAX_TEXTURE_MAX_ANISOTROPY_EXT).ok()?.as_f64().map(|max| max as f32)
+        })
+    })
+}
@@ -0,0 +1,70 @@
+use web_sys::{WebGl2RenderingContext, WebGlSync};
+use std::time::Duration;
+
+use crate::RendererError;
+
+type Gl = WebGl2RenderingContext;
+
+/// a fence sync object that is signaled once the gpu has finished all commands issued before it,
+/// used to tell when async work (eg. a readback into a pixel pack buffer) can be consumed without stalling
+///
+/// ```ignore
+/// context.read_pixels_with_i32(...); // into a PIXEL_PACK_BUFFER
+/// let fence = GpuFence::insert(context)?;
+/// ...
+/// // in a later frame
+/// if fence.is_signaled() {
+///     context.get_buffer_sub_data_with_i32_and_u8_array(...);
+/// }
+/// ```
+pub struct GpuFence {
+    context: WebGl2RenderingContext,
+    sync: WebGlSync,
+}
+
+impl GpuFence {
+    /// inserts a fence after all previously issued commands
+    ///
+    /// errors if the sync object could not be created
+    pub fn insert(context: &WebGl2RenderingContext) -> Result<GpuFence, RendererError> {
+        let sync = context.fence_sync(Gl::SYNC_GPU_COMMANDS_COMPLETE, 0).ok_or(RendererError::Create("sync"))?;
+        // without a flush the fence may never reach the gpu and so never be signaled
+        context.flush();
+        Ok(GpuFence {
+            context: context.clone(),
+            sync,
+        })
+    }
+
+    /// whether the gpu has reached the fence, without blocking
+    ///
+    /// webgl only updates sync status between tasks, so this will not become true within the frame the
+    /// fence was inserted in
+    pub fn is_signaled(&self) -> bool {
+        self.context.get_sync_parameter(&self.sync, Gl::SYNC_STATUS).as_f64() == Some(Gl::SIGNALED as f64)
+    }
+
+    /// blocks until the gpu has reached the fence or `timeout` has passed, returning whether it was reached
+    ///
+    /// `timeout` is clamped to `MAX_CLIENT_WAIT_TIMEOUT_WEBGL`, which is 0 in most browsers, so this is
+    /// mostly an escape hatch for when the result is needed immediately regardless of the stall
+    pub fn wait_blocking(&self, timeout: Duration) -> bool {
+        let max_timeout = self.context.get_parameter(Gl::MAX_CLIENT_WAIT_TIMEOUT_WEBGL)
+            .ok()
+            .and_then(|value| value.as_f64())
+            .unwrap_or(0.0);
+        let timeout = (timeout.as_nanos() as f64).min(max_timeout);
+        let status = self.context.client_wait_sync_with_f64(&self.sync, Gl::SYNC_FLUSH_COMMANDS_BIT, timeout);
+        status == Gl::ALREADY_SIGNALED || status == Gl::CONDITION_SATISFIED
+    }
+
+    pub fn sync(&self) -> &WebGlSync {
+        &self.sync
+    }
+}
+
+impl Drop for GpuFence {
+    fn drop(&mut self) {
+        self.context.delete_sync(Some(&self.sync));
+    }
+}
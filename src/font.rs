@@ -0,0 +1,349 @@
+use wasm_bindgen::JsValue;
+use web_sys::WebGlTexture;
+use js_sys::Array;
+use std::collections::HashMap;
+
+use crate::{RendererError, SpriteBatch, Texture2d};
+use crate::atlas::get;
+
+/// how the glyphs of a `Font` are stored in its texture
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FontKind {
+    /// the glyphs' coverage, drawn by tinting the texture like any other sprite
+    Bitmap,
+    /// a (multi-channel) signed distance field whose distances span `distance_range` texels, drawn with
+    /// `SpriteBatch::msdf_quad` so that it stays sharp at any size. Single-channel fields work too
+    Msdf { distance_range: f32 },
+}
+
+/// where a glyph is in a `Font`'s texture and how it is placed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glyph {
+    /// `[left, bottom, right, top]` of the glyph's quad relative to the pen on the baseline (with `y`
+    /// going up) in pixels at the font's size
+    pub plane_bounds: [f32; 4],
+    /// `[min_u, min_v, max_u, max_v]` in the font's texture
+    pub uv_rect: [f32; 4],
+    /// how far the pen moves after the glyph
+    pub advance: f32,
+}
+
+/// the glyphs of a font packed into a texture, read from a BMFont json description (eg. from
+/// msdf-bmfont-xml) or an msdf-atlas-gen json description:
+/// ```ignore
+/// let font = Font::load(context, "fonts/roboto.json", "fonts/roboto.png").await?;
+/// let text = Text::layout(&font, "Score: 100", 24.0, None);
+/// ...
+/// // in `on_render`
+/// text.draw(&mut sprites, [10.0, 590.0], [1.0; 4]);
+/// sprites.draw(&mut render_info, view_proj)?;
+/// ```
+pub struct Font {
+    texture: Texture2d,
+    kind: FontKind,
+    size: f32,
+    line_height: f32,
+    ascender: f32,
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), f32>,
+}
+
+impl Font {
+    /// reads the glyphs from `json`, with `texture` being the font's (single page) image loaded with
+    /// `Texture2d::from_image_bitmap` so that the glyphs' top-left origin lines up. A distance field
+    /// texture should be loaded with `ColorSpace::Linear`
+    ///
+    /// the format is detected from the json: BMFont's has `chars` and msdf-atlas-gen's has `glyphs`
+    ///
+    /// errors if the json can't be parsed or is missing a required field
+    pub fn from_json(json: &str, texture: Texture2d) -> Result<Font, RendererError> {
+        let root = js_sys::JSON::parse(json).map_err(|_| parse_error("invalid json".to_owned()))?;
+        if get(&root, "chars").is_some() {
+            Self::from_bmfont(&root, texture)
+        } else if get(&root, "glyphs").is_some() {
+            Self::from_msdf_atlas_gen(&root, texture)
+        } else {
+            Err(parse_error("missing `chars` (BMFont) or `glyphs` (msdf-atlas-gen)".to_owned()))
+        }
+    }
+
+    fn from_bmfont(root: &JsValue, texture: Texture2d) -> Result<Font, RendererError> {
+        let common = get(root, "common").ok_or_else(|| parse_error("missing `common`".to_owned()))?;
+        let line_height = number(&common, "lineHeight")? as f32;
+        let ascender = number(&common, "base")? as f32;
+        // some generators give the size as negative to mean it's in pixels rather than points
+        let size = get(root, "info").and_then(|info| get(&info, "size")).and_then(|size| size.as_f64())
+            .map_or(line_height, |size| size.abs() as f32);
+        let kind = match get(root, "distanceField") {
+            Some(field) => FontKind::Msdf { distance_range: number(&field, "distanceRange")? as f32 },
+            None => FontKind::Bitmap,
+        };
+
+        let (width, height) = (texture.width() as f32, texture.height() as f32);
+        let mut glyphs = HashMap::new();
+        for char in array(root, "chars")? {
+            let Some(character) = char::from_u32(number(&char, "id")? as u32) else { continue };
+            let [x, y, w, h, x_offset, y_offset, advance] = ["x", "y", "width", "height", "xoffset", "yoffset", "xadvance"]
+                .map(|key| number(&char, key).map(|value| value as f32));
+            let (x, y, w, h, x_offset, y_offset) = (x?, y?, w?, h?, x_offset?, y_offset?);
+            let top = ascender - y_offset;
+            glyphs.insert(character, Glyph {
+                plane_bounds: [x_offset, top - h, x_offset + w, top],
+                // the image has a top-left origin but the texture was flipped
+                uv_rect: [x / width, 1.0 - (y + h) / height, (x + w) / width, 1.0 - y / height],
+                advance: advance?,
+            });
+        }
+
+        let mut kerning = HashMap::new();
+        for pair in array(root, "kernings").unwrap_or_default() {
+            let [first, second] = ["first", "second"].map(|key| number(&pair, key).ok().and_then(|id| char::from_u32(id as u32)));
+            if let (Some(first), Some(second)) = (first, second) {
+                kerning.insert((first, second), number(&pair, "amount")? as f32);
+            }
+        }
+        Ok(Font { texture, kind, size, line_height, ascender, glyphs, kerning })
+    }
+
+    fn from_msdf_atlas_gen(root: &JsValue, texture: Texture2d) -> Result<Font, RendererError> {
+        let atlas = get(root, "atlas").ok_or_else(|| parse_error("missing `atlas`".to_owned()))?;
+        let metrics = get(root, "metrics").ok_or_else(|| parse_error("missing `metrics`".to_owned()))?;
+        // metrics are in ems, `size` is the pixels per em of the atlas
+        let size = number(&atlas, "size")? as f32;
+        let kind = match get(&atlas, "type").and_then(|kind| kind.as_string()).as_deref() {
+            Some("hardmask" | "softmask") => FontKind::Bitmap,
+            _ => FontKind::Msdf { distance_range: number(&atlas, "distanceRange")? as f32 },
+        };
+        let y_origin_top = get(&atlas, "yOrigin").and_then(|origin| origin.as_string()).as_deref() == Some("top");
+
+        let (width, height) = (texture.width() as f32, texture.height() as f32);
+        let mut glyphs = HashMap::new();
+        for glyph in array(root, "glyphs")? {
+            let Some(character) = char::from_u32(number(&glyph, "unicode")? as u32) else { continue };
+            let bounds = |key| get(&glyph, key).map(|bounds| {
+                ["left", "bottom", "right", "top"].map(|side| number(&bounds, side).map(|value| value as f32))
+            });
+            // whitespace has no bounds
+            let plane_bounds = match bounds("planeBounds") {
+                Some([left, bottom, right, top]) => [left? * size, bottom? * size, right? * size, top? * size],
+                None => [0.0; 4],
+            };
+            let uv_rect = match bounds("atlasBounds") {
+                Some([left, bottom, right, top]) => {
+                    let (left, bottom, right, top) = (left? / width, bottom? / height, right? / width, top? / height);
+                    // the texture was flipped so a top origin has to be flipped back
+                    if y_origin_top { [left, 1.0 - bottom, right, 1.0 - top] } else { [left, bottom, right, top] }
+                }
+                None => [0.0; 4],
+            };
+            glyphs.insert(character, Glyph { plane_bounds, uv_rect, advance: number(&glyph, "advance")? as f32 * size });
+        }
+
+        let mut kerning = HashMap::new();
+        for pair in array(root, "kerning").unwrap_or_default() {
+            let [first, second] = ["unicode1", "unicode2"].map(|key| number(&pair, key).ok().and_then(|id| char::from_u32(id as u32)));
+            if let (Some(first), Some(second)) = (first, second) {
+                kerning.insert((first, second), number(&pair, "advance")? as f32 * size);
+            }
+        }
+        Ok(Font {
+            texture,
+            kind,
+            size,
+            line_height: number(&metrics, "lineHeight")? as f32 * size,
+            ascender: number(&metrics, "ascender")? as f32 * size,
+            glyphs,
+            kerning,
+        })
+    }
+
+    /// downloads a font's json description and image, see `from_json`
+    ///
+    /// the image is loaded with `ColorSpace::Linear` and linear filtering
+    ///
+    /// errors if either download fails or the json is invalid
+    #[cfg(feature = "assets")]
+    pub async fn load(context: &web_sys::WebGl2RenderingContext, json_url: &str, image_url: &str) -> Result<Font, RendererError> {
+        let json = crate::assets::fetch_text(json_url).await?;
+        let texture = crate::assets::fetch_texture(context, image_url, crate::ColorSpace::Linear, &crate::SamplerOptions::default()).await?;
+        Self::from_json(&json, texture)
+    }
+
+    pub fn glyph(&self, character: char) -> Option<Glyph> {
+        self.glyphs.get(&character).copied()
+    }
+    /// the extra advance between `first` and `second` when they are next to each other, in pixels at
+    /// the font's size
+    pub fn kerning(&self, first: char, second: char) -> f32 {
+        self.kerning.get(&(first, second)).copied().unwrap_or(0.0)
+    }
+    /// the size in pixels that the glyphs were rendered at
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+    /// the distance between baselines in pixels at the font's size
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+    /// the distance from the top of a line to its baseline in pixels at the font's size
+    pub fn ascender(&self) -> f32 {
+        self.ascender
+    }
+    pub fn kind(&self) -> FontKind {
+        self.kind
+    }
+    pub fn texture(&self) -> &Texture2d {
+        &self.texture
+    }
+
+    /// the `(width, height)` that `text` would take up, see `Text::layout`
+    pub fn measure(&self, text: &str, size: f32, max_width: Option<f32>) -> (f32, f32) {
+        Text::layout(self, text, size, max_width).size()
+    }
+}
+
+/// a glyph's quad in a `Text`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionedGlyph {
+    pub character: char,
+    /// `[x, y, width, height]` relative to the top-left of the text
+    pub dst: [f32; 4],
+    pub uv_rect: [f32; 4],
+}
+
+/// text laid out with a `Font` into positioned glyph quads, ready to be added to a `SpriteBatch`
+///
+/// lines go down from the top-left of the text with `y` going up (like `SpriteBatch`), so every glyph
+/// has a negative `y`
+pub struct Text {
+    texture: WebGlTexture,
+    glyphs: Vec<PositionedGlyph>,
+    width: f32,
+    height: f32,
+    /// `None` for a bitmap font
+    screen_px_range: Option<f32>,
+}
+
+impl Text {
+    /// lays out `text` with glyphs `size` pixels tall (the font scaled by `size / font.size()`),
+    /// applying kerning and breaking lines at `\n` and, if `max_width` is given, between words so that
+    /// lines aren't wider than it. A word wider than `max_width` is put on its own line
+    pub fn layout(font: &Font, text: &str, size: f32, max_width: Option<f32>) -> Text {
+        let scale = size / font.size;
+        let space = font.glyph(' ').map_or(0.0, |glyph| glyph.advance) * scale;
+        let width_of = |word: &str| {
+            let mut previous = None;
+            word.chars().map(|character| {
+                let kerning = previous.map_or(0.0, |previous| font.kerning(previous, character));
+                previous = Some(character);
+                (kerning + font.glyph(character).map_or(0.0, |glyph| glyph.advance)) * scale
+            }).sum::<f32>()
+        };
+
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let mut line = String::new();
+            let mut line_width = 0.0;
+            for (i, word) in paragraph.split(' ').enumerate() {
+                let word_width = width_of(word);
+                if i == 0 {
+                    (line, line_width) = (word.to_owned(), word_width);
+                } else if max_width.is_some_and(|max_width| line_width + space + word_width > max_width) && !line.is_empty() {
+                    lines.push(std::mem::replace(&mut line, word.to_owned()));
+                    line_width = word_width;
+                } else {
+                    line.push(' ');
+                    line.push_str(word);
+                    line_width += space + word_width;
+                }
+            }
+            lines.push(line);
+        }
+
+        let line_height = font.line_height * scale;
+        let mut glyphs = Vec::new();
+        let mut width: f32 = 0.0;
+        for (i, line) in lines.iter().enumerate() {
+            let baseline = -font.ascender * scale - i as f32 * line_height;
+            let mut pen = 0.0;
+            let mut previous = None;
+            for character in line.chars() {
+                pen += previous.map_or(0.0, |previous| font.kerning(previous, character)) * scale;
+                previous = Some(character);
+                let Some(glyph) = font.glyph(character) else { continue };
+                let [left, bottom, right, top] = glyph.plane_bounds.map(|bound| bound * scale);
+                if right > left && top > bottom {
+                    glyphs.push(PositionedGlyph {
+                        character,
+                        dst: [pen + left, baseline + bottom, right - left, top - bottom],
+                        uv_rect: glyph.uv_rect,
+                    });
+                }
+                pen += glyph.advance * scale;
+            }
+            width = width.max(pen);
+        }
+
+        Text {
+            texture: font.texture.texture().clone(),
+            glyphs,
+            width,
+            height: lines.len() as f32 * line_height,
+            screen_px_range: match font.kind {
+                FontKind::Bitmap => None,
+                // assumes that a pixel of `size` is a pixel on screen
+                FontKind::Msdf { distance_range } => Some((distance_range * scale).max(1.0)),
+            },
+        }
+    }
+
+    pub fn glyphs(&self) -> &[PositionedGlyph] {
+        &self.glyphs
+    }
+    /// the width of the widest line
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+    /// the number of lines times the line height
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+    pub fn size(&self) -> (f32, f32) {
+        (self.width, self.height)
+    }
+
+    /// sets the `screen_px_range` passed to `SpriteBatch::msdf_quad`, which defaults to the font's
+    /// distance range scaled to the text's size, for when the text is drawn larger or smaller than
+    /// that on screen (eg. with a zoomed camera). Does nothing for a bitmap font
+    pub fn set_screen_px_range(&mut self, screen_px_range: f32) {
+        if let Some(range) = &mut self.screen_px_range {
+            *range = screen_px_range;
+        }
+    }
+
+    /// adds every glyph's quad to `batch` with the top-left of the text at `position`, tinted (or for a
+    /// distance field, colored) by `color`
+    pub fn draw(&self, batch: &mut SpriteBatch, position: [f32; 2], color: [f32; 4]) {
+        for glyph in &self.glyphs {
+            let [x, y, width, height] = glyph.dst;
+            let dst = [position[0] + x, position[1] + y, width, height];
+            match self.screen_px_range {
+                Some(screen_px_range) => batch.msdf_quad(&self.texture, dst, glyph.uv_rect, color, screen_px_range),
+                None => batch.quad(&self.texture, dst, glyph.uv_rect, color),
+            }
+        }
+    }
+}
+
+fn parse_error(msg: String) -> RendererError {
+    RendererError::Parse("font json", msg)
+}
+
+fn number(object: &JsValue, key: &str) -> Result<f64, RendererError> {
+    get(object, key).and_then(|value| value.as_f64()).ok_or_else(|| parse_error(format!("missing `{}`", key)))
+}
+
+fn array(object: &JsValue, key: &str) -> Result<Vec<JsValue>, RendererError> {
+    get(object, key).filter(Array::is_array).map(|value| Array::from(&value).to_vec())
+        .ok_or_else(|| parse_error(format!("missing `{}`", key)))
+}
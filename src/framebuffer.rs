@@ -0,0 +1,122 @@
+use wasm_bindgen::JsCast;
+use web_sys::{WebGl2RenderingContext, WebGlFramebuffer};
+
+use crate::{RenderInfo, RenderTarget};
+
+type Gl = WebGl2RenderingContext;
+
+/// a framebuffer that can be bound with `RenderInfo::bind_framebuffer`
+pub trait Framebuffer {
+    fn framebuffer(&self) -> &WebGlFramebuffer;
+    /// the size of the framebuffer's attachments in pixels
+    fn size(&self) -> (u32, u32);
+}
+
+impl Framebuffer for RenderTarget {
+    fn framebuffer(&self) -> &WebGlFramebuffer {
+        self.framebuffer()
+    }
+    fn size(&self) -> (u32, u32) {
+        (self.width(), self.height())
+    }
+}
+
+/// how a blit is filtered when the source and destination are different sizes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlitFilter {
+    Nearest,
+    Linear,
+}
+impl BlitFilter {
+    pub fn to_gl(self) -> u32 {
+        match self {
+            BlitFilter::Nearest => Gl::NEAREST,
+            BlitFilter::Linear => Gl::LINEAR,
+        }
+    }
+}
+
+/// the framebuffer bound to `pname` (eg. `READ_FRAMEBUFFER_BINDING`), `None` for the default framebuffer
+pub(crate) fn framebuffer_binding(context: &WebGl2RenderingContext, pname: u32) -> Option<WebGlFramebuffer> {
+    context.get_parameter(pname).ok()
+        .and_then(|value| value.dyn_into::<WebGlFramebuffer>().ok())
+}
+
+/// copies the buffers in `mask` from the whole of `src` to the whole of `dst`, restoring the read and
+/// draw framebuffer bindings afterwards
+fn blit(context: &WebGl2RenderingContext, src: &impl Framebuffer, dst: &impl Framebuffer, mask: u32, filter: BlitFilter) {
+    let previous_read = framebuffer_binding(context, Gl::READ_FRAMEBUFFER_BINDING);
+    let previous_draw = framebuffer_binding(context, Gl::DRAW_FRAMEBUFFER_BINDING);
+
+    let (src_width, src_height) = src.size();
+    let (dst_width, dst_height) = dst.size();
+    context.bind_framebuffer(Gl::READ_FRAMEBUFFER, Some(src.framebuffer()));
+    context.bind_framebuffer(Gl::DRAW_FRAMEBUFFER, Some(dst.framebuffer()));
+    context.blit_framebuffer(
+        0, 0, src_width as i32, src_height as i32,
+        0, 0, dst_width as i32, dst_height as i32,
+        mask, filter.to_gl(),
+    );
+
+    context.bind_framebuffer(Gl::READ_FRAMEBUFFER, previous_read.as_ref());
+    context.bind_framebuffer(Gl::DRAW_FRAMEBUFFER, previous_draw.as_ref());
+}
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// copies (and scales) the color of the whole of `src` into the whole of `dst`, eg. to resolve a
+    /// multisampled framebuffer, the framebuffer bindings are left as they were
+    ///
+    /// only the first color attachment of `src` is read (its `READ_BUFFER`) but it is written to every
+    /// draw buffer of `dst`
+    pub fn blit_color(&mut self, src: &impl Framebuffer, dst: &impl Framebuffer, filter: BlitFilter) {
+        blit(&self.renderer.context, src, dst, Gl::COLOR_BUFFER_BIT, filter);
+    }
+
+    /// copies the depth of the whole of `src` into the whole of `dst`, whose depth formats must match
+    pub fn blit_depth(&mut self, src: &impl Framebuffer, dst: &impl Framebuffer) {
+        blit(&self.renderer.context, src, dst, Gl::DEPTH_BUFFER_BIT, BlitFilter::Nearest);
+    }
+
+    /// copies the stencil of the whole of `src` into the whole of `dst`, whose stencil formats must match
+    pub fn blit_stencil(&mut self, src: &impl Framebuffer, dst: &impl Framebuffer) {
+        blit(&self.renderer.context, src, dst, Gl::STENCIL_BUFFER_BIT, BlitFilter::Nearest);
+    }
+
+    /// copies the color, depth and stencil of `src` into `dst`, see `blit_color`
+    ///
+    /// webgl only allows depth and stencil to be blitted with `BlitFilter::Nearest` so they are copied
+    /// separately when `filter` is `Linear`
+    pub fn blit_all(&mut self, src: &impl Framebuffer, dst: &impl Framebuffer, filter: BlitFilter) {
+        let context = &self.renderer.context;
+        match filter {
+            BlitFilter::Nearest => blit(context, src, dst, Gl::COLOR_BUFFER_BIT | Gl::DEPTH_BUFFER_BIT | Gl::STENCIL_BUFFER_BIT, filter),
+            BlitFilter::Linear => {
+                blit(context, src, dst, Gl::COLOR_BUFFER_BIT, filter);
+                blit(context, src, dst, Gl::DEPTH_BUFFER_BIT | Gl::STENCIL_BUFFER_BIT, BlitFilter::Nearest);
+            }
+        }
+    }
+
+    /// binds `framebuffer` so that subsequent draws render into it and sets the viewport to cover it
+    pub fn bind_framebuffer(&mut self, framebuffer: &impl Framebuffer) {
+        let (width, height) = framebuffer.size();
+        self.renderer.context.bind_framebuffer(Gl::FRAMEBUFFER, Some(framebuffer.framebuffer()));
+        self.renderer.context.viewport(0, 0, width as i32, height as i32);
+        self.renderer.bound_framebuffer_size = Some((width, height));
+    }
+
+    /// binds the default framebuffer (the canvas) and sets the viewport back to the canvas's, fitted by the resize policy
+    pub fn bind_default_framebuffer(&mut self) {
+        self.renderer.context.bind_framebuffer(Gl::FRAMEBUFFER, None);
+        self.renderer.reset_viewport();
+        self.renderer.bound_framebuffer_size = None;
+    }
+
+    /// the size of the framebuffer bound with `bind_framebuffer`, or `render_size` if the default
+    /// framebuffer is bound, eg. for the `buffer_height` of `Rect::to_gl`
+    ///
+    /// framebuffers bound directly on the context are not tracked
+    pub fn current_framebuffer_size(&self) -> (u32, u32) {
+        self.renderer.bound_framebuffer_size.unwrap_or_else(|| self.renderer.render_size())
+    }
+}
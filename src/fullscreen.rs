@@ -0,0 +1,70 @@
+use wasm_bindgen::JsCast;
+use web_sys::{WebGl2RenderingContext, WebGlVertexArrayObject};
+
+use crate::RenderInfo;
+use crate::stats;
+
+type Gl = WebGl2RenderingContext;
+
+/// a vertex shader for `RenderInfo::draw_fullscreen` that outputs `v_uv`, which is `(0, 0)` in the
+/// bottom-left corner of the screen and `(1, 1)` in the top-right, so only the fragment shader needs writing:
+/// ```ignore
+/// let frag_shader = "#version 300 es
+/// precision highp float;
+/// uniform sampler2D u_input;
+/// in vec2 v_uv;
+/// out vec4 color;
+/// void main() { color = texture(u_input, v_uv); }";
+/// renderer.with_shaders(FULLSCREEN_VERTEX_SHADER, frag_shader)?
+/// ```
+pub const FULLSCREEN_VERTEX_SHADER: &str = "\
+#version 300 es
+out vec2 v_uv;
+
+void main() {
+    // (0, 0), (2, 0) and (0, 2): a single triangle that covers the whole screen (and is clipped)
+    v_uv = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+    gl_Position = vec4(v_uv * 2.0 - 1.0, 0.0, 1.0);
+}
+";
+
+/// another name for `FULLSCREEN_VERTEX_SHADER`
+pub const FULLSCREEN_TRIANGLE_VERT: &str = FULLSCREEN_VERTEX_SHADER;
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// draws a single triangle that covers the viewport with the bound program, whose vertex shader
+    /// should be (or generate positions like) `FULLSCREEN_VERTEX_SHADER`, eg. for post-processing passes
+    ///
+    /// the draw uses an empty vertex array object so no attributes are read, the previously bound
+    /// vertex array object is rebound afterwards
+    pub fn draw_fullscreen(&mut self) {
+        let context = &self.renderer.context;
+        let Some(empty_vertex_array) = self.renderer.empty_vertex_array.get_or_init(|| context.create_vertex_array()) else { return };
+
+        let previous = context.get_parameter(Gl::VERTEX_ARRAY_BINDING).ok()
+            .and_then(|value| value.dyn_into::<WebGlVertexArrayObject>().ok());
+        context.bind_vertex_array(Some(empty_vertex_array));
+        context.draw_arrays(Gl::TRIANGLES, 0, 3);
+        stats::count(|stats| {
+            stats.draw_calls += 1;
+            stats.triangles += 1;
+        });
+        context.bind_vertex_array(previous.as_ref());
+    }
+
+    /// another name for `draw_fullscreen`
+    pub fn draw_fullscreen_triangle(&mut self) {
+        self.draw_fullscreen();
+    }
+
+    /// another name for `draw_fullscreen`, the single triangle covers exactly the same pixels as a
+    /// quad would (without the quad's overdraw along its diagonal) so is used instead
+    pub fn draw_fullscreen_quad(&mut self) {
+        self.draw_fullscreen();
+    }
+}
+
+/// the `on_render` of `RendererBuilder::with_fragment_shader`
+pub(crate) fn render_fullscreen<S>(mut render_info: RenderInfo<S>) {
+    render_info.draw_fullscreen();
+}
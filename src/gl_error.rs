@@ -0,0 +1,75 @@
+use web_sys::WebGl2RenderingContext;
+use std::fmt;
+
+use crate::RenderInfo;
+
+type Gl = WebGl2RenderingContext;
+
+/// an error flag reported by `getError`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlError {
+    InvalidEnum,
+    InvalidValue,
+    InvalidOperation,
+    OutOfMemory,
+    InvalidFramebufferOperation,
+    /// the context has been lost, every other call will fail until it is restored
+    ContextLost,
+}
+
+impl GlError {
+    /// converts a value returned by `getError`, returns `None` for `NO_ERROR` (or an unknown value)
+    pub fn from_gl(error: u32) -> Option<GlError> {
+        Some(match error {
+            Gl::INVALID_ENUM => GlError::InvalidEnum,
+            Gl::INVALID_VALUE => GlError::InvalidValue,
+            Gl::INVALID_OPERATION => GlError::InvalidOperation,
+            Gl::OUT_OF_MEMORY => GlError::OutOfMemory,
+            Gl::INVALID_FRAMEBUFFER_OPERATION => GlError::InvalidFramebufferOperation,
+            Gl::CONTEXT_LOST_WEBGL => GlError::ContextLost,
+            _ => return None,
+        })
+    }
+    pub fn to_gl(self) -> u32 {
+        match self {
+            GlError::InvalidEnum => Gl::INVALID_ENUM,
+            GlError::InvalidValue => Gl::INVALID_VALUE,
+            GlError::InvalidOperation => Gl::INVALID_OPERATION,
+            GlError::OutOfMemory => Gl::OUT_OF_MEMORY,
+            GlError::InvalidFramebufferOperation => Gl::INVALID_FRAMEBUFFER_OPERATION,
+            GlError::ContextLost => Gl::CONTEXT_LOST_WEBGL,
+        }
+    }
+}
+
+impl fmt::Display for GlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            GlError::InvalidEnum => "INVALID_ENUM",
+            GlError::InvalidValue => "INVALID_VALUE",
+            GlError::InvalidOperation => "INVALID_OPERATION",
+            GlError::OutOfMemory => "OUT_OF_MEMORY",
+            GlError::InvalidFramebufferOperation => "INVALID_FRAMEBUFFER_OPERATION",
+            GlError::ContextLost => "CONTEXT_LOST_WEBGL",
+        })
+    }
+}
+
+impl std::error::Error for GlError {}
+
+/// returns (and clears) the oldest error flag set since the last check, if there is one
+///
+/// `getError` waits for the gpu to catch up with every command issued so far, so this is for
+/// debugging and shouldn't be called after every draw in release builds
+pub fn check_gl_error(context: &WebGl2RenderingContext) -> Option<GlError> {
+    GlError::from_gl(context.get_error())
+}
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// see `check_gl_error`
+    ///
+    /// in debug builds errors are also checked (and logged to the console) after every `on_render`
+    pub fn check_error(&self) -> Option<GlError> {
+        check_gl_error(&self.renderer.context)
+    }
+}
@@ -0,0 +1,498 @@
+use js_sys::Array;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{WebGl2RenderingContext, WebGlProgram};
+use std::rc::Rc;
+
+use crate::assets::{fetch_bytes, fetch_texture, texture_from_bytes};
+use crate::atlas::get;
+use crate::camera3d::multiply;
+use crate::console::{log_at, LogLevel};
+use crate::{AttributeDesc, AttributeType, ColorSpace, MagFilter, Mesh, MinFilter, RenderInfo, RendererError, SamplerOptions, Texture2d, Vertex, WrapMode};
+
+type Gl = WebGl2RenderingContext;
+
+/// the interleaved vertex of meshes loaded from gltf files, with the attributes `a_position`,
+/// `a_normal` and `a_uv`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[repr(C)]
+pub struct GltfVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+}
+
+// SAFETY: `GltfVertex` is `#[repr(C)]` and made of `f32`s only, so it has no padding
+unsafe impl Vertex for GltfVertex {
+    const LAYOUT: &'static [AttributeDesc] = &[
+        AttributeDesc { name: "a_position", components: 3, attribute_type: AttributeType::Float, normalized: false, offset: 0 },
+        AttributeDesc { name: "a_normal", components: 3, attribute_type: AttributeType::Float, normalized: false, offset: 12 },
+        AttributeDesc { name: "a_uv", components: 2, attribute_type: AttributeType::Float, normalized: false, offset: 24 },
+    ];
+}
+
+/// a triangle primitive of a gltf mesh with the base color of its material
+pub struct GltfPrimitive {
+    pub mesh: Mesh,
+    pub base_color_factor: [f32; 4],
+    /// shared by every primitive whose material uses the same texture
+    pub base_color_texture: Option<Rc<Texture2d>>,
+}
+
+/// a node of a gltf scene
+pub struct GltfNode {
+    pub name: Option<String>,
+    /// the (column-major) transform relative to the parent node
+    pub transform: [[f32; 4]; 4],
+    /// the index of the node's mesh in `GltfModel::meshes`
+    pub mesh: Option<usize>,
+    pub children: Vec<GltfNode>,
+}
+
+/// the default scene of a gltf 2.0 model, loaded with `load` or `Assets::gltf` and drawn with `draw`:
+/// ```ignore
+/// // in `with_state_init`
+/// state.helmet = Some(assets.gltf("models/helmet.glb"));
+/// ...
+/// // in `on_render`, with a program that has `u_model`, `u_base_color` and `u_base_color_texture`
+/// if let Some(helmet) = state.helmet.as_ref().and_then(|helmet| helmet.get()) {
+///     render_info.set_uniform_cached("u_view_proj", camera)?;
+///     helmet.draw(&mut render_info, &program, IDENTITY);
+/// }
+/// ```
+///
+/// only node transforms, triangle primitives (with `POSITION`, `NORMAL` and `TEXCOORD_0`) and base
+/// color textures and factors are supported. Anything else (eg. skins, animations, morph targets and
+/// extensions) is skipped with a warning rather than failing the load, see `warnings`
+pub struct GltfModel {
+    /// the primitives of each gltf mesh
+    pub meshes: Vec<Vec<GltfPrimitive>>,
+    /// the root nodes of the scene
+    pub nodes: Vec<GltfNode>,
+    warnings: Vec<String>,
+    /// bound for primitives without a base color texture
+    white: Texture2d,
+}
+
+impl GltfModel {
+    /// downloads a `.gltf` (with its `.bin` buffers and images, which can be relative to it or base64
+    /// data uris) or a `.glb` and uploads its meshes and base color textures
+    ///
+    /// anything unsupported is skipped and logged as a warning at the default `LogLevel`
+    ///
+    /// errors if a download fails, the file is malformed or a buffer could not be created
+    pub async fn load(context: &WebGl2RenderingContext, url: &str) -> Result<GltfModel, RendererError> {
+        let bytes = fetch_bytes(url).await?;
+        let (json, mut bin) = if bytes.starts_with(b"glTF") {
+            split_glb(&bytes)?
+        } else {
+            (bytes.as_slice(), None)
+        };
+        let json = std::str::from_utf8(json).map_err(|_| parse_error("the json isn't utf-8".to_owned()))?;
+        let root = js_sys::JSON::parse(json).map_err(|_| parse_error("invalid json".to_owned()))?;
+        let base_url = &url[..url.rfind('/').map_or(0, |slash| slash + 1)];
+
+        let mut buffers = Vec::new();
+        for (index, buffer) in array(&root, "buffers").iter().enumerate() {
+            buffers.push(match string(buffer, "uri") {
+                Some(uri) => fetch_bytes(&resolve_uri(base_url, &uri)).await?,
+                None if index == 0 && bin.is_some() => bin.take().unwrap_or_default(),
+                None => return Err(parse_error(format!("buffer {} has no uri", index))),
+            });
+        }
+        let mut loader = Loader { root, buffers, warnings: Vec::new() };
+        loader.warn_unsupported();
+
+        let mut textures = Vec::new();
+        for (index, texture) in array(&loader.root, "textures").iter().enumerate() {
+            let texture = match loader.load_texture(context, base_url, texture).await {
+                Ok(texture) => Some(Rc::new(texture)),
+                Err(error) => {
+                    loader.warnings.push(format!("texture {} skipped: {}", index, error));
+                    None
+                }
+            };
+            textures.push(texture);
+        }
+
+        let mut materials = Vec::new();
+        for (index, material) in array(&loader.root, "materials").iter().enumerate() {
+            let pbr = get(material, "pbrMetallicRoughness");
+            let factor = pbr.as_ref().and_then(|pbr| numbers(pbr, "baseColorFactor")).unwrap_or_default();
+            let factor = <[f32; 4]>::try_from(factor).unwrap_or([1.0; 4]);
+            let texture_info = pbr.as_ref().and_then(|pbr| get(pbr, "baseColorTexture"));
+            if texture_info.as_ref().and_then(|info| number(info, "texCoord")).is_some_and(|tex_coord| tex_coord != 0.0) {
+                loader.warnings.push(format!("material {} uses a texture coordinate other than TEXCOORD_0, which is used instead", index));
+            }
+            let texture = texture_info
+                .and_then(|info| index_of(&info, "index"))
+                .and_then(|texture| textures.get(texture).cloned().flatten());
+            materials.push((factor, texture));
+        }
+
+        let mut meshes = Vec::new();
+        for (mesh_index, mesh) in array(&loader.root, "meshes").iter().enumerate() {
+            let mut primitives = Vec::new();
+            for (index, primitive) in array(mesh, "primitives").iter().enumerate() {
+                let (vertices, indices) = match loader.read_primitive(primitive) {
+                    Ok(geometry) => geometry,
+                    Err(msg) => {
+                        loader.warnings.push(format!("mesh {} primitive {} skipped: {}", mesh_index, index, msg));
+                        continue;
+                    }
+                };
+                let (base_color_factor, base_color_texture) = index_of(primitive, "material")
+                    .and_then(|material| materials.get(material).cloned())
+                    .unwrap_or(([1.0; 4], None));
+                primitives.push(GltfPrimitive {
+                    mesh: Mesh::new(context, &vertices, &indices)?,
+                    base_color_factor,
+                    base_color_texture,
+                });
+            }
+            meshes.push(primitives);
+        }
+
+        let nodes = loader.read_scene(meshes.len());
+        for warning in &loader.warnings {
+            log_at(LogLevel::default(), LogLevel::Warnings, || format!("{}: {}", url, warning));
+        }
+        Ok(GltfModel {
+            meshes,
+            nodes,
+            warnings: loader.warnings,
+            white: Texture2d::from_rgba8(context, 1, 1, &[255; 4], ColorSpace::Srgb, &SamplerOptions::default())?,
+        })
+    }
+
+    /// what was skipped while loading
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// draws every primitive with `program`, which must be in use, after setting its uniforms:
+    /// - `u_model`, the `mat4` transform of the primitive's node multiplied by `transform`
+    /// - `u_base_color`, the material's `vec4` base color factor
+    /// - `u_base_color_texture`, the material's `sampler2D` base color texture (or a white pixel)
+    ///
+    /// uniforms the program doesn't have are skipped
+    pub fn draw<S>(&self, render_info: &mut RenderInfo<S>, program: &WebGlProgram, transform: [[f32; 4]; 4]) {
+        let context = render_info.renderer.context.clone();
+        let model_location = context.get_uniform_location(program, "u_model");
+        let color_location = context.get_uniform_location(program, "u_base_color");
+        let mut stack: Vec<_> = self.nodes.iter().rev().map(|node| (node, transform)).collect();
+        while let Some((node, parent)) = stack.pop() {
+            let transform = multiply(parent, node.transform);
+            for primitive in node.mesh.and_then(|mesh| self.meshes.get(mesh)).into_iter().flatten() {
+                context.uniform_matrix4fv_with_f32_array(model_location.as_ref(), false, transform.as_flattened());
+                context.uniform4fv_with_f32_array(color_location.as_ref(), &primitive.base_color_factor);
+                let texture = primitive.base_color_texture.as_deref().unwrap_or(&self.white);
+                render_info.set_uniform_texture(program, "u_base_color_texture", texture);
+                primitive.mesh.draw(program);
+            }
+            stack.extend(node.children.iter().rev().map(|child| (child, transform)));
+        }
+    }
+}
+
+/// the parsed json and the loaded buffers of a gltf file
+struct Loader {
+    root: JsValue,
+    buffers: Vec<Vec<u8>>,
+    warnings: Vec<String>,
+}
+
+impl Loader {
+    fn warn_unsupported(&mut self) {
+        for extension in array(&self.root, "extensionsRequired") {
+            self.warnings.push(format!("the required extension `{}` is unsupported", extension.as_string().unwrap_or_default()));
+        }
+        for (key, what) in [("skins", "skins"), ("animations", "animations")] {
+            if !array(&self.root, key).is_empty() {
+                self.warnings.push(format!("{} are unsupported and ignored", what));
+            }
+        }
+    }
+
+    async fn load_texture(&self, context: &WebGl2RenderingContext, base_url: &str, texture: &JsValue) -> Result<Texture2d, RendererError> {
+        let sampler = index_of(texture, "sampler")
+            .and_then(|sampler| array(&self.root, "samplers").get(sampler).map(sampler_options))
+            .unwrap_or(SamplerOptions::mipmapped());
+        let image = index_of(texture, "source")
+            .and_then(|image| array(&self.root, "images").get(image).cloned())
+            .ok_or_else(|| parse_error("it has no image".to_owned()))?;
+        if let Some(uri) = string(&image, "uri") {
+            return fetch_texture(context, &resolve_uri(base_url, &uri), ColorSpace::Srgb, &sampler).await;
+        }
+        let view = index_of(&image, "bufferView").ok_or_else(|| parse_error("the image has no uri or buffer view".to_owned()))?;
+        let bytes = self.buffer_view(view).map_err(parse_error)?;
+        texture_from_bytes(context, bytes, ColorSpace::Srgb, &sampler).await
+    }
+
+    /// the vertices and triangle list indices of a primitive
+    fn read_primitive(&mut self, primitive: &JsValue) -> Result<(Vec<GltfVertex>, Vec<u32>), String> {
+        let mode = number(primitive, "mode").unwrap_or(4.0);
+        if mode != 4.0 {
+            return Err(format!("mode {} isn't triangles", mode));
+        }
+        if !array(primitive, "targets").is_empty() {
+            self.warnings.push("morph targets are unsupported and ignored".to_owned());
+        }
+        let attributes = get(primitive, "attributes").ok_or("it has no attributes")?;
+        let attribute = |name| index_of(&attributes, name);
+        let positions = self.read_accessor(attribute("POSITION").ok_or("it has no POSITION")?, 3)?;
+        let normals = attribute("NORMAL").map(|accessor| self.read_accessor(accessor, 3)).transpose()?;
+        let uvs = attribute("TEXCOORD_0").map(|accessor| self.read_accessor(accessor, 2)).transpose()?;
+
+        let count = positions.len() / 3;
+        if normals.as_ref().is_some_and(|normals| normals.len() != count * 3) || uvs.as_ref().is_some_and(|uvs| uvs.len() != count * 2) {
+            return Err("its attributes have different counts".to_owned());
+        }
+        let indices = match index_of(primitive, "indices") {
+            Some(accessor) => self.read_accessor(accessor, 1)?.into_iter().map(|index| index as u32).collect(),
+            None => (0..count as u32).collect::<Vec<_>>(),
+        };
+        if indices.iter().any(|&index| index as usize >= count) {
+            return Err("an index is out of range".to_owned());
+        }
+        let indices = indices[..indices.len() - indices.len() % 3].to_vec();
+
+        let mut vertices: Vec<_> = (0..count)
+            .map(|i| GltfVertex {
+                position: [positions[i * 3] as f32, positions[i * 3 + 1] as f32, positions[i * 3 + 2] as f32],
+                normal: normals.as_ref().map_or([0.0; 3], |normals| [normals[i * 3] as f32, normals[i * 3 + 1] as f32, normals[i * 3 + 2] as f32]),
+                uv: uvs.as_ref().map_or([0.0; 2], |uvs| [uvs[i * 2] as f32, uvs[i * 2 + 1] as f32]),
+            })
+            .collect();
+        if normals.is_none() {
+            generate_normals(&mut vertices, &indices);
+        }
+        Ok((vertices, indices))
+    }
+
+    /// the components of every element of an accessor, normalized if the accessor is
+    fn read_accessor(&self, index: usize, components: usize) -> Result<Vec<f64>, String> {
+        let accessor = array(&self.root, "accessors").get(index).cloned().ok_or_else(|| format!("accessor {} doesn't exist", index))?;
+        if get(&accessor, "sparse").is_some() {
+            return Err(format!("accessor {} is sparse, which is unsupported", index));
+        }
+        let element_type = string(&accessor, "type").unwrap_or_default();
+        let expected = ["", "SCALAR", "VEC2", "VEC3", "VEC4"][components];
+        if element_type != expected {
+            return Err(format!("accessor {} is a {} but a {} is needed", index, element_type, expected));
+        }
+        let count = index_of(&accessor, "count").ok_or_else(|| format!("accessor {} has no count", index))?;
+        let component_type = index_of(&accessor, "componentType").unwrap_or(0) as u32;
+        let size = match component_type {
+            Gl::BYTE | Gl::UNSIGNED_BYTE => 1,
+            Gl::SHORT | Gl::UNSIGNED_SHORT => 2,
+            Gl::UNSIGNED_INT | Gl::FLOAT => 4,
+            _ => return Err(format!("accessor {} has the unknown component type {}", index, component_type)),
+        };
+        let Some(view_index) = index_of(&accessor, "bufferView") else {
+            // accessors without a buffer view are all zeros
+            return Ok(vec![0.0; count * components]);
+        };
+        let view = self.buffer_view(view_index)?;
+        let stride = array(&self.root, "bufferViews").get(view_index)
+            .and_then(|view| index_of(view, "byteStride"))
+            .unwrap_or(size * components);
+        let offset = index_of(&accessor, "byteOffset").unwrap_or(0);
+        let normalized = get(&accessor, "normalized").and_then(|normalized| normalized.as_bool()).unwrap_or(false);
+
+        let mut values = Vec::with_capacity(count * components);
+        for element in 0..count {
+            for component in 0..components {
+                let start = offset + element * stride + component * size;
+                let bytes = view.get(start..start + size).ok_or_else(|| format!("accessor {} overruns its buffer view", index))?;
+                let value = match component_type {
+                    Gl::BYTE => bytes[0] as i8 as f64 / if normalized { 127.0 } else { 1.0 },
+                    Gl::UNSIGNED_BYTE => bytes[0] as f64 / if normalized { 255.0 } else { 1.0 },
+                    Gl::SHORT => i16::from_le_bytes([bytes[0], bytes[1]]) as f64 / if normalized { 32767.0 } else { 1.0 },
+                    Gl::UNSIGNED_SHORT => u16::from_le_bytes([bytes[0], bytes[1]]) as f64 / if normalized { 65535.0 } else { 1.0 },
+                    Gl::UNSIGNED_INT => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+                    _ => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+                };
+                // normalized signed values are clamped so that both -128 and -127 are -1
+                values.push(if normalized { value.max(-1.0) } else { value });
+            }
+        }
+        Ok(values)
+    }
+
+    fn buffer_view(&self, index: usize) -> Result<&[u8], String> {
+        let view = array(&self.root, "bufferViews").get(index).cloned().ok_or_else(|| format!("buffer view {} doesn't exist", index))?;
+        let buffer = index_of(&view, "buffer").and_then(|buffer| self.buffers.get(buffer))
+            .ok_or_else(|| format!("buffer view {} has no buffer", index))?;
+        let offset = index_of(&view, "byteOffset").unwrap_or(0);
+        let length = index_of(&view, "byteLength").unwrap_or(0);
+        buffer.get(offset..offset + length).ok_or_else(|| format!("buffer view {} overruns its buffer", index))
+    }
+
+    /// the root nodes of the default scene (or of every node if there are no scenes)
+    fn read_scene(&mut self, mesh_count: usize) -> Vec<GltfNode> {
+        let nodes = array(&self.root, "nodes");
+        let scenes = array(&self.root, "scenes");
+        let roots: Vec<usize> = match scenes.get(index_of(&self.root, "scene").unwrap_or(0)) {
+            Some(scene) => array(scene, "nodes").iter().filter_map(|node| node.as_f64().map(|node| node as usize)).collect(),
+            None => {
+                let children: Vec<usize> = nodes.iter()
+                    .flat_map(|node| array(node, "children"))
+                    .filter_map(|child| child.as_f64().map(|child| child as usize))
+                    .collect();
+                (0..nodes.len()).filter(|node| !children.contains(node)).collect()
+            }
+        };
+        roots.into_iter().filter_map(|root| self.read_node(&nodes, root, mesh_count, &mut Vec::new())).collect()
+    }
+
+    /// `ancestors` guards against cycles, which would otherwise recurse forever
+    fn read_node(&mut self, nodes: &[JsValue], index: usize, mesh_count: usize, ancestors: &mut Vec<usize>) -> Option<GltfNode> {
+        let Some(node) = nodes.get(index) else {
+            self.warnings.push(format!("node {} doesn't exist", index));
+            return None;
+        };
+        if ancestors.contains(&index) {
+            self.warnings.push(format!("node {} is its own ancestor", index));
+            return None;
+        }
+        if get(node, "skin").is_some() {
+            self.warnings.push(format!("node {} is skinned, which is unsupported, so it is drawn in its bind pose", index));
+        }
+        let mesh = index_of(node, "mesh").filter(|&mesh| {
+            let exists = mesh < mesh_count;
+            if !exists {
+                self.warnings.push(format!("node {} has the missing mesh {}", index, mesh));
+            }
+            exists
+        });
+        ancestors.push(index);
+        let children = array(node, "children").iter()
+            .filter_map(|child| child.as_f64())
+            .filter_map(|child| self.read_node(nodes, child as usize, mesh_count, ancestors))
+            .collect();
+        ancestors.pop();
+        Some(GltfNode {
+            name: string(node, "name"),
+            transform: node_transform(node),
+            mesh,
+            children,
+        })
+    }
+}
+
+/// the node's `matrix`, or else its translation * rotation * scale
+fn node_transform(node: &JsValue) -> [[f32; 4]; 4] {
+    if let Some(matrix) = numbers(node, "matrix").filter(|matrix| matrix.len() == 16) {
+        return [0, 1, 2, 3].map(|column| [0, 1, 2, 3].map(|row| matrix[column * 4 + row]));
+    }
+    let [tx, ty, tz] = numbers(node, "translation").and_then(|t| t.try_into().ok()).unwrap_or([0.0; 3]);
+    let [x, y, z, w] = numbers(node, "rotation").and_then(|r| r.try_into().ok()).unwrap_or([0.0, 0.0, 0.0, 1.0]);
+    let [sx, sy, sz] = numbers(node, "scale").and_then(|s| s.try_into().ok()).unwrap_or([1.0; 3]);
+    [
+        [(1.0 - 2.0 * (y * y + z * z)) * sx, 2.0 * (x * y + z * w) * sx, 2.0 * (x * z - y * w) * sx, 0.0],
+        [2.0 * (x * y - z * w) * sy, (1.0 - 2.0 * (x * x + z * z)) * sy, 2.0 * (y * z + x * w) * sy, 0.0],
+        [2.0 * (x * z + y * w) * sz, 2.0 * (y * z - x * w) * sz, (1.0 - 2.0 * (x * x + y * y)) * sz, 0.0],
+        [tx, ty, tz, 1.0],
+    ]
+}
+
+/// the sampler options of a gltf sampler, whose filters and wraps are webgl enums
+fn sampler_options(sampler: &JsValue) -> SamplerOptions {
+    let wrap = |key| match index_of(sampler, key).map(|wrap| wrap as u32) {
+        Some(Gl::CLAMP_TO_EDGE) => WrapMode::ClampToEdge,
+        Some(Gl::MIRRORED_REPEAT) => WrapMode::MirroredRepeat,
+        _ => WrapMode::Repeat,
+    };
+    SamplerOptions {
+        mag_filter: match index_of(sampler, "magFilter").map(|filter| filter as u32) {
+            Some(Gl::NEAREST) => MagFilter::Nearest,
+            _ => MagFilter::Linear,
+        },
+        min_filter: match index_of(sampler, "minFilter").map(|filter| filter as u32) {
+            Some(Gl::NEAREST) => MinFilter::Nearest,
+            Some(Gl::LINEAR) => MinFilter::Linear,
+            Some(Gl::NEAREST_MIPMAP_NEAREST) => MinFilter::NearestMipmapNearest,
+            Some(Gl::LINEAR_MIPMAP_NEAREST) => MinFilter::LinearMipmapNearest,
+            Some(Gl::NEAREST_MIPMAP_LINEAR) => MinFilter::NearestMipmapLinear,
+            _ => MinFilter::LinearMipmapLinear,
+        },
+        wrap_s: wrap("wrapS"),
+        wrap_t: wrap("wrapT"),
+        ..Default::default()
+    }
+}
+
+/// sets each vertex's normal to the (area weighted) average of the normals of its triangles
+fn generate_normals(vertices: &mut [GltfVertex], indices: &[u32]) {
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize].position);
+        let (ab, ac) = ([b[0] - a[0], b[1] - a[1], b[2] - a[2]], [c[0] - a[0], c[1] - a[1], c[2] - a[2]]);
+        // the cross product's length is twice the triangle's area
+        let normal = [ab[1] * ac[2] - ab[2] * ac[1], ab[2] * ac[0] - ab[0] * ac[2], ab[0] * ac[1] - ab[1] * ac[0]];
+        for &index in triangle {
+            for (sum, component) in vertices[index as usize].normal.iter_mut().zip(normal) {
+                *sum += component;
+            }
+        }
+    }
+    for vertex in vertices {
+        let [x, y, z] = vertex.normal;
+        let length = (x * x + y * y + z * z).sqrt();
+        vertex.normal = if length == 0.0 { [0.0, 1.0, 0.0] } else { [x / length, y / length, z / length] };
+    }
+}
+
+/// the json and binary chunks of a glb file
+fn split_glb(bytes: &[u8]) -> Result<(&[u8], Option<Vec<u8>>), RendererError> {
+    let error = |msg: &str| RendererError::Parse("glb", msg.to_owned());
+    let word = |offset: usize| bytes.get(offset..offset + 4).map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]) as usize);
+    if word(4) != Some(2) {
+        return Err(error("only version 2 is supported"));
+    }
+    let length = word(8).ok_or_else(|| error("the header is truncated"))?.min(bytes.len());
+    let mut chunks = Vec::new();
+    let mut offset = 12;
+    while offset + 8 <= length {
+        let (chunk_length, chunk_type) = (word(offset).unwrap_or(0), word(offset + 4).unwrap_or(0));
+        let chunk = bytes.get(offset + 8..offset + 8 + chunk_length).ok_or_else(|| error("a chunk is truncated"))?;
+        chunks.push((chunk_type, chunk));
+        offset += 8 + chunk_length;
+    }
+    const JSON_CHUNK: usize = 0x4E4F534A;
+    const BIN_CHUNK: usize = 0x004E4942;
+    match chunks.as_slice() {
+        [(JSON_CHUNK, json), rest @ ..] => Ok((json, rest.first().filter(|(chunk_type, _)| *chunk_type == BIN_CHUNK).map(|(_, bin)| bin.to_vec()))),
+        _ => Err(error("the first chunk isn't json")),
+    }
+}
+
+/// `uri` relative to the directory of the gltf file, unless it is absolute or a data uri
+fn resolve_uri(base_url: &str, uri: &str) -> String {
+    if uri.contains(':') || uri.starts_with('/') {
+        uri.to_owned()
+    } else {
+        format!("{}{}", base_url, uri)
+    }
+}
+
+fn parse_error(msg: String) -> RendererError {
+    RendererError::Parse("gltf", msg)
+}
+
+/// `object[key]` as an array, empty if it isn't one
+fn array(object: &JsValue, key: &str) -> Vec<JsValue> {
+    get(object, key).filter(Array::is_array).map_or_else(Vec::new, |array| array.unchecked_into::<Array>().to_vec())
+}
+fn number(object: &JsValue, key: &str) -> Option<f64> {
+    get(object, key).and_then(|value| value.as_f64())
+}
+fn index_of(object: &JsValue, key: &str) -> Option<usize> {
+    number(object, key).filter(|&index| index >= 0.0).map(|index| index as usize)
+}
+fn numbers(object: &JsValue, key: &str) -> Option<Vec<f32>> {
+    get(object, key).filter(Array::is_array)
+        .map(|array| array.unchecked_into::<Array>().iter().map(|value| value.as_f64().unwrap_or(0.0) as f32).collect())
+}
+fn string(object: &JsValue, key: &str) -> Option<String> {
+    get(object, key).and_then(|value| value.as_string())
+}
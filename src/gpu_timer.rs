@@ -0,0 +1,159 @@
+use web_sys::{WebGl2RenderingContext, WebGlQuery};
+use std::collections::{HashMap, VecDeque};
+
+use crate::{has_extension, RenderInfo, Renderer};
+
+type Gl = WebGl2RenderingContext;
+
+/// `TIME_ELAPSED_EXT` from `EXT_disjoint_timer_query_webgl2`
+const TIME_ELAPSED: u32 = 0x88BF;
+/// `GPU_DISJOINT_EXT` from `EXT_disjoint_timer_query_webgl2`
+const GPU_DISJOINT: u32 = 0x8FBB;
+/// how many samples `RenderInfo::gpu_timings` averages over
+const TIMING_SAMPLES: usize = 60;
+
+/// a `TIME_ELAPSED` query for how long the gpu spent on the commands issued between `begin` and `end`
+///
+/// results arrive some frames later, see `RenderInfo::measure_block` for timings that are collected
+/// and averaged automatically
+pub struct GpuTimer {
+    context: WebGl2RenderingContext,
+    query: WebGlQuery,
+    ended: bool,
+}
+
+impl GpuTimer {
+    /// starts timing, only one timer can be running at a time
+    ///
+    /// returns `None` if `EXT_disjoint_timer_query_webgl2` is unsupported or a query could not be created
+    pub fn begin(context: &WebGl2RenderingContext) -> Option<GpuTimer> {
+        if !has_extension(context, "EXT_disjoint_timer_query_webgl2") {
+            return None;
+        }
+        let query = context.create_query()?;
+        context.begin_query(TIME_ELAPSED, &query);
+        Some(GpuTimer { context: context.clone(), query, ended: false })
+    }
+
+    /// stops timing, does nothing if the timer has already ended
+    pub fn end(&mut self) {
+        if !self.ended {
+            self.context.end_query(TIME_ELAPSED);
+            self.ended = true;
+        }
+    }
+
+    /// whether the result is available, without blocking
+    pub fn is_ready(&self) -> bool {
+        self.ended && self.context.get_query_parameter(&self.query, Gl::QUERY_RESULT_AVAILABLE).as_bool().unwrap_or(false)
+    }
+
+    /// the gpu time in milliseconds once `is_ready`
+    ///
+    /// returns `None` if it isn't ready or the timing is unreliable because the gpu was disjoint
+    /// (eg. it changed clock speed or another context interrupted it)
+    pub fn elapsed_ms(&self) -> Option<f64> {
+        if !self.is_ready() {
+            return None;
+        }
+        let disjoint = self.context.get_parameter(GPU_DISJOINT).ok().and_then(|disjoint| disjoint.as_bool()).unwrap_or(false);
+        if disjoint {
+            return None;
+        }
+        let nanoseconds = self.context.get_query_parameter(&self.query, Gl::QUERY_RESULT).as_f64()?;
+        Some(nanoseconds / 1_000_000.0)
+    }
+}
+
+impl Drop for GpuTimer {
+    fn drop(&mut self) {
+        // a query can't be deleted while it is active and leaving it active would stop any other timer
+        // from beginning
+        self.end();
+        self.context.delete_query(Some(&self.query));
+    }
+}
+
+/// the average of the latest `capacity` samples
+#[derive(Debug, Clone, PartialEq)]
+pub struct RollingAverage {
+    samples: VecDeque<f64>,
+    capacity: usize,
+    sum: f64,
+}
+
+impl RollingAverage {
+    pub fn new(capacity: usize) -> Self {
+        RollingAverage { samples: VecDeque::with_capacity(capacity.max(1)), capacity: capacity.max(1), sum: 0.0 }
+    }
+
+    /// adds `sample`, dropping the oldest sample if there are already `capacity`
+    pub fn push(&mut self, sample: f64) {
+        if self.samples.len() == self.capacity {
+            self.sum -= self.samples.pop_front().unwrap();
+        }
+        self.samples.push_back(sample);
+        self.sum += sample;
+    }
+
+    /// `0.0` when there are no samples
+    pub fn average(&self) -> f64 {
+        if self.samples.is_empty() { 0.0 } else { self.sum / self.samples.len() as f64 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// times the gpu work issued by `f`, with the average over the latest results being available from
+    /// `gpu_timings` under `label` some frames later:
+    /// ```ignore
+    /// render_info.measure_block("shadow_pass", |render_info| draw_shadows(render_info, &state.lights));
+    /// ...
+    /// for (label, ms) in render_info.gpu_timings() { ... }
+    /// ```
+    ///
+    /// only one block can be timed at a time so nested blocks are not timed separately (their work
+    /// counts towards the outer block), and without `EXT_disjoint_timer_query_webgl2` this just calls `f`
+    pub fn measure_block<R>(&mut self, label: &'static str, f: impl FnOnce(&mut Self) -> R) -> R {
+        if self.renderer.gpu_timer_running {
+            return f(self);
+        }
+        let Some(mut timer) = GpuTimer::begin(&self.renderer.context) else { return f(self) };
+        self.renderer.gpu_timer_running = true;
+        let result = f(self);
+        timer.end();
+        self.renderer.gpu_timer_running = false;
+        self.renderer.pending_gpu_timers.push((label, timer));
+        result
+    }
+
+    /// the average gpu time in milliseconds of each `measure_block` label that has had a result
+    pub fn gpu_timings(&self) -> &HashMap<&'static str, f64> {
+        &self.renderer.gpu_timings
+    }
+}
+
+impl<S> Renderer<S> {
+    /// adds the results of the `measure_block` timers that are ready to their averages
+    pub(crate) fn poll_gpu_timers(&mut self) {
+        let timings = &mut self.gpu_timings;
+        let averages = &mut self.gpu_timing_averages;
+        self.pending_gpu_timers.retain(|(label, timer)| {
+            if !timer.is_ready() {
+                return true;
+            }
+            if let Some(ms) = timer.elapsed_ms() {
+                let average = averages.entry(label).or_insert_with(|| RollingAverage::new(TIMING_SAMPLES));
+                average.push(ms);
+                timings.insert(label, average.average());
+            }
+            false
+        });
+    }
+}
@@ -0,0 +1,68 @@
+use std::cell::{OnceCell, RefCell};
+use std::rc::Rc;
+
+use js_sys::{Function, Promise};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::WebGl2RenderingContext;
+
+use crate::{LoopMode, Renderer};
+
+/// the context and state of a renderer whose game loop has exited, from `Renderer::start_async`
+pub struct RendererHandle<S> {
+    context: Rc<WebGl2RenderingContext>,
+    state: Rc<OnceCell<RefCell<S>>>,
+}
+
+impl<S> RendererHandle<S> {
+    /// the context, eg. to read back the final frame's pixels
+    pub fn into_context(self) -> Rc<WebGl2RenderingContext> {
+        self.context
+    }
+    /// the final state, `None` if it is still shared (eg. by an `on_update_async` future that hasn't finished)
+    pub fn into_state(self) -> Option<S> {
+        Rc::into_inner(self.state)?.into_inner().map(RefCell::into_inner)
+    }
+    /// the context and the final state, see `into_state`
+    pub fn into_parts(self) -> (Rc<WebGl2RenderingContext>, Option<S>) {
+        let context = self.context.clone();
+        (context, self.into_state())
+    }
+}
+
+impl<S: 'static> Renderer<S> {
+    /// like `start` but resolves once the game loop has exited (after `exit` or the page unloading)
+    /// with the renderer's context and final state:
+    /// ```ignore
+    /// wasm_bindgen_futures::spawn_local(async move {
+    ///     let handle = renderer.start_async(state, 60, 0.1).await;
+    ///     let (context, state) = handle.into_parts();
+    ///     ...
+    /// });
+    /// ```
+    pub async fn start_async(mut self, state: S, updates_per_second: u32, max_frame_time: f64) -> RendererHandle<S> {
+        self.set_state(state, LoopMode { updates_per_second, max_frame_time });
+        self.run_async().await
+    }
+
+    /// like `run` but resolves once the game loop has exited, see `start_async`
+    pub async fn run_async(mut self) -> RendererHandle<S> {
+        let handle = RendererHandle { context: self.context.clone(), state: self.state.clone() };
+        let mut on_exit = None;
+        let exited = Promise::new(&mut |resolve, _| on_exit = Some(resolve));
+        self.on_exit = on_exit;
+        self.run();
+        // the renderer has been dropped by the time this resolves
+        let _ = JsFuture::from(exited).await;
+        handle
+    }
+}
+
+impl<S> Renderer<S> {
+    /// resolves the `run_async` future, called as the game loop exits
+    pub(crate) fn notify_exit(&mut self) {
+        if let Some(on_exit) = self.on_exit.take() {
+            let _ = Function::call0(&on_exit, &JsValue::NULL);
+        }
+    }
+}
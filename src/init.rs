@@ -0,0 +1,55 @@
+use web_sys::WebGl2RenderingContext;
+use std::ops::DerefMut;
+
+use crate::{Renderer, RendererError, Texture2d, ColorSpace, SamplerOptions};
+
+pub(crate) type OnStateInit<S> = fn(&mut S, InitInfo<S>);
+
+/// the information given to the state init function, see `Renderer::with_state_init`
+pub struct InitInfo<'a, S: 'static> {
+    renderer: &'a Renderer<S>,
+}
+
+impl<'a, S> InitInfo<'a, S> {
+    pub fn context(&self) -> &'a WebGl2RenderingContext {
+        &self.renderer.context
+    }
+    /// the logical size of the canvas, see `Renderer::canvas_size`
+    pub fn canvas_size(&self) -> (u32, u32) {
+        self.renderer.canvas_size()
+    }
+    /// the size of the drawing buffer including the resolution scale, see `Renderer::render_size`
+    pub fn render_size(&self) -> (u32, u32) {
+        self.renderer.render_size()
+    }
+    /// creates a texture from tightly packed rgba bytes, see `Texture2d::from_rgba8`
+    pub fn upload_texture_from_rgba_bytes(&self, width: u32, height: u32, data: &[u8], color_space: ColorSpace, sampler: &SamplerOptions) -> Result<Texture2d, RendererError> {
+        Texture2d::from_rgba8(&self.renderer.context, width, height, data, color_space, sampler)
+    }
+}
+
+impl<S> Renderer<S> {
+    /// sets a function that is called once with the state when the game loop is started (before the
+    /// first update), for initialisation that needs the context such as uploading textures:
+    /// ```ignore
+    /// fn init(state: &mut State, init_info: InitInfo<State>) {
+    ///     state.atlas = Some(init_info.upload_texture_from_rgba_bytes(256, 256, ATLAS, ColorSpace::Srgb, &SamplerOptions::nearest()).unwrap());
+    /// }
+    /// ```
+    ///
+    /// replaces any previously set state init function
+    ///
+    /// returns self for chaining
+    pub fn with_state_init(mut self, on_state_init: OnStateInit<S>) -> Self {
+        self.on_state_init = Some(on_state_init);
+        self
+    }
+
+    pub(crate) fn init_state(&mut self) {
+        if let Some(on_state_init) = self.on_state_init.take() {
+            on_state_init(self.state.get().unwrap().borrow_mut().deref_mut(), InitInfo {
+                renderer: self,
+            });
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::KeyboardEvent;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use crate::{Renderer, RendererError, UpdateInfo};
+
+/// keeps the held keys up to date as keyboard events arrive
+fn track_key(keys: &RefCell<HashSet<String>>, event: &JsValue) {
+    let Some(event) = event.dyn_ref::<KeyboardEvent>() else {
+        // the canvas lost focus so the keys' `keyup`s won't arrive
+        keys.borrow_mut().clear();
+        return;
+    };
+    match event.type_().as_str() {
+        "keydown" => { keys.borrow_mut().insert(event.code()); }
+        "keyup" => { keys.borrow_mut().remove(&event.code()); }
+        _ => {}
+    }
+}
+
+impl<S> Renderer<S> {
+    /// tracks the keys that are held for `UpdateInfo::is_key_down`, does nothing if they are already
+    /// tracked
+    ///
+    /// keyboard events only reach the canvas when it has focus, so it needs a `tabindex` attribute
+    /// (and to be clicked or focused)
+    ///
+    /// returns self for chaining
+    ///
+    /// errors if the event listeners could not be added or the renderer is headless
+    pub fn with_keyboard_tracking(mut self) -> Result<Self, RendererError> {
+        if self.keys.is_some() {
+            return Ok(self);
+        }
+        let keys = Rc::new(RefCell::new(HashSet::new()));
+        for event_type in ["keydown", "keyup", "blur"] {
+            let rc_keys = keys.clone();
+            // the lowest priority so that it runs before (and can't be skipped by) the other callbacks
+            self = self.add_event_listener(event_type, false, i32::MIN, Box::new(move |_, event| track_key(&rc_keys, event)))?;
+        }
+        self.keys = Some(keys);
+        Ok(self)
+    }
+
+    /// copies the held keys for the next update
+    pub(crate) fn snapshot_keys(&mut self) {
+        if let Some(keys) = &self.keys {
+            self.key_snapshot.clone_from(&keys.borrow());
+        }
+    }
+}
+
+impl<'a, S> UpdateInfo<'a, S> {
+    /// whether the key with the `KeyboardEvent.code` `code` (eg. `"KeyW"` or `"ArrowLeft"`, which
+    /// don't change with the keyboard layout) was held as of the start of this update
+    ///
+    /// always `false` unless tracking was started with `Renderer::with_keyboard_tracking`
+    pub fn is_key_down(&self, code: &str) -> bool {
+        self.renderer.key_snapshot.contains(code)
+    }
+    /// the `KeyboardEvent.code`s of the keys held as of the start of this update
+    pub fn keys_down(&self) -> &HashSet<String> {
+        &self.renderer.key_snapshot
+    }
+}
@@ -1,44 +1,327 @@
 use wasm_bindgen::{JsValue, JsCast, closure::Closure};
-use web_sys::{HtmlCanvasElement, WebGl2RenderingContext, WebGlProgram, WebGlShader, Event, window};
-use std::cell::{OnceCell, RefCell};
+use web_sys::{HtmlCanvasElement, OffscreenCanvas, WebGl2RenderingContext, WebGlProgram, WebGlUniformLocation, WebGlVertexArrayObject, AddEventListenerOptions, Event, window};
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ops::DerefMut;
 use std::rc::Rc;
 
+#[cfg(feature = "assets")]
+mod assets;
+#[cfg(feature = "futures")]
+mod async_update;
+#[cfg(feature = "capture")]
+mod capture;
+#[cfg(feature = "clipboard")]
+mod clipboard;
+#[cfg(feature = "debug-draw")]
+mod debug_draw;
+mod draw2d;
+#[cfg(feature = "drop")]
+mod drop_image;
+#[cfg(feature = "futures")]
+mod loading;
+#[cfg(feature = "gltf")]
+mod gltf;
+#[cfg(feature = "futures")]
+mod handle;
+#[cfg(feature = "obj")]
+mod obj;
+mod atlas;
+mod blit;
+mod builder;
+mod builtin_uniforms;
+mod camera2d;
+mod camera3d;
+mod clear;
+mod color;
+mod compressed;
+mod console;
+mod error;
+mod error_boundary;
+mod extensions;
+mod fence;
+mod font;
+mod framebuffer;
+mod fullscreen;
+mod gpu_timer;
+mod named_shaders;
+mod nine_patch;
+mod gl_error;
+mod init;
+mod keyboard;
+mod line_batch;
+mod mesh;
+mod occlusion;
+mod ortho;
+mod owned;
+mod particles;
+mod picking;
+mod pipeline;
+mod pointer;
+mod post;
+mod program_variants;
+mod readback;
+mod reflection;
+mod render_target;
+mod resize;
+mod sampler;
+mod shader;
+mod skybox;
+mod sprite_animation;
+mod sprite_batch;
+mod stats;
+mod systems;
+mod texture;
+mod texture_units;
+mod tile_map;
+mod tween;
+mod unload;
+mod uniform;
+mod validate;
+mod vertex;
+mod viewport;
+#[cfg(all(feature = "webxr", web_sys_unstable_apis))]
+mod xr;
+
+#[cfg(all(feature = "webxr", not(web_sys_unstable_apis)))]
+compile_error!("the `webxr` feature requires building with `RUSTFLAGS=--cfg=web_sys_unstable_apis`");
+
+#[cfg(feature = "assets")]
+pub use assets::{Assets, Asset};
+#[cfg(feature = "futures")]
+pub use async_update::AsyncUpdateInfo;
+#[cfg(feature = "capture")]
+pub use capture::MediaRecorderHandle;
+#[cfg(feature = "debug-draw")]
+pub use debug_draw::DebugDraw;
+#[cfg(feature = "drop")]
+pub use drop_image::LoadedTexture;
+pub use atlas::{TextureAtlas, TextureAtlasBuilder, AtlasRegion};
+pub use builder::{RendererBuilder, LoopMode};
+pub use builtin_uniforms::BuiltinUniforms;
+pub use camera2d::Camera2d;
+pub use camera3d::{Camera3d, OrbitController, FlyController, Ray};
+pub use color::Color;
+pub use compressed::{CompressedFormat, AstcBlockSize, TextureCompression, upload_texture_etc2_rgb, upload_texture_etc2_rgba, upload_texture_astc};
+#[doc(hidden)]
+pub use console::__console;
+#[cfg(feature = "log")]
+pub use console::ConsoleLogger;
+pub use console::LogLevel;
+pub use draw2d::Draw2d;
+pub use error::RendererError;
+pub use error_boundary::ErrorAction;
+pub use extensions::has_extension;
+pub use fence::GpuFence;
+pub use font::{Font, FontKind, Glyph, Text, PositionedGlyph};
+pub use framebuffer::{Framebuffer, BlitFilter};
+pub use fullscreen::{FULLSCREEN_VERTEX_SHADER, FULLSCREEN_TRIANGLE_VERT};
+pub use gl_error::{GlError, check_gl_error};
+pub use gpu_timer::{GpuTimer, RollingAverage};
+pub use init::InitInfo;
+pub use line_batch::{LineBatch, LineWidth, LineCap};
+pub use mesh::{Mesh, wireframe_indices};
+#[cfg(feature = "futures")]
+pub use handle::RendererHandle;
+#[cfg(feature = "gltf")]
+pub use gltf::{GltfModel, GltfNode, GltfPrimitive, GltfVertex};
+#[cfg(feature = "obj")]
+pub use obj::{ObjVertex, parse_obj};
+pub use nine_patch::{NinePatch, Margins};
+pub use occlusion::{OcclusionQuery, OcclusionCuller};
+pub use ortho::ortho;
+pub use owned::{OwnedBuffer, OwnedTexture, OwnedProgram};
+pub use particles::{ParticleSystem, EmitterConfig};
+pub use picking::{unproject, PICK_ID_GLSL, encode_pick_id, decode_pick_id, PickBuffer, PendingPick};
+pub use pointer::{PointerEventType, PointerType, PointerSnapshot};
+pub use pipeline::{PipelineState, BlendMode, StencilState, StencilFace, StencilOp, Cull, Winding, PolygonOffset};
+pub use post::PostChain;
+pub use program_variants::ProgramVariants;
+pub use readback::{PixelReadback, ReadbackToken};
+pub use reflection::{UniformInfo, AttributeInfo, LayoutMismatch, active_uniforms, active_attributes};
+pub use render_target::{RenderTarget, RenderTargetBuilder, DepthKind};
+pub use resize::{PixelRatio, ResizeObserverEntryData, ResizePolicy, VirtualViewport};
+use resize::{ResizeCallback, observe, resize_canvas, set_buffer_size, watch_device_pixel_ratio};
+pub use sampler::{SamplerOptions, Sampler, TextureParams, MinFilter, MagFilter, WrapMode, CompareFunc, CompareMode};
+pub use shader::{GlslVersion, Precision, ShaderConfig, SRGB_GLSL, compile_shader, compile_shader_with_defines, link_program};
+pub use skybox::Skybox;
+pub use sprite_animation::{SpriteAnimation, AnimationMode, AnimationSet};
+pub use sprite_batch::SpriteBatch;
+pub use stats::DrawStats;
+pub use texture::{ColorSpace, TextureFormat, Texture2d, Texture2dArray, Texture3d, TextureCube};
+pub use texture_units::TextureBinding;
+pub use tile_map::{TileMap, TileSet, FLIP_X, FLIP_Y};
+pub use tween::{Lerp, Easing, Tween, TweenId, Tweens};
+pub use uniform::{Uniform, UniformValue};
+pub use validate::{RendererWarning, WarningLevel};
+pub use vertex::{AttributeType, AttributeFormat, AttributeDesc, Vertex, VertexLayout, VertexBuffer};
+#[cfg(feature = "derive")]
+pub use web_render_rs_derive::Vertex;
+pub use viewport::Rect;
+#[cfg(all(feature = "webxr", web_sys_unstable_apis))]
+pub use xr::{XrSessionType, XrView, XrRenderInfo};
+
+type OnUpdate<S> = fn(UpdateInfo<S>);
+type OnResize<S> = fn(&mut S, (u32, u32), (u32, u32)) -> (u32, u32);
+type OnResizeEntry<S> = fn(&mut S, ResizeObserverEntryData) -> (u32, u32);
+type ResizeObserverCallback = Closure<dyn Fn(js_sys::Array)>;
+type EventCallback<S> = Box<dyn Fn(&mut S, &JsValue)>;
+/// the callbacks of an event listener by priority, in the order they were added within a priority
+type PrioritisedCallbacks<S> = Rc<RefCell<BTreeMap<i32, Vec<EventCallback<S>>>>>;
+type OnEvent<S> = fn(&mut S, Event);
+type OnRawEvent<S> = fn(&mut S, JsValue);
+
+/// the delay between frames of a headless renderer, which has no `requestAnimationFrame` to pace it
+const HEADLESS_FRAME_MILLIS: i32 = 16;
+
 pub struct Renderer<S>
     where S: 'static
 {
-    canvas: Rc<HtmlCanvasElement>,
+    /// `None` when headless
+    canvas: Option<Rc<HtmlCanvasElement>>,
     context: Rc<WebGl2RenderingContext>,
     state: Rc<OnceCell<RefCell<S>>>,
 
+    program: Option<WebGlProgram>,
+    /// the ids of the programs passed to `bind_ubo_to_block`, which (unlike their addresses) are never
+    /// reused, held weakly so that deleted programs can still be garbage collected
+    program_ids: js_sys::WeakMap,
+    next_program_id: u64,
+    /// the index and binding of each block, keyed by the id of the program and the name of the block
+    program_uniform_block_cache: HashMap<(u64, String), (u32, u32)>,
+    uniform_location_cache: HashMap<String, Option<WebGlUniformLocation>>,
+    named_shaders: HashMap<String, named_shaders::NamedShader>,
+    /// the named shader in use, `None` for `program`
+    current_shader: Option<String>,
+    readback_pool: Rc<readback::BufferPool>,
+    /// started with `on_readback`, polled at the start of each frame
+    pending_readbacks: Vec<readback::PendingReadback<S>>,
+    /// whether a `measure_block` timer is running, as only one can run at a time
+    gpu_timer_running: bool,
+    /// ended by `measure_block`, polled at the start of each frame
+    pending_gpu_timers: Vec<(&'static str, gpu_timer::GpuTimer)>,
+    gpu_timing_averages: HashMap<&'static str, gpu_timer::RollingAverage>,
+    /// the averages of `gpu_timing_averages`, for `RenderInfo::gpu_timings`
+    gpu_timings: HashMap<&'static str, f64>,
+    previous_draw_stats: DrawStats,
+    /// the size of the framebuffer bound with `RenderInfo::bind_framebuffer`, `None` for the default framebuffer
+    bound_framebuffer_size: Option<(u32, u32)>,
+    texture_units: texture_units::TextureUnits,
+    builtin_uniforms: Option<builtin_uniforms::Builtins>,
+    /// bound by `draw_fullscreen`, `None` inside if it couldn't be created
+    empty_vertex_array: OnceCell<Option<WebGlVertexArrayObject>>,
+    /// compiled by the first `Mesh::draw_wireframe`
+    wireframe_program: Option<mesh::WireframeProgram>,
+    /// compiled by the first `RenderInfo::blit`
+    blit_program: Option<blit::BlitProgram>,
+    /// cleared to at the start of every render, see `with_clear_color`
+    clear_color: Option<[f32; 4]>,
+    clear_depth: Option<f32>,
+    clear_stencil: Option<i32>,
+    /// the pixel projections for the latest canvas size, see `RenderInfo::ortho_pixels`
+    ortho_cache: Cell<Option<ortho::OrthoCache>>,
+    /// created by the first `RenderInfo::draw2d`
+    draw2d: Option<draw2d::Draw2dState>,
+
+    on_state_init: Option<init::OnStateInit<S>>,
     on_update: OnceCell<fn(UpdateInfo<S>)>,
     on_render: OnceCell<fn(RenderInfo<S>)>,
+    /// the error boundary around each frame's callbacks, see `with_on_error`
+    on_error: OnceCell<error_boundary::OnError>,
+    /// set by `ErrorAction::Pause`, cleared when the canvas is pressed
+    paused: Rc<Cell<bool>>,
+    /// set when the page is hidden to be unloaded (or cached), cleared if it is restored, see `with_on_unload`
+    unloaded: Rc<Cell<bool>>,
+    unload_listener: Option<unload::UnloadListener>,
+    /// resolves the `run_async` future when the loop exits
+    #[cfg(feature = "futures")]
+    on_exit: Option<js_sys::Function>,
+    #[cfg(feature = "futures")]
+    on_update_async: OnceCell<async_update::OnUpdateAsync<S>>,
+    #[cfg(feature = "futures")]
+    loading: loading::Loading<S>,
+    /// clones the state before each `on_update` into `prev_state`, see `with_sub_tick_state`
+    snapshot_state: Option<fn(&S) -> S>,
+    prev_state: Option<S>,
+    /// sorted by priority
+    update_systems: Vec<systems::UpdateSystem<S>>,
 
-    _resize_closure: Closure::<dyn Fn()>,
-    resize_observer: web_sys::ResizeObserver,
-    on_resize: Rc<OnceCell<fn(&mut S, (u32, u32)) -> (u32, u32)>>,
+    resize: Rc<dyn Fn()>,
+    resize_observer: Option<(web_sys::ResizeObserver, ResizeObserverCallback)>,
+    on_resize: Rc<OnceCell<ResizeCallback<S>>>,
+    /// whether the canvas's size is checked before each update, see `with_on_resize_during_update`
+    on_resize_during_update: bool,
+    /// the css size of the canvas as of the latest update
+    prev_update_width: u32,
+    prev_update_height: u32,
+    pixel_ratio: Rc<Cell<PixelRatio>>,
+    log_level: Rc<Cell<LogLevel>>,
+    /// multiplies the size of the drawing buffer, see `with_resolution_scale`
+    resolution_scale: Rc<Cell<f64>>,
+    /// the size of the drawing buffer before the resolution scale, `None` until the first resize, see `canvas_size`
+    unscaled_size: Rc<Cell<Option<(u32, u32)>>>,
+    resize_policy: Rc<Cell<ResizePolicy>>,
 
-    event_listeners: Vec<EventListener<'static>>,
+    event_listeners: Vec<EventListener<S>>,
+    /// updated by the pointer event listeners, `None` until `with_pointer_tracking`
+    pointers: Option<Rc<RefCell<Vec<pointer::PointerSnapshot>>>>,
+    /// `pointers` as of the start of the update
+    pointer_snapshot: Vec<pointer::PointerSnapshot>,
+    /// the wheel's vertical scroll in pixels since the previous update, `None` until `with_pointer_tracking`
+    wheel: Option<Rc<Cell<f64>>>,
+    /// `wheel` as of the start of the update
+    wheel_snapshot: f64,
+    /// the `KeyboardEvent.code`s of the keys that are held, `None` until `with_keyboard_tracking`
+    keys: Option<Rc<RefCell<HashSet<String>>>>,
+    /// `keys` as of the start of the update
+    key_snapshot: HashSet<String>,
+    /// whether `ConsoleLogger` is set as the global logger when the game loop starts
+    #[cfg(feature = "log")]
+    log_to_console: bool,
+    /// started by `RenderInfo::start_recording`, stopped when the game loop exits
+    #[cfg(feature = "capture")]
+    recorders: Vec<web_sys::MediaRecorder>,
+
+    #[cfg(all(feature = "webxr", web_sys_unstable_apis))]
+    xr: xr::Xr<S>,
 
     updates_per_second: u32,
     fixed_time_step: f64,
     max_frame_time: f64,
     accumulated_time: f64,
+    /// the sum of the (capped) elapsed times
+    running_time: f64,
+    /// the (capped) elapsed time of the latest frame
+    frame_time: f64,
     exit: bool,
+    /// the instant `run` started the game loop
+    start_instant: f64,
     previous_instant: f64,
+    /// the instant the latest `on_update` was called
+    last_update_instant: f64,
+    /// the instant the latest `on_render` was called
+    last_render_instant: f64,
 
     number_of_updates: u32,
     number_of_renders: u32,
+    /// the number of updates run by the latest frame
+    ticks_this_frame: u32,
+    /// the index of the running update within `ticks_this_frame`
+    current_tick_in_frame: u32,
 }
 
-struct EventListener<'a> {
+/// the single dom listener for an event type, which calls each of the renderer's callbacks for that type
+struct EventListener<S> {
     canvas: Rc<HtmlCanvasElement>,
-    event_type: &'a str,
+    event_type: &'static str,
+    passive: bool,
+    callbacks: PrioritisedCallbacks<S>,
     closure: Closure::<dyn Fn(JsValue)>,
 }
-impl Drop for EventListener<'_> {
+impl<S> Drop for EventListener<S> {
     fn drop(&mut self) {
-        let _ = self.canvas.remove_event_listener_with_callback(self.event_type, self.closure.as_ref().unchecked_ref());
+        // listeners are matched by their capture setting (which is always false) but not by `passive`
+        let _ = self.canvas.remove_event_listener_with_callback_and_bool(self.event_type, self.closure.as_ref().unchecked_ref(), false);
     }
 }
 
@@ -61,6 +344,54 @@ pub struct UpdateInfo<'a, S: 'static> {
     pub fn number_of_renders(&self) -> u32 {
         self.renderer.number_of_renders
     }
+    /// the wall time in seconds since the previous update started, eg. to see how long updates (and
+    /// the renders between them) are taking
+    ///
+    /// unlike `fixed_time_step` this isn't constant: updates that catch up on accumulated time run back to back
+    pub fn time_since_last_update(&self) -> f64 {
+        current_instant() - self.renderer.last_update_instant
+    }
+    /// the most time in seconds a single frame can accumulate, see `set_max_frame_time`
+    pub fn max_frame_time(&self) -> f64 {
+        self.renderer.max_frame_time
+    }
+    /// sets the most time in seconds a single frame can accumulate, eg. a longer catch-up window on
+    /// fast hardware or a shorter one on battery powered devices
+    ///
+    /// values that aren't positive and finite are ignored with a warning
+    pub fn set_max_frame_time(&mut self, seconds: f64) {
+        self.renderer.set_max_frame_time(seconds);
+    }
+    /// how many updates the current frame runs, more than one when catching up after a slow frame
+    pub fn ticks_this_frame(&self) -> u32 {
+        self.renderer.ticks_this_frame
+    }
+    /// the index of this update among the current frame's `ticks_this_frame`, starting from 0
+    pub fn current_tick_in_frame(&self) -> u32 {
+        self.renderer.current_tick_in_frame
+    }
+    /// whether this is the final update before the frame renders, eg. to only trigger sounds once
+    /// during a catch-up burst
+    pub fn is_last_tick_this_frame(&self) -> bool {
+        self.renderer.current_tick_in_frame + 1 == self.renderer.ticks_this_frame
+    }
+    /// the wall time in seconds since the game loop started, which unlike the `u_time` builtin isn't
+    /// capped by `max_frame_time` or paused while loading
+    pub fn time_since_start(&self) -> f64 {
+        current_instant() - self.renderer.start_instant
+    }
+    /// the css size of the canvas, which pointer positions are relative to
+    pub fn canvas_css_size(&self) -> (f64, f64) {
+        self.renderer.canvas_css_size()
+    }
+    /// changes the resolution scale at runtime (eg. to keep the frame rate up), resizing the drawing
+    /// buffer immediately rather than waiting for the canvas to be resized, see `Renderer::with_resolution_scale`
+    pub fn set_resolution_scale(&mut self, scale: f64) {
+        self.renderer.set_resolution_scale(scale);
+    }
+    pub fn resolution_scale(&self) -> f64 {
+        self.renderer.resolution_scale.get()
+    }
 }
 pub struct RenderInfo<'a, S: 'static> {
     pub state: &'a mut S,
@@ -84,114 +415,504 @@ pub struct RenderInfo<'a, S: 'static> {
     pub fn number_of_renders(&self) -> u32 {
         self.renderer.number_of_renders
     }
+    /// the wall time in seconds since the previous render started, eg. to skip expensive effects when
+    /// rendering more often than they need updating
+    pub fn time_since_last_render(&self) -> f64 {
+        current_instant() - self.renderer.last_render_instant
+    }
+    /// the most time in seconds a single frame can accumulate, see `UpdateInfo::set_max_frame_time`
+    pub fn max_frame_time(&self) -> f64 {
+        self.renderer.max_frame_time
+    }
+    /// see `UpdateInfo::set_max_frame_time`
+    pub fn set_max_frame_time(&mut self, seconds: f64) {
+        self.renderer.set_max_frame_time(seconds);
+    }
+    /// the wall time in seconds since the game loop started, see `UpdateInfo::time_since_start`
+    pub fn time_since_start(&self) -> f64 {
+        current_instant() - self.renderer.start_instant
+    }
     pub fn re_accumulate(&mut self) {
         self.renderer.accumulate(current_instant());
     }
     pub fn blending_factor(&self) -> f64 {
         self.renderer.accumulated_time / self.renderer.fixed_time_step
     }
+    /// the state from before the latest `on_update`, or the current state if `with_sub_tick_state`
+    /// wasn't called or there hasn't been an update yet
+    pub fn previous_state(&self) -> &S {
+        self.renderer.prev_state.as_ref().unwrap_or(self.state)
+    }
+    /// `field` of the previous state moved towards `field` of the current state by the blending factor,
+    /// eg. `render_info.interpolate_state(|state| state.player.x)`
+    pub fn interpolate_state(&self, field: impl Fn(&S) -> f32) -> f32 {
+        let (previous, current) = (field(self.previous_state()), field(self.state));
+        previous + (current - previous) * self.blending_factor() as f32
+    }
+    /// see `UpdateInfo::set_resolution_scale`, the drawing buffer is cleared by the resize
+    pub fn set_resolution_scale(&mut self, scale: f64) {
+        self.renderer.set_resolution_scale(scale);
+    }
+    pub fn resolution_scale(&self) -> f64 {
+        self.renderer.resolution_scale.get()
+    }
+    /// the logical size of the canvas, see `Renderer::canvas_size`
+    pub fn canvas_size(&self) -> (u32, u32) {
+        self.renderer.canvas_size()
+    }
+    pub fn canvas_css_size(&self) -> (f64, f64) {
+        self.renderer.canvas_css_size()
+    }
+    /// the size of the drawing buffer including the resolution scale, see `Renderer::render_size`
+    pub fn render_size(&self) -> (u32, u32) {
+        self.renderer.render_size()
+    }
+
+    /// binds the uniform block `block_name` of `program` to the uniform buffer binding point `binding`
+    ///
+    /// the block index is looked up once per program and then cached along with the binding, which is
+    /// part of the program's state so is only set again when it changes. This turns the naive
+    /// `get_uniform_block_index` + `uniform_block_binding` pair of calls into the javascript into a
+    /// single lookup of the program's id per draw:
+    /// ```text
+    /// 1000 draws, naive:  2000 ffi calls (1000 get_uniform_block_index, 1000 uniform_block_binding)
+    /// 1000 draws, cached: 1002 ffi calls (1000 program id lookups, 1 get_uniform_block_index, 1 uniform_block_binding)
+    /// ```
+    ///
+    /// bindings set directly on the context are not tracked
+    ///
+    /// errors if `program` has no active uniform block called `block_name`
+    pub fn bind_ubo_to_block(&mut self, program: &WebGlProgram, block_name: &str, binding: u32) -> Result<(), RendererError> {
+        let key = (self.renderer.program_id(program), block_name.to_owned());
+        let context = &self.renderer.context;
+        match self.renderer.program_uniform_block_cache.get_mut(&key) {
+            Some((_, cached_binding)) if *cached_binding == binding => {}
+            Some((index, cached_binding)) => {
+                context.uniform_block_binding(program, *index, binding);
+                *cached_binding = binding;
+            }
+            None => {
+                let index = context.get_uniform_block_index(program, block_name);
+                if index == WebGl2RenderingContext::INVALID_INDEX {
+                    return Err(RendererError::Unknown("uniform block", block_name.to_owned()));
+                }
+                context.uniform_block_binding(program, index, binding);
+                self.renderer.program_uniform_block_cache.insert(key, (index, binding));
+            }
+        }
+        Ok(())
+    }
+
+    /// sets the uniform `name` of the program in use (the one linked by `with_shaders` unless another
+    /// has been chosen with `use_shader`), caching its location
+    ///
+    /// `value` can be a `UniformValue` or, with the `glam` or `nalgebra` features, a vector, matrix or
+    /// slice of matrices from those crates, eg. `render_info.set_uniform_cached("u_mvp", &mvp)?`
+    ///
+    /// like `uniform_location` lookups in general, this silently does nothing if the uniform is not active
+    ///
+    /// the location is looked up once per program and then cached, so setting a uniform every draw costs
+    /// one call into the javascript instead of two:
+    /// ```text
+    /// 1000 draws, naive:  2000 ffi calls (1000 get_uniform_location, 1000 uniform*)
+    /// 1000 draws, cached: 1001 ffi calls (1 get_uniform_location, 1000 uniform*)
+    /// ```
+    ///
+    /// errors if no program is in use, ie. neither `with_shaders` nor `use_shader` has been called
+    pub fn set_uniform_cached(&mut self, name: &str, value: impl Uniform) -> Result<(), RendererError> {
+        let context = self.renderer.context.clone();
+        let (program, uniform_location_cache) = self.renderer.current_program_and_cache().ok_or(RendererError::NoProgram)?;
+        let location = uniform_location_cache
+            .entry(name.to_owned())
+            .or_insert_with(|| context.get_uniform_location(program, name));
+        value.upload(&context, location.as_ref());
+        Ok(())
+    }
 }
 
 impl<S> Drop for Renderer<S> {
     fn drop(&mut self) {
-        self.resize_observer.disconnect();
+        if let Some((resize_observer, _)) = &self.resize_observer {
+            resize_observer.disconnect();
+        }
+        if let Some(empty_vertex_array) = self.empty_vertex_array.get() {
+            self.context.delete_vertex_array(empty_vertex_array.as_ref());
+        }
+        extensions::evict_cache(&self.context);
     }
 }
 
 impl<S> Renderer<S> {
-    pub fn from_canvas(canvas: HtmlCanvasElement) -> Result<Renderer<S>, JsValue> {
-        
+    /// returns a builder to configure and construct a renderer
+    pub fn builder() -> RendererBuilder<S> {
+        RendererBuilder::new()
+    }
+
+    /// creates a renderer from `canvas`, kept for existing code that chains the `with_*` methods:
+    /// ```ignore
+    /// #[wasm_bindgen(start)]
+    /// pub fn start() -> Result<(), JsValue> {
+    ///     Renderer::from_canvas(canvas)?
+    ///         .with_on_render(on_render)?
+    ///         .with_shaders(vert_shader, frag_shader)?
+    ///         .with_on_event("keydown", on_keydown)?
+    ///         .start(state, 60, 0.1);
+    ///     Ok(())
+    /// }
+    /// ```
+    /// every step returns a `RendererError`, which converts into a `JsValue` (see `From<RendererError> for JsValue`)
+    /// so `?` still works in functions returning `Result<_, JsValue>`
+    #[deprecated(note = "use `Renderer::builder` instead")]
+    pub fn from_canvas(canvas: HtmlCanvasElement) -> Result<Renderer<S>, RendererError> {
+        Self::new(canvas)
+            .inspect_err(|err| console::log_at(LogLevel::default(), LogLevel::Errors, || format!("creating the renderer: {}", err)))
+    }
+
+    /// creates a renderer from `canvas` or, if it is `None`, a headless renderer drawing to a 1x1 `OffscreenCanvas`
+    ///
+    /// headless renderers are useful for testing rendering logic (shader compilation, buffer uploads, ...)
+    /// without a real DOM, they can't receive events and are ticked with `setTimeout` instead of
+    /// `requestAnimationFrame`
+    pub fn new_with_optional_canvas(canvas: Option<HtmlCanvasElement>) -> Result<Renderer<S>, RendererError> {
+        Self::create_with_optional_canvas(canvas)
+            .inspect_err(|err| console::log_at(LogLevel::default(), LogLevel::Errors, || format!("creating the renderer: {}", err)))
+    }
+
+    /// `new_with_optional_canvas` without logging errors
+    pub(crate) fn create_with_optional_canvas(canvas: Option<HtmlCanvasElement>) -> Result<Renderer<S>, RendererError> {
+        match canvas {
+            Some(canvas) => Self::new(canvas),
+            None => {
+                let offscreen_canvas = OffscreenCanvas::new(1, 1)?;
+                let context = webgl2_context(offscreen_canvas.get_context("webgl2")?)?;
+                Self::from_context(context, None)
+            }
+        }
+    }
+
+    /// errors with `RendererError::WebGl2Unavailable` if webgl2 isn't supported
+    pub(crate) fn new(canvas: HtmlCanvasElement) -> Result<Renderer<S>, RendererError> {
+        let context = webgl2_context(canvas.get_context("webgl2")?)?;
+
+        Self::from_canvas_and_context(canvas, context)
+    }
+
+    fn from_canvas_and_context(canvas: HtmlCanvasElement, context: WebGl2RenderingContext) -> Result<Renderer<S>, RendererError> {
         // makes canvas focusable and thus able to recieve key* events
         canvas.set_tab_index(0); // would use 1 but docs suggest only -1 and 0 should be used
 
-        let context = canvas
-            .get_context("webgl2")?
-            .unwrap()
-            .dyn_into::<WebGl2RenderingContext>()?;
+        let renderer = Self::from_context(context, Some(canvas))?;
+        let log_level = renderer.log_level.clone();
+        renderer.add_event_listener("webglcontextlost", true, i32::MIN, Box::new(move |_, _| {
+            console::log_at(log_level.get(), LogLevel::Warnings, || String::from("the webgl context was lost"));
+        }))
+    }
 
+    fn from_context(context: WebGl2RenderingContext, canvas: Option<HtmlCanvasElement>) -> Result<Renderer<S>, RendererError> {
         let context = Rc::new(context);
-        let canvas = Rc::new(canvas);
+        let canvas = canvas.map(Rc::new);
         let state = Rc::new(OnceCell::<RefCell<S>>::new());
         let on_resize = Rc::new(OnceCell::new());
+        let pixel_ratio = Rc::new(Cell::new(PixelRatio::default()));
+        let resolution_scale = Rc::new(Cell::new(1.0));
+        let unscaled_size = Rc::new(Cell::new(None));
+        let resize_policy = Rc::new(Cell::new(ResizePolicy::default()));
+        let log_level = Rc::new(Cell::new(LogLevel::default()));
+        let readback_pool = Rc::new(readback::BufferPool::new(&context));
+
+        let (resize, resize_observer): (Rc<dyn Fn()>, _) = match &canvas {
+            Some(canvas) => {
+                let rc_canvas = canvas.clone();
+                let rc_context = context.clone();
+                let rc_state = state.clone();
+                let rc_on_resize = on_resize.clone();
+                let rc_pixel_ratio = pixel_ratio.clone();
+                let rc_resolution_scale = resolution_scale.clone();
+                let rc_resize_policy = resize_policy.clone();
+                let rc_unscaled_size = unscaled_size.clone();
+                let rc_log_level = log_level.clone();
+                let last_entry = Rc::new(Cell::new(None));
+                let rc_last_entry = last_entry.clone();
+                let resize: Rc<dyn Fn()> = Rc::new(move || {
+                    if let Some(state) = rc_state.get() {
+                        let size = resize_canvas(&rc_canvas, &rc_context, state.borrow_mut().deref_mut(), rc_on_resize.get(), rc_pixel_ratio.get(), rc_resolution_scale.get(), rc_resize_policy.get(), rc_last_entry.get());
+                        rc_unscaled_size.set(Some(size));
+                        console::log_at(rc_log_level.get(), LogLevel::Verbose, || format!("resized the drawing buffer to {}x{}", rc_canvas.width(), rc_canvas.height()));
+                    }
+                });
 
-        let rc_canvas = canvas.clone();
-        let rc_context = context.clone();
-        let rc_state = state.clone();
-        let rc_on_resize = on_resize.clone();
-        let resize_closure = Closure::<dyn Fn()>::new(move || {
-            if let Some(state) = rc_state.get() {
-                resize_canvas(&rc_canvas, &rc_context, state.borrow_mut().deref_mut(), rc_on_resize.get())
+                let rc_resize = resize.clone();
+                let resize_closure = ResizeObserverCallback::new(move |entries: js_sys::Array| {
+                    // only the canvas is observed so the last entry is its most recent size
+                    if let Some(entry) = entries.iter().last() {
+                        last_entry.set(Some(ResizeObserverEntryData::from_entry(entry.unchecked_ref())));
+                    }
+                    rc_resize()
+                });
+                let resize_observer = web_sys::ResizeObserver::new(resize_closure.as_ref().unchecked_ref())?;
+                observe(&resize_observer, canvas);
+                watch_device_pixel_ratio(Rc::downgrade(&resize));
+                (resize, Some((resize_observer, resize_closure)))
             }
-        });
-        let resize_observer = web_sys::ResizeObserver::new(resize_closure.as_ref().unchecked_ref())?;
-        resize_observer.observe(&canvas);
-        
+            None => (Rc::new(|| {}), None),
+        };
+
         Ok(Renderer {
             canvas,
             context,
             state,
+
+            program: None,
+            program_ids: js_sys::WeakMap::new(),
+            next_program_id: 0,
+            program_uniform_block_cache: HashMap::new(),
+            uniform_location_cache: HashMap::new(),
+            named_shaders: HashMap::new(),
+            current_shader: None,
+            readback_pool,
+            pending_readbacks: Vec::new(),
+            gpu_timer_running: false,
+            pending_gpu_timers: Vec::new(),
+            gpu_timing_averages: HashMap::new(),
+            gpu_timings: HashMap::new(),
+            previous_draw_stats: DrawStats::default(),
+            bound_framebuffer_size: None,
+            texture_units: texture_units::TextureUnits::default(),
+            builtin_uniforms: None,
+            empty_vertex_array: OnceCell::new(),
+            wireframe_program: None,
+            blit_program: None,
+            clear_color: None,
+            clear_depth: None,
+            clear_stencil: None,
+            ortho_cache: Cell::new(None),
+            draw2d: None,
             
+            on_state_init: None,
             on_update: OnceCell::new(),
             on_render: OnceCell::new(),
+            on_error: OnceCell::new(),
+            paused: Rc::new(Cell::new(false)),
+            unloaded: Rc::new(Cell::new(false)),
+            unload_listener: None,
+            #[cfg(feature = "futures")]
+            on_exit: None,
+            #[cfg(feature = "futures")]
+            on_update_async: OnceCell::new(),
+            #[cfg(feature = "futures")]
+            loading: loading::Loading::default(),
+            snapshot_state: None,
+            prev_state: None,
+            update_systems: Vec::new(),
 
-            _resize_closure: resize_closure,
+            resize,
             resize_observer,
             on_resize,
+            on_resize_during_update: false,
+            prev_update_width: 0,
+            prev_update_height: 0,
+            pixel_ratio,
+            log_level,
+            resolution_scale,
+            unscaled_size,
+            resize_policy,
 
             event_listeners: Vec::new(),
+            pointers: None,
+            pointer_snapshot: Vec::new(),
+            wheel: None,
+            wheel_snapshot: 0.0,
+            keys: None,
+            key_snapshot: HashSet::new(),
+            #[cfg(feature = "log")]
+            log_to_console: false,
+            #[cfg(feature = "capture")]
+            recorders: Vec::new(),
+
+            #[cfg(all(feature = "webxr", web_sys_unstable_apis))]
+            xr: xr::Xr::default(),
 
             updates_per_second: 0,
             fixed_time_step: 0.0,
             max_frame_time: 0.0,
             accumulated_time: 0.0,
+            running_time: 0.0,
+            frame_time: 0.0,
             exit: false,
+            start_instant: 0.0,
             previous_instant: 0.0,
+            last_update_instant: 0.0,
+            last_render_instant: 0.0,
             number_of_updates: 0,
             number_of_renders: 0,
+            ticks_this_frame: 0,
+            current_tick_in_frame: 0,
         })
     }
 
     /// consumes self and starts the game loop.
     pub fn start(mut self, state: S, updates_per_second: u32, max_frame_time: f64) {
-        let _ = self.state.set(RefCell::new(state));
-        self.updates_per_second = updates_per_second;
-        self.fixed_time_step = 1.0 / updates_per_second as f64;
-        self.max_frame_time = max_frame_time;
-        self.next_frame()
+        self.set_state(state, LoopMode { updates_per_second, max_frame_time });
+        self.run()
         // game_loop(self, updates_per_second, max_frame_time, Self::update, Self::render);
     }
 
+    /// like `start` but with the state constructed by `S::default()`, eg. for demos with a simple state
+    ///
+    /// the state is still passed to the `with_state_init` callback before the first frame
+    pub fn start_default(self, updates_per_second: u32, max_frame_time: f64) where S: Default {
+        self.start(S::default(), updates_per_second, max_frame_time)
+    }
+
+    /// like `start` but with the state constructed by `init`, which is called immediately (before the
+    /// `with_state_init` callback)
+    pub fn start_with_init(self, init: impl FnOnce() -> S, updates_per_second: u32, max_frame_time: f64) {
+        self.start(init(), updates_per_second, max_frame_time)
+    }
+
+    /// like `start` but errors instead of failing silently, as a javascript `Error` (see
+    /// `From<RendererError> for JsValue`) so that it can be returned from a `#[wasm_bindgen]` function:
+    /// ```ignore
+    /// #[wasm_bindgen(start)]
+    /// pub fn main() -> Result<(), JsValue> {
+    ///     Renderer::new_with_optional_canvas(Some(canvas))?
+    ///         .with_shaders(VERT_SHADER, FRAG_SHADER)?
+    ///         .start_result(State::default(), 60, 0.1)
+    /// }
+    /// ```
+    ///
+    /// errors if there is no `window` to run the game loop with or the state has already been set (eg. by
+    /// `RendererBuilder::build`, in which case use `run`)
+    pub fn start_result(self, state: S, updates_per_second: u32, max_frame_time: f64) -> Result<(), JsValue> {
+        if window().is_none() {
+            return Err(RendererError::Unsupported("window").into());
+        }
+        if self.state.get().is_some() {
+            return Err(RendererError::AlreadySet("state").into());
+        }
+        self.start(state, updates_per_second, max_frame_time);
+        Ok(())
+    }
+
+    /// consumes self and starts the game loop with the state and loop mode given to `RendererBuilder::build`
+    pub fn run(mut self) {
+        #[cfg(feature = "log")]
+        self.init_logger();
+        self.log_validation();
+        self.init_state();
+        // so that the first frame's elapsed time is measured from now rather than from `timeOrigin`
+        self.start_instant = current_instant();
+        self.previous_instant = self.start_instant;
+        self.last_update_instant = self.start_instant;
+        self.last_render_instant = self.start_instant;
+        self.running_time = 0.0;
+        self.next_frame()
+    }
+
+    pub(crate) fn set_state(&mut self, state: S, loop_mode: LoopMode) {
+        let _ = self.state.set(RefCell::new(state));
+        self.updates_per_second = loop_mode.updates_per_second;
+        self.fixed_time_step = 1.0 / loop_mode.updates_per_second as f64;
+        self.max_frame_time = loop_mode.max_frame_time;
+    }
+
+    /// sets `max_frame_time` unless `seconds` isn't positive and finite, warning about values that allow
+    /// at most one update per frame
+    pub(crate) fn set_max_frame_time(&mut self, seconds: f64) {
+        if !(seconds > 0.0 && seconds.is_finite()) {
+            self.log(LogLevel::Warnings, || format!("ignoring a max frame time of {} seconds as it isn't positive and finite", seconds));
+            return;
+        }
+        if seconds < self.fixed_time_step {
+            self.log(LogLevel::Warnings, || format!(
+                "a max frame time of {} seconds is less than the fixed time step of {} seconds, so at most one update will run per frame",
+                seconds, self.fixed_time_step,
+            ));
+        }
+        self.max_frame_time = seconds;
+    }
+
     /// links shaders to a program and attaches the program to the context to allow for drawing
     /// 
     /// returns self for chaining
-    pub fn with_shaders(self, vert_shader: &str, frag_shader: &str) -> Result<Self, String> {
-        let vert_shader = compile_shader(&self.context, WebGl2RenderingContext::VERTEX_SHADER, vert_shader)
-            .map_err(|err| String::from("vertex shader: ") + &err)?;
-
-        let frag_shader = compile_shader(&self.context, WebGl2RenderingContext::FRAGMENT_SHADER, frag_shader)
-            .map_err(|err| String::from("fragment shader: ") + &err)?;
+    pub fn with_shaders(self, vert_shader: &str, frag_shader: &str) -> Result<Self, RendererError> {
+        self.with_shaders_configured(vert_shader, frag_shader, ShaderConfig::default())
+    }
 
-        let program = link_program(&self.context, &vert_shader, &frag_shader)?;
+    /// like `with_shaders` but preprocesses both shaders according to `config` first, eg:
+    /// ```ignore
+    /// renderer.with_shaders_configured(vert_shader, frag_shader, ShaderConfig::new()
+    ///     .with_glsl_version(GlslVersion::WebGl2)
+    ///     .with_define("MAX_LIGHTS", "8"))?
+    /// ```
+    /// 
+    /// returns self for chaining
+    pub fn with_shaders_configured(mut self, vert_shader: &str, frag_shader: &str, config: ShaderConfig) -> Result<Self, RendererError> {
+        let program = self.compile_program(&config.preprocess(vert_shader), &config.preprocess(frag_shader))?;
         self.context.use_program(Some(&program));
+        self.program = Some(program);
+        self.uniform_location_cache.clear();
+        self.current_shader = None;
+        if let Some(builtins) = &mut self.builtin_uniforms {
+            builtins.clear_locations();
+        }
         Ok(self)
     }
 
+    /// compiles and links the shaders, logging any failure
+    pub(crate) fn compile_program(&self, vert_shader: &str, frag_shader: &str) -> Result<WebGlProgram, RendererError> {
+        let compile_and_link = || {
+            let vert_shader = compile_shader(&self.context, WebGl2RenderingContext::VERTEX_SHADER, vert_shader)?;
+            let frag_shader = compile_shader(&self.context, WebGl2RenderingContext::FRAGMENT_SHADER, frag_shader)?;
+            link_program(&self.context, &vert_shader, &frag_shader)
+        };
+        compile_and_link().inspect_err(|err| self.log(LogLevel::Errors, || err.to_string()))
+    }
+
     /// adds an `on_update` function that is called `updates_per_second` times per second
     /// 
+    /// `on_update` always runs on the main thread and there is no worker variant: it is a function
+    /// pointer into this module instance's table, which a worker's separate instance can't call unless
+    /// both share memory and the module (wasm threads, which needs a nightly build with atomics), and
+    /// the main thread isn't allowed to block on `Atomics.wait` for the result. Expensive simulations
+    /// should instead spread their work across several updates
+    /// 
     /// returns self for chaining
     /// 
     /// errors if `on_update` has already been set
-    pub fn with_on_update(self, on_update: fn(UpdateInfo<S>)) -> Result<Self, ()> {
-        self.on_update.set(on_update).map_err(|_| ())?;
+    pub fn with_on_update(self, on_update: fn(UpdateInfo<S>)) -> Result<Self, RendererError> {
+        self.on_update.set(on_update).map_err(|_| RendererError::AlreadySet("on_update"))?;
         Ok(self)
     }
     fn update(&mut self) {
+        self.resize_if_changed();
+        self.snapshot_pointers();
+        self.snapshot_keys();
+        let update_instant = current_instant();
+        if let Some(snapshot_state) = self.snapshot_state {
+            self.prev_state = Some(snapshot_state(&self.state.get().unwrap().borrow()));
+        }
         if let Some(on_update) = self.on_update.get() {
             on_update(UpdateInfo {
                 state: self.state.clone().get().unwrap().borrow_mut().deref_mut(),
                 renderer: self,
             });
         }
+        self.last_update_instant = update_instant;
+        self.run_update_systems();
+        #[cfg(feature = "futures")]
+        self.update_async();
+    }
+
+    /// keeps a clone of the state from before the latest `on_update` so that renders can interpolate
+    /// between it and the current state, see `RenderInfo::interpolate_state`
+    ///
+    /// the state is cloned before every update so this is best kept to small states
+    ///
+    /// returns self for chaining
+    pub fn with_sub_tick_state(mut self) -> Self where S: Clone {
+        self.snapshot_state = Some(S::clone);
+        self
     }
 
     /// adds an `on_render` function that is called as often as is allowed by the web page
@@ -199,22 +920,35 @@ impl<S> Renderer<S> {
     /// returns self for chaining
     /// 
     /// errors if `on_render` has already been set
-    pub fn with_on_render(self, on_render: fn(RenderInfo<S>)) -> Result<Self, ()> {
-        self.on_render.set(on_render).map_err(|_| ())?;
+    pub fn with_on_render(self, on_render: fn(RenderInfo<S>)) -> Result<Self, RendererError> {
+        self.on_render.set(on_render).map_err(|_| RendererError::AlreadySet("on_render"))?;
         Ok(self)
     }
     fn render(&mut self) {
+        // discards anything counted outside a frame, eg. by another renderer on the page
+        stats::take();
+        self.set_builtin_uniforms();
+        self.clear_persistent();
+        let render_instant = current_instant();
         if let Some(on_render) = self.on_render.get() {
             on_render(RenderInfo {
                 state: self.state.clone().get().unwrap().borrow_mut().deref_mut(),
                 renderer: self,
             });
         }
+        self.flush_draw2d();
+        self.previous_draw_stats = stats::take();
+        self.last_render_instant = render_instant;
+        // checking forces the gpu to finish the frame so it is only done in debug builds
+        #[cfg(debug_assertions)]
+        while let Some(error) = check_gl_error(&self.context) {
+            self.log(LogLevel::Errors, || format!("webgl error after on_render: {}", error));
+        }
     }
 
     /// adds a custom event listener (that will not receive events until `start` is called) with a callback that has an `Event` argument.
     /// For most event types, this should be casted to the appropriate `*Event`:
-    /// ```
+    /// ```ignore
     /// renderer = renderer.with_on_event("keydown", on_keydown).unwrap();
     /// ...
     /// fn on_keydown(event: web_sys::Event, state: &mut S) {
@@ -222,128 +956,343 @@ impl<S> Renderer<S> {
     ///     ...
     /// }
     /// ```
+    /// values that aren't an `Event` are ignored, see `with_on_raw_event` to receive them
     /// 
     /// returns self for chaining
     /// 
-    /// errors if on_resize has already been set
-    pub fn with_on_event(mut self, event_type: &'static str, on_event: fn(&mut S, web_sys::Event)) -> Result<Self, JsValue> {
+    /// errors if the event listener could not be added or the renderer is headless
+    pub fn with_on_event(self, event_type: &'static str, on_event: OnEvent<S>) -> Result<Self, RendererError> {
+        self.with_on_event_with_priority(event_type, 0, on_event)
+    }
+
+    /// like `with_on_event` but with a `priority` (`0` for `with_on_event`) that orders the callbacks
+    /// for the same event type, lower priorities are called first and callbacks with the same priority
+    /// are called in the order they were added
+    /// 
+    /// if a callback calls `stop_propagation` (or `stop_immediate_propagation`) on the event then the
+    /// remaining callbacks are skipped, eg. so that ui can consume input before the game sees it:
+    /// ```ignore
+    /// renderer
+    ///     .with_on_event_with_priority("keydown", -10, on_ui_keydown)?
+    ///     .with_on_event("keydown", on_game_keydown)?
+    /// ```
+    /// 
+    /// returns self for chaining
+    /// 
+    /// errors if the event listener could not be added or the renderer is headless
+    pub fn with_on_event_with_priority(self, event_type: &'static str, priority: i32, on_event: OnEvent<S>) -> Result<Self, RendererError> {
+        self.add_event_listener(event_type, false, priority, event_callback(on_event))
+    }
+
+    /// like `with_on_event` but the listener is passive, which tells the browser that `prevent_default`
+    /// won't be called so it can scroll without waiting for the callback. Browsers warn about
+    /// `touchstart`, `touchmove` and `wheel` listeners that aren't passive
+    /// 
+    /// passive callbacks are called by a separate dom listener so they aren't ordered with (or skipped by
+    /// `stop_propagation` in) the other callbacks for the same event type, and `prevent_default` has no effect in them
+    /// 
+    /// returns self for chaining
+    /// 
+    /// errors if the event listener could not be added or the renderer is headless
+    pub fn with_on_passive_event(self, event_type: &'static str, on_event: OnEvent<S>) -> Result<Self, RendererError> {
+        self.add_event_listener(event_type, true, 0, event_callback(on_event))
+    }
+
+    /// like `with_on_event` but passes whatever value the listener receives without casting it to an
+    /// `Event`, eg. for custom events dispatched by other javascript or to inspect what the browser sends
+    /// 
+    /// returns self for chaining
+    /// 
+    /// errors if the event listener could not be added or the renderer is headless
+    pub fn with_on_raw_event(self, event_type: &'static str, on_raw_event: OnRawEvent<S>) -> Result<Self, RendererError> {
+        self.add_event_listener(event_type, false, 0, Box::new(move |state, event| on_raw_event(state, event.clone())))
+    }
+
+    /// adds `callback` to the dom listener for `event_type` (and `passive`), adding the listener if there isn't one yet
+    fn add_event_listener(mut self, event_type: &'static str, passive: bool, priority: i32, callback: EventCallback<S>) -> Result<Self, RendererError> {
+        let canvas = self.canvas.clone().ok_or(RendererError::Headless)?;
+        if let Some(event_listener) = self.event_listeners.iter().find(|event_listener| event_listener.event_type == event_type && event_listener.passive == passive) {
+            event_listener.callbacks.borrow_mut().entry(priority).or_default().push(callback);
+            return Ok(self);
+        }
+
+        let callbacks = PrioritisedCallbacks::<S>::default();
+        callbacks.borrow_mut().entry(priority).or_default().push(callback);
         let rc_state = self.state.clone();
+        let rc_callbacks = callbacks.clone();
+        let rc_log_level = self.log_level.clone();
         let closure = Closure::<dyn Fn(JsValue)>::new(move |event: JsValue| {
             if let Some(state) = rc_state.get() { // if state has been set then the loop has been started
-                on_event(state.borrow_mut().deref_mut(), event.dyn_into::<Event>().unwrap())
+                console::log_at(rc_log_level.get(), LogLevel::Verbose, || format!("dispatching a `{}` event", event_type));
+                let mut state = state.borrow_mut();
+                for callback in rc_callbacks.borrow().values().flatten() {
+                    callback(state.deref_mut(), &event);
+                    if event.dyn_ref::<Event>().is_some_and(Event::cancel_bubble) { break }
+                }
             }
         });
-        self.canvas.add_event_listener_with_callback(event_type, closure.as_ref().unchecked_ref())?;
+        let options = AddEventListenerOptions::new();
+        options.set_passive(passive);
+        canvas.add_event_listener_with_callback_and_add_event_listener_options(event_type, closure.as_ref().unchecked_ref(), &options)?;
 
         let event_listener = EventListener {
-            canvas: self.canvas.clone(),
+            canvas,
             event_type,
+            passive,
+            callbacks,
             closure,
         };
         self.event_listeners.push(event_listener);
         Ok(self)
     }
 
-    /// adds an 'on_resize' event listener (that also optionally mutates the size)
+    /// adds an 'on_resize' event listener that receives the css size of the canvas and the drawing-buffer
+    /// size chosen according to the pixel ratio, and returns the drawing-buffer size to actually use
     /// 
     /// returns self for chaining
     /// 
     /// errors if on_resize has already been set
-    pub fn with_on_resize(self, on_resize: fn(&mut S, (u32, u32)) -> (u32, u32)) -> Result<Self, ()> {
-        self.on_resize.set(on_resize).map_err(|_| ())?;
+    pub fn with_on_resize(self, on_resize: OnResize<S>) -> Result<Self, RendererError> {
+        self.on_resize.set(ResizeCallback::Size(on_resize)).map_err(|_| RendererError::AlreadySet("on_resize"))?;
+        Ok(self)
+    }
+
+    /// like `with_on_resize` but receives the size reported by the canvas's `ResizeObserver`, which
+    /// includes the exact device-pixel size of the canvas where supported, and returns the
+    /// drawing-buffer size to use, eg:
+    /// ```ignore
+    /// fn on_resize_entry(_state: &mut State, entry: ResizeObserverEntryData) -> (u32, u32) {
+    ///     (entry.device_pixel_width as u32, entry.device_pixel_height as u32)
+    /// }
+    /// ```
+    /// the pixel ratio is ignored when this is set
+    /// 
+    /// returns self for chaining
+    /// 
+    /// errors if on_resize or on_resize_entry has already been set
+    pub fn with_on_resize_entry(self, on_resize_entry: OnResizeEntry<S>) -> Result<Self, RendererError> {
+        self.on_resize.set(ResizeCallback::Entry(on_resize_entry)).map_err(|_| RendererError::AlreadySet("on_resize"))?;
         Ok(self)
     }
 
+    /// whether the canvas's css size is also checked at the start of each update tick, resizing (and
+    /// calling `on_resize`) before `on_update` if it has changed, so that a simulation sees the new size
+    /// in the same tick rather than after the `ResizeObserver` fires between frames (`false` by default)
+    /// 
+    /// returns self for chaining
+    pub fn with_on_resize_during_update(mut self, on_resize_during_update: bool) -> Self {
+        self.on_resize_during_update = on_resize_during_update;
+        self
+    }
+
+    /// resizes if `on_resize_during_update` is set and the canvas's css size changed since the latest update
+    fn resize_if_changed(&mut self) {
+        if !self.on_resize_during_update {
+            return;
+        }
+        let Some(canvas) = &self.canvas else { return };
+        let (width, height) = (canvas.client_width() as u32, canvas.client_height() as u32);
+        if (width, height) != (self.prev_update_width, self.prev_update_height) {
+            (self.prev_update_width, self.prev_update_height) = (width, height);
+            (self.resize)();
+        }
+    }
+
+    /// sets how many drawing-buffer pixels are used per css pixel (`PixelRatio::Auto` by default),
+    /// resizing immediately if the game loop has started
+    /// 
+    /// returns self for chaining
+    pub fn with_pixel_ratio(self, pixel_ratio: PixelRatio) -> Self {
+        self.pixel_ratio.set(pixel_ratio);
+        (self.resize)();
+        self
+    }
+
+    /// multiplies the size of the drawing buffer (after `on_resize`) by `scale`, eg. `0.5` to render at
+    /// half the width and height to keep the frame rate up or `2.0` to supersample. The css size is left
+    /// alone so the browser stretches the canvas to fill its layout box
+    ///
+    /// `scale` must be positive and finite, otherwise it is ignored with a warning. Resizes immediately
+    /// if the game loop has started
+    ///
+    /// returns self for chaining
+    pub fn with_resolution_scale(mut self, scale: f64) -> Self {
+        self.set_resolution_scale(scale);
+        self
+    }
+
+    /// sets the resolution scale and resizes the drawing buffer to match it
+    pub(crate) fn set_resolution_scale(&mut self, scale: f64) {
+        if !(scale > 0.0 && scale.is_finite()) {
+            self.log(LogLevel::Warnings, || format!("ignoring a resolution scale of {} as it isn't positive and finite", scale));
+            return;
+        }
+        self.resolution_scale.set(scale);
+        // `resize` borrows the state, which is already borrowed for the duration of the callbacks, so the
+        // size from the latest resize is scaled instead of asking `on_resize` again
+        if let (Some(canvas), Some(size)) = (&self.canvas, self.unscaled_size.get()) {
+            set_buffer_size(canvas, &self.context, size, scale, self.resize_policy.get());
+        }
+    }
+
+    /// the logical size of the canvas in pixels, ie. the drawing buffer's size before the resolution
+    /// scale (the size from `on_resize`), or the drawing buffer's size if the renderer is headless or
+    /// hasn't been resized yet
+    pub fn canvas_size(&self) -> (u32, u32) {
+        self.unscaled_size.get().unwrap_or_else(|| self.render_size())
+    }
+
+    /// the css size of the canvas, which pointer positions are relative to, or the drawing buffer's
+    /// size if the renderer is headless
+    pub fn canvas_css_size(&self) -> (f64, f64) {
+        match &self.canvas {
+            Some(canvas) => (canvas.client_width() as f64, canvas.client_height() as f64),
+            None => {
+                let (width, height) = self.render_size();
+                (width as f64, height as f64)
+            }
+        }
+    }
+
+    /// the size of the drawing buffer, which is `canvas_size` scaled by the resolution scale (see
+    /// `with_resolution_scale`), eg. to size render targets that are drawn onto the canvas
+    pub fn render_size(&self) -> (u32, u32) {
+        (self.context.drawing_buffer_width() as u32, self.context.drawing_buffer_height() as u32)
+    }
+
+    /// whether the renderer was created without a canvas, see `new_with_optional_canvas`
+    pub fn is_headless(&self) -> bool {
+        self.canvas.is_none()
+    }
+
     fn next_frame(mut self) {
-        if self.exit { return }
+        if self.is_exiting() {
+            return self.exit_loop();
+        }
+
+        #[cfg(all(feature = "webxr", web_sys_unstable_apis))]
+        if self.is_xr() { return self.request_xr_frame() }
 
         let current_instant = current_instant();
+        self.log(LogLevel::Verbose, || format!("next_frame at {:.1}ms (render {})", current_instant * 1000.0, self.number_of_renders));
 
-        self.accumulate(current_instant);
+        #[cfg(feature = "futures")]
+        let loading = self.poll_loading(current_instant);
+        #[cfg(not(feature = "futures"))]
+        let loading = false;
+        if !loading && !self.paused.get() {
+            self.accumulate(current_instant);
+            self.run_guarded(Self::frame);
+        }
 
-        while self.accumulated_time >= self.fixed_time_step {
-            Self::update(&mut self);
+        self.previous_instant = current_instant;
+        // breaks the closure chain as soon as the loop exits instead of requesting one more frame,
+        // which would keep the renderer alive until then
+        if self.is_exiting() {
+            return self.exit_loop();
+        }
 
-            self.accumulated_time -= self.fixed_time_step;
-            self.number_of_updates += 1;
+        let headless = self.is_headless();
+        let closure = Closure::once_into_js(move || self.next_frame());
+        if headless {
+            window().unwrap().set_timeout_with_callback_and_timeout_and_arguments_0(closure.as_ref().unchecked_ref(), HEADLESS_FRAME_MILLIS).unwrap();
+        } else {
+            window().unwrap().request_animation_frame(closure.as_ref().unchecked_ref()).unwrap();
         }
+    }
+
+    /// cleans up after the game loop exits, just before the renderer is dropped
+    fn exit_loop(&mut self) {
+        #[cfg(feature = "capture")]
+        self.stop_recorders();
+        #[cfg(feature = "futures")]
+        self.notify_exit();
+    }
+
+    /// runs the updates for the accumulated time and then renders
+    fn frame(&mut self) {
+        self.poll_buffer_readbacks();
+        self.poll_gpu_timers();
+        self.run_updates();
 
         // self.blending_factor = self.accumulated_time / self.fixed_time_step;
 
-        Self::render(&mut self);
+        Self::render(self);
         self.number_of_renders += 1;
+    }
 
-        self.previous_instant = current_instant;
-        
-        let closure = Closure::once_into_js(move || self.next_frame());
-        window().unwrap().request_animation_frame(closure.as_ref().unchecked_ref()).unwrap();
+    /// runs `on_update` until less than a fixed time step of time is accumulated
+    fn run_updates(&mut self) {
+        // counted up front (with the same subtractions as the updates) so that each update knows
+        // whether it is the last one of the frame
+        let mut remaining = self.accumulated_time;
+        self.ticks_this_frame = 0;
+        while remaining >= self.fixed_time_step {
+            remaining -= self.fixed_time_step;
+            self.ticks_this_frame += 1;
+        }
+
+        for tick in 0..self.ticks_this_frame {
+            self.current_tick_in_frame = tick;
+            Self::update(self);
+
+            self.accumulated_time -= self.fixed_time_step;
+            self.number_of_updates += 1;
+        }
     }
 
     fn accumulate(&mut self, current_instant: f64) {
-        let mut elapsed = current_instant - &self.previous_instant;
+        self.accumulate_elapsed(current_instant - self.previous_instant);
+    }
+
+    fn accumulate_elapsed(&mut self, mut elapsed: f64) {
         if elapsed > self.max_frame_time { elapsed = self.max_frame_time; }
 
-        // self.running_time += elapsed;
+        self.running_time += elapsed;
+        self.frame_time = elapsed;
         self.accumulated_time += elapsed;
     }
-}
 
-/// returns time since `timeOrigin` in seconds
-///
-fn current_instant() -> f64 {
-    window().unwrap().performance().unwrap().now() / 1000.0
+    /// synchronously runs a single frame as if `delta_seconds` had passed since the previous one
+    /// (still capped by `max_frame_time`), for testing update and render logic without `requestAnimationFrame`
+    ///
+    /// the renderer must have been created with `RendererBuilder::build` so that the state is set
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn tick_once(&mut self, delta_seconds: f64) {
+        self.init_state();
+        self.accumulate_elapsed(delta_seconds);
+        self.frame();
+    }
+
+    /// runs `tick_once` `n` times
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn tick_n(&mut self, n: u32, delta_seconds: f64) {
+        for _ in 0..n {
+            self.tick_once(delta_seconds);
+        }
+    }
 }
 
-fn resize_canvas<S>(canvas: &HtmlCanvasElement, context: &WebGl2RenderingContext, state: &mut S, on_resize: Option<&fn(&mut S, (u32, u32)) -> (u32, u32)>) {
+/// the context returned by `getContext("webgl2")`, which is `None` when webgl2 is unavailable
+fn webgl2_context(context: Option<js_sys::Object>) -> Result<WebGl2RenderingContext, RendererError> {
+    context
+        .ok_or(RendererError::WebGl2Unavailable)?
+        .dyn_into::<WebGl2RenderingContext>()
+        .map_err(|_| RendererError::ContextCastFailed)
+}
 
-    let mut width = canvas.client_width() as u32;
-    let mut height = canvas.client_height() as u32;
-    if let Some(on_resize) = on_resize {
-        (width, height) = on_resize(state, (width, height));
-    }
-    canvas.set_width(width);
-    canvas.set_height(height);
-    context.viewport(0, 0, width as i32, height as i32);
+/// wraps an `OnEvent` to ignore values that aren't an `Event`
+fn event_callback<S: 'static>(on_event: OnEvent<S>) -> EventCallback<S> {
+    Box::new(move |state, event| {
+        if let Some(event) = event.dyn_ref::<Event>() {
+            on_event(state, event.clone())
+        }
+    })
 }
 
-fn compile_shader(context: &WebGl2RenderingContext, shader_type: u32, source: &str) -> Result<WebGlShader, String> {
-    let shader = context.create_shader(shader_type)
-        .ok_or_else(|| String::from("Unable to create shader object"))?;
-    
-    context.shader_source(&shader, source);
-    context.compile_shader(&shader);
-
-    if context
-        .get_shader_parameter(&shader, WebGl2RenderingContext::COMPILE_STATUS)
-        .as_bool()
-        .unwrap_or(false)
-    {
-        Ok(shader)
-    } else {
-        Err(context
-            .get_shader_info_log(&shader)
-            .unwrap_or_else(|| String::from("Unknown error creating shader")))
-    }
+/// returns time since `timeOrigin` in seconds
+///
+fn current_instant() -> f64 {
+    window().unwrap().performance().unwrap().now() / 1000.0
 }
 
-fn link_program(context: &WebGl2RenderingContext, vert_shader: &WebGlShader, frag_shader: &WebGlShader) -> Result<WebGlProgram, String> {
-    let program = context
-        .create_program()
-        .ok_or_else(|| String::from("Unable to create program object"))?;
-
-    context.attach_shader(&program, vert_shader);
-    context.attach_shader(&program, frag_shader);
-    context.link_program(&program);
-
-    if context
-        .get_program_parameter(&program, WebGl2RenderingContext::LINK_STATUS)
-        .as_bool()
-        .unwrap_or(false)
-    {
-        Ok(program)
-    } else {
-        Err(context
-            .get_program_info_log(&program)
-            .unwrap_or_else(|| String::from("Unknown error linking shader objects to program object")))
-    }
-}
\ No newline at end of file
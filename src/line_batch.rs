@@ -0,0 +1,316 @@
+use wasm_bindgen::JsCast;
+use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlProgram, WebGlUniformLocation, WebGlVertexArrayObject};
+
+use crate::{RenderInfo, RendererError, compile_shader, link_program};
+use crate::stats;
+
+type Gl = WebGl2RenderingContext;
+
+const LINE_VERTEX_SHADER: &str = "\
+#version 300 es
+layout(location = 0) in vec2 a_position;
+layout(location = 1) in vec4 a_color;
+uniform mat4 u_view_proj;
+out vec4 v_color;
+
+void main() {
+    v_color = a_color;
+    gl_Position = u_view_proj * vec4(a_position, 0.0, 1.0);
+}
+";
+
+const LINE_FRAGMENT_SHADER: &str = "\
+#version 300 es
+precision mediump float;
+in vec4 v_color;
+out vec4 color;
+
+void main() {
+    color = v_color;
+}
+";
+
+/// the floats per vertex: a position and a color
+const VERTEX_FLOATS: usize = 6;
+
+/// how wide a line of a `LineBatch` is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineWidth {
+    /// in screen pixels, so the line stays the same width however far the camera is zoomed
+    Pixels(f32),
+    /// in world units, so the line scales with the camera's zoom
+    World(f32),
+}
+
+/// how the ends of an open polyline are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineCap {
+    /// ends exactly at the end points
+    #[default]
+    Butt,
+    /// extends past the end points by half the width
+    Square,
+}
+
+struct Polyline {
+    points: Vec<([f32; 2], [f32; 4])>,
+    width: LineWidth,
+    closed: bool,
+}
+
+/// thick 2d lines, which `LINES` can't draw as webgl clamps their width to 1 almost everywhere.
+/// Polylines are expanded into triangles on the cpu (with mitered joins) and drawn with a single draw call:
+/// ```ignore
+/// let mut lines = LineBatch::new(context)?;
+/// ...
+/// // in `on_render`
+/// lines.clear();
+/// lines.polyline(&graph_points, LineWidth::Pixels(2.0));
+/// lines.closed_polyline(&selection_outline, LineWidth::World(0.1));
+/// lines.draw(&mut render_info, view_proj, units_per_pixel)?;
+/// ```
+pub struct LineBatch {
+    context: WebGl2RenderingContext,
+    program: WebGlProgram,
+    view_proj_location: Option<WebGlUniformLocation>,
+    vertex_array: WebGlVertexArrayObject,
+    buffer: WebGlBuffer,
+    polylines: Vec<Polyline>,
+    cap: LineCap,
+    miter_limit: f32,
+    /// reused between draws
+    vertices: Vec<f32>,
+}
+
+impl LineBatch {
+    /// errors if the program, buffer or vertex array object could not be created
+    pub fn new(context: &WebGl2RenderingContext) -> Result<LineBatch, RendererError> {
+        let vert_shader = compile_shader(context, Gl::VERTEX_SHADER, LINE_VERTEX_SHADER)?;
+        let frag_shader = compile_shader(context, Gl::FRAGMENT_SHADER, LINE_FRAGMENT_SHADER)?;
+        let program = link_program(context, &vert_shader, &frag_shader);
+        context.delete_shader(Some(&vert_shader));
+        context.delete_shader(Some(&frag_shader));
+        let program = program?;
+
+        let buffer = context.create_buffer().ok_or(RendererError::Create("buffer"))?;
+        let vertex_array = context.create_vertex_array().ok_or(RendererError::Create("vertex array"))?;
+        let previous = context.get_parameter(Gl::VERTEX_ARRAY_BINDING).ok()
+            .and_then(|value| value.dyn_into::<WebGlVertexArrayObject>().ok());
+        context.bind_vertex_array(Some(&vertex_array));
+        context.bind_buffer(Gl::ARRAY_BUFFER, Some(&buffer));
+        let stride = (VERTEX_FLOATS * size_of::<f32>()) as i32;
+        context.enable_vertex_attrib_array(0);
+        context.vertex_attrib_pointer_with_i32(0, 2, Gl::FLOAT, false, stride, 0);
+        context.enable_vertex_attrib_array(1);
+        context.vertex_attrib_pointer_with_i32(1, 4, Gl::FLOAT, false, stride, 2 * size_of::<f32>() as i32);
+        context.bind_vertex_array(previous.as_ref());
+
+        Ok(LineBatch {
+            context: context.clone(),
+            view_proj_location: context.get_uniform_location(&program, "u_view_proj"),
+            program,
+            vertex_array,
+            buffer,
+            polylines: Vec::new(),
+            cap: LineCap::default(),
+            miter_limit: 4.0,
+            vertices: Vec::new(),
+        })
+    }
+
+    /// adds an open polyline through `points`, each with its own color (which is blended along the segments)
+    pub fn polyline(&mut self, points: &[([f32; 2], [f32; 4])], width: LineWidth) {
+        self.push(points, width, false);
+    }
+    /// adds a polyline through `points` that joins the last point back to the first
+    pub fn closed_polyline(&mut self, points: &[([f32; 2], [f32; 4])], width: LineWidth) {
+        self.push(points, width, true);
+    }
+    /// adds a single segment from `start` to `end`
+    pub fn line(&mut self, start: [f32; 2], end: [f32; 2], color: [f32; 4], width: LineWidth) {
+        self.push(&[(start, color), (end, color)], width, false);
+    }
+    fn push(&mut self, points: &[([f32; 2], [f32; 4])], width: LineWidth, closed: bool) {
+        let mut deduped: Vec<([f32; 2], [f32; 4])> = Vec::with_capacity(points.len());
+        for &point in points {
+            // zero length segments have no direction to expand along
+            if deduped.last().is_none_or(|last| last.0 != point.0) {
+                deduped.push(point);
+            }
+        }
+        if closed && deduped.len() > 1 && deduped.first().map(|first| first.0) == deduped.last().map(|last| last.0) {
+            deduped.pop();
+        }
+        self.polylines.push(Polyline { points: deduped, width, closed });
+    }
+
+    /// removes every line, eg. at the start of each frame
+    pub fn clear(&mut self) {
+        self.polylines.clear();
+    }
+    pub fn is_empty(&self) -> bool {
+        self.polylines.is_empty()
+    }
+
+    /// sets how the ends of open polylines are drawn, `LineCap::Butt` by default
+    pub fn set_cap(&mut self, cap: LineCap) {
+        self.cap = cap;
+    }
+    /// sets how far (as a multiple of half the width) a miter join can reach before it is beveled
+    /// instead, `4.0` by default
+    pub fn set_miter_limit(&mut self, miter_limit: f32) {
+        self.miter_limit = miter_limit.max(1.0);
+    }
+
+    /// draws every line with blending as it is and the previously bound program and vertex array
+    /// object rebound afterwards
+    ///
+    /// `view_proj` is the (column-major) view-projection matrix and `units_per_pixel` is how many world
+    /// units a screen pixel covers (eg. `1.0 / zoom` for a 2d camera whose zoom is in pixels per unit),
+    /// which `LineWidth::Pixels` widths are scaled by
+    ///
+    /// errors if the vertices could not be uploaded
+    pub fn draw<S>(&mut self, render_info: &mut RenderInfo<S>, view_proj: [[f32; 4]; 4], units_per_pixel: f32) -> Result<(), RendererError> {
+        self.vertices.clear();
+        for polyline in &self.polylines {
+            let width = match polyline.width {
+                LineWidth::Pixels(pixels) => pixels * units_per_pixel,
+                LineWidth::World(units) => units,
+            };
+            expand(&polyline.points, width / 2.0, polyline.closed, self.cap, self.miter_limit, &mut self.vertices);
+        }
+        let vertex_count = self.vertices.len() / VERTEX_FLOATS;
+        if vertex_count == 0 {
+            return Ok(());
+        }
+
+        let context = &self.context;
+        let previous_program = context.get_parameter(Gl::CURRENT_PROGRAM).ok()
+            .and_then(|value| value.dyn_into::<WebGlProgram>().ok());
+        let previous_vertex_array = context.get_parameter(Gl::VERTEX_ARRAY_BINDING).ok()
+            .and_then(|value| value.dyn_into::<WebGlVertexArrayObject>().ok());
+
+        context.bind_buffer(Gl::ARRAY_BUFFER, Some(&self.buffer));
+        // SAFETY: the view is only used to upload the vertices, without allocating, before they are next modified
+        unsafe {
+            context.buffer_data_with_array_buffer_view(Gl::ARRAY_BUFFER, &js_sys::Float32Array::view(&self.vertices), Gl::STREAM_DRAW);
+        }
+        context.use_program(Some(&self.program));
+        context.uniform_matrix4fv_with_f32_array(self.view_proj_location.as_ref(), false, view_proj.as_flattened());
+        context.bind_vertex_array(Some(&self.vertex_array));
+        context.draw_arrays(Gl::TRIANGLES, 0, vertex_count as i32);
+        render_info.note_draw_call((vertex_count / 3) as u32);
+        stats::count(|stats| stats.program_switches += 1);
+
+        context.bind_vertex_array(previous_vertex_array.as_ref());
+        context.use_program(previous_program.as_ref());
+        Ok(())
+    }
+}
+
+impl Drop for LineBatch {
+    fn drop(&mut self) {
+        self.context.delete_program(Some(&self.program));
+        self.context.delete_buffer(Some(&self.buffer));
+        self.context.delete_vertex_array(Some(&self.vertex_array));
+    }
+}
+
+fn add(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] + b[0], a[1] + b[1]]
+}
+fn scale(a: [f32; 2], s: f32) -> [f32; 2] {
+    [a[0] * s, a[1] * s]
+}
+fn normalize(a: [f32; 2]) -> [f32; 2] {
+    scale(a, 1.0 / a[0].hypot(a[1]))
+}
+/// the left-hand normal of the direction `d`
+fn normal(d: [f32; 2]) -> [f32; 2] {
+    [-d[1], d[0]]
+}
+
+/// where the segments on either side of a point start and end, `[left, right]`
+struct Joint {
+    incoming: [[f32; 2]; 2],
+    outgoing: [[f32; 2]; 2],
+    /// fills the gap on the outside of a beveled join
+    bevel: Option<[[f32; 2]; 3]>,
+}
+
+/// appends the triangles of a polyline to `vertices`, `points` must not have consecutive duplicates
+fn expand(points: &[([f32; 2], [f32; 4])], half_width: f32, closed: bool, cap: LineCap, miter_limit: f32, vertices: &mut Vec<f32>) {
+    let n = points.len();
+    if n < 2 {
+        return;
+    }
+    let position = |i: usize| points[i % n].0;
+    let direction = |i: usize| {
+        let (from, to) = (position(i), position(i + 1));
+        normalize([to[0] - from[0], to[1] - from[1]])
+    };
+    let cap_extension = match cap {
+        LineCap::Butt => 0.0,
+        LineCap::Square => half_width,
+    };
+
+    let joint = |i: usize| -> Joint {
+        let p = position(i);
+        let end = |d: [f32; 2], extension: f32| {
+            let p = add(p, scale(d, extension));
+            let offset = scale(normal(d), half_width);
+            let sides = [add(p, offset), add(p, scale(offset, -1.0))];
+            Joint { incoming: sides, outgoing: sides, bevel: None }
+        };
+        if !closed && i == 0 {
+            return end(direction(0), -cap_extension);
+        }
+        if !closed && i == n - 1 {
+            return end(direction(n - 2), cap_extension);
+        }
+        let (d0, d1) = (direction(i + n - 1), direction(i));
+        let (n0, n1) = (normal(d0), normal(d1));
+        let sum = add(n0, n1);
+        let sum_length = sum[0].hypot(sum[1]);
+        if sum_length > 1e-4 {
+            let miter = scale(sum, 1.0 / sum_length);
+            // the miter reaches out to where the offset edges of the two segments meet
+            let miter_length = 1.0 / (miter[0] * n1[0] + miter[1] * n1[1]);
+            if miter_length <= miter_limit {
+                let offset = scale(miter, half_width * miter_length);
+                let sides = [add(p, offset), add(p, scale(offset, -1.0))];
+                return Joint { incoming: sides, outgoing: sides, bevel: None };
+            }
+        }
+        let (offset0, offset1) = (scale(n0, half_width), scale(n1, half_width));
+        // turning left leaves the gap on the right
+        let outer_sign = if d0[0] * d1[1] - d0[1] * d1[0] > 0.0 { -1.0 } else { 1.0 };
+        Joint {
+            incoming: [add(p, offset0), add(p, scale(offset0, -1.0))],
+            outgoing: [add(p, offset1), add(p, scale(offset1, -1.0))],
+            bevel: Some([p, add(p, scale(offset0, outer_sign)), add(p, scale(offset1, outer_sign))]),
+        }
+    };
+
+    let mut push = |position: [f32; 2], color: [f32; 4]| vertices.extend(position.into_iter().chain(color));
+    let segments = if closed { n } else { n - 1 };
+    let joints: Vec<Joint> = (0..n).map(joint).collect();
+    for i in 0..segments {
+        let j = (i + 1) % n;
+        let (start, end) = (&joints[i].outgoing, &joints[j].incoming);
+        let (start_color, end_color) = (points[i].1, points[j].1);
+        push(start[0], start_color);
+        push(start[1], start_color);
+        push(end[0], end_color);
+        push(end[0], end_color);
+        push(start[1], start_color);
+        push(end[1], end_color);
+    }
+    for (i, joint) in joints.iter().enumerate() {
+        if let Some(bevel) = joint.bevel {
+            for corner in bevel {
+                push(corner, points[i].1);
+            }
+        }
+    }
+}
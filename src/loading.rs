@@ -0,0 +1,205 @@
+use wasm_bindgen::JsCast;
+use web_sys::WebGl2RenderingContext;
+use js_sys::Float32Array;
+use std::cell::RefCell;
+use std::future::Future;
+use std::ops::DerefMut;
+use std::pin::Pin;
+use std::rc::Rc;
+
+use crate::{LogLevel, Renderer, RendererError};
+use crate::console::log_at;
+
+type Gl = WebGl2RenderingContext;
+
+/// what a loaded asset does to the state, eg. storing itself in it
+pub(crate) type AssetResult<S> = Result<Box<dyn FnOnce(&mut S)>, RendererError>;
+pub(crate) type AssetFuture<S> = Pin<Box<dyn Future<Output = AssetResult<S>>>>;
+pub(crate) type OnLoadingProgress<S> = fn(&mut S, usize, usize);
+pub(crate) type OnLoadingError<S> = fn(&mut S, RendererError);
+
+/// the assets that the game loop waits for before the first update, see `Renderer::with_asset`
+pub(crate) struct Loading<S> {
+    /// started when the game loop starts
+    pending: Vec<AssetFuture<S>>,
+    /// pushed to by the futures as they complete
+    finished: Rc<RefCell<Vec<AssetResult<S>>>>,
+    total: usize,
+    loaded: usize,
+    pub(crate) on_progress: Option<OnLoadingProgress<S>>,
+    pub(crate) on_error: Option<OnLoadingError<S>>,
+    /// in seconds
+    pub(crate) timeout: Option<f64>,
+    /// the instant the futures were started
+    started: Option<f64>,
+    done: bool,
+}
+
+impl<S> Default for Loading<S> {
+    fn default() -> Self {
+        Loading {
+            pending: Vec::new(),
+            finished: Rc::default(),
+            total: 0,
+            loaded: 0,
+            on_progress: None,
+            on_error: None,
+            timeout: None,
+            started: None,
+            done: false,
+        }
+    }
+}
+
+impl<S> Loading<S> {
+    pub(crate) fn push(&mut self, asset: AssetFuture<S>) {
+        self.pending.push(asset);
+        self.total += 1;
+    }
+}
+
+impl<S> Renderer<S> {
+    /// adds an asset that is loaded before the game loop starts updating, the future resolves to a
+    /// function that stores the asset in the state:
+    /// ```ignore
+    /// renderer
+    ///     .with_asset(async {
+    ///         let bytes = fetch_bytes("levels/1.bin").await?;
+    ///         Ok(move |state: &mut State| state.level = Some(Level::parse(&bytes)))
+    ///     })
+    ///     .with_on_loading_progress(|_, loaded, total| log(&format!("{}/{}", loaded, total)))
+    /// ```
+    ///
+    /// the futures are started (with `spawn_local`) when the game loop starts, which then only renders
+    /// a progress bar until they have all completed, one of them errors or the timeout set with
+    /// `with_loading_timeout` passes (see `with_on_loading_error`). Assets that complete afterwards are
+    /// still stored in the state
+    ///
+    /// returns self for chaining
+    pub fn with_asset<F, Fut>(mut self, asset: Fut) -> Self
+        where F: FnOnce(&mut S) + 'static,
+              Fut: Future<Output = Result<F, RendererError>> + 'static,
+    {
+        self.loading.push(Box::pin(async move {
+            asset.await.map(|store| Box::new(store) as Box<dyn FnOnce(&mut S)>)
+        }));
+        self
+    }
+
+    /// sets a function that is called every frame while assets are loading with the number of assets
+    /// that have loaded and the total number, see `with_asset`
+    ///
+    /// replaces any previously set loading progress function
+    ///
+    /// returns self for chaining
+    pub fn with_on_loading_progress(mut self, on_loading_progress: OnLoadingProgress<S>) -> Self {
+        self.loading.on_progress = Some(on_loading_progress);
+        self
+    }
+
+    /// sets a function that is called with the error when an asset fails to load (or with
+    /// `RendererError::Timeout` if the loading timeout passes), after which the game loop starts
+    /// updating without waiting for the rest. Errors are logged to the console if this isn't set
+    ///
+    /// replaces any previously set loading error function
+    ///
+    /// returns self for chaining
+    pub fn with_on_loading_error(mut self, on_loading_error: OnLoadingError<S>) -> Self {
+        self.loading.on_error = Some(on_loading_error);
+        self
+    }
+
+    /// stops waiting for assets after `seconds`, see `with_on_loading_error`
+    ///
+    /// returns self for chaining
+    pub fn with_loading_timeout(mut self, seconds: f64) -> Self {
+        self.loading.timeout = Some(seconds);
+        self
+    }
+
+    /// starts the asset futures if they haven't been, stores any that have completed in the state and
+    /// draws the progress bar if they are still loading
+    ///
+    /// returns whether the game loop should keep waiting
+    pub(crate) fn poll_loading(&mut self, current_instant: f64) -> bool {
+        let log_level = self.log_level.get();
+        let loading = &mut self.loading;
+        if loading.started.is_none() {
+            loading.started = Some(current_instant);
+            for asset in loading.pending.drain(..) {
+                let finished = loading.finished.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let result = asset.await;
+                    finished.borrow_mut().push(result);
+                });
+            }
+        }
+
+        let mut state = self.state.get().unwrap().borrow_mut();
+        let finished = std::mem::take(loading.finished.borrow_mut().deref_mut());
+        for result in finished {
+            match result {
+                Ok(store) => {
+                    store(&mut state);
+                    loading.loaded += 1;
+                }
+                Err(error) => {
+                    loading.done = true;
+                    report_error(log_level, loading.on_error, &mut state, error);
+                }
+            }
+        }
+        if loading.done {
+            return false;
+        }
+
+        let timed_out = loading.timeout.zip(loading.started).is_some_and(|(timeout, started)| current_instant - started >= timeout);
+        if loading.loaded == loading.total || timed_out {
+            loading.done = true;
+            if loading.loaded < loading.total {
+                report_error(log_level, loading.on_error, &mut state, RendererError::Timeout("loading assets"));
+            }
+            return false;
+        }
+
+        if let Some(on_progress) = loading.on_progress {
+            on_progress(&mut state, loading.loaded, loading.total);
+        }
+        draw_progress_bar(&self.context, loading.loaded as f32 / loading.total as f32);
+        true
+    }
+}
+
+/// passes `error` to `on_error`, or logs it if there is no `on_error`
+fn report_error<S>(log_level: LogLevel, on_error: Option<OnLoadingError<S>>, state: &mut S, error: RendererError) {
+    match on_error {
+        Some(on_error) => on_error(state, error),
+        None => log_at(log_level, LogLevel::Errors, || format!("failed to load an asset: {}", error)),
+    }
+}
+
+/// clears the canvas and draws a bar across the middle that is `progress` of the way filled, restoring
+/// the clear color afterwards
+fn draw_progress_bar(context: &WebGl2RenderingContext, progress: f32) {
+    let clear_color = context.get_parameter(Gl::COLOR_CLEAR_VALUE).ok()
+        .and_then(|value| value.dyn_into::<Float32Array>().ok())
+        .map(|color| [0, 1, 2, 3].map(|i| color.get_index(i)))
+        .unwrap_or([0.0; 4]);
+
+    let (width, height) = (context.drawing_buffer_width(), context.drawing_buffer_height());
+    let (bar_width, bar_height) = (width / 2, (height / 40).max(2));
+    let (x, y) = ((width - bar_width) / 2, (height - bar_height) / 2);
+    context.clear_color(0.0, 0.0, 0.0, 1.0);
+    context.clear(Gl::COLOR_BUFFER_BIT);
+    context.enable(Gl::SCISSOR_TEST);
+    context.scissor(x, y, bar_width, bar_height);
+    context.clear_color(0.25, 0.25, 0.25, 1.0);
+    context.clear(Gl::COLOR_BUFFER_BIT);
+    context.scissor(x, y, (bar_width as f32 * progress) as i32, bar_height);
+    context.clear_color(1.0, 1.0, 1.0, 1.0);
+    context.clear(Gl::COLOR_BUFFER_BIT);
+    context.disable(Gl::SCISSOR_TEST);
+
+    let [red, green, blue, alpha] = clear_color;
+    context.clear_color(red, green, blue, alpha);
+}
@@ -0,0 +1,211 @@
+use wasm_bindgen::JsCast;
+use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlProgram, WebGlUniformLocation};
+use std::collections::HashSet;
+
+use crate::{RenderInfo, RendererError, Vertex, VertexBuffer, compile_shader, link_program};
+use crate::stats;
+
+type Gl = WebGl2RenderingContext;
+
+const WIREFRAME_VERTEX_SHADER: &str = "\
+#version 300 es
+layout(location = 0) in vec3 a_position;
+uniform mat4 u_view_proj;
+
+void main() {
+    gl_Position = u_view_proj * vec4(a_position, 1.0);
+}
+";
+
+const WIREFRAME_FRAGMENT_SHADER: &str = "\
+#version 300 es
+precision mediump float;
+uniform vec4 u_color;
+out vec4 color;
+
+void main() {
+    color = u_color;
+}
+";
+
+/// the flat color program of `Mesh::draw_wireframe`, compiled the first time a wireframe is drawn
+pub(crate) struct WireframeProgram {
+    context: WebGl2RenderingContext,
+    program: WebGlProgram,
+    view_proj_location: Option<WebGlUniformLocation>,
+    color_location: Option<WebGlUniformLocation>,
+}
+
+impl WireframeProgram {
+    fn new(context: &WebGl2RenderingContext) -> Result<WireframeProgram, RendererError> {
+        let vert_shader = compile_shader(context, Gl::VERTEX_SHADER, WIREFRAME_VERTEX_SHADER)?;
+        let frag_shader = compile_shader(context, Gl::FRAGMENT_SHADER, WIREFRAME_FRAGMENT_SHADER)?;
+        let program = link_program(context, &vert_shader, &frag_shader);
+        context.delete_shader(Some(&vert_shader));
+        context.delete_shader(Some(&frag_shader));
+        let program = program?;
+        Ok(WireframeProgram {
+            context: context.clone(),
+            view_proj_location: context.get_uniform_location(&program, "u_view_proj"),
+            color_location: context.get_uniform_location(&program, "u_color"),
+            program,
+        })
+    }
+}
+
+impl Drop for WireframeProgram {
+    fn drop(&mut self) {
+        self.context.delete_program(Some(&self.program));
+    }
+}
+
+/// the `LINES` indices of every edge of the triangles in `indices`, with each edge only once however
+/// many triangles share it (in either direction)
+pub fn wireframe_indices(indices: &[u32]) -> Vec<u32> {
+    let mut seen = HashSet::new();
+    let mut lines = Vec::new();
+    for triangle in indices.chunks_exact(3) {
+        for (a, b) in [(triangle[0], triangle[1]), (triangle[1], triangle[2]), (triangle[2], triangle[0])] {
+            if seen.insert((a.min(b), a.max(b))) {
+                lines.extend([a, b]);
+            }
+        }
+    }
+    lines
+}
+
+/// an indexed triangle mesh: a `VertexBuffer` and an `ELEMENT_ARRAY_BUFFER` of `u32` indices
+///
+/// ```ignore
+/// let mut mesh = Mesh::new(context, &vertices, &indices)?;
+/// mesh.set_wireframe(state.debug);
+/// ...
+/// // in `on_render`
+/// mesh.draw(&program);
+/// mesh.draw_wireframe(&mut render_info, view_proj, [0.0, 1.0, 0.0, 1.0])?;
+/// ```
+pub struct Mesh {
+    context: WebGl2RenderingContext,
+    vertices: VertexBuffer,
+    index_buffer: WebGlBuffer,
+    indices: Vec<u32>,
+    /// the `LINES` index buffer and its length, created the first time the wireframe is drawn
+    wireframe: Option<(WebGlBuffer, usize)>,
+    show_wireframe: bool,
+}
+
+impl Mesh {
+    /// uploads `vertices` and the triangle list `indices` with `STATIC_DRAW`, the index buffer is left
+    /// bound to `ELEMENT_ARRAY_BUFFER`
+    ///
+    /// errors if a buffer could not be created
+    pub fn new<V: Vertex>(context: &WebGl2RenderingContext, vertices: &[V], indices: &[u32]) -> Result<Mesh, RendererError> {
+        let mut vertex_buffer = VertexBuffer::new(context)?;
+        vertex_buffer.upload_structs(vertices, Gl::STATIC_DRAW);
+        let index_buffer = upload_indices(context, indices)?;
+        Ok(Mesh {
+            context: context.clone(),
+            vertices: vertex_buffer,
+            index_buffer,
+            indices: indices.to_vec(),
+            wireframe: None,
+            show_wireframe: false,
+        })
+    }
+
+    /// draws the triangles with `program`, pointing its attributes at the vertices (see `VertexBuffer::apply_layout`)
+    pub fn draw(&self, program: &WebGlProgram) {
+        self.vertices.apply_layout(program);
+        self.context.bind_buffer(Gl::ELEMENT_ARRAY_BUFFER, Some(&self.index_buffer));
+        self.context.draw_elements_with_i32(Gl::TRIANGLES, self.indices.len() as i32, Gl::UNSIGNED_INT, 0);
+        stats::count(|stats| {
+            stats.draw_calls += 1;
+            stats.triangles += (self.indices.len() / 3) as u32;
+        });
+    }
+
+    /// the `LINES` indices of the mesh's edges, see `wireframe_indices`
+    pub fn wireframe_indices(&self) -> Vec<u32> {
+        wireframe_indices(&self.indices)
+    }
+
+    /// whether `draw_wireframe` draws anything, `false` by default
+    pub fn set_wireframe(&mut self, show_wireframe: bool) {
+        self.show_wireframe = show_wireframe;
+    }
+    pub fn is_wireframe(&self) -> bool {
+        self.show_wireframe
+    }
+
+    /// draws the edges of the mesh as lines of `color` with a debug program shipped by the crate, if
+    /// `set_wireframe` has been enabled, eg. over the mesh after `draw` to see its triangles
+    ///
+    /// the position is the attribute named `a_position` or else the first attribute of the vertices,
+    /// `view_proj` is the (column-major) view-projection matrix. The previously bound program is
+    /// rebound afterwards
+    ///
+    /// errors if the debug program or the line index buffer could not be created
+    pub fn draw_wireframe<S>(&mut self, render_info: &mut RenderInfo<S>, view_proj: [[f32; 4]; 4], color: [f32; 4]) -> Result<(), RendererError> {
+        if !self.show_wireframe {
+            return Ok(());
+        }
+        let Some(layout) = self.vertices.layout() else { return Ok(()) };
+        let Some(position) = layout.attributes.iter().find(|attribute| attribute.name == "a_position").or(layout.attributes.first()) else {
+            return Ok(());
+        };
+        let context = &self.context;
+        if self.wireframe.is_none() {
+            let lines = self.wireframe_indices();
+            self.wireframe = Some((upload_indices(context, &lines)?, lines.len()));
+        }
+        if render_info.renderer.wireframe_program.is_none() {
+            render_info.renderer.wireframe_program = Some(WireframeProgram::new(context)?);
+        }
+        let (Some((line_buffer, line_count)), Some(program)) = (&self.wireframe, &render_info.renderer.wireframe_program) else {
+            return Ok(());
+        };
+
+        let previous = context.get_parameter(Gl::CURRENT_PROGRAM).ok()
+            .and_then(|value| value.dyn_into::<WebGlProgram>().ok());
+        context.use_program(Some(&program.program));
+        context.uniform_matrix4fv_with_f32_array(program.view_proj_location.as_ref(), false, view_proj.as_flattened());
+        context.uniform4fv_with_f32_array(program.color_location.as_ref(), &color);
+
+        self.vertices.bind();
+        context.enable_vertex_attrib_array(0);
+        context.vertex_attrib_pointer_with_i32(0, position.components, position.attribute_type.to_gl(), position.normalized, layout.stride as i32, position.offset as i32);
+        context.bind_buffer(Gl::ELEMENT_ARRAY_BUFFER, Some(line_buffer));
+        context.draw_elements_with_i32(Gl::LINES, *line_count as i32, Gl::UNSIGNED_INT, 0);
+        stats::count(|stats| stats.draw_calls += 1);
+
+        context.use_program(previous.as_ref());
+        Ok(())
+    }
+
+    pub fn vertices(&self) -> &VertexBuffer {
+        &self.vertices
+    }
+    pub fn index_buffer(&self) -> &WebGlBuffer {
+        &self.index_buffer
+    }
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+}
+
+impl Drop for Mesh {
+    fn drop(&mut self) {
+        self.context.delete_buffer(Some(&self.index_buffer));
+        if let Some((line_buffer, _)) = &self.wireframe {
+            self.context.delete_buffer(Some(line_buffer));
+        }
+    }
+}
+
+/// creates an `ELEMENT_ARRAY_BUFFER` of `indices`, which is left bound
+fn upload_indices(context: &WebGl2RenderingContext, indices: &[u32]) -> Result<WebGlBuffer, RendererError> {
+    let buffer = context.create_buffer().ok_or(RendererError::Create("buffer"))?;
+    context.bind_buffer(Gl::ELEMENT_ARRAY_BUFFER, Some(&buffer));
+    context.buffer_data_with_u8_array(Gl::ELEMENT_ARRAY_BUFFER, &indices.iter().flat_map(|index| index.to_ne_bytes()).collect::<Vec<_>>(), Gl::STATIC_DRAW);
+    Ok(buffer)
+}
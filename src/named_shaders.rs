@@ -0,0 +1,103 @@
+use wasm_bindgen::JsValue;
+use web_sys::{WebGlProgram, WebGlUniformLocation};
+use std::collections::HashMap;
+
+use crate::{LogLevel, Renderer, RenderInfo, RendererError};
+use crate::stats;
+
+/// a program added with `Renderer::with_named_shader`
+pub(crate) struct NamedShader {
+    pub(crate) program: WebGlProgram,
+    uniform_location_cache: HashMap<String, Option<WebGlUniformLocation>>,
+}
+
+impl<S> Renderer<S> {
+    /// links shaders to a program that can be switched to by name with `RenderInfo::use_shader`, eg. to
+    /// draw the scene and the ui with different programs:
+    /// ```ignore
+    /// renderer
+    ///     .with_named_shader("scene", scene_vert_shader, scene_frag_shader)?
+    ///     .with_named_shader("ui", ui_vert_shader, ui_frag_shader)?
+    /// ...
+    /// render_info.use_shader("scene")?;
+    /// render_info.set_uniform_cached("u_mvp", &mvp)?;
+    /// ```
+    ///
+    /// replaces any program previously added under `name`
+    ///
+    /// returns self for chaining
+    ///
+    /// errors if either shader fails to compile or the program fails to link
+    pub fn with_named_shader(mut self, name: &str, vert_shader: &str, frag_shader: &str) -> Result<Self, RendererError> {
+        let program = self.compile_program(vert_shader, frag_shader)?;
+        if self.current_shader.as_deref() == Some(name) {
+            self.context.use_program(Some(&program));
+        }
+        self.named_shaders.insert(name.to_owned(), NamedShader {
+            program,
+            uniform_location_cache: HashMap::new(),
+        });
+        if let Some(builtins) = &mut self.builtin_uniforms {
+            builtins.clear_locations();
+        }
+        Ok(self)
+    }
+
+    /// the program in use, which is the named shader chosen with `use_shader` or else the one linked by `with_shaders`
+    pub(crate) fn current_program(&self) -> Option<&WebGlProgram> {
+        match &self.current_shader {
+            Some(name) => self.named_shaders.get(name).map(|shader| &shader.program),
+            None => self.program.as_ref(),
+        }
+    }
+
+    /// the id of `program` for `bind_ubo_to_block`, giving it one if it doesn't have one yet
+    pub(crate) fn program_id(&mut self, program: &WebGlProgram) -> u64 {
+        if let Some(id) = self.program_ids.get(program).as_f64() {
+            return id as u64;
+        }
+        let id = self.next_program_id;
+        self.next_program_id += 1;
+        self.program_ids.set(program, &JsValue::from(id as f64));
+        id
+    }
+
+    /// the program in use and the cache of its uniform locations
+    pub(crate) fn current_program_and_cache(&mut self) -> Option<(&WebGlProgram, &mut HashMap<String, Option<WebGlUniformLocation>>)> {
+        match &self.current_shader {
+            Some(name) => self.named_shaders.get_mut(name).map(|shader| (&shader.program, &mut shader.uniform_location_cache)),
+            None => self.program.as_ref().map(|program| (program, &mut self.uniform_location_cache)),
+        }
+    }
+}
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// switches to the program added as `name` with `Renderer::with_named_shader`, which `set_uniform_cached`
+    /// then sets the uniforms of
+    ///
+    /// errors if there is no shader called `name` (which is also logged as an error, see `Renderer::with_log_level`)
+    pub fn use_shader(&mut self, name: &str) -> Result<(), RendererError> {
+        let Some(shader) = self.renderer.named_shaders.get(name) else {
+            self.renderer.log(LogLevel::Errors, || format!("use_shader: there is no shader called `{}`", name));
+            return Err(RendererError::Unknown("shader", name.to_owned()));
+        };
+        self.renderer.context.use_program(Some(&shader.program));
+        stats::count(|stats| stats.program_switches += 1);
+        if self.renderer.current_shader.as_deref() != Some(name) {
+            self.renderer.current_shader = Some(name.to_owned());
+        }
+        Ok(())
+    }
+
+    /// switches back to the program linked by `with_shaders`
+    pub fn use_default_shader(&mut self) {
+        self.renderer.context.use_program(self.renderer.program.as_ref());
+        stats::count(|stats| stats.program_switches += 1);
+        self.renderer.current_shader = None;
+    }
+
+    /// the name of the shader chosen with `use_shader`, `None` if the program linked by `with_shaders` is in use
+    pub fn current_shader_name(&self) -> Option<&str> {
+        self.renderer.current_shader.as_deref()
+    }
+}
@@ -0,0 +1,115 @@
+use web_sys::WebGlTexture;
+
+use crate::{SpriteBatch, Texture2d, TextureAtlas, AtlasRegion};
+
+/// the widths of the borders of a `NinePatch` in pixels of its image
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Margins {
+    pub left: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub top: f32,
+}
+
+impl Margins {
+    /// the same margin on every side
+    pub fn uniform(margin: f32) -> Self {
+        Margins { left: margin, right: margin, bottom: margin, top: margin }
+    }
+}
+
+/// an image (eg. a ui panel) whose corners are drawn unscaled, whose edges are stretched along their
+/// length and whose center is stretched to fill the rest, so that borders stay crisp at any size:
+/// ```ignore
+/// let panel = NinePatch::from_atlas(&state.atlas, "panel", Margins::uniform(8.0)).unwrap();
+/// ...
+/// panel.draw(&mut sprites, [10.0, 10.0, 300.0, 120.0], [1.0; 4]);
+/// ```
+///
+/// the destination's units are taken to be the image's pixels (eg. with a pixel-space camera)
+pub struct NinePatch {
+    texture: WebGlTexture,
+    uv_rect: [f32; 4],
+    pixel_size: (u32, u32),
+    margins: Margins,
+}
+
+impl NinePatch {
+    /// a nine patch of the whole of `texture`, which must outlive the nine patch's draws
+    pub fn from_texture(texture: &Texture2d, margins: Margins) -> Self {
+        NinePatch {
+            texture: texture.texture().clone(),
+            uv_rect: [0.0, 0.0, 1.0, 1.0],
+            pixel_size: (texture.width(), texture.height()),
+            margins,
+        }
+    }
+
+    /// a nine patch of `region` of `atlas`, which must outlive the nine patch's draws
+    pub fn from_region(atlas: &TextureAtlas, region: AtlasRegion, margins: Margins) -> Self {
+        NinePatch {
+            texture: atlas.texture().texture().clone(),
+            uv_rect: region.uv_rect,
+            pixel_size: region.pixel_size,
+            margins,
+        }
+    }
+
+    /// a nine patch of the image `name` in `atlas`, `None` if there is no such image
+    pub fn from_atlas(atlas: &TextureAtlas, name: &str, margins: Margins) -> Option<Self> {
+        atlas.region(name).map(|region| Self::from_region(atlas, region, margins))
+    }
+
+    /// adds the (up to) nine quads covering `dst` (`[x, y, width, height]`) to `batch`
+    ///
+    /// if `dst` is narrower (or shorter) than the margins on either side combined then those margins
+    /// are shrunk proportionally and the center column (or row) is skipped
+    pub fn draw(&self, batch: &mut SpriteBatch, dst: [f32; 4], color: [f32; 4]) {
+        let [x, y, width, height] = dst;
+        let Margins { left, right, bottom, top } = self.margins;
+        let (left, right) = shrink(left, right, width);
+        let (bottom, top) = shrink(bottom, top, height);
+        let xs = [x, x + left, x + width - right, x + width];
+        let ys = [y, y + bottom, y + height - top, y + height];
+
+        let [min_u, min_v, max_u, max_v] = self.uv_rect;
+        let (pixel_width, pixel_height) = (self.pixel_size.0.max(1) as f32, self.pixel_size.1.max(1) as f32);
+        let u = |pixels: f32| min_u + pixels / pixel_width * (max_u - min_u);
+        let v = |pixels: f32| min_v + pixels / pixel_height * (max_v - min_v);
+        // the source always uses the full margins, only the destination is shrunk
+        let us = [min_u, u(self.margins.left), u(pixel_width - self.margins.right), max_u];
+        let vs = [min_v, v(self.margins.bottom), v(pixel_height - self.margins.top), max_v];
+
+        for row in 0..3 {
+            for column in 0..3 {
+                let (quad_width, quad_height) = (xs[column + 1] - xs[column], ys[row + 1] - ys[row]);
+                if quad_width <= 0.0 || quad_height <= 0.0 {
+                    continue;
+                }
+                batch.quad(
+                    &self.texture,
+                    [xs[column], ys[row], quad_width, quad_height],
+                    [us[column], vs[row], us[column + 1], vs[row + 1]],
+                    color,
+                );
+            }
+        }
+    }
+
+    pub fn margins(&self) -> Margins {
+        self.margins
+    }
+    pub fn set_margins(&mut self, margins: Margins) {
+        self.margins = margins;
+    }
+}
+
+/// scales the margins on either side of a `size` span down so that they fit in it
+fn shrink(start: f32, end: f32, size: f32) -> (f32, f32) {
+    let total = start + end;
+    if total <= size || total <= 0.0 {
+        return (start, end);
+    }
+    let scale = size.max(0.0) / total;
+    (start * scale, end * scale)
+}
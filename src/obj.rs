@@ -0,0 +1,172 @@
+use web_sys::WebGl2RenderingContext;
+use std::collections::HashMap;
+use std::str::SplitWhitespace;
+
+use crate::{AttributeDesc, AttributeType, Mesh, RendererError, Vertex};
+
+/// the interleaved vertex of meshes loaded from obj files, with the attributes `a_position`,
+/// `a_normal` and `a_uv`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[repr(C)]
+pub struct ObjVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+}
+
+// SAFETY: `ObjVertex` is `#[repr(C)]` and made of `f32`s only, so it has no padding
+unsafe impl Vertex for ObjVertex {
+    const LAYOUT: &'static [AttributeDesc] = &[
+        AttributeDesc { name: "a_position", components: 3, attribute_type: AttributeType::Float, normalized: false, offset: 0 },
+        AttributeDesc { name: "a_normal", components: 3, attribute_type: AttributeType::Float, normalized: false, offset: 12 },
+        AttributeDesc { name: "a_uv", components: 2, attribute_type: AttributeType::Float, normalized: false, offset: 24 },
+    ];
+}
+
+/// a `v/vt/vn` triple of zero-based indices
+type Corner = (usize, Option<usize>, Option<usize>);
+
+/// parses the `v`, `vt`, `vn` and `f` lines of an obj file into indexed triangles, ignoring everything
+/// else (eg. objects, groups and materials)
+///
+/// faces with more than three corners are triangulated as fans, corners with the same `v/vt/vn` are
+/// shared and vertices without a normal get the (area weighted) average of the normals of the faces
+/// around their position. Missing uvs are `[0.0, 0.0]`
+///
+/// errors (with the line number) if a line is malformed or an index is out of range
+pub fn parse_obj(source: &str) -> Result<(Vec<ObjVertex>, Vec<u32>), RendererError> {
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut vertices = Vec::new();
+    // the position index of each vertex and whether its normal needs generating
+    let mut vertex_sources: Vec<(usize, bool)> = Vec::new();
+    let mut indices = Vec::new();
+    let mut shared: HashMap<Corner, u32> = HashMap::new();
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let error = |msg: String| RendererError::Parse("obj", format!("line {}: {}", line_number, msg));
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => positions.push(floats::<3>(&mut tokens).map_err(error)?),
+            Some("vn") => normals.push(floats::<3>(&mut tokens).map_err(error)?),
+            Some("vt") => {
+                // the `v` (and `w`) of a texture coordinate are optional
+                let u = tokens.next().ok_or_else(|| error(String::from("expected a u coordinate")))?;
+                let u = parse_float(u).map_err(error)?;
+                let v = tokens.next().map(parse_float).transpose().map_err(error)?.unwrap_or(0.0);
+                uvs.push([u, v]);
+            }
+            Some("f") => {
+                let corners = tokens
+                    .map(|token| parse_corner(token, positions.len(), uvs.len(), normals.len()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?;
+                if corners.len() < 3 {
+                    return Err(error(format!("a face needs at least 3 corners but has {}", corners.len())));
+                }
+                let mut corner_index = |corner: Corner| {
+                    *shared.entry(corner).or_insert_with(|| {
+                        let (position, uv, normal) = corner;
+                        vertices.push(ObjVertex {
+                            position: positions[position],
+                            normal: normal.map_or([0.0; 3], |normal| normals[normal]),
+                            uv: uv.map_or([0.0; 2], |uv| uvs[uv]),
+                        });
+                        vertex_sources.push((position, normal.is_none()));
+                        vertices.len() as u32 - 1
+                    })
+                };
+                let first = corner_index(corners[0]);
+                for pair in corners[1..].windows(2) {
+                    indices.extend([first, corner_index(pair[0]), corner_index(pair[1])]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if vertex_sources.iter().any(|&(_, generate)| generate) {
+        generate_normals(&mut vertices, &vertex_sources, &indices, positions.len());
+    }
+    Ok((vertices, indices))
+}
+
+/// sets the normals that need generating to the sum of the normals of the triangles around their
+/// position (each weighted by its area) so that they are smooth across uv seams
+fn generate_normals(vertices: &mut [ObjVertex], vertex_sources: &[(usize, bool)], indices: &[u32], position_count: usize) {
+    let mut sums = vec![[0.0f32; 3]; position_count];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize].position);
+        let (ab, ac) = ([b[0] - a[0], b[1] - a[1], b[2] - a[2]], [c[0] - a[0], c[1] - a[1], c[2] - a[2]]);
+        // the cross product's length is twice the triangle's area
+        let normal = [ab[1] * ac[2] - ab[2] * ac[1], ab[2] * ac[0] - ab[0] * ac[2], ab[0] * ac[1] - ab[1] * ac[0]];
+        for &index in triangle {
+            let sum = &mut sums[vertex_sources[index as usize].0];
+            for (sum, component) in sum.iter_mut().zip(normal) {
+                *sum += component;
+            }
+        }
+    }
+    for (vertex, &(position, generate)) in vertices.iter_mut().zip(vertex_sources) {
+        if generate {
+            let sum = sums[position];
+            let length = (sum[0] * sum[0] + sum[1] * sum[1] + sum[2] * sum[2]).sqrt();
+            vertex.normal = if length == 0.0 { [0.0, 1.0, 0.0] } else { sum.map(|component| component / length) };
+        }
+    }
+}
+
+/// the next `N` tokens as floats, ignoring any after them (eg. a position's optional `w`)
+fn floats<const N: usize>(tokens: &mut SplitWhitespace) -> Result<[f32; N], String> {
+    let mut values = [0.0; N];
+    for (i, value) in values.iter_mut().enumerate() {
+        let token = tokens.next().ok_or_else(|| format!("expected {} numbers but got {}", N, i))?;
+        *value = parse_float(token)?;
+    }
+    Ok(values)
+}
+
+fn parse_float(token: &str) -> Result<f32, String> {
+    token.parse().map_err(|_| format!("`{}` is not a number", token))
+}
+
+/// parses a face corner (`v`, `v/vt`, `v//vn` or `v/vt/vn`) into zero-based indices
+fn parse_corner(token: &str, position_count: usize, uv_count: usize, normal_count: usize) -> Result<Corner, String> {
+    let mut parts = token.split('/');
+    let position = parse_index(parts.next().unwrap_or(""), position_count, "position")?;
+    let uv = match parts.next() {
+        None | Some("") => None,
+        Some(uv) => Some(parse_index(uv, uv_count, "texture coordinate")?),
+    };
+    let normal = match parts.next() {
+        None | Some("") => None,
+        Some(normal) => Some(parse_index(normal, normal_count, "normal")?),
+    };
+    if parts.next().is_some() {
+        return Err(format!("`{}` has more than 3 indices", token));
+    }
+    Ok((position, uv, normal))
+}
+
+/// a one-based (or, if negative, relative to the end) index into a list of `count` items
+fn parse_index(token: &str, count: usize, kind: &str) -> Result<usize, String> {
+    let index: i64 = token.parse().map_err(|_| format!("`{}` is not a valid {} index", token, kind))?;
+    let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(format!("{} index {} is out of range, there are {} so far", kind, index, count));
+    }
+    Ok(resolved as usize)
+}
+
+impl Mesh {
+    /// parses an obj file with `parse_obj` and uploads it as a mesh of `ObjVertex`s
+    ///
+    /// errors if the obj is malformed or a buffer could not be created
+    pub fn from_obj_str(context: &WebGl2RenderingContext, source: &str) -> Result<Mesh, RendererError> {
+        let (vertices, indices) = parse_obj(source)?;
+        Mesh::new(context, &vertices, &indices)
+    }
+}
@@ -0,0 +1,403 @@
+use wasm_bindgen::JsCast;
+use js_sys::Array;
+use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlProgram, WebGlQuery, WebGlUniformLocation, WebGlVertexArrayObject};
+use std::collections::{HashMap, VecDeque};
+
+use crate::{RenderInfo, RendererError, compile_shader, link_program};
+use crate::stats;
+use crate::viewport::RestoreEnabled;
+
+type Gl = WebGl2RenderingContext;
+
+const BOUNDS_VERTEX_SHADER: &str = "\
+#version 300 es
+uniform mat4 u_view_proj;
+uniform vec3 u_min;
+uniform vec3 u_max;
+
+void main() {
+    // the indices are the corners of the box, with a bit for each axis
+    vec3 corner = vec3(gl_VertexID & 1, (gl_VertexID >> 1) & 1, (gl_VertexID >> 2) & 1);
+    gl_Position = u_view_proj * vec4(mix(u_min, u_max, corner), 1.0);
+}
+";
+
+const BOUNDS_FRAGMENT_SHADER: &str = "\
+#version 300 es
+precision lowp float;
+out vec4 color;
+
+void main() {
+    color = vec4(1.0);
+}
+";
+
+/// the two triangles of each face of a box, indexing its corners by their axis bits
+const BOX_INDICES: [u8; 36] = [
+    0, 2, 6, 0, 6, 4, // -x
+    1, 5, 7, 1, 7, 3, // +x
+    0, 4, 5, 0, 5, 1, // -y
+    2, 3, 7, 2, 7, 6, // +y
+    0, 1, 3, 0, 3, 2, // -z
+    4, 6, 7, 4, 7, 5, // +z
+];
+
+/// how many queries an object can have in flight before no more are issued for it until one completes
+const MAX_PENDING_PER_OBJECT: usize = 3;
+
+/// an `ANY_SAMPLES_PASSED` query for whether anything drawn between `begin` and `end` passed the
+/// depth test, eg. to skip expensive effects for objects that are fully occluded
+///
+/// query results arrive some frames after the query is issued, so each `begin`/`end` pair issues a
+/// new query and `result` reports the most recent one that has completed, with `latency` saying how
+/// many queries ago that was:
+/// ```ignore
+/// let visible = query.result().unwrap_or(true); // assume visible until the first result arrives
+/// query.begin()?;
+/// draw_bounding_box(render_info);
+/// query.end();
+/// ```
+pub struct OcclusionQuery {
+    context: WebGl2RenderingContext,
+    active: Option<WebGlQuery>,
+    /// ended queries whose results have not been read yet, oldest first, with their issue number
+    pending: VecDeque<(WebGlQuery, u64)>,
+    /// queries whose results have been read and can be reused
+    free: Vec<WebGlQuery>,
+    issued: u64,
+    latest: Option<(bool, u64)>,
+}
+
+impl OcclusionQuery {
+    pub fn new(context: &WebGl2RenderingContext) -> OcclusionQuery {
+        OcclusionQuery {
+            context: context.clone(),
+            active: None,
+            pending: VecDeque::new(),
+            free: Vec::new(),
+            issued: 0,
+            latest: None,
+        }
+    }
+
+    /// starts a query, ending the active one first if there is one
+    /// (only one occlusion query can be active at a time)
+    ///
+    /// errors if a query object could not be created
+    pub fn begin(&mut self) -> Result<(), RendererError> {
+        self.end();
+        let query = match self.free.pop() {
+            Some(query) => query,
+            None => self.context.create_query().ok_or(RendererError::Create("query"))?,
+        };
+        self.context.begin_query(Gl::ANY_SAMPLES_PASSED, &query);
+        self.active = Some(query);
+        Ok(())
+    }
+
+    /// ends the active query, does nothing if `begin` has not been called
+    pub fn end(&mut self) {
+        if let Some(query) = self.active.take() {
+            self.context.end_query(Gl::ANY_SAMPLES_PASSED);
+            self.issued += 1;
+            self.pending.push_back((query, self.issued));
+        }
+    }
+
+    /// whether any samples passed in the most recent query whose result is available, without blocking
+    ///
+    /// returns `None` until the first query completes
+    pub fn result(&mut self) -> Option<bool> {
+        while let Some((query, _)) = self.pending.front() {
+            let available = self.context.get_query_parameter(query, Gl::QUERY_RESULT_AVAILABLE).as_bool().unwrap_or(false);
+            if !available { break }
+
+            let (query, issue) = self.pending.pop_front().unwrap();
+            let any_samples_passed = self.context.get_query_parameter(&query, Gl::QUERY_RESULT).as_bool().unwrap_or(true);
+            self.latest = Some((any_samples_passed, issue));
+            self.free.push(query);
+        }
+        self.latest.map(|(any_samples_passed, _)| any_samples_passed)
+    }
+
+    /// how many queries have been ended since the one reported by `result`, or `None` if there is no result yet
+    ///
+    /// with one query per frame this is how many frames old the result is
+    pub fn latency(&self) -> Option<u64> {
+        self.latest.map(|(_, issue)| self.issued - issue)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+}
+
+impl Drop for OcclusionQuery {
+    fn drop(&mut self) {
+        // a query can't be deleted while it is active and leaving it active would stop any other
+        // occlusion query from beginning
+        if self.active.is_some() {
+            self.context.end_query(Gl::ANY_SAMPLES_PASSED);
+        }
+        let queries = self.active.iter()
+            .chain(self.pending.iter().map(|(query, _)| query))
+            .chain(self.free.iter());
+        for query in queries {
+            self.context.delete_query(Some(query));
+        }
+    }
+}
+
+/// the queries of an object in an `OcclusionCuller`
+struct CulledObject {
+    /// ended queries whose results have not been read yet, oldest first
+    pending: VecDeque<WebGlQuery>,
+    /// the most recent result, `true` until the first one arrives
+    visible: bool,
+}
+
+/// occlusion culling for many objects: each frame their bounding boxes are drawn (after the occluders)
+/// into an `ANY_SAMPLES_PASSED` query each and the objects whose boxes were hidden are skipped:
+/// ```ignore
+/// // in `with_state_init`
+/// let culler = OcclusionCuller::new(context, 1024)?;
+/// ...
+/// // in `on_render`
+/// culler.begin_frame();
+/// draw_terrain_and_big_occluders(&mut render_info);
+/// for building in &state.buildings {
+///     if culler.submit_bounds(building.id, building.min, building.max) {
+///         building.draw(&mut render_info);
+///     }
+/// }
+/// culler.end_frame(&mut render_info, camera.view_proj());
+/// ```
+///
+/// query results arrive asynchronously, so `submit_bounds` reports whether the box was visible in the
+/// most recent completed query, which is at least a frame old. An object that comes into view can
+/// therefore pop in a frame (or a few, on a busy gpu) late, which is the price of never stalling on
+/// the gpu. Objects are visible until their first result arrives and whenever the camera is inside or
+/// near their box
+pub struct OcclusionCuller {
+    context: WebGl2RenderingContext,
+    program: WebGlProgram,
+    view_proj_location: Option<WebGlUniformLocation>,
+    min_location: Option<WebGlUniformLocation>,
+    max_location: Option<WebGlUniformLocation>,
+    vertex_array: WebGlVertexArrayObject,
+    index_buffer: WebGlBuffer,
+    max_objects: usize,
+    objects: HashMap<u64, CulledObject>,
+    /// the boxes submitted this frame, drawn by `end_frame`
+    submitted: Vec<(u64, [f32; 3], [f32; 3])>,
+    /// queries whose results have been read that can be reused
+    free: Vec<WebGlQuery>,
+}
+
+impl OcclusionCuller {
+    /// a culler that queries at most `max_objects` objects per frame, any more are always visible
+    ///
+    /// errors if the bounding box program, index buffer or vertex array object could not be created
+    pub fn new(context: &WebGl2RenderingContext, max_objects: usize) -> Result<OcclusionCuller, RendererError> {
+        let vert_shader = compile_shader(context, Gl::VERTEX_SHADER, BOUNDS_VERTEX_SHADER)?;
+        let frag_shader = compile_shader(context, Gl::FRAGMENT_SHADER, BOUNDS_FRAGMENT_SHADER)?;
+        let program = link_program(context, &vert_shader, &frag_shader);
+        context.delete_shader(Some(&vert_shader));
+        context.delete_shader(Some(&frag_shader));
+        let program = program?;
+
+        let index_buffer = context.create_buffer().ok_or(RendererError::Create("buffer"))?;
+        let vertex_array = context.create_vertex_array().ok_or(RendererError::Create("vertex array"))?;
+        let previous = context.get_parameter(Gl::VERTEX_ARRAY_BINDING).ok()
+            .and_then(|value| value.dyn_into::<WebGlVertexArrayObject>().ok());
+        // the element array buffer binding is part of the vertex array object
+        context.bind_vertex_array(Some(&vertex_array));
+        context.bind_buffer(Gl::ELEMENT_ARRAY_BUFFER, Some(&index_buffer));
+        context.buffer_data_with_u8_array(Gl::ELEMENT_ARRAY_BUFFER, &BOX_INDICES, Gl::STATIC_DRAW);
+        context.bind_vertex_array(previous.as_ref());
+
+        Ok(OcclusionCuller {
+            context: context.clone(),
+            view_proj_location: context.get_uniform_location(&program, "u_view_proj"),
+            min_location: context.get_uniform_location(&program, "u_min"),
+            max_location: context.get_uniform_location(&program, "u_max"),
+            program,
+            vertex_array,
+            index_buffer,
+            max_objects,
+            objects: HashMap::new(),
+            submitted: Vec::new(),
+            free: Vec::new(),
+        })
+    }
+
+    /// reads the results that have arrived since the previous frame and starts a new frame, without
+    /// blocking
+    pub fn begin_frame(&mut self) {
+        self.submitted.clear();
+        for object in self.objects.values_mut() {
+            while let Some(query) = object.pending.front() {
+                let available = self.context.get_query_parameter(query, Gl::QUERY_RESULT_AVAILABLE).as_bool().unwrap_or(false);
+                if !available { break }
+
+                let query = object.pending.pop_front().unwrap();
+                object.visible = self.context.get_query_parameter(&query, Gl::QUERY_RESULT).as_bool().unwrap_or(true);
+                self.free.push(query);
+            }
+        }
+    }
+
+    /// submits the axis-aligned bounding box of the object `id` to be queried by `end_frame` and returns
+    /// whether it was visible in its most recent completed query, ie. whether to draw it
+    ///
+    /// `id` should stay the same for the object from frame to frame. Objects beyond `max_objects` in a
+    /// frame are always visible
+    pub fn submit_bounds(&mut self, id: u64, aabb_min: [f32; 3], aabb_max: [f32; 3]) -> bool {
+        if self.submitted.len() >= self.max_objects {
+            return true;
+        }
+        self.submitted.push((id, aabb_min, aabb_max));
+        self.objects.get(&id).is_none_or(|object| object.visible)
+    }
+
+    /// whether the object `id` was visible in its most recent completed query, `true` if it hasn't
+    /// been queried yet
+    pub fn is_visible(&self, id: u64) -> bool {
+        self.objects.get(&id).is_none_or(|object| object.visible)
+    }
+
+    /// draws the boxes submitted this frame into their queries, testing them against the depth buffer
+    /// without writing to it or to the color buffer, so it should come after the occluders have been
+    /// drawn. `view_proj` is the (column-major) view-projection matrix they were drawn with
+    ///
+    /// objects that weren't submitted this frame are forgotten. The depth test, face culling, write masks,
+    /// program and vertex array object are restored afterwards
+    ///
+    /// errors if a query object could not be created
+    pub fn end_frame<S>(&mut self, render_info: &mut RenderInfo<S>, view_proj: [[f32; 4]; 4]) -> Result<(), RendererError> {
+        let submitted = std::mem::take(&mut self.submitted);
+        self.forget_unsubmitted(&submitted);
+        let context = self.context.clone();
+
+        let previous_program = context.get_parameter(Gl::CURRENT_PROGRAM).ok()
+            .and_then(|value| value.dyn_into::<WebGlProgram>().ok());
+        let previous_vertex_array = context.get_parameter(Gl::VERTEX_ARRAY_BINDING).ok()
+            .and_then(|value| value.dyn_into::<WebGlVertexArrayObject>().ok());
+        let color_writemask = context.get_parameter(Gl::COLOR_WRITEMASK).ok()
+            .and_then(|value| value.dyn_into::<Array>().ok())
+            .map(|mask| [0, 1, 2, 3].map(|i| mask.get(i).as_bool().unwrap_or(true)))
+            .unwrap_or([true; 4]);
+        let depth_writemask = context.get_parameter(Gl::DEPTH_WRITEMASK).ok()
+            .and_then(|value| value.as_bool())
+            .unwrap_or(true);
+        let _restore_depth_test = RestoreEnabled::new(&context, Gl::DEPTH_TEST);
+        let _restore_cull_face = RestoreEnabled::new(&context, Gl::CULL_FACE);
+
+        context.enable(Gl::DEPTH_TEST);
+        // the back faces still count when the front faces are clipped
+        context.disable(Gl::CULL_FACE);
+        context.color_mask(false, false, false, false);
+        context.depth_mask(false);
+        context.use_program(Some(&self.program));
+        context.uniform_matrix4fv_with_f32_array(self.view_proj_location.as_ref(), false, view_proj.as_flattened());
+        context.bind_vertex_array(Some(&self.vertex_array));
+
+        let mut result = Ok(());
+        for (id, min, max) in submitted {
+            let object = self.objects.entry(id).or_insert_with(|| CulledObject { pending: VecDeque::new(), visible: true });
+            // a box that crosses the near plane would be clipped, hiding it even though the camera is
+            // (nearly) inside it
+            if crosses_near_plane(view_proj, min, max) {
+                object.visible = true;
+                continue;
+            }
+            if object.pending.len() >= MAX_PENDING_PER_OBJECT {
+                continue;
+            }
+            let query = match self.free.pop().map_or_else(|| context.create_query().ok_or(RendererError::Create("query")), Ok) {
+                Ok(query) => query,
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            };
+            context.uniform3fv_with_f32_array(self.min_location.as_ref(), &min);
+            context.uniform3fv_with_f32_array(self.max_location.as_ref(), &max);
+            context.begin_query(Gl::ANY_SAMPLES_PASSED, &query);
+            context.draw_elements_with_i32(Gl::TRIANGLES, BOX_INDICES.len() as i32, Gl::UNSIGNED_BYTE, 0);
+            context.end_query(Gl::ANY_SAMPLES_PASSED);
+            render_info.note_draw_call(BOX_INDICES.len() as u32 / 3);
+            object.pending.push_back(query);
+        }
+        stats::count(|stats| stats.program_switches += 1);
+
+        let [red, green, blue, alpha] = color_writemask;
+        context.color_mask(red, green, blue, alpha);
+        context.depth_mask(depth_writemask);
+        context.bind_vertex_array(previous_vertex_array.as_ref());
+        context.use_program(previous_program.as_ref());
+        result
+    }
+
+    /// forgets every object, eg. after a camera cut where the previous results are meaningless
+    pub fn clear(&mut self) {
+        for (_, object) in self.objects.drain() {
+            for query in &object.pending {
+                self.context.delete_query(Some(query));
+            }
+        }
+    }
+
+    /// the number of objects with query state
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+    pub fn max_objects(&self) -> usize {
+        self.max_objects
+    }
+
+    /// drops the objects that weren't submitted this frame, deleting their in-flight queries as they
+    /// can't be reused before their results have been read
+    fn forget_unsubmitted(&mut self, submitted: &[(u64, [f32; 3], [f32; 3])]) {
+        if submitted.len() == self.objects.len() && submitted.iter().all(|(id, _, _)| self.objects.contains_key(id)) {
+            return;
+        }
+        let submitted_ids: std::collections::HashSet<u64> = submitted.iter().map(|(id, _, _)| *id).collect();
+        let context = &self.context;
+        self.objects.retain(|id, object| {
+            let keep = submitted_ids.contains(id);
+            if !keep {
+                for query in &object.pending {
+                    context.delete_query(Some(query));
+                }
+            }
+            keep
+        });
+    }
+}
+
+/// whether any corner of the box is in front of the near plane in clip space (`z < -w`)
+fn crosses_near_plane(view_proj: [[f32; 4]; 4], min: [f32; 3], max: [f32; 3]) -> bool {
+    (0..8).any(|corner: usize| {
+        let position = [0, 1, 2].map(|axis| if corner & (1 << axis) == 0 { min[axis] } else { max[axis] });
+        let [z, w] = [2, 3].map(|row| (0..3).map(|column| view_proj[column][row] * position[column]).sum::<f32>() + view_proj[3][row]);
+        z < -w
+    })
+}
+
+impl Drop for OcclusionCuller {
+    fn drop(&mut self) {
+        let queries = self.objects.values()
+            .flat_map(|object| object.pending.iter())
+            .chain(self.free.iter());
+        for query in queries {
+            self.context.delete_query(Some(query));
+        }
+        self.context.delete_program(Some(&self.program));
+        self.context.delete_buffer(Some(&self.index_buffer));
+        self.context.delete_vertex_array(Some(&self.vertex_array));
+    }
+}
@@ -0,0 +1,57 @@
+use crate::RenderInfo;
+
+/// a (column-major) orthographic projection mapping the box from `left`, `bottom`, `-near` to `right`,
+/// `top`, `-far` to clip space, like `glOrtho`
+///
+/// `bottom` can be greater than `top` for `y` to go down
+pub fn ortho(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> [f32; 16] {
+    let (width, height, depth) = (right - left, top - bottom, far - near);
+    [
+        2.0 / width, 0.0, 0.0, 0.0,
+        0.0, 2.0 / height, 0.0, 0.0,
+        0.0, 0.0, -2.0 / depth, 0.0,
+        -(right + left) / width, -(top + bottom) / height, -(far + near) / depth, 1.0,
+    ]
+}
+
+/// the pixel projections for a canvas size, see `RenderInfo::ortho_pixels`
+#[derive(Clone, Copy)]
+pub(crate) struct OrthoCache {
+    size: (u32, u32),
+    y_down: [f32; 16],
+    y_up: [f32; 16],
+}
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// an orthographic projection where one unit is one pixel of the canvas (drawing buffer), with the
+    /// origin at the top-left and `y` going down like pointer positions and `Draw2d`
+    ///
+    /// it is only recomputed when the canvas's size changes so it can be called every frame:
+    /// ```ignore
+    /// let projection = render_info.ortho_pixels();
+    /// render_info.set_uniform_cached("u_projection", UniformValue::Mat4(projection))?;
+    /// ```
+    pub fn ortho_pixels(&self) -> [f32; 16] {
+        self.ortho_cache().y_down
+    }
+
+    /// like `ortho_pixels` but with the origin at the bottom-left and `y` going up
+    pub fn ortho_pixels_y_up(&self) -> [f32; 16] {
+        self.ortho_cache().y_up
+    }
+
+    fn ortho_cache(&self) -> OrthoCache {
+        let size = self.canvas_size();
+        if let Some(cache) = self.renderer.ortho_cache.get().filter(|cache| cache.size == size) {
+            return cache;
+        }
+        let (width, height) = (size.0.max(1) as f32, size.1.max(1) as f32);
+        let cache = OrthoCache {
+            size,
+            y_down: ortho(0.0, width, height, 0.0, -1.0, 1.0),
+            y_up: ortho(0.0, width, 0.0, height, -1.0, 1.0),
+        };
+        self.renderer.ortho_cache.set(Some(cache));
+        cache
+    }
+}
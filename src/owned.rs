@@ -0,0 +1,77 @@
+use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlProgram, WebGlShader, WebGlTexture};
+use std::ops::Deref;
+
+use crate::{RendererError, link_program};
+
+macro_rules! owned_handle {
+    ($(#[$doc:meta])* $owned:ident($handle:ty), $delete:ident) => {
+        $(#[$doc])*
+        pub struct $owned {
+            context: WebGl2RenderingContext,
+            handle: $handle,
+        }
+
+        impl $owned {
+            /// takes ownership of `handle`, which will be deleted when this is dropped
+            pub fn from_raw(context: &WebGl2RenderingContext, handle: $handle) -> Self {
+                $owned {
+                    context: context.clone(),
+                    handle,
+                }
+            }
+        }
+
+        impl Deref for $owned {
+            type Target = $handle;
+            fn deref(&self) -> &$handle {
+                &self.handle
+            }
+        }
+
+        impl Drop for $owned {
+            fn drop(&mut self) {
+                self.context.$delete(Some(&self.handle));
+            }
+        }
+    };
+}
+
+owned_handle!(
+    /// a `WebGlBuffer` that is deleted when dropped, it derefs to the buffer so can be passed anywhere a
+    /// `&WebGlBuffer` is expected, eg. `context.bind_buffer(Gl::ARRAY_BUFFER, Some(&buffer))`
+    OwnedBuffer(WebGlBuffer), delete_buffer
+);
+owned_handle!(
+    /// a `WebGlTexture` that is deleted when dropped, see `OwnedBuffer`
+    ///
+    /// `Texture2d` (and the other texture types) also manage their dimensions and uploads
+    OwnedTexture(WebGlTexture), delete_texture
+);
+owned_handle!(
+    /// a `WebGlProgram` that is deleted when dropped, see `OwnedBuffer`
+    OwnedProgram(WebGlProgram), delete_program
+);
+
+impl OwnedBuffer {
+    /// errors if the buffer could not be created
+    pub fn new(context: &WebGl2RenderingContext) -> Result<OwnedBuffer, RendererError> {
+        let buffer = context.create_buffer().ok_or(RendererError::Create("buffer"))?;
+        Ok(Self::from_raw(context, buffer))
+    }
+}
+
+impl OwnedTexture {
+    /// errors if the texture could not be created
+    pub fn new(context: &WebGl2RenderingContext) -> Result<OwnedTexture, RendererError> {
+        let texture = context.create_texture().ok_or(RendererError::Create("texture"))?;
+        Ok(Self::from_raw(context, texture))
+    }
+}
+
+impl OwnedProgram {
+    /// links a vertex and fragment shader into a program, see `link_program`
+    pub fn link(context: &WebGl2RenderingContext, vert_shader: &WebGlShader, frag_shader: &WebGlShader) -> Result<OwnedProgram, RendererError> {
+        let program = link_program(context, vert_shader, frag_shader)?;
+        Ok(Self::from_raw(context, program))
+    }
+}
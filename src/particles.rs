@@ -0,0 +1,306 @@
+use wasm_bindgen::JsCast;
+use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlProgram, WebGlUniformLocation, WebGlVertexArrayObject};
+
+use crate::{RenderInfo, RendererError, compile_shader, link_program};
+
+type Gl = WebGl2RenderingContext;
+
+const PARTICLE_VERTEX_SHADER: &str = "\
+#version 300 es
+layout(location = 0) in vec2 a_position;
+layout(location = 1) in float a_size;
+layout(location = 2) in vec4 a_color;
+uniform mat4 u_view_proj;
+out vec2 v_offset;
+out vec4 v_color;
+
+void main() {
+    v_offset = vec2(gl_VertexID & 1, gl_VertexID >> 1) * 2.0 - 1.0;
+    v_color = a_color;
+    gl_Position = u_view_proj * vec4(a_position + v_offset * a_size * 0.5, 0.0, 1.0);
+}
+";
+
+const PARTICLE_FRAGMENT_SHADER: &str = "\
+#version 300 es
+precision mediump float;
+in vec2 v_offset;
+in vec4 v_color;
+out vec4 color;
+
+void main() {
+    float falloff = 1.0 - smoothstep(0.0, 1.0, length(v_offset));
+    // premultiplied so that it can be added to the framebuffer
+    color = vec4(v_color.rgb * v_color.a * falloff, v_color.a * falloff);
+}
+";
+
+/// the floats per particle instance: a position, a size and a color
+const INSTANCE_FLOATS: usize = 7;
+
+/// how a `ParticleSystem` spawns its particles and how they change over their lives, in world units
+/// and seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmitterConfig {
+    /// the particles spawned per second
+    pub spawn_rate: f32,
+    /// the range each particle's lifetime is picked from
+    pub lifetime: (f32, f32),
+    /// the range each particle's initial horizontal velocity is picked from
+    pub velocity_x: (f32, f32),
+    /// the range each particle's initial vertical velocity is picked from
+    pub velocity_y: (f32, f32),
+    /// added to every particle's velocity each second, eg. gravity
+    pub acceleration: [f32; 2],
+    /// the color at the start of a particle's life, which fades to `end_color`
+    pub start_color: [f32; 4],
+    pub end_color: [f32; 4],
+    /// the width of a particle at the start of its life, which changes to `end_size`
+    pub start_size: f32,
+    pub end_size: f32,
+    /// no more particles are spawned while there are this many
+    pub max_particles: usize,
+}
+
+impl Default for EmitterConfig {
+    fn default() -> Self {
+        EmitterConfig {
+            spawn_rate: 50.0,
+            lifetime: (1.0, 2.0),
+            velocity_x: (-1.0, 1.0),
+            velocity_y: (-1.0, 1.0),
+            acceleration: [0.0, 0.0],
+            start_color: [1.0; 4],
+            end_color: [1.0, 1.0, 1.0, 0.0],
+            start_size: 0.1,
+            end_size: 0.0,
+            max_particles: 1000,
+        }
+    }
+}
+
+/// a deterministic random number generator (splitmix64) so that the same seed gives the same particles
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+    /// a value in `[min, max)`
+    fn range(&mut self, (min, max): (f32, f32)) -> f32 {
+        let unit = (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+        min + (max - min) * unit
+    }
+}
+
+struct Particle {
+    position: [f32; 2],
+    velocity: [f32; 2],
+    age: f32,
+    lifetime: f32,
+}
+
+/// a pool of particles spawned from a point, updated on the cpu with a fixed time step and drawn as
+/// instanced soft round quads with additive blending:
+/// ```ignore
+/// let mut sparks = ParticleSystem::new(context, EmitterConfig { spawn_rate: 200.0, ..Default::default() }, 42)?;
+/// ...
+/// // in `on_update`
+/// sparks.set_position(player_position);
+/// sparks.update(update_info.fixed_time_step() as f32);
+/// ...
+/// // in `on_render`
+/// sparks.draw(&mut render_info, view_proj)?;
+/// ```
+///
+/// given the same seed, configuration and sequence of `update`s (and `emit`s) the same particles are spawned
+pub struct ParticleSystem {
+    context: WebGl2RenderingContext,
+    program: WebGlProgram,
+    view_proj_location: Option<WebGlUniformLocation>,
+    vertex_array: WebGlVertexArrayObject,
+    buffer: WebGlBuffer,
+    config: EmitterConfig,
+    rng: Rng,
+    position: [f32; 2],
+    emitting: bool,
+    /// the fraction of a particle left over from previous updates' spawning
+    spawn_accumulator: f32,
+    particles: Vec<Particle>,
+    instances: Vec<f32>,
+}
+
+impl ParticleSystem {
+    /// errors if the program, buffer or vertex array object could not be created
+    pub fn new(context: &WebGl2RenderingContext, config: EmitterConfig, seed: u64) -> Result<ParticleSystem, RendererError> {
+        let vert_shader = compile_shader(context, Gl::VERTEX_SHADER, PARTICLE_VERTEX_SHADER)?;
+        let frag_shader = compile_shader(context, Gl::FRAGMENT_SHADER, PARTICLE_FRAGMENT_SHADER)?;
+        let program = link_program(context, &vert_shader, &frag_shader);
+        context.delete_shader(Some(&vert_shader));
+        context.delete_shader(Some(&frag_shader));
+        let program = program?;
+
+        let buffer = context.create_buffer().ok_or(RendererError::Create("buffer"))?;
+        let vertex_array = context.create_vertex_array().ok_or(RendererError::Create("vertex array"))?;
+        let previous = context.get_parameter(Gl::VERTEX_ARRAY_BINDING).ok()
+            .and_then(|value| value.dyn_into::<WebGlVertexArrayObject>().ok());
+        context.bind_vertex_array(Some(&vertex_array));
+        context.bind_buffer(Gl::ARRAY_BUFFER, Some(&buffer));
+        let stride = (INSTANCE_FLOATS * size_of::<f32>()) as i32;
+        for (location, components, offset) in [(0, 2, 0), (1, 1, 2), (2, 4, 3)] {
+            context.enable_vertex_attrib_array(location);
+            context.vertex_attrib_pointer_with_i32(location, components, Gl::FLOAT, false, stride, offset * size_of::<f32>() as i32);
+            context.vertex_attrib_divisor(location, 1);
+        }
+        context.bind_vertex_array(previous.as_ref());
+
+        Ok(ParticleSystem {
+            context: context.clone(),
+            view_proj_location: context.get_uniform_location(&program, "u_view_proj"),
+            program,
+            vertex_array,
+            buffer,
+            config,
+            rng: Rng(seed),
+            position: [0.0, 0.0],
+            emitting: true,
+            spawn_accumulator: 0.0,
+            particles: Vec::new(),
+            instances: Vec::new(),
+        })
+    }
+
+    /// ages and moves the particles by `dt` seconds, removing those that have died, and then spawns
+    /// new ones according to the spawn rate (if emitting)
+    pub fn update(&mut self, dt: f32) {
+        let acceleration = self.config.acceleration;
+        let mut i = 0;
+        while i < self.particles.len() {
+            let particle = &mut self.particles[i];
+            particle.age += dt;
+            if particle.age >= particle.lifetime {
+                self.particles.swap_remove(i);
+                continue;
+            }
+            for ((position, velocity), acceleration) in particle.position.iter_mut().zip(&mut particle.velocity).zip(acceleration) {
+                *velocity += acceleration * dt;
+                *position += *velocity * dt;
+            }
+            i += 1;
+        }
+
+        if self.emitting {
+            self.spawn_accumulator += self.config.spawn_rate * dt;
+            let count = self.spawn_accumulator.floor();
+            self.spawn_accumulator -= count;
+            self.emit(count as usize);
+        }
+    }
+
+    /// spawns `count` particles at once (eg. for an explosion), up to `max_particles`
+    pub fn emit(&mut self, count: usize) {
+        let count = count.min(self.config.max_particles.saturating_sub(self.particles.len()));
+        for _ in 0..count {
+            let lifetime = self.rng.range(self.config.lifetime);
+            let velocity = [self.rng.range(self.config.velocity_x), self.rng.range(self.config.velocity_y)];
+            self.particles.push(Particle { position: self.position, velocity, age: 0.0, lifetime });
+        }
+    }
+
+    /// where new particles are spawned, `[0.0, 0.0]` by default
+    pub fn set_position(&mut self, position: [f32; 2]) {
+        self.position = position;
+    }
+    pub fn position(&self) -> [f32; 2] {
+        self.position
+    }
+    /// whether `update` spawns particles, `true` by default, existing particles live out their lives either way
+    pub fn set_emitting(&mut self, emitting: bool) {
+        self.emitting = emitting;
+    }
+    pub fn is_emitting(&self) -> bool {
+        self.emitting
+    }
+    pub fn config(&self) -> &EmitterConfig {
+        &self.config
+    }
+    /// changes how new particles are spawned and how all particles change over their lives
+    pub fn set_config(&mut self, config: EmitterConfig) {
+        self.config = config;
+    }
+
+    /// the number of living particles
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+    /// removes every particle
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    /// draws every particle with one instanced draw call
+    ///
+    /// `view_proj` is the (column-major) view-projection matrix. Blending is set to additive for the draw
+    /// and then restored, as are the previously bound program and vertex array object
+    pub fn draw<S>(&mut self, render_info: &mut RenderInfo<S>, view_proj: [[f32; 4]; 4]) -> Result<(), RendererError> {
+        if self.particles.is_empty() {
+            return Ok(());
+        }
+        let EmitterConfig { start_color, end_color, start_size, end_size, .. } = self.config;
+        self.instances.clear();
+        for particle in &self.particles {
+            let t = (particle.age / particle.lifetime).clamp(0.0, 1.0);
+            self.instances.extend(particle.position);
+            self.instances.push(start_size + (end_size - start_size) * t);
+            self.instances.extend(std::array::from_fn::<f32, 4, _>(|i| start_color[i] + (end_color[i] - start_color[i]) * t));
+        }
+
+        let context = &self.context;
+        let previous_program = context.get_parameter(Gl::CURRENT_PROGRAM).ok()
+            .and_then(|value| value.dyn_into::<WebGlProgram>().ok());
+        let previous_vertex_array = context.get_parameter(Gl::VERTEX_ARRAY_BINDING).ok()
+            .and_then(|value| value.dyn_into::<WebGlVertexArrayObject>().ok());
+        let blend_enabled = context.is_enabled(Gl::BLEND);
+        let blend_factors = [Gl::BLEND_SRC_RGB, Gl::BLEND_DST_RGB, Gl::BLEND_SRC_ALPHA, Gl::BLEND_DST_ALPHA]
+            .map(|parameter| context.get_parameter(parameter).ok().and_then(|value| value.as_f64()).map(|value| value as u32));
+
+        context.bind_buffer(Gl::ARRAY_BUFFER, Some(&self.buffer));
+        // SAFETY: the view is only used to upload the instances, without allocating, before they are next modified
+        unsafe {
+            context.buffer_data_with_array_buffer_view(Gl::ARRAY_BUFFER, &js_sys::Float32Array::view(&self.instances), Gl::STREAM_DRAW);
+        }
+        context.use_program(Some(&self.program));
+        context.uniform_matrix4fv_with_f32_array(self.view_proj_location.as_ref(), false, view_proj.as_flattened());
+        context.bind_vertex_array(Some(&self.vertex_array));
+        context.enable(Gl::BLEND);
+        context.blend_func(Gl::ONE, Gl::ONE);
+
+        context.draw_arrays_instanced(Gl::TRIANGLE_STRIP, 0, 4, self.particles.len() as i32);
+        render_info.note_draw_call(self.particles.len() as u32 * 2);
+
+        if let [Some(src_rgb), Some(dst_rgb), Some(src_alpha), Some(dst_alpha)] = blend_factors {
+            context.blend_func_separate(src_rgb, dst_rgb, src_alpha, dst_alpha);
+        }
+        if !blend_enabled {
+            context.disable(Gl::BLEND);
+        }
+        context.bind_vertex_array(previous_vertex_array.as_ref());
+        context.use_program(previous_program.as_ref());
+        Ok(())
+    }
+}
+
+impl Drop for ParticleSystem {
+    fn drop(&mut self) {
+        self.context.delete_program(Some(&self.program));
+        self.context.delete_buffer(Some(&self.buffer));
+        self.context.delete_vertex_array(Some(&self.vertex_array));
+    }
+}
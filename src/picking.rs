@@ -0,0 +1,229 @@
+use web_sys::{WebGl2RenderingContext, WebGlFramebuffer};
+use js_sys::Float32Array;
+
+use crate::{RenderInfo, RendererError, RenderTarget, TextureFormat, DepthKind, Framebuffer, PixelReadback, Rect, check_gl_error};
+use crate::readback::read_pixels_async;
+
+type Gl = WebGl2RenderingContext;
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// reads the depth (in `[0, 1]`) at the pixel `(x, y)` of the bound framebuffer, where `(x, y)`
+    /// has a top-left origin relative to the drawing buffer, eg. to `unproject` the mouse position
+    ///
+    /// this should be called in `on_render` after drawing: the canvas is created without
+    /// `preserveDrawingBuffer` so its contents (including depth) are cleared once the frame is presented
+    /// and reads from eg. event listeners would only see the cleared buffer
+    ///
+    /// this blocks until the gpu has finished drawing, see `read_pixels_async` for a non-blocking read
+    ///
+    /// errors if the read is rejected, which it is by most webgl2 implementations as `DEPTH_COMPONENT`
+    /// reads are an optional extension to the spec, in which case depth should instead be written to
+    /// a color attachment (eg. of a `RenderTarget`) and read from there
+    pub fn read_depth_value(&self, x: i32, y: i32) -> Result<f32, RendererError> {
+        let context = &self.renderer.context;
+        let y = context.drawing_buffer_height() - y - 1;
+
+        // clear any earlier error so that it isn't mistaken for the read failing
+        while check_gl_error(context).is_some() {}
+        let depth = Float32Array::new_with_length(1);
+        context.read_pixels_with_opt_array_buffer_view(x, y, 1, 1, Gl::DEPTH_COMPONENT, Gl::FLOAT, Some(&depth))?;
+        if check_gl_error(context).is_some() {
+            return Err(RendererError::Unsupported("reading DEPTH_COMPONENT with readPixels"));
+        }
+        Ok(depth.get_index(0))
+    }
+}
+
+/// converts a pixel position (with a top-left origin) and the depth at that pixel back into a world
+/// space position, `view_proj_inverse` is the inverse of the (column-major) view-projection matrix used
+/// to draw the pixel and `viewport_size` is the size of the viewport in pixels
+///
+/// ```ignore
+/// let depth = render_info.read_depth_value(mouse_x, mouse_y)?;
+/// let position = unproject(mouse_x as f32, mouse_y as f32, depth, (width, height), view_proj_inverse);
+/// ```
+pub fn unproject(pixel_x: f32, pixel_y: f32, depth: f32, viewport_size: (f32, f32), view_proj_inverse: [[f32; 4]; 4]) -> [f32; 3] {
+    // to normalized device coordinates, which have a bottom-left origin
+    let ndc = [
+        (pixel_x + 0.5) / viewport_size.0 * 2.0 - 1.0,
+        1.0 - (pixel_y + 0.5) / viewport_size.1 * 2.0,
+        depth * 2.0 - 1.0,
+        1.0,
+    ];
+    let mut clip = [0.0; 4];
+    for (column, &component) in view_proj_inverse.iter().zip(&ndc) {
+        for (row, value) in clip.iter_mut().enumerate() {
+            *value += column[row] * component;
+        }
+    }
+    [clip[0] / clip[3], clip[1] / clip[3], clip[2] / clip[3]]
+}
+
+/// glsl for writing object ids as colors into a `PickBuffer`, to be pasted into a fragment shader:
+/// ```ignore
+/// uniform uint u_object_id;
+/// out vec4 color;
+/// void main() {
+///     color = encode_pick_id(u_object_id);
+/// }
+/// ```
+pub const PICK_ID_GLSL: &str = "vec4 encode_pick_id(uint id) {
+    return vec4(uvec4(id, id >> 8u, id >> 16u, id >> 24u) & 0xffu) / 255.0;
+}
+";
+
+/// the color that `decode_pick_id` decodes back into `id`, eg. for a `vec4` uniform when the shader
+/// can't use `PICK_ID_GLSL`
+///
+/// id `0` is reserved for "nothing", which is what `PickBuffer` is cleared to
+pub fn encode_pick_id(id: u32) -> [f32; 4] {
+    id.to_le_bytes().map(|byte| byte as f32 / 255.0)
+}
+
+/// the id in an rgba8 pixel of a `PickBuffer`, see `encode_pick_id`
+pub fn decode_pick_id(pixel: [u8; 4]) -> u32 {
+    u32::from_le_bytes(pixel)
+}
+
+/// an offscreen `Rgba8` target with a depth buffer that objects are drawn into with their id as their
+/// color (see `PICK_ID_GLSL`), so that the object under a pixel can be read back:
+/// ```ignore
+/// // in `on_render`, after drawing the scene
+/// render_info.bind_pick_buffer(&mut state.pick_buffer)?;
+/// for object in &state.objects {
+///     context.uniform1ui(Some(&id_location), object.id);
+///     object.draw(context);
+/// }
+/// render_info.bind_default_framebuffer();
+/// state.pending_pick = Some(render_info.pick_at_async(&state.pick_buffer, mouse_x, mouse_y)?);
+/// ...
+/// if let Some(picked) = state.pending_pick.as_mut().and_then(PendingPick::try_get) {
+///     state.hovered = picked;
+/// }
+/// ```
+///
+/// blending must be disabled while drawing into it, the alpha channel holds the top byte of the id
+pub struct PickBuffer {
+    target: RenderTarget,
+}
+
+impl PickBuffer {
+    /// usually sized to the canvas, which `RenderInfo::bind_pick_buffer` keeps it in sync with
+    ///
+    /// errors if the render target could not be created
+    pub fn new(context: &WebGl2RenderingContext, width: u32, height: u32) -> Result<PickBuffer, RendererError> {
+        Ok(PickBuffer {
+            target: RenderTarget::builder()
+                .color(TextureFormat::Rgba8)
+                .depth(DepthKind::Renderbuffer)
+                .build(context, width.max(1), height.max(1))?,
+        })
+    }
+
+    /// recreates the render target if it isn't already `width` by `height`, which clears it
+    ///
+    /// errors if the render target could not be created
+    pub fn resize(&mut self, context: &WebGl2RenderingContext, width: u32, height: u32) -> Result<(), RendererError> {
+        let (width, height) = (width.max(1), height.max(1));
+        if (self.target.width(), self.target.height()) != (width, height) {
+            *self = PickBuffer::new(context, width, height)?;
+        }
+        Ok(())
+    }
+
+    /// the id drawn at the pixel `(x, y)` (with a top-left origin), `None` if nothing was drawn there
+    /// or it is outside the buffer
+    ///
+    /// this blocks until the gpu has finished drawing, see `RenderInfo::pick_at_async` for a
+    /// non-blocking pick. Leaves the default framebuffer bound to `READ_FRAMEBUFFER`
+    pub fn pick_at(&self, context: &WebGl2RenderingContext, x: i32, y: i32) -> Option<u32> {
+        let rect = self.pixel_rect(x, y)?;
+        let [x, y, _, _] = rect.to_gl(self.target.height() as i32);
+        let mut pixel = [0; 4];
+        context.bind_framebuffer(Gl::READ_FRAMEBUFFER, Some(self.target.framebuffer()));
+        let result = context.read_pixels_with_opt_u8_array(x, y, 1, 1, Gl::RGBA, Gl::UNSIGNED_BYTE, Some(&mut pixel));
+        context.bind_framebuffer(Gl::READ_FRAMEBUFFER, None);
+        result.ok()?;
+        Some(decode_pick_id(pixel)).filter(|&id| id != 0)
+    }
+
+    /// the 1x1 rect at `(x, y)` if it is inside the buffer
+    fn pixel_rect(&self, x: i32, y: i32) -> Option<Rect> {
+        let inside = (0..self.target.width() as i32).contains(&x) && (0..self.target.height() as i32).contains(&y);
+        inside.then(|| Rect::new(x, y, 1, 1))
+    }
+
+    pub fn render_target(&self) -> &RenderTarget {
+        &self.target
+    }
+}
+
+impl Framebuffer for PickBuffer {
+    fn framebuffer(&self) -> &WebGlFramebuffer {
+        self.target.framebuffer()
+    }
+    fn size(&self) -> (u32, u32) {
+        (self.target.width(), self.target.height())
+    }
+}
+
+/// an in-flight read of a `PickBuffer`, see `RenderInfo::pick_at_async`
+pub struct PendingPick {
+    /// `None` if the pixel was outside the buffer, in which case nothing is picked
+    readback: Option<PixelReadback>,
+    taken: bool,
+}
+
+impl PendingPick {
+    /// `Some` with the picked id (or `None` if nothing was drawn there) once the gpu has finished
+    /// reading it, without blocking
+    ///
+    /// returns `Some` at most once
+    pub fn try_get(&mut self) -> Option<Option<u32>> {
+        if self.taken {
+            return None;
+        }
+        let picked = match &mut self.readback {
+            Some(readback) => {
+                let pixel = readback.try_get()?;
+                Some(decode_pick_id([pixel[0], pixel[1], pixel[2], pixel[3]])).filter(|&id| id != 0)
+            }
+            None => None,
+        };
+        self.taken = true;
+        Some(picked)
+    }
+}
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// resizes `pick_buffer` to `render_size` if it has changed, then binds it (see `bind_framebuffer`)
+    /// and clears it to id `0` and the far plane, restore the canvas with `bind_default_framebuffer`
+    ///
+    /// errors if the pick buffer had to be recreated and that failed
+    pub fn bind_pick_buffer(&mut self, pick_buffer: &mut PickBuffer) -> Result<(), RendererError> {
+        let (width, height) = self.renderer.render_size();
+        pick_buffer.resize(&self.renderer.context, width, height)?;
+        self.bind_framebuffer(pick_buffer);
+        let context = &self.renderer.context;
+        context.clear_bufferfv_with_f32_array(Gl::COLOR, 0, &[0.0; 4]);
+        context.clear_bufferfv_with_f32_array(Gl::DEPTH, 0, &[1.0]);
+        Ok(())
+    }
+
+    /// starts reading the id at the pixel `(x, y)` (with a top-left origin) of `pick_buffer` into a
+    /// pixel pack buffer so that it doesn't stall, poll it with `PendingPick::try_get` on later frames
+    ///
+    /// leaves the default framebuffer bound to `READ_FRAMEBUFFER`
+    ///
+    /// errors if a buffer or fence could not be created or `read_pixels` fails
+    pub fn pick_at_async(&mut self, pick_buffer: &PickBuffer, x: i32, y: i32) -> Result<PendingPick, RendererError> {
+        let Some(rect) = pick_buffer.pixel_rect(x, y) else {
+            return Ok(PendingPick { readback: None, taken: false });
+        };
+        let context = &self.renderer.context;
+        context.bind_framebuffer(Gl::READ_FRAMEBUFFER, Some(pick_buffer.target.framebuffer()));
+        let readback = read_pixels_async(context, &self.renderer.readback_pool, rect.to_gl(pick_buffer.target.height() as i32));
+        context.bind_framebuffer(Gl::READ_FRAMEBUFFER, None);
+        Ok(PendingPick { readback: Some(readback?), taken: false })
+    }
+}
@@ -0,0 +1,409 @@
+use wasm_bindgen::JsCast;
+use web_sys::WebGl2RenderingContext;
+use js_sys::Array;
+
+use crate::{CompareFunc, RenderInfo};
+use crate::extensions::with_cache;
+
+type Gl = WebGl2RenderingContext;
+
+/// what happens to the stencil value of a fragment, see `StencilFace`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    /// sets the value to the reference value
+    Replace,
+    /// increments the value, clamping at the maximum
+    Increment,
+    /// increments the value, wrapping to 0
+    IncrementWrap,
+    /// decrements the value, clamping at 0
+    Decrement,
+    /// decrements the value, wrapping to the maximum
+    DecrementWrap,
+    Invert,
+}
+impl StencilOp {
+    pub fn to_gl(self) -> u32 {
+        match self {
+            StencilOp::Keep => Gl::KEEP,
+            StencilOp::Zero => Gl::ZERO,
+            StencilOp::Replace => Gl::REPLACE,
+            StencilOp::Increment => Gl::INCR,
+            StencilOp::IncrementWrap => Gl::INCR_WRAP,
+            StencilOp::Decrement => Gl::DECR,
+            StencilOp::DecrementWrap => Gl::DECR_WRAP,
+            StencilOp::Invert => Gl::INVERT,
+        }
+    }
+}
+
+/// the stencil test and operations for one side of a polygon
+///
+/// a fragment passes the test if `func(reference & read_mask, value & read_mask)` is true
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilFace {
+    pub func: CompareFunc,
+    pub reference: i32,
+    pub read_mask: u32,
+    /// the bits of the stencil value that the operations can change
+    pub write_mask: u32,
+    /// the operation when the stencil test fails
+    pub fail: StencilOp,
+    /// the operation when the stencil test passes but the depth test fails
+    pub depth_fail: StencilOp,
+    /// the operation when both tests pass
+    pub pass: StencilOp,
+}
+
+impl StencilFace {
+    /// passes every fragment and writes `reference` wherever something is drawn
+    pub fn write(reference: i32) -> Self {
+        StencilFace {
+            func: CompareFunc::Always,
+            reference,
+            read_mask: !0,
+            write_mask: !0,
+            fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Replace,
+        }
+    }
+    /// passes fragments where `func(reference, value)` is true without changing the stencil buffer
+    pub fn test(func: CompareFunc, reference: i32) -> Self {
+        StencilFace {
+            func,
+            reference,
+            read_mask: !0,
+            write_mask: 0,
+            fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+}
+
+/// the stencil state of a `PipelineState`
+///
+/// a stencil buffer is needed for it to have any effect, eg. a render target with `depth_stencil`
+/// (the canvas is created without one)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilState {
+    pub front: StencilFace,
+    /// the state for back-facing polygons, `None` to use `front` for both
+    pub back: Option<StencilFace>,
+}
+
+impl StencilState {
+    /// uses `face` for front and back-facing polygons
+    pub fn new(face: StencilFace) -> Self {
+        StencilState {
+            front: face,
+            back: None,
+        }
+    }
+    /// uses `front` and `back` for front and back-facing polygons respectively, eg. for stencil shadow volumes
+    pub fn separate(front: StencilFace, back: StencilFace) -> Self {
+        StencilState {
+            front,
+            back: Some(back),
+        }
+    }
+
+    fn apply(&self, context: &WebGl2RenderingContext) {
+        match self.back {
+            None => apply_stencil_face(context, Gl::FRONT_AND_BACK, &self.front),
+            Some(back) => {
+                apply_stencil_face(context, Gl::FRONT, &self.front);
+                apply_stencil_face(context, Gl::BACK, &back);
+            }
+        }
+    }
+}
+
+fn apply_stencil_face(context: &WebGl2RenderingContext, face: u32, state: &StencilFace) {
+    context.stencil_func_separate(face, state.func.to_gl(), state.reference, state.read_mask);
+    context.stencil_op_separate(face, state.fail.to_gl(), state.depth_fail.to_gl(), state.pass.to_gl());
+    context.stencil_mask_separate(face, state.write_mask);
+}
+
+/// which faces are discarded before rasterisation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Cull {
+    /// culling is disabled
+    #[default]
+    None,
+    Back,
+    Front,
+}
+
+/// the winding order of the vertices of front-facing triangles, as seen on screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Winding {
+    /// counter-clockwise
+    #[default]
+    Ccw,
+    /// clockwise
+    Cw,
+}
+impl Winding {
+    pub fn to_gl(self) -> u32 {
+        match self {
+            Winding::Ccw => Gl::CCW,
+            Winding::Cw => Gl::CW,
+        }
+    }
+}
+
+/// offsets the depth of polygons by `factor * slope + units * r` (where `r` is the smallest resolvable
+/// depth difference), positive values push them away from the camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PolygonOffset {
+    pub factor: f32,
+    pub units: f32,
+}
+
+/// how the color of a fragment is combined with the color already in the framebuffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    /// the fragment replaces the framebuffer's color
+    #[default]
+    Disabled,
+    /// standard transparency for colors that aren't premultiplied: `(SRC_ALPHA, ONE_MINUS_SRC_ALPHA)`
+    AlphaBlend,
+    /// transparency for colors that are already multiplied by their alpha: `(ONE, ONE_MINUS_SRC_ALPHA)`
+    PremultipliedAlpha,
+    /// adds the colors, eg. for lights and particles: `(ONE, ONE)`
+    Additive,
+    /// multiplies the colors, eg. for shadows and tinting: `(DST_COLOR, ZERO)`
+    Multiply,
+    /// brightens the framebuffer's color by the fragment's: `(ONE, ONE_MINUS_SRC_COLOR)`
+    Screen,
+}
+impl BlendMode {
+    /// the `(src, dst)` factors for `blend_func`, `None` if blending is disabled
+    pub fn to_gl(self) -> Option<(u32, u32)> {
+        match self {
+            BlendMode::Disabled => None,
+            BlendMode::AlphaBlend => Some((Gl::SRC_ALPHA, Gl::ONE_MINUS_SRC_ALPHA)),
+            BlendMode::PremultipliedAlpha => Some((Gl::ONE, Gl::ONE_MINUS_SRC_ALPHA)),
+            BlendMode::Additive => Some((Gl::ONE, Gl::ONE)),
+            BlendMode::Multiply => Some((Gl::DST_COLOR, Gl::ZERO)),
+            BlendMode::Screen => Some((Gl::ONE, Gl::ONE_MINUS_SRC_COLOR)),
+        }
+    }
+}
+
+/// the fixed-function state that draws are made with, built up with the `with_*` methods:
+/// ```ignore
+/// let outline = PipelineState::new()
+///     .with_stencil(StencilState::new(StencilFace::test(CompareFunc::NotEqual, 1)));
+/// outline.apply(context);
+/// ```
+///
+/// `apply` remembers the state it last applied to each context and only makes the calls needed to get
+/// from that to the new state, so applying a state before every draw is cheap. State that is changed
+/// directly on the context isn't seen, so call `PipelineState::invalidate` after doing that
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PipelineState {
+    /// `None` disables the stencil test
+    pub stencil: Option<StencilState>,
+    pub cull: Cull,
+    pub front_face: Winding,
+    /// `None` disables the polygon offset
+    pub polygon_offset: Option<PolygonOffset>,
+    pub blend: BlendMode,
+}
+
+impl PipelineState {
+    /// the default webgl state
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// front-face culling with a polygon offset, the standard tricks for avoiding shadow acne when
+    /// rendering a shadow map (back faces are further from the light so are less likely to self-shadow)
+    pub fn shadow_pass() -> Self {
+        Self::new()
+            .with_cull(Cull::Front)
+            .with_polygon_offset(2.0, 4.0)
+    }
+    /// a negative polygon offset so that decals drawn on top of a surface don't z-fight with it
+    pub fn decal() -> Self {
+        Self::new().with_polygon_offset(-1.0, -1.0)
+    }
+
+    /// enables the stencil test with `stencil`
+    ///
+    /// returns self for chaining
+    pub fn with_stencil(mut self, stencil: StencilState) -> Self {
+        self.stencil = Some(stencil);
+        self
+    }
+
+    /// sets which faces are culled
+    ///
+    /// returns self for chaining
+    pub fn with_cull(mut self, cull: Cull) -> Self {
+        self.cull = cull;
+        self
+    }
+
+    /// sets the winding order of front faces
+    ///
+    /// returns self for chaining
+    pub fn with_front_face(mut self, front_face: Winding) -> Self {
+        self.front_face = front_face;
+        self
+    }
+
+    /// enables the polygon offset, see `PolygonOffset`
+    ///
+    /// returns self for chaining
+    pub fn with_polygon_offset(mut self, factor: f32, units: f32) -> Self {
+        self.polygon_offset = Some(PolygonOffset { factor, units });
+        self
+    }
+
+    /// sets the blend mode
+    ///
+    /// returns self for chaining
+    pub fn with_blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    /// sets the state of `context` to `self`, skipping anything that is unchanged since the last `apply`
+    pub fn apply(&self, context: &WebGl2RenderingContext) {
+        let previous = with_cache(context, |cache| cache.pipeline_state.replace(*self));
+        // the state is unknown before the first apply so everything is set
+        let known = previous.is_some();
+        let previous = previous.unwrap_or_default();
+
+        if !known || previous.stencil.is_some() != self.stencil.is_some() {
+            set_enabled(context, Gl::STENCIL_TEST, self.stencil.is_some());
+        }
+        if let Some(stencil) = self.stencil {
+            if !known || previous.stencil != Some(stencil) {
+                stencil.apply(context);
+            }
+        }
+
+        if !known || (previous.cull == Cull::None) != (self.cull == Cull::None) {
+            set_enabled(context, Gl::CULL_FACE, self.cull != Cull::None);
+        }
+        if !known || previous.cull != self.cull {
+            match self.cull {
+                Cull::None => {}
+                Cull::Back => context.cull_face(Gl::BACK),
+                Cull::Front => context.cull_face(Gl::FRONT),
+            }
+        }
+        if !known || previous.front_face != self.front_face {
+            context.front_face(self.front_face.to_gl());
+        }
+
+        if !known || previous.polygon_offset.is_some() != self.polygon_offset.is_some() {
+            set_enabled(context, Gl::POLYGON_OFFSET_FILL, self.polygon_offset.is_some());
+        }
+        if let Some(polygon_offset) = self.polygon_offset {
+            if !known || previous.polygon_offset != Some(polygon_offset) {
+                context.polygon_offset(polygon_offset.factor, polygon_offset.units);
+            }
+        }
+
+        if !known || previous.blend != self.blend {
+            apply_blend(context, known.then_some(previous.blend), self.blend);
+        }
+    }
+
+    /// forgets the state last applied to `context` so that the next `apply` sets everything, needed
+    /// after changing any of the state directly on the context
+    pub fn invalidate(context: &WebGl2RenderingContext) {
+        with_cache(context, |cache| cache.pipeline_state = None);
+    }
+}
+
+/// sets the blend mode of `context` to `blend`, `previous` is `None` if it isn't known
+fn apply_blend(context: &WebGl2RenderingContext, previous: Option<BlendMode>, blend: BlendMode) {
+    if previous.is_none_or(|previous| (previous == BlendMode::Disabled) != (blend == BlendMode::Disabled)) {
+        set_enabled(context, Gl::BLEND, blend != BlendMode::Disabled);
+    }
+    if let Some((src, dst)) = blend.to_gl() {
+        context.blend_func(src, dst);
+    }
+}
+
+fn set_enabled(context: &WebGl2RenderingContext, capability: u32, enabled: bool) {
+    if enabled {
+        context.enable(capability);
+    } else {
+        context.disable(capability);
+    }
+}
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// enables blending with the factors of `mode`, or disables it for `BlendMode::Disabled`:
+    /// ```ignore
+    /// render_info.set_blend_mode(BlendMode::Additive);
+    /// draw_particles(&mut render_info);
+    /// render_info.set_blend_mode(BlendMode::Disabled);
+    /// ```
+    ///
+    /// the rest of the pipeline state is left as it is, see `PipelineState::with_blend`
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        let context = &self.renderer.context;
+        let previous = with_cache(context, |cache| cache.pipeline_state.as_mut().map(|state| std::mem::replace(&mut state.blend, mode)));
+        if previous != Some(mode) {
+            apply_blend(context, previous, mode);
+        }
+    }
+
+    /// draws a stencil mask with `write_mask` and then draws `masked` only where the mask was drawn, eg.
+    /// for portals or for clipping the contents of a window to its shape:
+    /// ```ignore
+    /// render_info.with_stencil_mask(
+    ///     |render_info| draw_portal_frame(render_info),
+    ///     |render_info| draw_other_world(render_info),
+    /// );
+    /// ```
+    ///
+    /// the stencil buffer is cleared first and color and depth writes are disabled while the mask is
+    /// drawn, the pipeline state and write masks are restored afterwards. The bound framebuffer needs a
+    /// stencil buffer, see `StencilState`
+    pub fn with_stencil_mask<R>(&mut self, write_mask: impl FnOnce(&mut Self), masked: impl FnOnce(&mut Self) -> R) -> R {
+        let context = (*self.renderer.context).clone();
+        let previous = with_cache(&context, |cache| cache.pipeline_state);
+        let color_writemask = context.get_parameter(Gl::COLOR_WRITEMASK).ok()
+            .and_then(|value| value.dyn_into::<Array>().ok())
+            .map(|mask| [0, 1, 2, 3].map(|i| mask.get(i).as_bool().unwrap_or(true)))
+            .unwrap_or([true; 4]);
+        let depth_writemask = context.get_parameter(Gl::DEPTH_WRITEMASK).ok()
+            .and_then(|value| value.as_bool())
+            .unwrap_or(true);
+
+        let base = previous.unwrap_or_default();
+        base.with_stencil(StencilState::new(StencilFace::write(1))).apply(&context);
+        context.clear_stencil(0);
+        context.clear(Gl::STENCIL_BUFFER_BIT);
+        context.color_mask(false, false, false, false);
+        context.depth_mask(false);
+        write_mask(self);
+
+        let [red, green, blue, alpha] = color_writemask;
+        context.color_mask(red, green, blue, alpha);
+        context.depth_mask(depth_writemask);
+        base.with_stencil(StencilState::new(StencilFace::test(CompareFunc::Equal, 1))).apply(&context);
+        let result = masked(self);
+
+        match previous {
+            Some(previous) => previous.apply(&context),
+            None => {
+                PipelineState::default().apply(&context);
+                PipelineState::invalidate(&context);
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,196 @@
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{PointerEvent, WheelEvent};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use crate::{Renderer, RendererError, UpdateInfo};
+
+pub(crate) type OnPointer<S> = fn(&mut S, PointerEvent);
+
+/// the pointer events that `Renderer::with_on_pointer` can listen for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PointerEventType {
+    Down,
+    Up,
+    Move,
+    /// the browser took over the pointer (eg. to scroll) or the device stopped sending events
+    Cancel,
+    Over,
+    Out,
+    Enter,
+    Leave,
+}
+impl PointerEventType {
+    /// eg. `"pointerdown"`
+    pub fn event_type(self) -> &'static str {
+        match self {
+            PointerEventType::Down => "pointerdown",
+            PointerEventType::Up => "pointerup",
+            PointerEventType::Move => "pointermove",
+            PointerEventType::Cancel => "pointercancel",
+            PointerEventType::Over => "pointerover",
+            PointerEventType::Out => "pointerout",
+            PointerEventType::Enter => "pointerenter",
+            PointerEventType::Leave => "pointerleave",
+        }
+    }
+}
+
+/// the kind of device a pointer is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerType {
+    Mouse,
+    Pen,
+    Touch,
+    /// the browser couldn't tell
+    Unknown,
+}
+/// from `PointerEvent.pointerType`
+impl From<&str> for PointerType {
+    fn from(pointer_type: &str) -> Self {
+        match pointer_type {
+            "mouse" => PointerType::Mouse,
+            "pen" => PointerType::Pen,
+            "touch" => PointerType::Touch,
+            _ => PointerType::Unknown,
+        }
+    }
+}
+
+/// a pointer that is over the canvas, see `UpdateInfo::pointers`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointerSnapshot {
+    /// unique among the pointers that are active at the same time
+    pub id: i32,
+    /// in css pixels from the left of the canvas
+    pub x: f64,
+    /// in css pixels from the top of the canvas
+    pub y: f64,
+    /// in `[0, 1]`, `0.5` for a pressed mouse button and `0` when nothing is pressed
+    pub pressure: f64,
+    pub pointer_type: PointerType,
+}
+
+impl PointerSnapshot {
+    // `offset_x` and `offset_y` are already `f64` with `web_sys_unstable_apis`
+    #[allow(clippy::unnecessary_cast)]
+    fn from_event(event: &PointerEvent) -> Self {
+        PointerSnapshot {
+            id: event.pointer_id(),
+            x: event.offset_x() as f64,
+            y: event.offset_y() as f64,
+            pressure: event.pressure() as f64,
+            pointer_type: PointerType::from(event.pointer_type().as_str()),
+        }
+    }
+}
+
+/// keeps the active pointers up to date as pointer events arrive
+fn track_pointer(pointers: &RefCell<Vec<PointerSnapshot>>, event: &JsValue) {
+    let Some(event) = event.dyn_ref::<PointerEvent>() else { return };
+    let snapshot = PointerSnapshot::from_event(event);
+    let mut pointers = pointers.borrow_mut();
+    let existing = pointers.iter().position(|pointer| pointer.id == snapshot.id);
+    // a mouse is still over the canvas after its button is released but a finger or pen is gone
+    let remove = match event.type_().as_str() {
+        "pointerup" => snapshot.pointer_type != PointerType::Mouse,
+        "pointercancel" | "pointerleave" => true,
+        _ => false,
+    };
+    match (existing, remove) {
+        (Some(i), true) => { pointers.remove(i); }
+        (Some(i), false) => pointers[i] = snapshot,
+        (None, false) => pointers.push(snapshot),
+        (None, true) => {}
+    }
+}
+
+/// `deltaY` in pixels, converting scrolls by lines or pages
+fn wheel_pixels(event: &WheelEvent) -> f64 {
+    match event.delta_mode() {
+        WheelEvent::DOM_DELTA_LINE => event.delta_y() * 16.0,
+        WheelEvent::DOM_DELTA_PAGE => event.delta_y() * 800.0,
+        _ => event.delta_y(),
+    }
+}
+
+impl<S> Renderer<S> {
+    /// adds a pointer event listener, pointer events cover mice, touches and pens alike:
+    /// ```ignore
+    /// renderer = renderer.with_on_pointer(PointerEventType::Down, on_pointer_down)?;
+    /// ...
+    /// fn on_pointer_down(state: &mut State, event: web_sys::PointerEvent) {
+    ///     state.drag_start = Some((event.offset_x(), event.offset_y()));
+    /// }
+    /// ```
+    /// also starts tracking the pointers for `UpdateInfo::pointers`
+    ///
+    /// touches still scroll the page unless the canvas has the css `touch-action: none`
+    ///
+    /// returns self for chaining
+    ///
+    /// errors if the event listener could not be added or the renderer is headless
+    pub fn with_on_pointer(self, event_type: PointerEventType, on_pointer: OnPointer<S>) -> Result<Self, RendererError> {
+        self.with_pointer_tracking()?.add_event_listener(event_type.event_type(), false, 0, Box::new(move |state, event| {
+            if let Some(event) = event.dyn_ref::<PointerEvent>() {
+                on_pointer(state, event.clone())
+            }
+        }))
+    }
+
+    /// tracks the pointers that are over the canvas for `UpdateInfo::pointers` (and the mouse wheel
+    /// for `UpdateInfo::wheel_delta`), does nothing if they are already tracked
+    ///
+    /// returns self for chaining
+    ///
+    /// errors if the event listeners could not be added or the renderer is headless
+    pub fn with_pointer_tracking(mut self) -> Result<Self, RendererError> {
+        if self.pointers.is_some() {
+            return Ok(self);
+        }
+        let pointers = Rc::new(RefCell::new(Vec::new()));
+        for event_type in ["pointerdown", "pointermove", "pointerup", "pointercancel", "pointerleave"] {
+            let rc_pointers = pointers.clone();
+            // the lowest priority so that it runs before (and can't be skipped by) the other callbacks
+            self = self.add_event_listener(event_type, false, i32::MIN, Box::new(move |_, event| track_pointer(&rc_pointers, event)))?;
+        }
+        self.pointers = Some(pointers);
+
+        let wheel = Rc::new(Cell::new(0.0));
+        let rc_wheel = wheel.clone();
+        self = self.add_event_listener("wheel", true, i32::MIN, Box::new(move |_, event| {
+            if let Some(event) = event.dyn_ref::<WheelEvent>() {
+                rc_wheel.set(rc_wheel.get() + wheel_pixels(event));
+            }
+        }))?;
+        self.wheel = Some(wheel);
+        Ok(self)
+    }
+
+    /// copies the tracked pointers for the next update
+    pub(crate) fn snapshot_pointers(&mut self) {
+        if let Some(pointers) = &self.pointers {
+            self.pointer_snapshot.clone_from(&pointers.borrow());
+        }
+        if let Some(wheel) = &self.wheel {
+            self.wheel_snapshot = wheel.take();
+        }
+    }
+}
+
+impl<'a, S> UpdateInfo<'a, S> {
+    /// the pointers over the canvas as of the start of this update, in the order they arrived, eg. for
+    /// pinch zooming with two touches
+    ///
+    /// empty unless tracking was started with `Renderer::with_on_pointer` or `with_pointer_tracking`
+    pub fn pointers(&self) -> &[PointerSnapshot] {
+        &self.renderer.pointer_snapshot
+    }
+    /// how far the mouse wheel scrolled down (negative for up) between the previous update and this
+    /// one, in pixels
+    ///
+    /// `0.0` unless tracking was started with `Renderer::with_on_pointer` or `with_pointer_tracking`
+    pub fn wheel_delta(&self) -> f64 {
+        self.renderer.wheel_snapshot
+    }
+}
@@ -0,0 +1,122 @@
+use web_sys::{WebGl2RenderingContext, WebGlProgram, WebGlUniformLocation};
+
+use crate::{RenderInfo, RendererError, RenderTarget, Texture2d, TextureFormat};
+use crate::stats;
+
+type PassSetup<S> = Box<dyn Fn(&S, &WebGl2RenderingContext, &WebGlProgram)>;
+
+struct PostPass<S> {
+    program: WebGlProgram,
+    input_location: Option<WebGlUniformLocation>,
+    setup: PassSetup<S>,
+}
+
+/// a chain of post-processing passes, each drawn fullscreen (see `RenderInfo::draw_fullscreen`) with
+/// the output of the previous pass (or the scene for the first) bound to the `sampler2D u_input`:
+/// ```ignore
+/// let post_chain = PostChain::new(context, TextureFormat::Rgba16F)
+///     .with_pass(bloom_program, |state: &State, context, program| {
+///         context.uniform1f(context.get_uniform_location(program, "u_threshold").as_ref(), state.bloom_threshold);
+///     })
+///     .with_pass(tonemap_program, |_, _, _| {});
+/// ...
+/// // in on_render, after drawing the scene into `scene_target`
+/// post_chain.run_to_canvas(&mut render_info, scene_target.color_texture())?;
+/// ```
+///
+/// the intermediate passes render into two render targets that are swapped between passes, they are
+/// (re)created to match `RenderInfo::render_size` whenever it changes (eg. when the canvas is resized)
+pub struct PostChain<S> {
+    context: WebGl2RenderingContext,
+    format: TextureFormat,
+    passes: Vec<PostPass<S>>,
+    /// created by the first `run`
+    targets: Option<[RenderTarget; 2]>,
+}
+
+impl<S> PostChain<S> {
+    /// creates an empty chain whose intermediate render targets are of `format`
+    pub fn new(context: &WebGl2RenderingContext, format: TextureFormat) -> Self {
+        PostChain {
+            context: context.clone(),
+            format,
+            passes: Vec::new(),
+            targets: None,
+        }
+    }
+
+    /// appends a pass that draws with `program` (which should use `FULLSCREEN_VERTEX_SHADER`) after
+    /// calling `setup` to set any uniforms other than `u_input`
+    ///
+    /// returns self for chaining
+    pub fn with_pass(mut self, program: WebGlProgram, setup: impl Fn(&S, &WebGl2RenderingContext, &WebGlProgram) + 'static) -> Self {
+        self.passes.push(PostPass {
+            input_location: self.context.get_uniform_location(&program, "u_input"),
+            program,
+            setup: Box::new(setup),
+        });
+        self
+    }
+
+    /// runs every pass on `scene` and returns the output of the last pass (or `scene` if there are none)
+    ///
+    /// leaves the default framebuffer bound with the viewport set to `render_size` and restores the
+    /// program that was in use
+    ///
+    /// errors if the render targets could not be created
+    pub fn run<'t>(&'t mut self, render_info: &mut RenderInfo<S>, scene: &'t Texture2d) -> Result<&'t Texture2d, RendererError> {
+        let output = self.run_passes(render_info, scene, false)?;
+        Ok(output.unwrap_or(scene))
+    }
+
+    /// like `run` but the last pass renders straight into the canvas, saving a copy, does nothing if
+    /// there are no passes
+    pub fn run_to_canvas(&mut self, render_info: &mut RenderInfo<S>, scene: &Texture2d) -> Result<(), RendererError> {
+        self.run_passes(render_info, scene, true)?;
+        Ok(())
+    }
+
+    /// returns the render target the last pass drew into, `None` if it drew into the canvas or there are no passes
+    fn run_passes(&mut self, render_info: &mut RenderInfo<S>, scene: &Texture2d, to_canvas: bool) -> Result<Option<&Texture2d>, RendererError> {
+        let (width, height) = render_info.render_size();
+        let context = &self.context;
+        let targets = resize_targets(&mut self.targets, context, self.format, width, height)?;
+
+        let mut input = scene;
+        let mut output = None;
+        for (i, pass) in self.passes.iter().enumerate() {
+            let target = &targets[i % 2];
+            if to_canvas && i == self.passes.len() - 1 {
+                RenderTarget::unbind(context);
+                context.viewport(0, 0, width as i32, height as i32);
+                output = None;
+            } else {
+                target.bind();
+                output = Some(target.color_texture());
+            }
+
+            context.use_program(Some(&pass.program));
+            stats::count(|stats| stats.program_switches += 1);
+            input.bind(0);
+            render_info.renderer.texture_units.forget(0);
+            context.uniform1i(pass.input_location.as_ref(), 0);
+            (pass.setup)(render_info.state, context, &pass.program);
+            render_info.draw_fullscreen();
+            input = target.color_texture();
+        }
+
+        render_info.bind_default_framebuffer();
+        context.use_program(render_info.renderer.current_program());
+        stats::count(|stats| stats.program_switches += 1);
+        Ok(output)
+    }
+}
+
+/// (re)creates the targets if they don't exist yet or aren't `width`x`height`
+fn resize_targets<'t>(targets: &'t mut Option<[RenderTarget; 2]>, context: &WebGl2RenderingContext, format: TextureFormat, width: u32, height: u32) -> Result<&'t [RenderTarget; 2], RendererError> {
+    if targets.as_ref().is_none_or(|[target, _]| (target.width(), target.height()) != (width, height)) {
+        let builder = RenderTarget::builder().color(format);
+        return Ok(targets.insert([builder.build(context, width, height)?, builder.build(context, width, height)?]));
+    }
+    Ok(targets.as_ref().unwrap())
+}
@@ -0,0 +1,78 @@
+use web_sys::{WebGl2RenderingContext, WebGlProgram};
+use std::collections::HashMap;
+
+use crate::{RendererError, ShaderConfig, compile_shader, link_program};
+
+/// compiles permutations of the same shader sources with different sets of defines on demand and
+/// caches them, eg. for optional material features:
+/// ```ignore
+/// let mut variants = ProgramVariants::new(context, vert_shader, frag_shader, ShaderConfig::new().with_precision(Precision::High));
+/// ...
+/// let program = variants.get(&[("USE_NORMAL_MAP", "1")])?;
+/// context.use_program(Some(program));
+/// ```
+///
+/// the programs are deleted when this is dropped
+pub struct ProgramVariants {
+    context: WebGl2RenderingContext,
+    vert_shader: String,
+    frag_shader: String,
+    /// the defines of each variant are added to the ones in here
+    config: ShaderConfig,
+    /// keyed by the sorted defines
+    programs: HashMap<Vec<(String, String)>, WebGlProgram>,
+}
+
+impl ProgramVariants {
+    pub fn new(context: &WebGl2RenderingContext, vert_shader: &str, frag_shader: &str, config: ShaderConfig) -> Self {
+        ProgramVariants {
+            context: context.clone(),
+            vert_shader: vert_shader.to_owned(),
+            frag_shader: frag_shader.to_owned(),
+            config,
+            programs: HashMap::new(),
+        }
+    }
+
+    /// the program compiled with `defines` (in any order), compiling and linking it if this is the
+    /// first time it has been asked for
+    ///
+    /// errors if either shader fails to compile or the program fails to link, which is retried the next time
+    pub fn get(&mut self, defines: &[(&str, &str)]) -> Result<&WebGlProgram, RendererError> {
+        let mut key: Vec<(String, String)> = defines.iter().map(|&(key, value)| (key.to_owned(), value.to_owned())).collect();
+        key.sort();
+        key.dedup();
+
+        if !self.programs.contains_key(&key) {
+            let mut config = self.config.clone();
+            config.defines.extend(key.iter().cloned());
+            let vert_shader = compile_shader(&self.context, WebGl2RenderingContext::VERTEX_SHADER, &config.preprocess(&self.vert_shader))?;
+            let frag_shader = compile_shader(&self.context, WebGl2RenderingContext::FRAGMENT_SHADER, &config.preprocess(&self.frag_shader));
+            let program = frag_shader.and_then(|frag_shader| {
+                let program = link_program(&self.context, &vert_shader, &frag_shader);
+                // the shaders are only freed once the program is deleted too
+                self.context.delete_shader(Some(&frag_shader));
+                program
+            });
+            self.context.delete_shader(Some(&vert_shader));
+            self.programs.insert(key.clone(), program?);
+        }
+        Ok(&self.programs[&key])
+    }
+
+    /// the number of variants compiled so far
+    pub fn len(&self) -> usize {
+        self.programs.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.programs.is_empty()
+    }
+}
+
+impl Drop for ProgramVariants {
+    fn drop(&mut self) {
+        for program in self.programs.values() {
+            self.context.delete_program(Some(program));
+        }
+    }
+}
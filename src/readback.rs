@@ -0,0 +1,269 @@
+use web_sys::{WebGl2RenderingContext, WebGlBuffer};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::{RenderInfo, UpdateInfo, Renderer, RendererError, GpuFence, Rect};
+
+type Gl = WebGl2RenderingContext;
+
+/// pixel pack buffers that are reused between readbacks so that a buffer isn't created (and
+/// deleted) for every one, the buffers are deleted once the renderer and all readbacks are dropped
+pub(crate) struct BufferPool {
+    context: WebGl2RenderingContext,
+    /// unused buffers with their size in bytes
+    buffers: RefCell<Vec<(WebGlBuffer, usize)>>,
+}
+
+impl BufferPool {
+    pub(crate) fn new(context: &WebGl2RenderingContext) -> BufferPool {
+        BufferPool {
+            context: context.clone(),
+            buffers: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// takes an unused buffer of at least `len` bytes, creating one if there are none
+    fn take(&self, len: usize) -> Result<(WebGlBuffer, usize), RendererError> {
+        let mut buffers = self.buffers.borrow_mut();
+        if let Some(index) = buffers.iter().position(|&(_, size)| size >= len) {
+            return Ok(buffers.swap_remove(index));
+        }
+        let buffer = self.context.create_buffer().ok_or(RendererError::Create("buffer"))?;
+        self.context.bind_buffer(Gl::PIXEL_PACK_BUFFER, Some(&buffer));
+        self.context.buffer_data_with_i32(Gl::PIXEL_PACK_BUFFER, len as i32, Gl::STREAM_READ);
+        self.context.bind_buffer(Gl::PIXEL_PACK_BUFFER, None);
+        Ok((buffer, len))
+    }
+
+    fn give_back(&self, buffer: (WebGlBuffer, usize)) {
+        self.buffers.borrow_mut().push(buffer);
+    }
+}
+
+impl Drop for BufferPool {
+    fn drop(&mut self) {
+        for (buffer, _) in self.buffers.get_mut().drain(..) {
+            self.context.delete_buffer(Some(&buffer));
+        }
+    }
+}
+
+/// an in-flight `read_pixels` into a pixel pack buffer, see `RenderInfo::read_pixels_async`
+pub struct PixelReadback {
+    pool: Rc<BufferPool>,
+    /// `None` once the pixels have been taken
+    buffer: Option<(WebGlBuffer, usize)>,
+    fence: GpuFence,
+    len: usize,
+    width: u32,
+    height: u32,
+}
+
+impl PixelReadback {
+    /// the rgba8 pixels (bottom row first) if the gpu has finished reading them, without blocking
+    ///
+    /// returns `Some` at most once, after which the buffer is given back to be reused by other readbacks
+    pub fn try_get(&mut self) -> Option<Vec<u8>> {
+        if self.buffer.is_none() || !self.fence.is_signaled() {
+            return None;
+        }
+        let buffer = self.buffer.take().unwrap();
+        let context = &self.pool.context;
+
+        let mut pixels = vec![0; self.len];
+        context.bind_buffer(Gl::PIXEL_PACK_BUFFER, Some(&buffer.0));
+        context.get_buffer_sub_data_with_i32_and_u8_array(Gl::PIXEL_PACK_BUFFER, 0, &mut pixels);
+        context.bind_buffer(Gl::PIXEL_PACK_BUFFER, None);
+
+        self.pool.give_back(buffer);
+        Some(pixels)
+    }
+
+    /// whether `try_get` has already returned the pixels
+    pub fn is_taken(&self) -> bool {
+        self.buffer.is_none()
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+impl Drop for PixelReadback {
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            self.pool.give_back(buffer);
+        }
+    }
+}
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// starts reading the rgba8 pixels in `rect` of the bound framebuffer without stalling, poll the
+    /// result with `PixelReadback::try_get` on later frames:
+    /// ```ignore
+    /// state.pick = Some(render_info.read_pixels_async(Rect::new(mouse_x, mouse_y, 1, 1))?);
+    /// ...
+    /// if let Some(pixels) = state.pick.as_mut().and_then(PixelReadback::try_get) {
+    ///     state.selected = pixels[0];
+    /// }
+    /// ```
+    ///
+    /// `rect` has a top-left origin relative to the drawing buffer. The bound framebuffer must have a
+    /// fixed-point color buffer (eg. `Rgba8`), float render targets are read with `RenderTarget::read_pixels_f32`
+    ///
+    /// errors if a buffer or fence could not be created or `read_pixels` fails
+    pub fn read_pixels_async(&mut self, rect: Rect) -> Result<PixelReadback, RendererError> {
+        let context = &self.renderer.context;
+        read_pixels_async(context, &self.renderer.readback_pool, rect.to_gl(context.drawing_buffer_height()))
+    }
+}
+
+/// starts reading the rgba8 pixels in `[x, y, width, height]` (with a bottom-left origin) of the
+/// framebuffer bound to `READ_FRAMEBUFFER` into a buffer from `pool`
+pub(crate) fn read_pixels_async(context: &WebGl2RenderingContext, pool: &Rc<BufferPool>, [x, y, width, height]: [i32; 4]) -> Result<PixelReadback, RendererError> {
+    let len = width.max(0) as usize * height.max(0) as usize * 4;
+
+    let buffer = pool.take(len)?;
+    context.bind_buffer(Gl::PIXEL_PACK_BUFFER, Some(&buffer.0));
+    let result = context.read_pixels_with_i32(x, y, width, height, Gl::RGBA, Gl::UNSIGNED_BYTE, 0);
+    context.bind_buffer(Gl::PIXEL_PACK_BUFFER, None);
+
+    match result.map_err(RendererError::from).and_then(|()| GpuFence::insert(context)) {
+        Ok(fence) => Ok(PixelReadback {
+            pool: pool.clone(),
+            buffer: Some(buffer),
+            fence,
+            len,
+            width: width as u32,
+            height: height as u32,
+        }),
+        Err(err) => {
+            pool.give_back(buffer);
+            Err(err)
+        }
+    }
+}
+
+/// an in-flight copy of a buffer's contents, see `VertexBuffer::begin_async_readback`
+///
+/// the copy is made into a separate buffer so that the original can be modified (or dropped) while
+/// the readback is pending
+pub struct ReadbackToken {
+    context: WebGl2RenderingContext,
+    /// `None` once the data has been taken
+    buffer: Option<WebGlBuffer>,
+    fence: GpuFence,
+    len: usize,
+}
+
+impl ReadbackToken {
+    /// copies the first `len` bytes of `source` and inserts a fence after the copy
+    pub(crate) fn copy(context: &WebGl2RenderingContext, source: &WebGlBuffer, len: usize) -> Result<ReadbackToken, RendererError> {
+        let buffer = context.create_buffer().ok_or(RendererError::Create("buffer"))?;
+        context.bind_buffer(Gl::COPY_READ_BUFFER, Some(source));
+        context.bind_buffer(Gl::COPY_WRITE_BUFFER, Some(&buffer));
+        context.buffer_data_with_i32(Gl::COPY_WRITE_BUFFER, len as i32, Gl::STREAM_READ);
+        context.copy_buffer_sub_data_with_i32_and_i32_and_i32(Gl::COPY_READ_BUFFER, Gl::COPY_WRITE_BUFFER, 0, 0, len as i32);
+        context.bind_buffer(Gl::COPY_READ_BUFFER, None);
+        context.bind_buffer(Gl::COPY_WRITE_BUFFER, None);
+
+        match GpuFence::insert(context) {
+            Ok(fence) => Ok(ReadbackToken {
+                context: context.clone(),
+                buffer: Some(buffer),
+                fence,
+                len,
+            }),
+            Err(err) => {
+                context.delete_buffer(Some(&buffer));
+                Err(err)
+            }
+        }
+    }
+
+    /// the bytes if the gpu has finished copying them, without blocking (`getBufferSubData` is only
+    /// called once the fence has been signaled)
+    ///
+    /// returns `Some` at most once, after which the copy is deleted
+    pub fn poll(&mut self) -> Option<Vec<u8>> {
+        if self.buffer.is_none() || !self.fence.is_signaled() {
+            return None;
+        }
+        let buffer = self.buffer.take().unwrap();
+
+        let mut data = vec![0; self.len];
+        self.context.bind_buffer(Gl::COPY_READ_BUFFER, Some(&buffer));
+        self.context.get_buffer_sub_data_with_i32_and_u8_array(Gl::COPY_READ_BUFFER, 0, &mut data);
+        self.context.bind_buffer(Gl::COPY_READ_BUFFER, None);
+
+        self.context.delete_buffer(Some(&buffer));
+        Some(data)
+    }
+
+    /// whether `poll` has already returned the data
+    pub fn is_taken(&self) -> bool {
+        self.buffer.is_none()
+    }
+    /// the number of bytes being read back
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Drop for ReadbackToken {
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            self.context.delete_buffer(Some(&buffer));
+        }
+    }
+}
+
+/// a `ReadbackToken` polled by the renderer with the callback to call once it has the data
+pub(crate) type PendingReadback<S> = (ReadbackToken, Box<dyn FnOnce(&mut S, Vec<u8>)>);
+
+impl<S> Renderer<S> {
+    /// polls the pending buffer readbacks, calling the callbacks of those that are ready in the order
+    /// they were started
+    pub(crate) fn poll_buffer_readbacks(&mut self) {
+        if self.pending_readbacks.is_empty() {
+            return;
+        }
+        // the callbacks have to wait for the state to be initialised
+        let Some(state) = self.state.get() else { return };
+        let mut state = state.borrow_mut();
+        let mut i = 0;
+        while i < self.pending_readbacks.len() {
+            match self.pending_readbacks[i].0.poll() {
+                Some(data) => {
+                    let (_, callback) = self.pending_readbacks.remove(i);
+                    callback(&mut state, data);
+                }
+                None => i += 1,
+            }
+        }
+    }
+}
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// calls `callback` with the data of `token` once it has been read back, polling it at the start
+    /// of each frame:
+    /// ```ignore
+    /// let token = state.particles.begin_async_readback()?;
+    /// render_info.on_readback(token, |state, data| state.particle_data = data);
+    /// ```
+    pub fn on_readback(&mut self, token: ReadbackToken, callback: impl FnOnce(&mut S, Vec<u8>) + 'static) {
+        self.renderer.pending_readbacks.push((token, Box::new(callback)));
+    }
+}
+
+impl<'a, S> UpdateInfo<'a, S> {
+    /// see `RenderInfo::on_readback`
+    pub fn on_readback(&mut self, token: ReadbackToken, callback: impl FnOnce(&mut S, Vec<u8>) + 'static) {
+        self.renderer.pending_readbacks.push((token, Box::new(callback)));
+    }
+}
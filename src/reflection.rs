@@ -0,0 +1,151 @@
+use wasm_bindgen::JsValue;
+use web_sys::{WebGl2RenderingContext, WebGlProgram, WebGlUniformLocation};
+use js_sys::Array;
+use std::fmt;
+
+use crate::{AttributeType, VertexLayout};
+
+type Gl = WebGl2RenderingContext;
+
+/// an active uniform of a linked program, see `active_uniforms`
+#[derive(Debug, Clone)]
+pub struct UniformInfo {
+    /// the name, array uniforms end in `[0]`
+    pub name: String,
+    /// eg. `FLOAT_VEC3` or `SAMPLER_2D`
+    pub gl_type: u32,
+    /// the number of elements for arrays, 1 otherwise
+    pub size: i32,
+    /// `None` for uniforms in a uniform block
+    pub location: Option<WebGlUniformLocation>,
+    /// the index of the uniform block the uniform is in, if it is in one
+    pub block: Option<u32>,
+}
+
+/// an active vertex attribute of a linked program, see `active_attributes`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeInfo {
+    pub name: String,
+    /// eg. `FLOAT_VEC3` or `INT_VEC4`
+    pub gl_type: u32,
+    /// the number of elements for arrays, 1 otherwise
+    pub size: i32,
+    pub location: i32,
+}
+
+/// lists the uniforms that are active in `program` (ie. that weren't optimised out when it was linked)
+pub fn active_uniforms(context: &WebGl2RenderingContext, program: &WebGlProgram) -> Vec<UniformInfo> {
+    let count = program_parameter(context, program, Gl::ACTIVE_UNIFORMS);
+    let indices: Array = (0..count).map(JsValue::from).collect();
+    let blocks = Array::from(&context.get_active_uniforms(program, &indices, Gl::UNIFORM_BLOCK_INDEX));
+
+    (0..count).filter_map(|index| {
+        let info = context.get_active_uniform(program, index)?;
+        let block = blocks.get(index).as_f64().filter(|&block| block >= 0.0).map(|block| block as u32);
+        Some(UniformInfo {
+            location: match block {
+                Some(_) => None,
+                None => context.get_uniform_location(program, &info.name()),
+            },
+            name: info.name(),
+            gl_type: info.type_(),
+            size: info.size(),
+            block,
+        })
+    }).collect()
+}
+
+/// lists the vertex attributes that are active in `program`
+pub fn active_attributes(context: &WebGl2RenderingContext, program: &WebGlProgram) -> Vec<AttributeInfo> {
+    let count = program_parameter(context, program, Gl::ACTIVE_ATTRIBUTES);
+    (0..count).filter_map(|index| {
+        let info = context.get_active_attrib(program, index)?;
+        Some(AttributeInfo {
+            location: context.get_attrib_location(program, &info.name()),
+            name: info.name(),
+            gl_type: info.type_(),
+            size: info.size(),
+        })
+    }).collect()
+}
+
+fn program_parameter(context: &WebGl2RenderingContext, program: &WebGlProgram, pname: u32) -> u32 {
+    context.get_program_parameter(program, pname).as_f64().unwrap_or(0.0) as u32
+}
+
+/// a difference between a `VertexLayout` and the attributes of a program, see `VertexLayout::check`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutMismatch {
+    /// the program has an active attribute that isn't in the layout, so it will read a constant value
+    Missing(String),
+    /// the layout has an attribute that isn't active in the program (which may just have been optimised out)
+    Unused(String),
+    /// the layout and the program have a different number of components for an attribute
+    Components { name: String, layout: i32, program: i32 },
+    /// an integer attribute in the program is fed floats by the layout or vice versa, which is undefined
+    Type { name: String },
+}
+
+impl fmt::Display for LayoutMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutMismatch::Missing(name) => write!(f, "attribute `{}` is active in the program but not in the layout", name),
+            LayoutMismatch::Unused(name) => write!(f, "attribute `{}` is in the layout but not active in the program", name),
+            LayoutMismatch::Components { name, layout, program } => write!(f, "attribute `{}` has {} components in the layout but {} in the program", name, layout, program),
+            LayoutMismatch::Type { name } => write!(f, "attribute `{}` is an integer in one of the layout and the program but a float in the other", name),
+        }
+    }
+}
+
+impl VertexLayout {
+    /// compares the layout against the active attributes of `program`, which catches eg. typos in
+    /// attribute names that would otherwise silently fail
+    ///
+    /// ```ignore
+    /// for mismatch in layout.check(context, &program) {
+    ///     web_sys::console::warn_1(&mismatch.to_string().into());
+    /// }
+    /// ```
+    pub fn check(&self, context: &WebGl2RenderingContext, program: &WebGlProgram) -> Vec<LayoutMismatch> {
+        let active = active_attributes(context, program);
+        let mut mismatches = Vec::new();
+
+        for info in &active {
+            // built in attributes like `gl_VertexID` are listed too
+            if info.name.starts_with("gl_") { continue }
+            let Some(attribute) = self.attributes.iter().find(|attribute| attribute.name == info.name) else {
+                mismatches.push(LayoutMismatch::Missing(info.name.clone()));
+                continue
+            };
+            let Some((components, is_integer)) = attribute_type_shape(info.gl_type) else { continue };
+            if attribute.components != components {
+                mismatches.push(LayoutMismatch::Components { name: info.name.clone(), layout: attribute.components, program: components });
+            }
+            let feeds_integer = attribute.attribute_type != AttributeType::Float && !attribute.normalized;
+            if feeds_integer != is_integer {
+                mismatches.push(LayoutMismatch::Type { name: info.name.clone() });
+            }
+        }
+        for attribute in &self.attributes {
+            if !active.iter().any(|info| info.name == attribute.name) {
+                mismatches.push(LayoutMismatch::Unused(attribute.name.to_owned()));
+            }
+        }
+        mismatches
+    }
+}
+
+/// the number of components (per column for matrices) of an attribute type and whether they are integers
+fn attribute_type_shape(gl_type: u32) -> Option<(i32, bool)> {
+    Some(match gl_type {
+        Gl::FLOAT => (1, false),
+        Gl::FLOAT_VEC2 | Gl::FLOAT_MAT2 | Gl::FLOAT_MAT3X2 | Gl::FLOAT_MAT4X2 => (2, false),
+        Gl::FLOAT_VEC3 | Gl::FLOAT_MAT3 | Gl::FLOAT_MAT2X3 | Gl::FLOAT_MAT4X3 => (3, false),
+        Gl::FLOAT_VEC4 | Gl::FLOAT_MAT4 | Gl::FLOAT_MAT2X4 | Gl::FLOAT_MAT3X4 => (4, false),
+        Gl::INT | Gl::UNSIGNED_INT => (1, true),
+        Gl::INT_VEC2 | Gl::UNSIGNED_INT_VEC2 => (2, true),
+        Gl::INT_VEC3 | Gl::UNSIGNED_INT_VEC3 => (3, true),
+        Gl::INT_VEC4 | Gl::UNSIGNED_INT_VEC4 => (4, true),
+        _ => return None,
+    })
+}
@@ -0,0 +1,291 @@
+use web_sys::{WebGl2RenderingContext, WebGlFramebuffer, WebGlRenderbuffer};
+use wasm_bindgen::JsValue;
+use js_sys::{Array, Float32Array};
+
+use crate::{RendererError, Texture2d, TextureFormat, ColorSpace, SamplerOptions, Rect, has_extension};
+use crate::framebuffer::framebuffer_binding;
+
+type Gl = WebGl2RenderingContext;
+
+/// how the depth attachment of a `RenderTarget` is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthKind {
+    /// a renderbuffer, which is cheaper but can't be sampled
+    Renderbuffer,
+    /// a depth texture that can be sampled, eg. as a shadow map, see `RenderTarget::depth_texture`
+    Texture,
+}
+
+/// the depth attachment of a `RenderTarget`
+enum Depth {
+    Renderbuffer(WebGlRenderbuffer),
+    Texture(Texture2d),
+}
+
+/// describes the attachments of a `RenderTarget`, created with `RenderTarget::builder`
+///
+/// ```ignore
+/// let g_buffer = RenderTarget::builder()
+///     .color(TextureFormat::Rgba8)
+///     .color(TextureFormat::Rgba16F)
+///     .depth(DepthKind::Renderbuffer)
+///     .build(context, width, height)?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RenderTargetBuilder {
+    colors: Vec<TextureFormat>,
+    /// the kind of depth attachment and whether it has a stencil buffer too
+    depth: Option<(DepthKind, bool)>,
+}
+
+impl RenderTargetBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// adds a color attachment at the next `COLOR_ATTACHMENTi`
+    ///
+    /// float formats (eg. `Rgba16F` for hdr) need `EXT_color_buffer_float`, which `build` checks for.
+    /// `Rgba32F` attachments are sampled with nearest filtering unless `OES_texture_float_linear` is supported
+    ///
+    /// returns self for chaining
+    pub fn color(mut self, format: TextureFormat) -> Self {
+        self.colors.push(format);
+        self
+    }
+
+    /// adds a 24-bit depth attachment, replacing any previous depth attachment
+    ///
+    /// a `DepthKind::Texture` is sampled with `SamplerOptions::shadow` (for a `sampler2DShadow`), use
+    /// `Texture2d::set_sampler` with nearest filtering and no `compare_mode` to read the depth values instead
+    ///
+    /// returns self for chaining
+    pub fn depth(mut self, kind: DepthKind) -> Self {
+        self.depth = Some((kind, false));
+        self
+    }
+
+    /// like `depth` but with an 8-bit stencil buffer as well (`DEPTH24_STENCIL8`)
+    ///
+    /// returns self for chaining
+    pub fn depth_stencil(mut self, kind: DepthKind) -> Self {
+        self.depth = Some((kind, true));
+        self
+    }
+
+    /// creates the framebuffer and its attachments, then selects every color attachment with `drawBuffers`
+    ///
+    /// the framebuffers that were bound beforehand (eg. another render target being drawn into) are
+    /// bound again afterwards
+    ///
+    /// errors if a format needs an unsupported extension, if there are more color attachments than
+    /// `MAX_COLOR_ATTACHMENTS`, or if attaching something leaves the framebuffer incomplete
+    pub fn build(&self, context: &WebGl2RenderingContext, width: u32, height: u32) -> Result<RenderTarget, RendererError> {
+        for format in &self.colors {
+            if let Some(extension) = format.render_extension() {
+                if !has_extension(context, extension) {
+                    return Err(RendererError::MissingExtension(extension));
+                }
+            }
+        }
+        let max_attachments = context.get_parameter(Gl::MAX_COLOR_ATTACHMENTS)
+            .ok()
+            .and_then(|value| value.as_f64())
+            .unwrap_or(4.0) as usize;
+        if self.colors.len() > max_attachments {
+            return Err(RendererError::Framebuffer(format!(
+                "{} color attachments exceeds MAX_COLOR_ATTACHMENTS ({})", self.colors.len(), max_attachments,
+            )));
+        }
+
+        let framebuffer = context.create_framebuffer().ok_or(RendererError::Create("framebuffer"))?;
+        // constructed up front so the framebuffer (and anything already attached) is cleaned up on error
+        let mut target = RenderTarget {
+            context: context.clone(),
+            framebuffer,
+            colors: Vec::with_capacity(self.colors.len()),
+            formats: self.colors.clone(),
+            depth: None,
+            width,
+            height,
+        };
+
+        let previous_read = framebuffer_binding(context, Gl::READ_FRAMEBUFFER_BINDING);
+        let previous_draw = framebuffer_binding(context, Gl::DRAW_FRAMEBUFFER_BINDING);
+        context.bind_framebuffer(Gl::FRAMEBUFFER, Some(&target.framebuffer));
+        let result = self.attach(&mut target);
+        context.bind_framebuffer(Gl::READ_FRAMEBUFFER, previous_read.as_ref());
+        context.bind_framebuffer(Gl::DRAW_FRAMEBUFFER, previous_draw.as_ref());
+        result.map(|()| target)
+    }
+
+    fn attach(&self, target: &mut RenderTarget) -> Result<(), RendererError> {
+        let context = target.context.clone();
+        let (width, height) = (target.width, target.height);
+
+        let draw_buffers = Array::new();
+        for (i, &format) in self.colors.iter().enumerate() {
+            let attachment = Gl::COLOR_ATTACHMENT0 + i as u32;
+            // float textures that can't be filtered are incomplete (so sample as black) with linear filtering
+            let sampler = match format.filter_extension() {
+                Some(extension) if !has_extension(&context, extension) => SamplerOptions::nearest(),
+                _ => SamplerOptions::default(),
+            };
+            let color = Texture2d::empty_with_format(&context, width, height, format, &sampler)?;
+            context.framebuffer_texture_2d(Gl::FRAMEBUFFER, attachment, Gl::TEXTURE_2D, Some(color.texture()), 0);
+            target.colors.push(color);
+            check_status(&context, &format!("color attachment {} ({:?})", i, format))?;
+            draw_buffers.push(&JsValue::from(attachment));
+        }
+
+        if let Some((kind, stencil)) = self.depth {
+            let (internal_format, attachment) = match stencil {
+                false => (Gl::DEPTH_COMPONENT24, Gl::DEPTH_ATTACHMENT),
+                true => (Gl::DEPTH24_STENCIL8, Gl::DEPTH_STENCIL_ATTACHMENT),
+            };
+            match kind {
+                DepthKind::Renderbuffer => {
+                    let depth = context.create_renderbuffer().ok_or(RendererError::Create("renderbuffer"))?;
+                    context.bind_renderbuffer(Gl::RENDERBUFFER, Some(&depth));
+                    context.renderbuffer_storage(Gl::RENDERBUFFER, internal_format, width as i32, height as i32);
+                    context.bind_renderbuffer(Gl::RENDERBUFFER, None);
+                    context.framebuffer_renderbuffer(Gl::FRAMEBUFFER, attachment, Gl::RENDERBUFFER, Some(&depth));
+                    target.depth = Some(Depth::Renderbuffer(depth));
+                }
+                DepthKind::Texture => {
+                    let depth = Texture2d::empty_depth(&context, width, height, stencil, &SamplerOptions::shadow())?;
+                    context.framebuffer_texture_2d(Gl::FRAMEBUFFER, attachment, Gl::TEXTURE_2D, Some(depth.texture()), 0);
+                    target.depth = Some(Depth::Texture(depth));
+                }
+            }
+            check_status(&context, &format!("depth attachment ({:?})", kind))?;
+        }
+
+        if self.colors.is_empty() {
+            draw_buffers.push(&JsValue::from(Gl::NONE));
+        }
+        context.draw_buffers(&draw_buffers);
+        Ok(())
+    }
+}
+
+fn check_status(context: &WebGl2RenderingContext, attachment: &str) -> Result<(), RendererError> {
+    let status = context.check_framebuffer_status(Gl::FRAMEBUFFER);
+    if status != Gl::FRAMEBUFFER_COMPLETE {
+        return Err(RendererError::Framebuffer(format!("incomplete after adding {} (status {:#x})", attachment, status)));
+    }
+    Ok(())
+}
+
+/// an offscreen framebuffer with color texture attachments (and optionally a depth buffer) that can be
+/// rendered into and then sampled
+pub struct RenderTarget {
+    context: WebGl2RenderingContext,
+    framebuffer: WebGlFramebuffer,
+    colors: Vec<Texture2d>,
+    formats: Vec<TextureFormat>,
+    depth: Option<Depth>,
+    width: u32,
+    height: u32,
+}
+
+impl RenderTarget {
+    /// creates a render target with a single color attachment stored in `color_space`
+    ///
+    /// with `ColorSpace::Srgb`, linear values written by shaders are encoded to sRGB automatically
+    /// (and decoded again when the color texture is sampled)
+    pub fn new(context: &WebGl2RenderingContext, width: u32, height: u32, color_space: ColorSpace) -> Result<RenderTarget, RendererError> {
+        Self::builder().color(color_space.into()).build(context, width, height)
+    }
+
+    /// for render targets with multiple color attachments (eg. for deferred shading) or a depth buffer
+    pub fn builder() -> RenderTargetBuilder {
+        RenderTargetBuilder::new()
+    }
+
+    /// binds the framebuffer so that subsequent draws render into it and sets the viewport to cover it
+    ///
+    /// see `RenderInfo::bind_framebuffer`, which also keeps track of the bound framebuffer's size
+    pub fn bind(&self) {
+        self.context.bind_framebuffer(Gl::FRAMEBUFFER, Some(&self.framebuffer));
+        self.context.viewport(0, 0, self.width as i32, self.height as i32);
+    }
+
+    /// binds the default framebuffer (the canvas), the viewport must be restored by the caller
+    pub fn unbind(context: &WebGl2RenderingContext) {
+        context.bind_framebuffer(Gl::FRAMEBUFFER, None);
+    }
+
+    /// the first color attachment
+    ///
+    /// panics if the render target was built without any color attachments
+    pub fn color_texture(&self) -> &Texture2d {
+        &self.colors[0]
+    }
+    /// the color attachments, in `COLOR_ATTACHMENTi` order
+    pub fn color_textures(&self) -> &[Texture2d] {
+        &self.colors
+    }
+    /// the formats of the color attachments, in `COLOR_ATTACHMENTi` order
+    pub fn color_formats(&self) -> &[TextureFormat] {
+        &self.formats
+    }
+    /// the depth attachment if it is a `DepthKind::Renderbuffer`
+    pub fn depth_renderbuffer(&self) -> Option<&WebGlRenderbuffer> {
+        match &self.depth {
+            Some(Depth::Renderbuffer(depth)) => Some(depth),
+            _ => None,
+        }
+    }
+    /// the depth attachment if it is a `DepthKind::Texture`, eg. to bind as a shadow map
+    pub fn depth_texture(&self) -> Option<&Texture2d> {
+        match &self.depth {
+            Some(Depth::Texture(depth)) => Some(depth),
+            _ => None,
+        }
+    }
+    /// reads the pixels in `rect` (with a top-left origin) of the float color attachment `attachment` as
+    /// 4 floats per pixel (bottom row first), eg. to inspect the result of a gpgpu pass
+    ///
+    /// float attachments can't be read as bytes so `RenderInfo::read_pixels_async` can't be used for
+    /// them, instead use this or draw them into an `Rgba8` target first. This blocks until the gpu has
+    /// finished drawing and restores the framebuffer bound to `READ_FRAMEBUFFER`
+    ///
+    /// errors if there is no such attachment, it isn't a float format or the read fails
+    pub fn read_pixels_f32(&self, attachment: usize, rect: Rect) -> Result<Vec<f32>, RendererError> {
+        let format = *self.formats.get(attachment).ok_or_else(|| RendererError::Framebuffer(format!(
+            "there is no color attachment {} ({} attachments)", attachment, self.formats.len(),
+        )))?;
+        if !format.is_float() {
+            return Err(RendererError::Framebuffer(format!("color attachment {} ({:?}) is not a float format", attachment, format)));
+        }
+        let [x, y, width, height] = rect.to_gl(self.height as i32);
+        let pixels = Float32Array::new_with_length(width.max(0) as u32 * height.max(0) as u32 * 4);
+
+        let context = &self.context;
+        let previous_read = framebuffer_binding(context, Gl::READ_FRAMEBUFFER_BINDING);
+        context.bind_framebuffer(Gl::READ_FRAMEBUFFER, Some(&self.framebuffer));
+        context.read_buffer(Gl::COLOR_ATTACHMENT0 + attachment as u32);
+        let result = context.read_pixels_with_opt_array_buffer_view(x, y, width, height, Gl::RGBA, Gl::FLOAT, Some(&pixels));
+        context.bind_framebuffer(Gl::READ_FRAMEBUFFER, previous_read.as_ref());
+        result?;
+        Ok(pixels.to_vec())
+    }
+
+    pub fn framebuffer(&self) -> &WebGlFramebuffer {
+        &self.framebuffer
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        self.context.delete_framebuffer(Some(&self.framebuffer));
+        self.context.delete_renderbuffer(self.depth_renderbuffer());
+    }
+}
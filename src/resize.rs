@@ -0,0 +1,320 @@
+use wasm_bindgen::{JsCast, JsValue, closure::Closure};
+use web_sys::{HtmlCanvasElement, WebGl2RenderingContext, AddEventListenerOptions, ResizeObserver, ResizeObserverBoxOptions, ResizeObserverEntry, ResizeObserverOptions, ResizeObserverSize, window};
+use js_sys::Reflect;
+use std::rc::{Rc, Weak};
+
+use crate::{OnResize, OnResizeEntry, Rect, RenderInfo, Renderer, UpdateInfo};
+
+/// how many drawing-buffer pixels are used per css pixel of the canvas
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PixelRatio {
+    /// uses `window.devicePixelRatio` so that rendering is sharp on high-dpi displays
+    #[default]
+    Auto,
+    Fixed(f64),
+    /// one drawing-buffer pixel per css pixel, which looks blurry on high-dpi displays
+    Off,
+}
+
+impl PixelRatio {
+    pub fn ratio(self) -> f64 {
+        match self {
+            PixelRatio::Auto => window().unwrap().device_pixel_ratio(),
+            PixelRatio::Fixed(ratio) => ratio,
+            PixelRatio::Off => 1.0,
+        }
+    }
+}
+
+/// how the viewport fills the drawing buffer when it is
+/// resized, see `Renderer::with_resize_policy`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResizePolicy {
+    /// the viewport covers the whole drawing buffer, stretching a fixed virtual resolution if the
+    /// canvas is a different shape
+    #[default]
+    Stretch,
+    /// the largest viewport with the aspect ratio of the virtual resolution, centered with bars at the
+    /// sides (pillarboxing) or at the top and bottom (letterboxing)
+    FitPreserveAspect { virtual_width: u32, virtual_height: u32 },
+    /// like `FitPreserveAspect` but only scaling by whole numbers (at least 1) so that pixel art stays
+    /// crisp, leaving bigger bars
+    IntegerScale { virtual_width: u32, virtual_height: u32 },
+}
+
+impl ResizePolicy {
+    /// where the virtual resolution goes in a drawing buffer of `buffer_size`
+    pub fn virtual_viewport(self, buffer_size: (u32, u32)) -> VirtualViewport {
+        let (width, height) = (buffer_size.0.max(1), buffer_size.1.max(1));
+        let (virtual_width, virtual_height, integer) = match self {
+            ResizePolicy::Stretch => return VirtualViewport {
+                rect: Rect::new(0, 0, width as i32, height as i32),
+                virtual_size: (width, height),
+            },
+            ResizePolicy::FitPreserveAspect { virtual_width, virtual_height } => (virtual_width.max(1), virtual_height.max(1), false),
+            ResizePolicy::IntegerScale { virtual_width, virtual_height } => (virtual_width.max(1), virtual_height.max(1), true),
+        };
+        let scale = (width as f64 / virtual_width as f64).min(height as f64 / virtual_height as f64);
+        let scale = if integer { scale.floor().max(1.0) } else { scale };
+        let (viewport_width, viewport_height) = ((virtual_width as f64 * scale).round() as i32, (virtual_height as f64 * scale).round() as i32);
+        VirtualViewport {
+            rect: Rect::new((width as i32 - viewport_width) / 2, (height as i32 - viewport_height) / 2, viewport_width, viewport_height),
+            virtual_size: (virtual_width, virtual_height),
+        }
+    }
+}
+
+/// where a virtual resolution is drawn in the drawing buffer, from `ResizePolicy::virtual_viewport`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VirtualViewport {
+    /// the viewport, in drawing-buffer pixels with a top-left origin
+    pub rect: Rect,
+    /// the virtual resolution, the drawing buffer's size for `ResizePolicy::Stretch`
+    pub virtual_size: (u32, u32),
+}
+
+impl VirtualViewport {
+    /// the drawing-buffer pixels per virtual pixel horizontally and vertically, which are the same
+    /// unless the policy is `ResizePolicy::Stretch`
+    pub fn scale(&self) -> (f64, f64) {
+        (self.rect.width as f64 / self.virtual_size.0 as f64, self.rect.height as f64 / self.virtual_size.1 as f64)
+    }
+    /// converts a virtual position to drawing-buffer pixels, both with a top-left origin
+    pub fn to_buffer(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let (scale_x, scale_y) = self.scale();
+        (self.rect.x as f64 + x * scale_x, self.rect.y as f64 + y * scale_y)
+    }
+    /// converts a drawing-buffer position to virtual pixels, both with a top-left origin. Positions
+    /// in the bars are outside `(0, 0)..virtual_size`
+    pub fn from_buffer(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let (scale_x, scale_y) = self.scale();
+        ((x - self.rect.x as f64) / scale_x, (y - self.rect.y as f64) / scale_y)
+    }
+    /// the bars around the viewport in drawing-buffer pixels with a top-left origin, empty for
+    /// `ResizePolicy::Stretch`
+    pub fn bars(&self, buffer_size: (u32, u32)) -> Vec<Rect> {
+        let (width, height) = (buffer_size.0 as i32, buffer_size.1 as i32);
+        let Rect { x, y, width: viewport_width, height: viewport_height } = self.rect;
+        [
+            Rect::new(0, 0, width, y),
+            Rect::new(0, y + viewport_height, width, height - (y + viewport_height)),
+            Rect::new(0, y, x, viewport_height),
+            Rect::new(x + viewport_width, y, width - (x + viewport_width), viewport_height),
+        ].into_iter().filter(|bar| bar.width > 0 && bar.height > 0).collect()
+    }
+}
+
+/// the size of the canvas as reported by a `ResizeObserver`, see `Renderer::with_on_resize_entry`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ResizeObserverEntryData {
+    pub css_width: f64,
+    pub css_height: f64,
+    /// the exact size of the content box in device pixels (`devicePixelContentBoxSize`), or the css
+    /// size multiplied by `devicePixelRatio` in browsers that don't report it
+    pub device_pixel_width: f64,
+    pub device_pixel_height: f64,
+}
+
+impl ResizeObserverEntryData {
+    pub(crate) fn from_entry(entry: &ResizeObserverEntry) -> Self {
+        let content_rect = entry.content_rect();
+        let (css_width, css_height) = (content_rect.width(), content_rect.height());
+        // `devicePixelContentBoxSize` is undefined in browsers that don't support it
+        let device_pixel_size = Reflect::get(entry, &JsValue::from_str("devicePixelContentBoxSize"))
+            .ok()
+            .filter(|sizes| !sizes.is_undefined())
+            .map(|_| entry.device_pixel_content_box_size().get(0).unchecked_into::<ResizeObserverSize>());
+        let (device_pixel_width, device_pixel_height) = match device_pixel_size {
+            Some(size) => (size.inline_size(), size.block_size()),
+            None => {
+                let ratio = window().unwrap().device_pixel_ratio();
+                (css_width * ratio, css_height * ratio)
+            }
+        };
+        ResizeObserverEntryData { css_width, css_height, device_pixel_width, device_pixel_height }
+    }
+
+    /// for resizes that didn't come from the observer, eg. when the pixel ratio is changed
+    fn from_canvas(canvas: &HtmlCanvasElement) -> Self {
+        let (css_width, css_height) = (canvas.client_width() as f64, canvas.client_height() as f64);
+        let ratio = window().unwrap().device_pixel_ratio();
+        ResizeObserverEntryData { css_width, css_height, device_pixel_width: css_width * ratio, device_pixel_height: css_height * ratio }
+    }
+}
+
+/// the callback that chooses the size of the drawing buffer, only one can be set
+pub(crate) enum ResizeCallback<S> {
+    Size(OnResize<S>),
+    Entry(OnResizeEntry<S>),
+}
+
+/// observes the content box of `canvas`, in device pixels if the browser supports it so that the
+/// observer also fires when only the device pixel size changes
+pub(crate) fn observe(resize_observer: &ResizeObserver, canvas: &HtmlCanvasElement) {
+    let supports_device_pixels = Reflect::get(&js_sys::global(), &JsValue::from_str("ResizeObserverEntry"))
+        .and_then(|class| Reflect::get(&class, &JsValue::from_str("prototype")))
+        .and_then(|prototype| Reflect::has(&prototype, &JsValue::from_str("devicePixelContentBoxSize")))
+        .unwrap_or(false);
+    if supports_device_pixels {
+        let options = ResizeObserverOptions::new();
+        options.set_box(ResizeObserverBoxOptions::DevicePixelContentBox);
+        resize_observer.observe_with_options(canvas, &options);
+    } else {
+        resize_observer.observe(canvas);
+    }
+}
+
+/// sizes the drawing buffer to match the css size of the canvas (or the size from `on_resize`) scaled
+/// by `resolution_scale` and the viewport to fill it as `resize_policy` says, returning the unscaled size
+///
+/// `entry` is the most recent observation of the canvas, if there has been one
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn resize_canvas<S>(canvas: &HtmlCanvasElement, context: &WebGl2RenderingContext, state: &mut S, on_resize: Option<&ResizeCallback<S>>, pixel_ratio: PixelRatio, resolution_scale: f64, resize_policy: ResizePolicy, entry: Option<ResizeObserverEntryData>) -> (u32, u32) {
+
+    let css_size = (canvas.client_width() as u32, canvas.client_height() as u32);
+    let ratio = pixel_ratio.ratio();
+    let proposed_size = (
+        (css_size.0 as f64 * ratio).round() as u32,
+        (css_size.1 as f64 * ratio).round() as u32,
+    );
+    let (width, height) = match on_resize {
+        Some(ResizeCallback::Size(on_resize)) => on_resize(state, css_size, proposed_size),
+        Some(ResizeCallback::Entry(on_resize_entry)) => on_resize_entry(state, entry.unwrap_or_else(|| ResizeObserverEntryData::from_canvas(canvas))),
+        None => proposed_size,
+    };
+    set_buffer_size(canvas, context, (width, height), resolution_scale, resize_policy);
+    (width, height)
+}
+
+/// sizes the drawing buffer to `size` scaled by `resolution_scale`, leaving the css size alone so the
+/// canvas still fills its layout box, and sets the viewport to fill it as `resize_policy` says
+pub(crate) fn set_buffer_size(canvas: &HtmlCanvasElement, context: &WebGl2RenderingContext, size: (u32, u32), resolution_scale: f64, resize_policy: ResizePolicy) {
+    let (width, height) = scale_size(size, resolution_scale);
+    canvas.set_width(width);
+    canvas.set_height(height);
+    let [x, y, width, height] = resize_policy.virtual_viewport((width, height)).rect.to_gl(height as i32);
+    context.viewport(x, y, width, height);
+}
+
+/// scales a drawing-buffer size by a resolution scale, never going below 1x1
+fn scale_size((width, height): (u32, u32), resolution_scale: f64) -> (u32, u32) {
+    (
+        ((width as f64 * resolution_scale).round() as u32).max(1),
+        ((height as f64 * resolution_scale).round() as u32).max(1),
+    )
+}
+
+/// calls `resize` whenever `devicePixelRatio` changes (eg. when the window is dragged to another monitor)
+/// until `resize` is dropped
+///
+/// a `resolution` media query only matches the current ratio, so it is re-registered after each change
+pub(crate) fn watch_device_pixel_ratio(resize: Weak<dyn Fn()>) {
+    let window = window().unwrap();
+    let query = format!("(resolution: {}dppx)", window.device_pixel_ratio());
+    let Ok(Some(media_query_list)) = window.match_media(&query) else { return };
+
+    let closure = Closure::once_into_js(move || {
+        if let Some(resize) = resize.upgrade() {
+            resize();
+            watch_device_pixel_ratio(Rc::downgrade(&resize));
+        }
+    });
+    let options = AddEventListenerOptions::new();
+    options.set_once(true);
+    let _ = media_query_list.add_event_listener_with_callback_and_add_event_listener_options("change", closure.unchecked_ref(), &options);
+}
+
+impl<S> Renderer<S> {
+    /// sets how the viewport fills the drawing buffer (`ResizePolicy::Stretch` by default), eg. to
+    /// letterbox a fixed 16:9 virtual resolution:
+    /// ```ignore
+    /// renderer.with_resize_policy(ResizePolicy::FitPreserveAspect { virtual_width: 320, virtual_height: 180 })
+    /// ```
+    ///
+    /// `on_resize` still chooses the size of the drawing buffer, which the policy then fits the
+    /// viewport into. Resizes immediately if the game loop has started
+    ///
+    /// returns self for chaining
+    pub fn with_resize_policy(self, resize_policy: ResizePolicy) -> Self {
+        self.resize_policy.set(resize_policy);
+        (self.resize)();
+        self
+    }
+
+    /// where the virtual resolution is drawn within the drawing buffer
+    pub(crate) fn virtual_viewport(&self) -> VirtualViewport {
+        self.resize_policy.get().virtual_viewport(self.render_size())
+    }
+
+    /// sets the viewport to the policy's viewport within the drawing buffer
+    pub(crate) fn reset_viewport(&self) {
+        let [x, y, width, height] = self.virtual_viewport().rect.to_gl(self.render_size().1 as i32);
+        self.context.viewport(x, y, width, height);
+    }
+
+    /// converts a css position on the canvas (eg. a pointer's) to virtual pixels, which accounts for the
+    /// resolution scale as it is part of the drawing buffer's size
+    fn css_to_virtual(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let (css_width, css_height) = self.canvas_css_size();
+        let (width, height) = self.render_size();
+        let buffer_position = (x * width as f64 / css_width.max(1.0), y * height as f64 / css_height.max(1.0));
+        self.virtual_viewport().from_buffer(buffer_position)
+    }
+}
+
+impl<'a, S> UpdateInfo<'a, S> {
+    /// where the virtual resolution of the resize policy is drawn, see `Renderer::with_resize_policy`
+    pub fn virtual_viewport(&self) -> VirtualViewport {
+        self.renderer.virtual_viewport()
+    }
+    /// converts a css position on the canvas, eg. `PointerSnapshot::x` and `y`, to virtual pixels with
+    /// a top-left origin, which are drawing-buffer pixels with `ResizePolicy::Stretch`
+    pub fn to_virtual(&self, css_position: (f64, f64)) -> (f64, f64) {
+        self.renderer.css_to_virtual(css_position)
+    }
+    /// changes the resize policy at runtime and sets the viewport to match it
+    pub fn set_resize_policy(&mut self, resize_policy: ResizePolicy) {
+        self.renderer.resize_policy.set(resize_policy);
+        // `resize` borrows the state, which is already borrowed for the duration of `on_update`
+        self.renderer.reset_viewport();
+    }
+}
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// see `UpdateInfo::virtual_viewport`
+    pub fn virtual_viewport(&self) -> VirtualViewport {
+        self.renderer.virtual_viewport()
+    }
+    /// see `UpdateInfo::to_virtual`
+    pub fn to_virtual(&self, css_position: (f64, f64)) -> (f64, f64) {
+        self.renderer.css_to_virtual(css_position)
+    }
+    /// see `UpdateInfo::set_resize_policy`
+    pub fn set_resize_policy(&mut self, resize_policy: ResizePolicy) {
+        self.renderer.resize_policy.set(resize_policy);
+        self.renderer.reset_viewport();
+    }
+    /// clears the bars around the viewport of the resize policy to `color` (eg. `Color::BLACK`), leaving
+    /// the clear color and the scissor test as they were
+    pub fn clear_bars(&mut self, color: impl Into<[f32; 4]>) {
+        let bars = self.renderer.virtual_viewport().bars(self.renderer.render_size());
+        if bars.is_empty() {
+            return;
+        }
+        let [r, g, b, a] = color.into();
+        let context = (*self.renderer.context).clone();
+        let previous_color = context.get_parameter(WebGl2RenderingContext::COLOR_CLEAR_VALUE).ok()
+            .and_then(|value| value.dyn_into::<js_sys::Float32Array>().ok())
+            .map_or([0.0; 4], |array| {
+                let mut color = [0.0; 4];
+                array.copy_to(&mut color);
+                color
+            });
+        context.clear_color(r, g, b, a);
+        for bar in bars {
+            self.with_scissor(bar, |render_info| render_info.renderer.context.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT));
+        }
+        let [r, g, b, a] = previous_color;
+        context.clear_color(r, g, b, a);
+    }
+}
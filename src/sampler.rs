@@ -0,0 +1,287 @@
+use web_sys::{WebGl2RenderingContext, WebGlSampler};
+
+use crate::RendererError;
+use crate::extensions::max_anisotropy;
+
+type Gl = WebGl2RenderingContext;
+
+const TEXTURE_MAX_ANISOTROPY_EXT: u32 = 0x84FE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MinFilter {
+    Nearest,
+    Linear,
+    NearestMipmapNearest,
+    LinearMipmapNearest,
+    NearestMipmapLinear,
+    LinearMipmapLinear,
+}
+impl MinFilter {
+    pub fn to_gl(self) -> u32 {
+        match self {
+            MinFilter::Nearest => Gl::NEAREST,
+            MinFilter::Linear => Gl::LINEAR,
+            MinFilter::NearestMipmapNearest => Gl::NEAREST_MIPMAP_NEAREST,
+            MinFilter::LinearMipmapNearest => Gl::LINEAR_MIPMAP_NEAREST,
+            MinFilter::NearestMipmapLinear => Gl::NEAREST_MIPMAP_LINEAR,
+            MinFilter::LinearMipmapLinear => Gl::LINEAR_MIPMAP_LINEAR,
+        }
+    }
+    /// whether sampling with this filter requires the texture to have mipmaps
+    pub fn uses_mipmaps(self) -> bool {
+        !matches!(self, MinFilter::Nearest | MinFilter::Linear)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MagFilter {
+    Nearest,
+    Linear,
+}
+impl MagFilter {
+    pub fn to_gl(self) -> u32 {
+        match self {
+            MagFilter::Nearest => Gl::NEAREST,
+            MagFilter::Linear => Gl::LINEAR,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrapMode {
+    ClampToEdge,
+    Repeat,
+    MirroredRepeat,
+}
+impl WrapMode {
+    pub fn to_gl(self) -> u32 {
+        match self {
+            WrapMode::ClampToEdge => Gl::CLAMP_TO_EDGE,
+            WrapMode::Repeat => Gl::REPEAT,
+            WrapMode::MirroredRepeat => Gl::MIRRORED_REPEAT,
+        }
+    }
+}
+
+/// a comparison between a new value and an existing one (eg. the reference value and the sampled depth)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareFunc {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+impl CompareFunc {
+    pub fn to_gl(self) -> u32 {
+        match self {
+            CompareFunc::Never => Gl::NEVER,
+            CompareFunc::Less => Gl::LESS,
+            CompareFunc::Equal => Gl::EQUAL,
+            CompareFunc::LessEqual => Gl::LEQUAL,
+            CompareFunc::Greater => Gl::GREATER,
+            CompareFunc::NotEqual => Gl::NOTEQUAL,
+            CompareFunc::GreaterEqual => Gl::GEQUAL,
+            CompareFunc::Always => Gl::ALWAYS,
+        }
+    }
+}
+
+/// whether sampling a depth texture returns the depth or the result of a comparison (for `sampler2DShadow`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareMode {
+    None,
+    RefToTexture(CompareFunc),
+}
+
+/// filtering and wrapping parameters used when sampling a texture
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerOptions {
+    pub min_filter: MinFilter,
+    pub mag_filter: MagFilter,
+    pub wrap_s: WrapMode,
+    pub wrap_t: WrapMode,
+    /// only used by 3d textures
+    pub wrap_r: WrapMode,
+    /// the maximum anisotropy, clamped to what `EXT_texture_filter_anisotropic` supports, `1.0`
+    /// disables anisotropic filtering
+    ///
+    /// when the extension is unsupported this is ignored, so a mipmapped `min_filter` falls back to
+    /// plain trilinear filtering
+    pub max_anisotropy: f32,
+    pub compare_mode: CompareMode,
+}
+
+impl Default for SamplerOptions {
+    fn default() -> Self {
+        SamplerOptions {
+            min_filter: MinFilter::Linear,
+            mag_filter: MagFilter::Linear,
+            wrap_s: WrapMode::ClampToEdge,
+            wrap_t: WrapMode::ClampToEdge,
+            wrap_r: WrapMode::ClampToEdge,
+            max_anisotropy: 1.0,
+            compare_mode: CompareMode::None,
+        }
+    }
+}
+
+impl SamplerOptions {
+    /// nearest filtering without mipmaps, for pixel art
+    pub fn nearest() -> Self {
+        SamplerOptions {
+            min_filter: MinFilter::Nearest,
+            mag_filter: MagFilter::Nearest,
+            ..Default::default()
+        }
+    }
+    /// trilinear filtering with repeat wrapping, generates mipmaps
+    pub fn mipmapped() -> Self {
+        SamplerOptions {
+            min_filter: MinFilter::LinearMipmapLinear,
+            wrap_s: WrapMode::Repeat,
+            wrap_t: WrapMode::Repeat,
+            wrap_r: WrapMode::Repeat,
+            ..Default::default()
+        }
+    }
+    /// `mipmapped` with anisotropic filtering, for textures viewed at grazing angles (eg. the ground)
+    pub fn anisotropic(max_anisotropy: f32) -> Self {
+        SamplerOptions {
+            max_anisotropy,
+            ..Self::mipmapped()
+        }
+    }
+
+    /// linear filtering with depth comparison, for sampling a depth texture (eg. a shadow map) as a
+    /// `sampler2DShadow`, where the hardware filters the comparison results (percentage closer filtering)
+    pub fn shadow() -> Self {
+        SamplerOptions {
+            compare_mode: CompareMode::RefToTexture(CompareFunc::LessEqual),
+            ..Default::default()
+        }
+    }
+
+    /// applies the options to whichever texture is bound to `target`
+    pub(crate) fn apply_to_texture(&self, context: &WebGl2RenderingContext, target: u32) {
+        self.apply(
+            context,
+            |pname, param| context.tex_parameteri(target, pname, param),
+            |pname, param| context.tex_parameterf(target, pname, param),
+        );
+    }
+
+    fn apply(&self, context: &WebGl2RenderingContext, parameteri: impl Fn(u32, i32), parameterf: impl Fn(u32, f32)) {
+        parameteri(Gl::TEXTURE_MIN_FILTER, self.min_filter.to_gl() as i32);
+        parameteri(Gl::TEXTURE_MAG_FILTER, self.mag_filter.to_gl() as i32);
+        parameteri(Gl::TEXTURE_WRAP_S, self.wrap_s.to_gl() as i32);
+        parameteri(Gl::TEXTURE_WRAP_T, self.wrap_t.to_gl() as i32);
+        parameteri(Gl::TEXTURE_WRAP_R, self.wrap_r.to_gl() as i32);
+        match self.compare_mode {
+            CompareMode::None => {
+                parameteri(Gl::TEXTURE_COMPARE_MODE, Gl::NONE as i32);
+            }
+            CompareMode::RefToTexture(func) => {
+                parameteri(Gl::TEXTURE_COMPARE_MODE, Gl::COMPARE_REF_TO_TEXTURE as i32);
+                parameteri(Gl::TEXTURE_COMPARE_FUNC, func.to_gl() as i32);
+            }
+        }
+        if let Some(anisotropy) = clamp_anisotropy(context, self.max_anisotropy) {
+            parameterf(TEXTURE_MAX_ANISOTROPY_EXT, anisotropy);
+        }
+    }
+}
+
+/// the parameters set on a plain `WebGlTexture` by `RenderInfo::configure_texture_2d`, for textures
+/// created outside of `Texture2d`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureParams {
+    pub min_filter: MinFilter,
+    pub mag_filter: MagFilter,
+    pub wrap_s: WrapMode,
+    pub wrap_t: WrapMode,
+    /// whether to generate mipmaps from level 0, which must already be uploaded
+    pub generate_mipmaps: bool,
+}
+
+impl Default for TextureParams {
+    fn default() -> Self {
+        TextureParams {
+            min_filter: MinFilter::Linear,
+            mag_filter: MagFilter::Linear,
+            wrap_s: WrapMode::ClampToEdge,
+            wrap_t: WrapMode::ClampToEdge,
+            generate_mipmaps: false,
+        }
+    }
+}
+
+impl TextureParams {
+    /// applies the parameters to whichever texture is bound to `TEXTURE_2D`
+    pub(crate) fn apply(&self, context: &WebGl2RenderingContext) {
+        context.tex_parameteri(Gl::TEXTURE_2D, Gl::TEXTURE_MIN_FILTER, self.min_filter.to_gl() as i32);
+        context.tex_parameteri(Gl::TEXTURE_2D, Gl::TEXTURE_MAG_FILTER, self.mag_filter.to_gl() as i32);
+        context.tex_parameteri(Gl::TEXTURE_2D, Gl::TEXTURE_WRAP_S, self.wrap_s.to_gl() as i32);
+        context.tex_parameteri(Gl::TEXTURE_2D, Gl::TEXTURE_WRAP_T, self.wrap_t.to_gl() as i32);
+        if self.generate_mipmaps {
+            context.generate_mipmap(Gl::TEXTURE_2D);
+        }
+    }
+}
+
+/// clamps the requested anisotropy to the supported range,
+/// returns `None` when anisotropic filtering is unsupported
+fn clamp_anisotropy(context: &WebGl2RenderingContext, anisotropy: f32) -> Option<f32> {
+    Some(anisotropy.clamp(1.0, max_anisotropy(context)?.max(1.0)))
+}
+
+/// a WebGL2 sampler object that overrides the sampling parameters of whichever texture is bound to the same unit
+///
+/// useful for sampling the same texture in different ways without changing its parameters
+pub struct Sampler {
+    context: WebGl2RenderingContext,
+    sampler: WebGlSampler,
+}
+
+impl Sampler {
+    pub fn new(context: &WebGl2RenderingContext, options: &SamplerOptions) -> Result<Sampler, RendererError> {
+        let sampler = context.create_sampler().ok_or(RendererError::Create("sampler"))?;
+        let sampler = Sampler {
+            context: context.clone(),
+            sampler,
+        };
+        sampler.set_options(options);
+        Ok(sampler)
+    }
+
+    pub fn set_options(&self, options: &SamplerOptions) {
+        options.apply(
+            &self.context,
+            |pname, param| self.context.sampler_parameteri(&self.sampler, pname, param),
+            |pname, param| self.context.sampler_parameterf(&self.sampler, pname, param),
+        );
+    }
+
+    /// binds the sampler to the texture unit `unit` (ie. `TEXTURE0 + unit`)
+    pub fn bind(&self, unit: u32) {
+        self.context.bind_sampler(unit, Some(&self.sampler));
+    }
+
+    /// unbinds any sampler from the texture unit `unit`, restoring the texture's own parameters
+    pub fn unbind(context: &WebGl2RenderingContext, unit: u32) {
+        context.bind_sampler(unit, None);
+    }
+
+    pub fn sampler(&self) -> &WebGlSampler {
+        &self.sampler
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        self.context.delete_sampler(Some(&self.sampler));
+    }
+}
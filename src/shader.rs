@@ -0,0 +1,223 @@
+use web_sys::{WebGl2RenderingContext, WebGlProgram, WebGlShader};
+
+use crate::RendererError;
+
+/// glsl helpers for lighting in linear space, to be pasted into a fragment shader after its `#version` directive
+///
+/// sample `ColorSpace::Srgb` textures as usual (they are decoded to linear by the hardware), do all
+/// lighting maths on linear values and then write `output_color(color)`. the default framebuffer is
+/// not sRGB so set `u_encode_srgb` to `true` when drawing to the canvas (and to `false` when drawing
+/// into a `ColorSpace::Srgb` render target, which encodes on write):
+/// ```ignore
+/// let frag_shader = format!("#version 300 es\nprecision highp float;\n{}\n{}", SRGB_GLSL, body);
+/// ```
+pub const SRGB_GLSL: &str = "\
+uniform bool u_encode_srgb;
+
+vec3 srgb_to_linear(vec3 srgb) {
+    return mix(srgb / 12.92, pow((srgb + 0.055) / 1.055, vec3(2.4)), step(vec3(0.04045), srgb));
+}
+vec3 linear_to_srgb(vec3 linear) {
+    return mix(linear * 12.92, 1.055 * pow(linear, vec3(1.0 / 2.4)) - 0.055, step(vec3(0.0031308), linear));
+}
+vec4 output_color(vec4 linear) {
+    return u_encode_srgb ? vec4(linear_to_srgb(linear.rgb), linear.a) : linear;
+}
+";
+
+/// a glsl version whose `#version` directive (and default precision) is prepended to shader sources
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlslVersion {
+    /// `#version 300 es` followed by `precision highp float;`
+    WebGl2,
+}
+
+impl GlslVersion {
+    pub fn preamble(self) -> &'static str {
+        match self {
+            GlslVersion::WebGl2 => "#version 300 es\nprecision highp float;\n",
+        }
+    }
+}
+
+/// the default precision of floats (and ints) declared by `ShaderConfig::precision`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precision {
+    Low,
+    Medium,
+    High,
+}
+
+impl Precision {
+    pub fn preamble(self) -> &'static str {
+        match self {
+            Precision::Low => "precision lowp float;\nprecision lowp int;\n",
+            Precision::Medium => "precision mediump float;\nprecision mediump int;\n",
+            Precision::High => "precision highp float;\nprecision highp int;\n",
+        }
+    }
+}
+
+/// preprocessing applied to shader sources before they are compiled, eg. to compile the same source
+/// with different features:
+/// ```ignore
+/// let config = ShaderConfig::new()
+///     .with_precision(Precision::High)
+///     .with_define("USE_NORMAL_MAP", "1");
+/// ```
+///
+/// anything injected goes immediately after the `#version` directive (`#version 300 es` is added if
+/// the source doesn't have one), followed by a `#line` directive so that line numbers in compile
+/// errors still match the original source
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShaderConfig {
+    /// if set, replaces any `#version` directive in the source (eg. a desktop `#version 330`)
+    pub glsl_version: Option<GlslVersion>,
+    /// if set, injects default precision statements for floats and ints
+    pub precision: Option<Precision>,
+    /// injected as `#define KEY VALUE` lines after the `#version` directive
+    pub defines: Vec<(String, String)>,
+}
+
+impl ShaderConfig {
+    /// no preprocessing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// replaces the `#version` directive with the preamble of `glsl_version`
+    ///
+    /// returns self for chaining
+    pub fn with_glsl_version(mut self, glsl_version: GlslVersion) -> Self {
+        self.glsl_version = Some(glsl_version);
+        self
+    }
+
+    /// injects default precision statements
+    ///
+    /// returns self for chaining
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        self.precision = Some(precision);
+        self
+    }
+
+    /// injects `#define key value`
+    ///
+    /// returns self for chaining
+    pub fn with_define(mut self, key: &str, value: &str) -> Self {
+        self.defines.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// returns `source` with the version preamble, precision and defines inserted
+    pub fn preprocess(&self, source: &str) -> String {
+        let (version_line, body_line, body) = split_version_line(source);
+        let injecting = self.precision.is_some() || !self.defines.is_empty();
+
+        let mut output = String::with_capacity(source.len());
+        match (self.glsl_version, version_line) {
+            (Some(glsl_version), _) => output.push_str(glsl_version.preamble()),
+            (None, Some(version_line)) => {
+                output.push_str(version_line);
+                output.push('\n');
+            }
+            (None, None) if injecting => output.push_str("#version 300 es\n"),
+            (None, None) => {}
+        }
+        if let Some(precision) = self.precision {
+            output.push_str(precision.preamble());
+        }
+        for (key, value) in &self.defines {
+            output.push_str("#define ");
+            output.push_str(key);
+            output.push(' ');
+            output.push_str(value);
+            output.push('\n');
+        }
+        if output.matches('\n').count() + 1 != body_line {
+            // in glsl es 1.00 `#line n` sets the number of the line after next
+            let is_es1 = self.glsl_version.is_none() && version_line.is_some_and(|line| line.split_whitespace().nth(1) == Some("100"));
+            output.push_str(&format!("#line {}\n", if is_es1 { body_line - 1 } else { body_line }));
+        }
+        output.push_str(body);
+        output
+    }
+}
+
+/// splits off the `#version` directive if it is the first non-blank line of `source`, returning it,
+/// the (1-based) line number in `source` that the rest starts on and the rest
+fn split_version_line(source: &str) -> (Option<&str>, usize, &str) {
+    let trimmed = source.trim_start();
+    if !trimmed.starts_with("#version") {
+        return (None, 1, source);
+    }
+    let version_line_number = source[..source.len() - trimmed.len()].matches('\n').count() + 1;
+    match trimmed.split_once('\n') {
+        Some((version_line, body)) => (Some(version_line.trim_end()), version_line_number + 1, body),
+        None => (Some(trimmed.trim_end()), version_line_number + 1, ""),
+    }
+}
+
+/// compiles a shader of `shader_type` (`VERTEX_SHADER` or `FRAGMENT_SHADER`)
+///
+/// errors with the info log as `RendererError::VertexShader` or `RendererError::FragmentShader`
+pub fn compile_shader(context: &WebGl2RenderingContext, shader_type: u32, source: &str) -> Result<WebGlShader, RendererError> {
+    let shader = context.create_shader(shader_type)
+        .ok_or(RendererError::Create("shader"))?;
+    
+    context.shader_source(&shader, source);
+    context.compile_shader(&shader);
+
+    if context
+        .get_shader_parameter(&shader, WebGl2RenderingContext::COMPILE_STATUS)
+        .as_bool()
+        .unwrap_or(false)
+    {
+        Ok(shader)
+    } else {
+        let log = context
+            .get_shader_info_log(&shader)
+            .unwrap_or_else(|| String::from("Unknown error creating shader"));
+        context.delete_shader(Some(&shader));
+        Err(match shader_type {
+            WebGl2RenderingContext::VERTEX_SHADER => RendererError::VertexShader(log),
+            _ => RendererError::FragmentShader(log),
+        })
+    }
+}
+
+/// like `compile_shader` but injects `#define KEY VALUE` lines (after any `#version` directive) first
+pub fn compile_shader_with_defines(context: &WebGl2RenderingContext, shader_type: u32, source: &str, defines: &[(&str, &str)]) -> Result<WebGlShader, RendererError> {
+    let config = ShaderConfig {
+        defines: defines.iter().map(|&(key, value)| (key.to_owned(), value.to_owned())).collect(),
+        ..ShaderConfig::default()
+    };
+    compile_shader(context, shader_type, &config.preprocess(source))
+}
+
+/// links a vertex and fragment shader into a program
+///
+/// errors with the info log as `RendererError::Link`
+pub fn link_program(context: &WebGl2RenderingContext, vert_shader: &WebGlShader, frag_shader: &WebGlShader) -> Result<WebGlProgram, RendererError> {
+    let program = context
+        .create_program()
+        .ok_or(RendererError::Create("program"))?;
+
+    context.attach_shader(&program, vert_shader);
+    context.attach_shader(&program, frag_shader);
+    context.link_program(&program);
+
+    if context
+        .get_program_parameter(&program, WebGl2RenderingContext::LINK_STATUS)
+        .as_bool()
+        .unwrap_or(false)
+    {
+        Ok(program)
+    } else {
+        let log = context
+            .get_program_info_log(&program)
+            .unwrap_or_else(|| String::from("Unknown error linking shader objects to program object"));
+        context.delete_program(Some(&program));
+        Err(RendererError::Link(log))
+    }
+}
\ No newline at end of file
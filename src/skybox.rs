@@ -0,0 +1,207 @@
+use wasm_bindgen::JsCast;
+use web_sys::{WebGl2RenderingContext, WebGlFramebuffer, WebGlProgram, WebGlUniformLocation, WebGlVertexArrayObject};
+
+use crate::{Camera3d, RenderInfo, RendererError, SamplerOptions, Texture2d, TextureCube, TextureFormat, FULLSCREEN_VERTEX_SHADER, compile_shader, link_program};
+use crate::camera3d::{invert, multiply};
+use crate::stats;
+use crate::viewport::{RestoreEnabled, get_box};
+
+type Gl = WebGl2RenderingContext;
+
+const SKYBOX_VERTEX_SHADER: &str = "\
+#version 300 es
+uniform mat4 u_inverse_view_proj;
+out vec4 v_direction;
+
+void main() {
+    // a fullscreen triangle at the far plane, so that it is behind everything drawn before it
+    vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    gl_Position = vec4(position, 1.0, 1.0);
+    v_direction = u_inverse_view_proj * gl_Position;
+}
+";
+
+const SKYBOX_FRAGMENT_SHADER: &str = "\
+#version 300 es
+precision mediump float;
+uniform samplerCube u_sky;
+in vec4 v_direction;
+out vec4 color;
+
+void main() {
+    color = texture(u_sky, v_direction.xyz / v_direction.w);
+}
+";
+
+const EQUIRECTANGULAR_FRAGMENT_SHADER: &str = "\
+#version 300 es
+precision highp float;
+uniform sampler2D u_panorama;
+uniform int u_face;
+in vec2 v_uv;
+out vec4 color;
+
+const float PI = 3.14159265359;
+
+void main() {
+    // the direction through this texel of the face, from the cube map face selection table
+    vec2 st = v_uv * 2.0 - 1.0;
+    vec3 direction;
+    if (u_face == 0) direction = vec3(1.0, -st.y, -st.x);
+    else if (u_face == 1) direction = vec3(-1.0, -st.y, st.x);
+    else if (u_face == 2) direction = vec3(st.x, 1.0, st.y);
+    else if (u_face == 3) direction = vec3(st.x, -1.0, -st.y);
+    else if (u_face == 4) direction = vec3(st.x, -st.y, 1.0);
+    else direction = vec3(-st.x, -st.y, -1.0);
+    direction = normalize(direction);
+    // the middle of the panorama faces -z and its top faces +y
+    vec2 uv = vec2(atan(direction.x, -direction.z) / (2.0 * PI) + 0.5, asin(clamp(direction.y, -1.0, 1.0)) / PI + 0.5);
+    // the lod is fixed as the derivatives jump where u wraps around
+    color = textureLod(u_panorama, uv, 0.0);
+}
+";
+
+/// a cube map drawn behind everything else as the background of a 3d scene:
+/// ```ignore
+/// // in `with_state_init`
+/// let sky = TextureCube::from_equirectangular(context, &panorama, 512, TextureFormat::Srgb8Alpha8, &SamplerOptions::default())?;
+/// let skybox = Skybox::new(context, sky)?;
+/// ...
+/// // in `on_render`, after drawing the opaque geometry
+/// skybox.draw(&mut render_info, &camera);
+/// ```
+pub struct Skybox {
+    context: WebGl2RenderingContext,
+    program: WebGlProgram,
+    inverse_view_proj_location: Option<WebGlUniformLocation>,
+    sky_location: Option<WebGlUniformLocation>,
+    texture: TextureCube,
+}
+
+impl Skybox {
+    /// errors if the skybox program could not be created
+    pub fn new(context: &WebGl2RenderingContext, texture: TextureCube) -> Result<Skybox, RendererError> {
+        let program = compile(context, SKYBOX_VERTEX_SHADER, SKYBOX_FRAGMENT_SHADER)?;
+        Ok(Skybox {
+            context: context.clone(),
+            inverse_view_proj_location: context.get_uniform_location(&program, "u_inverse_view_proj"),
+            sky_location: context.get_uniform_location(&program, "u_sky"),
+            program,
+            texture,
+        })
+    }
+
+    /// draws the sky seen through `camera` wherever nothing has been drawn yet (with the depth test
+    /// at `LEQUAL` at the far plane and without writing depth), so it is cheapest after the opaque
+    /// geometry and must come before anything transparent
+    ///
+    /// the camera's position is ignored so the sky is infinitely far away. The depth state and the
+    /// previously bound program are restored afterwards
+    pub fn draw<S>(&self, render_info: &mut RenderInfo<S>, camera: &Camera3d) {
+        let context = self.context.clone();
+        let unit = render_info.bind_texture(&self.texture);
+        let mut view = camera.view();
+        view[3] = [0.0, 0.0, 0.0, 1.0];
+        let inverse_view_proj = invert(multiply(camera.projection(), view));
+
+        let previous_program = context.get_parameter(Gl::CURRENT_PROGRAM).ok()
+            .and_then(|value| value.dyn_into::<WebGlProgram>().ok());
+        let depth_func = context.get_parameter(Gl::DEPTH_FUNC).ok()
+            .and_then(|value| value.as_f64())
+            .map_or(Gl::LESS, |func| func as u32);
+        let depth_writemask = context.get_parameter(Gl::DEPTH_WRITEMASK).ok()
+            .and_then(|value| value.as_bool())
+            .unwrap_or(true);
+        let _restore_depth_test = RestoreEnabled::new(&context, Gl::DEPTH_TEST);
+
+        context.enable(Gl::DEPTH_TEST);
+        context.depth_func(Gl::LEQUAL);
+        context.depth_mask(false);
+        context.use_program(Some(&self.program));
+        context.uniform_matrix4fv_with_f32_array(self.inverse_view_proj_location.as_ref(), false, inverse_view_proj.as_flattened());
+        context.uniform1i(self.sky_location.as_ref(), unit as i32);
+        render_info.draw_fullscreen();
+
+        context.depth_func(depth_func);
+        context.depth_mask(depth_writemask);
+        context.use_program(previous_program.as_ref());
+    }
+
+    pub fn texture(&self) -> &TextureCube {
+        &self.texture
+    }
+}
+
+impl Drop for Skybox {
+    fn drop(&mut self) {
+        self.context.delete_program(Some(&self.program));
+    }
+}
+
+impl TextureCube {
+    /// renders the six faces of a cube map of `size` from an equirectangular (latitude-longitude)
+    /// panorama, eg. an hdri downscaled to ldr, whose middle faces `-z`
+    ///
+    /// `format` must be renderable (see `TextureFormat::render_extension`). The panorama is left bound
+    /// to texture unit 0 like a newly created texture, the rest of the state is restored afterwards
+    ///
+    /// errors if the conversion program, framebuffer or texture could not be created
+    pub fn from_equirectangular(context: &WebGl2RenderingContext, panorama: &Texture2d, size: u32, format: TextureFormat, sampler: &SamplerOptions) -> Result<TextureCube, RendererError> {
+        let cube = TextureCube::empty(context, size, format, &SamplerOptions::default())?;
+        let program = compile(context, FULLSCREEN_VERTEX_SHADER, EQUIRECTANGULAR_FRAGMENT_SHADER)?;
+        let (Some(framebuffer), Some(vertex_array)) = (context.create_framebuffer(), context.create_vertex_array()) else {
+            context.delete_program(Some(&program));
+            return Err(RendererError::Create("framebuffer"));
+        };
+
+        let previous_framebuffer = context.get_parameter(Gl::FRAMEBUFFER_BINDING).ok()
+            .and_then(|value| value.dyn_into::<WebGlFramebuffer>().ok());
+        let previous_program = context.get_parameter(Gl::CURRENT_PROGRAM).ok()
+            .and_then(|value| value.dyn_into::<WebGlProgram>().ok());
+        let previous_vertex_array = context.get_parameter(Gl::VERTEX_ARRAY_BINDING).ok()
+            .and_then(|value| value.dyn_into::<WebGlVertexArrayObject>().ok());
+        let [x, y, width, height] = get_box(context, Gl::VIEWPORT);
+        let _restore_enabled = [Gl::BLEND, Gl::CULL_FACE, Gl::DEPTH_TEST, Gl::SCISSOR_TEST, Gl::STENCIL_TEST].map(|capability| {
+            let restore = RestoreEnabled::new(context, capability);
+            context.disable(capability);
+            restore
+        });
+
+        context.bind_framebuffer(Gl::FRAMEBUFFER, Some(&framebuffer));
+        context.viewport(0, 0, size as i32, size as i32);
+        context.use_program(Some(&program));
+        context.bind_vertex_array(Some(&vertex_array));
+        panorama.bind(0);
+        context.uniform1i(context.get_uniform_location(&program, "u_panorama").as_ref(), 0);
+        let face_location = context.get_uniform_location(&program, "u_face");
+        for face in 0..6 {
+            context.framebuffer_texture_2d(Gl::FRAMEBUFFER, Gl::COLOR_ATTACHMENT0, Gl::TEXTURE_CUBE_MAP_POSITIVE_X + face, Some(cube.texture()), 0);
+            context.uniform1i(face_location.as_ref(), face as i32);
+            context.draw_arrays(Gl::TRIANGLES, 0, 3);
+            stats::count(|stats| {
+                stats.draw_calls += 1;
+                stats.triangles += 1;
+            });
+        }
+
+        context.bind_framebuffer(Gl::FRAMEBUFFER, previous_framebuffer.as_ref());
+        context.viewport(x, y, width, height);
+        context.use_program(previous_program.as_ref());
+        context.bind_vertex_array(previous_vertex_array.as_ref());
+        context.delete_framebuffer(Some(&framebuffer));
+        context.delete_vertex_array(Some(&vertex_array));
+        context.delete_program(Some(&program));
+
+        cube.set_sampler(sampler);
+        Ok(cube)
+    }
+}
+
+fn compile(context: &WebGl2RenderingContext, vert_shader: &str, frag_shader: &str) -> Result<WebGlProgram, RendererError> {
+    let vert_shader = compile_shader(context, Gl::VERTEX_SHADER, vert_shader)?;
+    let frag_shader = compile_shader(context, Gl::FRAGMENT_SHADER, frag_shader)?;
+    let program = link_program(context, &vert_shader, &frag_shader);
+    context.delete_shader(Some(&vert_shader));
+    context.delete_shader(Some(&frag_shader));
+    program
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+use crate::{AtlasRegion, RendererError, TextureAtlas};
+
+/// what a `SpriteAnimation` does after its last frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AnimationMode {
+    /// starts again from the first frame
+    #[default]
+    Loop,
+    /// stays on the last frame and finishes
+    Once,
+    /// plays backwards to the first frame and then forwards again
+    PingPong,
+}
+
+/// frames of a sprite sheet played at a fixed rate, advanced with the fixed time step in `on_update`
+/// so that which frame is shown only depends on how many updates there have been:
+/// ```ignore
+/// // in `with_state_init`
+/// let walk = SpriteAnimation::from_atlas(&atlas, &["walk_0", "walk_1", "walk_2", "walk_3"], 12.0, AnimationMode::Loop)?;
+/// ...
+/// // in `on_update`
+/// state.walk.tick(update_info.fixed_time_step());
+/// ...
+/// // in `on_render`
+/// if let Some(frame) = state.walk.current_frame() {
+///     sprites.region(&atlas, frame, player_rect, Color::WHITE);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpriteAnimation {
+    pub frames: Vec<AtlasRegion>,
+    /// frames per second
+    pub fps: f64,
+    pub mode: AnimationMode,
+    elapsed: f64,
+    just_finished: bool,
+}
+
+impl SpriteAnimation {
+    pub fn new(frames: Vec<AtlasRegion>, fps: f64, mode: AnimationMode) -> Self {
+        SpriteAnimation { frames, fps, mode, elapsed: 0.0, just_finished: false }
+    }
+
+    /// an animation of the images `names` of `atlas`, in order
+    ///
+    /// errors if `atlas` has no image called one of `names`
+    pub fn from_atlas(atlas: &TextureAtlas, names: &[&str], fps: f64, mode: AnimationMode) -> Result<Self, RendererError> {
+        let frames = names.iter()
+            .map(|name| atlas.region(name).ok_or_else(|| RendererError::Unknown("atlas region", (*name).to_owned())))
+            .collect::<Result<_, _>>()?;
+        Ok(SpriteAnimation::new(frames, fps, mode))
+    }
+
+    /// advances the animation by `delta` seconds, usually `UpdateInfo::fixed_time_step`
+    pub fn tick(&mut self, delta: f64) {
+        let was_finished = self.is_finished();
+        self.elapsed += delta;
+        self.just_finished = !was_finished && self.is_finished();
+    }
+
+    /// the number of frames played so far, including the current one
+    fn step(&self) -> usize {
+        (self.elapsed * self.fps.max(0.0)).floor() as usize
+    }
+
+    /// the index into `frames` of the frame to show
+    pub fn current_index(&self) -> usize {
+        let (step, len) = (self.step(), self.frames.len());
+        match self.mode {
+            _ if len <= 1 => 0,
+            AnimationMode::Loop => step % len,
+            AnimationMode::Once => step.min(len - 1),
+            AnimationMode::PingPong => {
+                let period = 2 * len - 2;
+                let step = step % period;
+                if step < len { step } else { period - step }
+            }
+        }
+    }
+    /// the frame to show, `None` if there are no frames
+    pub fn current_frame(&self) -> Option<AtlasRegion> {
+        self.frames.get(self.current_index()).copied()
+    }
+
+    /// whether an `AnimationMode::Once` animation has played its last frame for its full duration,
+    /// always false for the other modes
+    pub fn is_finished(&self) -> bool {
+        self.mode == AnimationMode::Once && self.step() >= self.frames.len()
+    }
+    /// whether the animation finished during the latest tick, eg. to go back to an idle animation
+    pub fn just_finished(&self) -> bool {
+        self.just_finished
+    }
+
+    /// starts the animation again from the first frame
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+        self.just_finished = false;
+    }
+    /// the seconds played since the animation started or was reset
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+}
+
+/// named animations of which one plays at a time, eg. a character's "idle", "walk" and "jump":
+/// ```ignore
+/// // in `on_update`
+/// state.animations.play(if moving { "walk" } else { "idle" })?;
+/// state.animations.tick(update_info.fixed_time_step());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AnimationSet {
+    animations: HashMap<String, SpriteAnimation>,
+    current: Option<String>,
+}
+
+impl AnimationSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// adds (or replaces) the animation called `name`, playing it if nothing is playing yet
+    ///
+    /// returns self for chaining
+    pub fn with(mut self, name: &str, animation: SpriteAnimation) -> Self {
+        self.insert(name, animation);
+        self
+    }
+    /// adds (or replaces) the animation called `name`, playing it if nothing is playing yet
+    pub fn insert(&mut self, name: &str, animation: SpriteAnimation) {
+        self.animations.insert(name.to_owned(), animation);
+        if self.current.is_none() {
+            self.current = Some(name.to_owned());
+        }
+    }
+
+    /// switches straight to the animation called `name` from its first frame, unless it is already
+    /// playing (so this can be called every update), see `restart`
+    ///
+    /// errors if there is no animation called `name`
+    pub fn play(&mut self, name: &str) -> Result<(), RendererError> {
+        if self.current.as_deref() == Some(name) {
+            return Ok(());
+        }
+        self.restart(name)
+    }
+    /// switches to the animation called `name` from its first frame, even if it is already playing
+    ///
+    /// errors if there is no animation called `name`
+    pub fn restart(&mut self, name: &str) -> Result<(), RendererError> {
+        let animation = self.animations.get_mut(name).ok_or_else(|| RendererError::Unknown("animation", name.to_owned()))?;
+        animation.reset();
+        self.current = Some(name.to_owned());
+        Ok(())
+    }
+
+    /// advances the playing animation by `delta` seconds, usually `UpdateInfo::fixed_time_step`
+    pub fn tick(&mut self, delta: f64) {
+        if let Some(animation) = self.current_mut() {
+            animation.tick(delta);
+        }
+    }
+
+    /// the name of the playing animation, `None` if there are no animations
+    pub fn current_name(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+    pub fn current(&self) -> Option<&SpriteAnimation> {
+        self.animations.get(self.current.as_deref()?)
+    }
+    pub fn current_mut(&mut self) -> Option<&mut SpriteAnimation> {
+        self.animations.get_mut(self.current.as_deref()?)
+    }
+    /// the frame of the playing animation to show
+    pub fn current_frame(&self) -> Option<AtlasRegion> {
+        self.current()?.current_frame()
+    }
+    /// whether the playing animation is an `AnimationMode::Once` animation that has finished
+    pub fn is_finished(&self) -> bool {
+        self.current().is_some_and(SpriteAnimation::is_finished)
+    }
+    /// whether the playing animation finished during the latest tick
+    pub fn just_finished(&self) -> bool {
+        self.current().is_some_and(SpriteAnimation::just_finished)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SpriteAnimation> {
+        self.animations.get(name)
+    }
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut SpriteAnimation> {
+        self.animations.get_mut(name)
+    }
+}
@@ -0,0 +1,285 @@
+use wasm_bindgen::JsCast;
+use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlProgram, WebGlTexture, WebGlUniformLocation, WebGlVertexArrayObject};
+
+use crate::{RenderInfo, RendererError, Texture2d, TextureAtlas, AtlasRegion, compile_shader, link_program};
+use crate::stats;
+
+type Gl = WebGl2RenderingContext;
+
+const SPRITE_VERTEX_SHADER: &str = "\
+#version 300 es
+layout(location = 0) in vec2 a_position;
+layout(location = 1) in vec2 a_uv;
+layout(location = 2) in vec4 a_color;
+uniform mat4 u_view_proj;
+out vec2 v_uv;
+out vec4 v_color;
+
+void main() {
+    v_uv = a_uv;
+    v_color = a_color;
+    gl_Position = u_view_proj * vec4(a_position, 0.0, 1.0);
+}
+";
+
+const SPRITE_FRAGMENT_SHADER: &str = "\
+#version 300 es
+precision mediump float;
+uniform sampler2D u_texture;
+in vec2 v_uv;
+in vec4 v_color;
+out vec4 color;
+
+void main() {
+    color = texture(u_texture, v_uv) * v_color;
+}
+";
+
+/// like `SPRITE_FRAGMENT_SHADER` but the texture is a multi-channel signed distance field (eg. of a
+/// `Font`), whose edge is at 0.5 in the median of its color channels
+const MSDF_FRAGMENT_SHADER: &str = "\
+#version 300 es
+precision mediump float;
+uniform sampler2D u_texture;
+uniform float u_screen_px_range;
+in vec2 v_uv;
+in vec4 v_color;
+out vec4 color;
+
+float median(vec3 v) {
+    return max(min(v.r, v.g), min(max(v.r, v.g), v.b));
+}
+
+void main() {
+    float distance = u_screen_px_range * (median(texture(u_texture, v_uv).rgb) - 0.5);
+    color = vec4(v_color.rgb, v_color.a * clamp(distance + 0.5, 0.0, 1.0));
+}
+";
+
+/// the floats per vertex: a position, texture coordinates and a color
+const VERTEX_FLOATS: usize = 8;
+
+/// textured quads (sprites) that are drawn with as few draw calls as possible: one per run of quads
+/// that use the same texture (and shader), so sprites from a `TextureAtlas` are all drawn at once
+///
+/// ```ignore
+/// let mut sprites = SpriteBatch::new(context)?;
+/// ...
+/// // in `on_render`
+/// sprites.sprite(&state.background, [0.0, 0.0, 800.0, 600.0], [1.0; 4]);
+/// sprites.region(&state.atlas, state.atlas.region("player").unwrap(), [x, y, 32.0, 32.0], [1.0; 4]);
+/// sprites.draw(&mut render_info, view_proj)?;
+/// ```
+///
+/// a quad's `dst` is `[x, y, width, height]` with `(x, y)` at the `min` corner of its `uv_rect`, so
+/// textures (whose rows are bottom up) are upright when `y` goes up
+pub struct SpriteBatch {
+    context: WebGl2RenderingContext,
+    program: BatchProgram,
+    /// compiled by the first `draw` with `msdf_quad`s
+    msdf_program: Option<BatchProgram>,
+    vertex_array: WebGlVertexArrayObject,
+    buffer: WebGlBuffer,
+    vertices: Vec<f32>,
+    runs: Vec<Run>,
+}
+
+/// quads that are drawn together
+struct Run {
+    texture: WebGlTexture,
+    /// the vertex that the run starts at
+    start: usize,
+    /// the screen pixel range for `MSDF_FRAGMENT_SHADER`, `None` for `SPRITE_FRAGMENT_SHADER`
+    msdf: Option<f32>,
+}
+
+struct BatchProgram {
+    program: WebGlProgram,
+    view_proj_location: Option<WebGlUniformLocation>,
+    texture_location: Option<WebGlUniformLocation>,
+    screen_px_range_location: Option<WebGlUniformLocation>,
+}
+
+impl BatchProgram {
+    fn new(context: &WebGl2RenderingContext, fragment_shader: &str) -> Result<BatchProgram, RendererError> {
+        let vert_shader = compile_shader(context, Gl::VERTEX_SHADER, SPRITE_VERTEX_SHADER)?;
+        let frag_shader = compile_shader(context, Gl::FRAGMENT_SHADER, fragment_shader);
+        let program = frag_shader.and_then(|frag_shader| {
+            let program = link_program(context, &vert_shader, &frag_shader);
+            context.delete_shader(Some(&frag_shader));
+            program
+        });
+        context.delete_shader(Some(&vert_shader));
+        let program = program?;
+        Ok(BatchProgram {
+            view_proj_location: context.get_uniform_location(&program, "u_view_proj"),
+            texture_location: context.get_uniform_location(&program, "u_texture"),
+            screen_px_range_location: context.get_uniform_location(&program, "u_screen_px_range"),
+            program,
+        })
+    }
+}
+
+impl SpriteBatch {
+    /// errors if the program, buffer or vertex array object could not be created
+    pub fn new(context: &WebGl2RenderingContext) -> Result<SpriteBatch, RendererError> {
+        let program = BatchProgram::new(context, SPRITE_FRAGMENT_SHADER)?;
+
+        let buffer = context.create_buffer().ok_or(RendererError::Create("buffer"))?;
+        let vertex_array = context.create_vertex_array().ok_or(RendererError::Create("vertex array"))?;
+        let previous = context.get_parameter(Gl::VERTEX_ARRAY_BINDING).ok()
+            .and_then(|value| value.dyn_into::<WebGlVertexArrayObject>().ok());
+        context.bind_vertex_array(Some(&vertex_array));
+        context.bind_buffer(Gl::ARRAY_BUFFER, Some(&buffer));
+        let stride = (VERTEX_FLOATS * size_of::<f32>()) as i32;
+        for (location, components, offset) in [(0, 2, 0), (1, 2, 2), (2, 4, 4)] {
+            context.enable_vertex_attrib_array(location);
+            context.vertex_attrib_pointer_with_i32(location, components, Gl::FLOAT, false, stride, offset * size_of::<f32>() as i32);
+        }
+        context.bind_vertex_array(previous.as_ref());
+
+        Ok(SpriteBatch {
+            context: context.clone(),
+            program,
+            msdf_program: None,
+            vertex_array,
+            buffer,
+            vertices: Vec::new(),
+            runs: Vec::new(),
+        })
+    }
+
+    /// adds a quad covering `dst` showing `uv_rect` (`[min_u, min_v, max_u, max_v]`) of `texture`,
+    /// tinted by multiplying with `color`
+    pub fn quad(&mut self, texture: &WebGlTexture, dst: [f32; 4], uv_rect: [f32; 4], color: impl Into<[f32; 4]>) {
+        self.push_quad(texture, None, dst, uv_rect, color.into());
+    }
+
+    /// like `quad` but `texture` is a multi-channel signed distance field (eg. the glyphs of a `Font`),
+    /// drawn with a sharp edge at any scale and colored with `color`
+    ///
+    /// `screen_px_range` is how many screen pixels the field's distance range covers where it's drawn,
+    /// ie. the range in texels times the texels' size on screen, with larger values giving sharper edges
+    pub fn msdf_quad(&mut self, texture: &WebGlTexture, dst: [f32; 4], uv_rect: [f32; 4], color: impl Into<[f32; 4]>, screen_px_range: f32) {
+        self.push_quad(texture, Some(screen_px_range), dst, uv_rect, color.into());
+    }
+
+    fn push_quad(&mut self, texture: &WebGlTexture, msdf: Option<f32>, dst: [f32; 4], uv_rect: [f32; 4], color: [f32; 4]) {
+        if self.runs.last().is_none_or(|run| run.texture != *texture || run.msdf != msdf) {
+            self.runs.push(Run { texture: texture.clone(), start: self.vertices.len() / VERTEX_FLOATS, msdf });
+        }
+        let [x, y, width, height] = dst;
+        let [min_u, min_v, max_u, max_v] = uv_rect;
+        let corners = [
+            (x, y, min_u, min_v),
+            (x + width, y, max_u, min_v),
+            (x + width, y + height, max_u, max_v),
+            (x, y, min_u, min_v),
+            (x + width, y + height, max_u, max_v),
+            (x, y + height, min_u, max_v),
+        ];
+        for (x, y, u, v) in corners {
+            self.vertices.extend([x, y, u, v]);
+            self.vertices.extend(color);
+        }
+    }
+
+    /// adds a triangle of `[x, y, u, v]` corners, for shapes that aren't quads
+    pub(crate) fn triangle(&mut self, texture: &WebGlTexture, corners: [[f32; 4]; 3], color: [f32; 4]) {
+        if self.runs.last().is_none_or(|run| run.texture != *texture || run.msdf.is_some()) {
+            self.runs.push(Run { texture: texture.clone(), start: self.vertices.len() / VERTEX_FLOATS, msdf: None });
+        }
+        for corner in corners {
+            self.vertices.extend(corner);
+            self.vertices.extend(color);
+        }
+    }
+
+    /// adds a quad covering `dst` showing the whole of `texture`
+    pub fn sprite(&mut self, texture: &Texture2d, dst: [f32; 4], color: impl Into<[f32; 4]>) {
+        self.quad(texture.texture(), dst, [0.0, 0.0, 1.0, 1.0], color);
+    }
+
+    /// adds a quad covering `dst` showing `region` of `atlas`
+    pub fn region(&mut self, atlas: &TextureAtlas, region: AtlasRegion, dst: [f32; 4], color: impl Into<[f32; 4]>) {
+        self.quad(atlas.texture().texture(), dst, region.uv_rect, color);
+    }
+
+    /// the number of quads added since the last `draw` or `clear`
+    pub fn len(&self) -> usize {
+        self.vertices.len() / VERTEX_FLOATS / 6
+    }
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+    /// removes every quad without drawing them
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.runs.clear();
+    }
+
+    /// draws every quad in the order they were added, then clears the batch
+    ///
+    /// `view_proj` is the (column-major) view-projection matrix. Blending is left as it is (see
+    /// `RenderInfo::set_blend_mode`), the textures are bound to unit 0 and the previously bound program
+    /// and vertex array object are rebound afterwards
+    pub fn draw<S>(&mut self, render_info: &mut RenderInfo<S>, view_proj: [[f32; 4]; 4]) -> Result<(), RendererError> {
+        if self.vertices.is_empty() {
+            return Ok(());
+        }
+        if self.msdf_program.is_none() && self.runs.iter().any(|run| run.msdf.is_some()) {
+            self.msdf_program = Some(BatchProgram::new(&self.context, MSDF_FRAGMENT_SHADER)?);
+        }
+        let context = &self.context;
+        let previous_program = context.get_parameter(Gl::CURRENT_PROGRAM).ok()
+            .and_then(|value| value.dyn_into::<WebGlProgram>().ok());
+        let previous_vertex_array = context.get_parameter(Gl::VERTEX_ARRAY_BINDING).ok()
+            .and_then(|value| value.dyn_into::<WebGlVertexArrayObject>().ok());
+
+        context.bind_buffer(Gl::ARRAY_BUFFER, Some(&self.buffer));
+        // SAFETY: the view is only used to upload the vertices, without allocating, before they are next modified
+        unsafe {
+            context.buffer_data_with_array_buffer_view(Gl::ARRAY_BUFFER, &js_sys::Float32Array::view(&self.vertices), Gl::STREAM_DRAW);
+        }
+        for program in [Some(&self.program), self.msdf_program.as_ref()].into_iter().flatten() {
+            context.use_program(Some(&program.program));
+            context.uniform_matrix4fv_with_f32_array(program.view_proj_location.as_ref(), false, view_proj.as_flattened());
+            context.uniform1i(program.texture_location.as_ref(), 0);
+        }
+        context.bind_vertex_array(Some(&self.vertex_array));
+        context.active_texture(Gl::TEXTURE0);
+
+        let vertex_count = self.vertices.len() / VERTEX_FLOATS;
+        for (i, run) in self.runs.iter().enumerate() {
+            let (start, end) = (run.start, self.runs.get(i + 1).map_or(vertex_count, |next| next.start));
+            match (run.msdf, &self.msdf_program) {
+                (Some(screen_px_range), Some(msdf_program)) => {
+                    context.use_program(Some(&msdf_program.program));
+                    context.uniform1f(msdf_program.screen_px_range_location.as_ref(), screen_px_range);
+                }
+                _ => context.use_program(Some(&self.program.program)),
+            }
+            context.bind_texture(Gl::TEXTURE_2D, Some(&run.texture));
+            context.draw_arrays(Gl::TRIANGLES, start as i32, (end - start) as i32);
+            stats::count(|stats| stats.texture_binds += 1);
+            render_info.note_draw_call(((end - start) / 3) as u32);
+        }
+        render_info.renderer.texture_units.forget(0);
+
+        context.bind_vertex_array(previous_vertex_array.as_ref());
+        context.use_program(previous_program.as_ref());
+        self.clear();
+        Ok(())
+    }
+}
+
+impl Drop for SpriteBatch {
+    fn drop(&mut self) {
+        self.context.delete_program(Some(&self.program.program));
+        if let Some(msdf_program) = &self.msdf_program {
+            self.context.delete_program(Some(&msdf_program.program));
+        }
+        self.context.delete_buffer(Some(&self.buffer));
+        self.context.delete_vertex_array(Some(&self.vertex_array));
+    }
+}
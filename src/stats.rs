@@ -0,0 +1,54 @@
+use std::cell::Cell;
+
+use crate::RenderInfo;
+
+/// counts of what the crate's helpers did during a frame, see `RenderInfo::draw_stats`
+///
+/// only calls made through the crate (eg. `draw_fullscreen`, `use_shader`, `Texture2d::bind`) are
+/// counted, use `RenderInfo::note_draw_call` to count draws made directly on the context
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DrawStats {
+    pub draw_calls: u32,
+    pub triangles: u32,
+    pub texture_binds: u32,
+    pub program_switches: u32,
+}
+
+thread_local! {
+    /// the stats of the frame being rendered, which aren't keyed by context as renderers (even several
+    /// on one page) render their frames one at a time
+    static FRAME_STATS: Cell<DrawStats> = const { Cell::new(DrawStats { draw_calls: 0, triangles: 0, texture_binds: 0, program_switches: 0 }) };
+}
+
+/// updates the stats of the current frame, without going through the context so that counting stays
+/// cheap on the hot paths (draws and binds)
+pub(crate) fn count(f: impl FnOnce(&mut DrawStats)) {
+    FRAME_STATS.with(|stats| {
+        let mut frame_stats = stats.get();
+        f(&mut frame_stats);
+        stats.set(frame_stats);
+    });
+}
+
+/// resets the stats for a new frame, returning those counted since the previous reset
+pub(crate) fn take() -> DrawStats {
+    FRAME_STATS.with(|stats| stats.take())
+}
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// the stats of the frame so far
+    pub fn draw_stats(&self) -> DrawStats {
+        FRAME_STATS.with(Cell::get)
+    }
+    /// the stats of the whole previous frame
+    pub fn previous_draw_stats(&self) -> DrawStats {
+        self.renderer.previous_draw_stats
+    }
+    /// counts a draw call of `triangles` triangles that was made directly on the context
+    pub fn note_draw_call(&mut self, triangles: u32) {
+        count(|stats| {
+            stats.draw_calls += 1;
+            stats.triangles += triangles;
+        });
+    }
+}
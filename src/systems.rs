@@ -0,0 +1,78 @@
+use std::ops::DerefMut;
+
+use crate::{Renderer, UpdateInfo, OnUpdate};
+
+/// an update system added with `Renderer::with_update_system`
+pub(crate) struct UpdateSystem<S: 'static> {
+    name: &'static str,
+    priority: i32,
+    system: OnUpdate<S>,
+    enabled: bool,
+}
+
+impl<S> Renderer<S> {
+    /// adds an update system: a function that is called every update (after `on_update`) alongside the
+    /// other systems in order of `priority`, lower priorities first and systems with the same priority in
+    /// the order they were added. This splits a large `on_update` into independent parts that can be
+    /// toggled with `UpdateInfo::enable_system` and `disable_system`:
+    /// ```ignore
+    /// renderer
+    ///     .with_update_system("input", -10, update_input)
+    ///     .with_update_system("physics", 0, update_physics)
+    ///     .with_update_system("ai", 10, update_ai)
+    /// ```
+    ///
+    /// systems start enabled, the same function can be added more than once under different names
+    ///
+    /// returns self for chaining
+    pub fn with_update_system(mut self, name: &'static str, priority: i32, system: OnUpdate<S>) -> Self {
+        let index = self.update_systems.partition_point(|update_system| update_system.priority <= priority);
+        self.update_systems.insert(index, UpdateSystem {
+            name,
+            priority,
+            system,
+            enabled: true,
+        });
+        self
+    }
+
+    pub(crate) fn run_update_systems(&mut self) {
+        // indexed as systems may be toggled (but not added) while the loop runs
+        for index in 0..self.update_systems.len() {
+            let update_system = &self.update_systems[index];
+            if !update_system.enabled { continue }
+            let system = update_system.system;
+            system(UpdateInfo {
+                state: self.state.clone().get().unwrap().borrow_mut().deref_mut(),
+                renderer: self,
+            });
+        }
+    }
+
+    fn set_system_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let mut found = false;
+        for update_system in self.update_systems.iter_mut().filter(|update_system| update_system.name == name) {
+            update_system.enabled = enabled;
+            found = true;
+        }
+        found
+    }
+}
+
+impl<'a, S> UpdateInfo<'a, S> {
+    /// stops the update system `name` (every system called `name` if there are several) from being called
+    /// until it is re-enabled, starting with this update if it hasn't been called yet
+    ///
+    /// returns whether there is a system called `name`
+    pub fn disable_system(&mut self, name: &str) -> bool {
+        self.renderer.set_system_enabled(name, false)
+    }
+
+    /// re-enables the update system `name` after `disable_system`, starting with this update if it
+    /// hasn't been called yet
+    ///
+    /// returns whether there is a system called `name`
+    pub fn enable_system(&mut self, name: &str) -> bool {
+        self.renderer.set_system_enabled(name, true)
+    }
+}
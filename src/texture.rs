@@ -0,0 +1,659 @@
+use web_sys::{ImageBitmap, WebGl2RenderingContext, WebGlTexture};
+
+use crate::{RendererError, SamplerOptions, CompressedFormat};
+use crate::stats;
+
+type Gl = WebGl2RenderingContext;
+
+/// how the rgba bytes of a texture are interpreted when sampled (or written when rendered into)
+///
+/// `Srgb` textures are stored as `SRGB8_ALPHA8`, they are decoded to linear values when sampled and
+/// encoded back to sRGB when rendered into, so lighting can be done in linear space
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorSpace {
+    #[default]
+    Linear,
+    Srgb,
+}
+
+impl ColorSpace {
+    pub fn internal_format(self) -> u32 {
+        match self {
+            ColorSpace::Linear => Gl::RGBA8,
+            ColorSpace::Srgb => Gl::SRGB8_ALPHA8,
+        }
+    }
+}
+
+/// the format a texture's texels are stored in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFormat {
+    Rgba8,
+    /// see `ColorSpace::Srgb`
+    Srgb8Alpha8,
+    /// half-float rgba, can only be rendered into with `EXT_color_buffer_float`
+    Rgba16F,
+    /// float rgba, can only be rendered into with `EXT_color_buffer_float` and only linearly filtered
+    /// with `OES_texture_float_linear`
+    Rgba32F,
+    /// packed float rgb (no alpha) at 32 bits per texel, can only be rendered into with `EXT_color_buffer_float`
+    R11G11B10F,
+}
+
+impl TextureFormat {
+    pub fn internal_format(self) -> u32 {
+        match self {
+            TextureFormat::Rgba8 => Gl::RGBA8,
+            TextureFormat::Srgb8Alpha8 => Gl::SRGB8_ALPHA8,
+            TextureFormat::Rgba16F => Gl::RGBA16F,
+            TextureFormat::Rgba32F => Gl::RGBA32F,
+            TextureFormat::R11G11B10F => Gl::R11F_G11F_B10F,
+        }
+    }
+    /// the format of pixel data uploaded to the texture
+    pub fn format(self) -> u32 {
+        match self {
+            TextureFormat::R11G11B10F => Gl::RGB,
+            _ => Gl::RGBA,
+        }
+    }
+    /// the type of pixel data uploaded to the texture
+    pub fn data_type(self) -> u32 {
+        match self {
+            TextureFormat::Rgba8 | TextureFormat::Srgb8Alpha8 => Gl::UNSIGNED_BYTE,
+            TextureFormat::Rgba16F | TextureFormat::R11G11B10F => Gl::HALF_FLOAT,
+            TextureFormat::Rgba32F => Gl::FLOAT,
+        }
+    }
+    /// whether the texels are floats, which are read back as `RGBA`/`FLOAT` rather than `RGBA`/`UNSIGNED_BYTE`
+    pub fn is_float(self) -> bool {
+        !matches!(self, TextureFormat::Rgba8 | TextureFormat::Srgb8Alpha8)
+    }
+    /// the extension that is required to render into a texture of this format, if any
+    pub fn render_extension(self) -> Option<&'static str> {
+        self.is_float().then_some("EXT_color_buffer_float")
+    }
+    /// the extension that is required to linearly filter a texture of this format, if any
+    pub fn filter_extension(self) -> Option<&'static str> {
+        match self {
+            TextureFormat::Rgba32F => Some("OES_texture_float_linear"),
+            _ => None,
+        }
+    }
+}
+
+impl From<ColorSpace> for TextureFormat {
+    fn from(color_space: ColorSpace) -> Self {
+        match color_space {
+            ColorSpace::Linear => TextureFormat::Rgba8,
+            ColorSpace::Srgb => TextureFormat::Srgb8Alpha8,
+        }
+    }
+}
+
+/// a 2d texture that is deleted when dropped
+pub struct Texture2d {
+    context: WebGl2RenderingContext,
+    texture: WebGlTexture,
+    width: u32,
+    height: u32,
+    /// compressed textures can't have mipmaps generated for them
+    compressed: bool,
+}
+
+impl Texture2d {
+    /// creates a texture from tightly packed rgba bytes (4 per pixel, rows from the bottom up)
+    ///
+    /// generates mipmaps if `sampler.min_filter` requires them
+    pub fn from_rgba8(context: &WebGl2RenderingContext, width: u32, height: u32, data: &[u8], color_space: ColorSpace, sampler: &SamplerOptions) -> Result<Texture2d, RendererError> {
+        check_rgba8_len(data, width, height, "texture")?;
+        Self::with_data(context, width, height, Some(data), color_space.into(), sampler)
+    }
+
+    /// creates a texture from a decoded image (eg. from `createImageBitmap`), flipping it so that its
+    /// rows are bottom up like `from_rgba8`
+    ///
+    /// generates mipmaps if `sampler.min_filter` requires them
+    pub fn from_image_bitmap(context: &WebGl2RenderingContext, image: &ImageBitmap, color_space: ColorSpace, sampler: &SamplerOptions) -> Result<Texture2d, RendererError> {
+        let texture = context.create_texture().ok_or(RendererError::Create("texture"))?;
+        let texture = Texture2d {
+            context: context.clone(),
+            texture,
+            width: image.width(),
+            height: image.height(),
+            compressed: false,
+        };
+
+        let format = TextureFormat::from(color_space);
+        texture.bind(0);
+        context.pixel_storei(Gl::UNPACK_FLIP_Y_WEBGL, 1);
+        let result = context.tex_image_2d_with_u32_and_u32_and_image_bitmap(
+            Gl::TEXTURE_2D,
+            0,
+            format.internal_format() as i32,
+            format.format(),
+            format.data_type(),
+            image,
+        );
+        context.pixel_storei(Gl::UNPACK_FLIP_Y_WEBGL, 0);
+        result?;
+        texture.set_sampler(sampler);
+        Ok(texture)
+    }
+
+    /// creates a texture with uninitialised contents, eg. for rendering into
+    pub fn empty(context: &WebGl2RenderingContext, width: u32, height: u32, color_space: ColorSpace, sampler: &SamplerOptions) -> Result<Texture2d, RendererError> {
+        Self::with_data(context, width, height, None, color_space.into(), sampler)
+    }
+
+    /// creates a texture of `format` with uninitialised contents, eg. for rendering into
+    pub fn empty_with_format(context: &WebGl2RenderingContext, width: u32, height: u32, format: TextureFormat, sampler: &SamplerOptions) -> Result<Texture2d, RendererError> {
+        Self::with_data(context, width, height, None, format, sampler)
+    }
+
+    /// creates a depth texture (`DEPTH_COMPONENT24`, or `DEPTH24_STENCIL8` with `stencil`) with
+    /// uninitialised contents, eg. for a shadow map, see `SamplerOptions::shadow`
+    ///
+    /// depth textures can only be linearly filtered when `sampler.compare_mode` isn't `None`
+    pub fn empty_depth(context: &WebGl2RenderingContext, width: u32, height: u32, stencil: bool, sampler: &SamplerOptions) -> Result<Texture2d, RendererError> {
+        let texture = context.create_texture().ok_or(RendererError::Create("texture"))?;
+        let texture = Texture2d {
+            context: context.clone(),
+            texture,
+            width,
+            height,
+            compressed: false,
+        };
+
+        let (internal_format, format, data_type) = match stencil {
+            false => (Gl::DEPTH_COMPONENT24, Gl::DEPTH_COMPONENT, Gl::UNSIGNED_INT),
+            true => (Gl::DEPTH24_STENCIL8, Gl::DEPTH_STENCIL, Gl::UNSIGNED_INT_24_8),
+        };
+        texture.bind(0);
+        context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            Gl::TEXTURE_2D,
+            0,
+            internal_format as i32,
+            width as i32,
+            height as i32,
+            0,
+            format,
+            data_type,
+            None,
+        )?;
+        texture.set_sampler(sampler);
+        Ok(texture)
+    }
+
+    fn with_data(context: &WebGl2RenderingContext, width: u32, height: u32, data: Option<&[u8]>, format: TextureFormat, sampler: &SamplerOptions) -> Result<Texture2d, RendererError> {
+        let texture = context.create_texture().ok_or(RendererError::Create("texture"))?;
+        let texture = Texture2d {
+            context: context.clone(),
+            texture,
+            width,
+            height,
+            compressed: false,
+        };
+
+        texture.bind(0);
+        context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            Gl::TEXTURE_2D,
+            0,
+            format.internal_format() as i32,
+            width as i32,
+            height as i32,
+            0,
+            format.format(),
+            format.data_type(),
+            data,
+        )?;
+        texture.set_sampler(sampler);
+        Ok(texture)
+    }
+
+    /// creates a texture from pre-compressed data, with one slice per mip level starting at the base level
+    ///
+    /// mipmaps can't be generated for compressed textures so only the given levels are used,
+    /// errors if the format's extension is unsupported or any level's length doesn't match the block maths
+    pub fn from_compressed(context: &WebGl2RenderingContext, format: CompressedFormat, width: u32, height: u32, mip_levels: &[&[u8]], sampler: &SamplerOptions) -> Result<Texture2d, RendererError> {
+        if !format.is_supported(context) {
+            return Err(RendererError::MissingExtension(format.extension()));
+        }
+        if mip_levels.is_empty() {
+            return Err(RendererError::Texture(String::from("at least one mip level is required")));
+        }
+        for (level, data) in mip_levels.iter().enumerate() {
+            let (level_width, level_height) = mip_level_size(width, height, level as u32);
+            let expected_len = format.byte_len(level_width, level_height);
+            if data.len() != expected_len {
+                return Err(RendererError::Texture(format!(
+                    "expected {} bytes of {:?} data for mip level {} ({}x{}) but got {}",
+                    expected_len, format, level, level_width, level_height, data.len(),
+                )));
+            }
+        }
+
+        let texture = context.create_texture().ok_or(RendererError::Create("texture"))?;
+        let texture = Texture2d {
+            context: context.clone(),
+            texture,
+            width,
+            height,
+            compressed: true,
+        };
+
+        texture.bind(0);
+        for (level, data) in mip_levels.iter().enumerate() {
+            let (level_width, level_height) = mip_level_size(width, height, level as u32);
+            context.compressed_tex_image_2d_with_u8_array(
+                Gl::TEXTURE_2D,
+                level as i32,
+                format.to_gl(),
+                level_width as i32,
+                level_height as i32,
+                0,
+                data,
+            );
+        }
+        // makes the texture complete with a partial mip chain
+        context.tex_parameteri(Gl::TEXTURE_2D, Gl::TEXTURE_MAX_LEVEL, mip_levels.len() as i32 - 1);
+        texture.set_sampler(sampler);
+        Ok(texture)
+    }
+
+    /// replaces the sampling parameters, generating mipmaps if `sampler.min_filter` requires them
+    /// (unless the texture is compressed)
+    ///
+    /// leaves the texture bound to `TEXTURE_2D` on the active texture unit
+    pub fn set_sampler(&self, sampler: &SamplerOptions) {
+        self.context.bind_texture(Gl::TEXTURE_2D, Some(&self.texture));
+        if self.compressed {
+            sampler.apply_to_texture(&self.context, Gl::TEXTURE_2D);
+        } else {
+            apply_sampler(&self.context, Gl::TEXTURE_2D, sampler);
+        }
+    }
+
+    /// regenerates the mipmap chain from the base level, eg. after rendering into the texture
+    pub fn generate_mipmaps(&self) {
+        self.context.bind_texture(Gl::TEXTURE_2D, Some(&self.texture));
+        self.context.generate_mipmap(Gl::TEXTURE_2D);
+    }
+
+    /// binds the texture to the texture unit `unit` (ie. `TEXTURE0 + unit`), leaving that unit active
+    pub fn bind(&self, unit: u32) {
+        self.context.active_texture(Gl::TEXTURE0 + unit);
+        self.context.bind_texture(Gl::TEXTURE_2D, Some(&self.texture));
+        stats::count(|stats| stats.texture_binds += 1);
+    }
+
+    pub fn texture(&self) -> &WebGlTexture {
+        &self.texture
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+impl Drop for Texture2d {
+    fn drop(&mut self) {
+        self.context.delete_texture(Some(&self.texture));
+    }
+}
+
+/// a 2d array texture (`sampler2DArray`) made of equally sized layers, eg. for tile-map layers
+pub struct Texture2dArray {
+    context: WebGl2RenderingContext,
+    texture: WebGlTexture,
+    width: u32,
+    height: u32,
+    layers: u32,
+}
+
+impl Texture2dArray {
+    /// creates an array texture from one slice of tightly packed rgba bytes per layer
+    ///
+    /// errors if any layer has the wrong length or there are more layers than `MAX_ARRAY_TEXTURE_LAYERS`
+    pub fn from_layers(context: &WebGl2RenderingContext, width: u32, height: u32, layers: &[&[u8]], color_space: ColorSpace, sampler: &SamplerOptions) -> Result<Texture2dArray, RendererError> {
+        check_max(context, Gl::MAX_ARRAY_TEXTURE_LAYERS, "MAX_ARRAY_TEXTURE_LAYERS", "layers", layers.len())?;
+        let texture = create_3d(context, Gl::TEXTURE_2D_ARRAY, width, height, layers, color_space, sampler)?;
+        Ok(Texture2dArray {
+            context: context.clone(),
+            texture,
+            width,
+            height,
+            layers: layers.len() as u32,
+        })
+    }
+
+    /// replaces the contents of a single layer
+    ///
+    /// leaves the texture bound to `TEXTURE_2D_ARRAY` on the active texture unit
+    pub fn update_layer(&self, layer: u32, data: &[u8]) -> Result<(), RendererError> {
+        if layer >= self.layers {
+            return Err(RendererError::Texture(format!("layer {} is out of range for {} layers", layer, self.layers)));
+        }
+        self.context.bind_texture(Gl::TEXTURE_2D_ARRAY, Some(&self.texture));
+        upload_slice(&self.context, Gl::TEXTURE_2D_ARRAY, self.width, self.height, layer, data)
+    }
+
+    /// replaces the sampling parameters, generating mipmaps if `sampler.min_filter` requires them
+    ///
+    /// leaves the texture bound to `TEXTURE_2D_ARRAY` on the active texture unit
+    pub fn set_sampler(&self, sampler: &SamplerOptions) {
+        self.context.bind_texture(Gl::TEXTURE_2D_ARRAY, Some(&self.texture));
+        apply_sampler(&self.context, Gl::TEXTURE_2D_ARRAY, sampler);
+    }
+
+    /// binds the texture to the texture unit `unit` (ie. `TEXTURE0 + unit`), leaving that unit active
+    pub fn bind(&self, unit: u32) {
+        self.context.active_texture(Gl::TEXTURE0 + unit);
+        self.context.bind_texture(Gl::TEXTURE_2D_ARRAY, Some(&self.texture));
+        stats::count(|stats| stats.texture_binds += 1);
+    }
+
+    pub fn texture(&self) -> &WebGlTexture {
+        &self.texture
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    pub fn layers(&self) -> u32 {
+        self.layers
+    }
+}
+
+impl Drop for Texture2dArray {
+    fn drop(&mut self) {
+        self.context.delete_texture(Some(&self.texture));
+    }
+}
+
+/// a 3d texture (`sampler3D`) made of equally sized slices along the depth axis, eg. for volume rendering
+pub struct Texture3d {
+    context: WebGl2RenderingContext,
+    texture: WebGlTexture,
+    width: u32,
+    height: u32,
+    depth: u32,
+}
+
+impl Texture3d {
+    /// creates a 3d texture from one slice of tightly packed rgba bytes per depth slice
+    ///
+    /// errors if any slice has the wrong length or any dimension is larger than `MAX_3D_TEXTURE_SIZE`
+    pub fn from_slices(context: &WebGl2RenderingContext, width: u32, height: u32, slices: &[&[u8]], color_space: ColorSpace, sampler: &SamplerOptions) -> Result<Texture3d, RendererError> {
+        let largest = width.max(height).max(slices.len() as u32);
+        check_max(context, Gl::MAX_3D_TEXTURE_SIZE, "MAX_3D_TEXTURE_SIZE", "texels along an axis", largest as usize)?;
+        let texture = create_3d(context, Gl::TEXTURE_3D, width, height, slices, color_space, sampler)?;
+        Ok(Texture3d {
+            context: context.clone(),
+            texture,
+            width,
+            height,
+            depth: slices.len() as u32,
+        })
+    }
+
+    /// replaces the contents of a single depth slice
+    ///
+    /// leaves the texture bound to `TEXTURE_3D` on the active texture unit
+    pub fn update_slice(&self, slice: u32, data: &[u8]) -> Result<(), RendererError> {
+        if slice >= self.depth {
+            return Err(RendererError::Texture(format!("slice {} is out of range for a depth of {}", slice, self.depth)));
+        }
+        self.context.bind_texture(Gl::TEXTURE_3D, Some(&self.texture));
+        upload_slice(&self.context, Gl::TEXTURE_3D, self.width, self.height, slice, data)
+    }
+
+    /// replaces the sampling parameters, generating mipmaps if `sampler.min_filter` requires them
+    ///
+    /// leaves the texture bound to `TEXTURE_3D` on the active texture unit
+    pub fn set_sampler(&self, sampler: &SamplerOptions) {
+        self.context.bind_texture(Gl::TEXTURE_3D, Some(&self.texture));
+        apply_sampler(&self.context, Gl::TEXTURE_3D, sampler);
+    }
+
+    /// binds the texture to the texture unit `unit` (ie. `TEXTURE0 + unit`), leaving that unit active
+    pub fn bind(&self, unit: u32) {
+        self.context.active_texture(Gl::TEXTURE0 + unit);
+        self.context.bind_texture(Gl::TEXTURE_3D, Some(&self.texture));
+        stats::count(|stats| stats.texture_binds += 1);
+    }
+
+    pub fn texture(&self) -> &WebGlTexture {
+        &self.texture
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+}
+
+impl Drop for Texture3d {
+    fn drop(&mut self) {
+        self.context.delete_texture(Some(&self.texture));
+    }
+}
+
+/// a cube map texture (`samplerCube`) made of six square faces, eg. for a `Skybox` or reflections
+///
+/// the faces are in `TEXTURE_CUBE_MAP_POSITIVE_X + i` order (+x, -x, +y, -y, +z, -z) and, unlike
+/// `Texture2d`, their rows are top down as cube maps expect
+pub struct TextureCube {
+    context: WebGl2RenderingContext,
+    texture: WebGlTexture,
+    size: u32,
+}
+
+impl TextureCube {
+    /// creates a cube map from one slice of tightly packed rgba bytes per face
+    ///
+    /// errors if any face has the wrong length or `size` is larger than `MAX_CUBE_MAP_TEXTURE_SIZE`
+    pub fn from_faces(context: &WebGl2RenderingContext, size: u32, faces: [&[u8]; 6], color_space: ColorSpace, sampler: &SamplerOptions) -> Result<TextureCube, RendererError> {
+        for (i, face) in faces.iter().enumerate() {
+            check_rgba8_len(face, size, size, &format!("cube map face (face {})", i))?;
+        }
+        Self::with_faces(context, size, color_space.into(), sampler, |target, format| {
+            context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                target,
+                0,
+                format.internal_format() as i32,
+                size as i32,
+                size as i32,
+                0,
+                format.format(),
+                format.data_type(),
+                Some(faces[(target - Gl::TEXTURE_CUBE_MAP_POSITIVE_X) as usize]),
+            )
+        })
+    }
+
+    /// creates a cube map from six decoded square images (eg. from `createImageBitmap`)
+    ///
+    /// errors if the images aren't all square and the same size
+    pub fn from_image_bitmaps(context: &WebGl2RenderingContext, faces: [&ImageBitmap; 6], color_space: ColorSpace, sampler: &SamplerOptions) -> Result<TextureCube, RendererError> {
+        let size = faces[0].width();
+        if faces.iter().any(|face| face.width() != size || face.height() != size) {
+            return Err(RendererError::Texture(format!("cube map faces must all be {}x{} like the first", size, size)));
+        }
+        Self::with_faces(context, size, color_space.into(), sampler, |target, format| {
+            context.tex_image_2d_with_u32_and_u32_and_image_bitmap(
+                target,
+                0,
+                format.internal_format() as i32,
+                format.format(),
+                format.data_type(),
+                faces[(target - Gl::TEXTURE_CUBE_MAP_POSITIVE_X) as usize],
+            )
+        })
+    }
+
+    /// creates a cube map of `format` with uninitialised contents, eg. for rendering into
+    pub fn empty(context: &WebGl2RenderingContext, size: u32, format: TextureFormat, sampler: &SamplerOptions) -> Result<TextureCube, RendererError> {
+        Self::with_faces(context, size, format, sampler, |target, format| {
+            context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                target,
+                0,
+                format.internal_format() as i32,
+                size as i32,
+                size as i32,
+                0,
+                format.format(),
+                format.data_type(),
+                None,
+            )
+        })
+    }
+
+    /// creates the texture and calls `upload` with the target of each face
+    fn with_faces(context: &WebGl2RenderingContext, size: u32, format: TextureFormat, sampler: &SamplerOptions, upload: impl Fn(u32, TextureFormat) -> Result<(), wasm_bindgen::JsValue>) -> Result<TextureCube, RendererError> {
+        check_max(context, Gl::MAX_CUBE_MAP_TEXTURE_SIZE, "MAX_CUBE_MAP_TEXTURE_SIZE", "texels along a face's side", size as usize)?;
+        let texture = context.create_texture().ok_or(RendererError::Create("texture"))?;
+        let texture = TextureCube {
+            context: context.clone(),
+            texture,
+            size,
+        };
+
+        texture.bind(0);
+        for face in 0..6 {
+            upload(Gl::TEXTURE_CUBE_MAP_POSITIVE_X + face, format)?;
+        }
+        texture.set_sampler(sampler);
+        Ok(texture)
+    }
+
+    /// replaces the sampling parameters, generating mipmaps if `sampler.min_filter` requires them
+    ///
+    /// leaves the texture bound to `TEXTURE_CUBE_MAP` on the active texture unit
+    pub fn set_sampler(&self, sampler: &SamplerOptions) {
+        self.context.bind_texture(Gl::TEXTURE_CUBE_MAP, Some(&self.texture));
+        apply_sampler(&self.context, Gl::TEXTURE_CUBE_MAP, sampler);
+    }
+
+    /// regenerates the mipmap chain from the base level, eg. after rendering into the faces
+    pub fn generate_mipmaps(&self) {
+        self.context.bind_texture(Gl::TEXTURE_CUBE_MAP, Some(&self.texture));
+        self.context.generate_mipmap(Gl::TEXTURE_CUBE_MAP);
+    }
+
+    /// binds the texture to the texture unit `unit` (ie. `TEXTURE0 + unit`), leaving that unit active
+    pub fn bind(&self, unit: u32) {
+        self.context.active_texture(Gl::TEXTURE0 + unit);
+        self.context.bind_texture(Gl::TEXTURE_CUBE_MAP, Some(&self.texture));
+        stats::count(|stats| stats.texture_binds += 1);
+    }
+
+    pub fn texture(&self) -> &WebGlTexture {
+        &self.texture
+    }
+    /// the width and height of each face
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+}
+
+impl Drop for TextureCube {
+    fn drop(&mut self) {
+        self.context.delete_texture(Some(&self.texture));
+    }
+}
+
+fn check_rgba8_len(data: &[u8], width: u32, height: u32, what: &str) -> Result<(), RendererError> {
+    let expected_len = width as usize * height as usize * 4;
+    if data.len() != expected_len {
+        return Err(RendererError::Texture(format!(
+            "expected {} bytes of rgba data for a {}x{} {} but got {}",
+            expected_len, width, height, what, data.len(),
+        )));
+    }
+    Ok(())
+}
+
+/// the size of mip level `level` of a `width`x`height` texture
+fn mip_level_size(width: u32, height: u32, level: u32) -> (u32, u32) {
+    ((width >> level).max(1), (height >> level).max(1))
+}
+
+fn check_max(context: &WebGl2RenderingContext, pname: u32, pname_str: &str, what: &str, requested: usize) -> Result<(), RendererError> {
+    let max = context.get_parameter(pname)?.as_f64().unwrap_or(0.0) as usize;
+    if requested > max {
+        return Err(RendererError::Texture(format!(
+            "{} {} were requested but this device only supports {} ({})",
+            requested, what, max, pname_str,
+        )));
+    }
+    Ok(())
+}
+
+fn apply_sampler(context: &WebGl2RenderingContext, target: u32, sampler: &SamplerOptions) {
+    sampler.apply_to_texture(context, target);
+    if sampler.min_filter.uses_mipmaps() {
+        context.generate_mipmap(target);
+    }
+}
+
+/// allocates a `TEXTURE_2D_ARRAY` or `TEXTURE_3D` and uploads each slice, leaving it bound to `target`
+fn create_3d(context: &WebGl2RenderingContext, target: u32, width: u32, height: u32, slices: &[&[u8]], color_space: ColorSpace, sampler: &SamplerOptions) -> Result<WebGlTexture, RendererError> {
+    for (i, slice) in slices.iter().enumerate() {
+        check_rgba8_len(slice, width, height, &format!("texture layer (layer {})", i))?;
+    }
+
+    let texture = context.create_texture().ok_or(RendererError::Create("texture"))?;
+    context.bind_texture(target, Some(&texture));
+    let result = (|| {
+        context.tex_image_3d_with_opt_u8_array(
+            target,
+            0,
+            color_space.internal_format() as i32,
+            width as i32,
+            height as i32,
+            slices.len() as i32,
+            0,
+            Gl::RGBA,
+            Gl::UNSIGNED_BYTE,
+            None,
+        )?;
+        for (i, slice) in slices.iter().enumerate() {
+            upload_slice(context, target, width, height, i as u32, slice)?;
+        }
+        Ok(())
+    })();
+    if let Err(err) = result {
+        context.delete_texture(Some(&texture));
+        return Err(err);
+    }
+    apply_sampler(context, target, sampler);
+    Ok(texture)
+}
+
+/// uploads a single slice of the 3d texture bound to `target`
+fn upload_slice(context: &WebGl2RenderingContext, target: u32, width: u32, height: u32, slice: u32, data: &[u8]) -> Result<(), RendererError> {
+    check_rgba8_len(data, width, height, "texture layer")?;
+    context.tex_sub_image_3d_with_opt_u8_array(
+        target,
+        0,
+        0,
+        0,
+        slice as i32,
+        width as i32,
+        height as i32,
+        1,
+        Gl::RGBA,
+        Gl::UNSIGNED_BYTE,
+        Some(data),
+    )?;
+    Ok(())
+}
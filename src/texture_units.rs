@@ -0,0 +1,162 @@
+use web_sys::{WebGl2RenderingContext, WebGlProgram, WebGlTexture};
+
+use crate::{RenderInfo, Texture2d, Texture2dArray, Texture3d, TextureCube, TextureParams};
+use crate::stats;
+
+type Gl = WebGl2RenderingContext;
+
+/// a texture that can be bound to a texture unit by `RenderInfo::bind_texture`
+pub trait TextureBinding {
+    fn texture(&self) -> &WebGlTexture;
+    /// the target it is bound to, eg. `TEXTURE_2D`
+    fn target(&self) -> u32;
+}
+
+impl TextureBinding for Texture2d {
+    fn texture(&self) -> &WebGlTexture {
+        self.texture()
+    }
+    fn target(&self) -> u32 {
+        Gl::TEXTURE_2D
+    }
+}
+impl TextureBinding for Texture2dArray {
+    fn texture(&self) -> &WebGlTexture {
+        self.texture()
+    }
+    fn target(&self) -> u32 {
+        Gl::TEXTURE_2D_ARRAY
+    }
+}
+impl TextureBinding for Texture3d {
+    fn texture(&self) -> &WebGlTexture {
+        self.texture()
+    }
+    fn target(&self) -> u32 {
+        Gl::TEXTURE_3D
+    }
+}
+impl TextureBinding for TextureCube {
+    fn texture(&self) -> &WebGlTexture {
+        self.texture()
+    }
+    fn target(&self) -> u32 {
+        Gl::TEXTURE_CUBE_MAP
+    }
+}
+
+/// the texture bound to each unit by `RenderInfo::bind_texture`
+#[derive(Default)]
+pub(crate) struct TextureUnits {
+    /// `None` for units that are free (or whose texture isn't known), sized to `MAX_COMBINED_TEXTURE_IMAGE_UNITS`
+    /// when first used
+    units: Vec<Option<ResidentTexture>>,
+    /// incremented on every bind so that the least recently used unit has the smallest `last_used`
+    clock: u64,
+}
+
+struct ResidentTexture {
+    texture: WebGlTexture,
+    target: u32,
+    last_used: u64,
+}
+
+impl TextureUnits {
+    /// forgets the texture bound to `unit`, eg. after something else is bound to it directly
+    pub(crate) fn forget(&mut self, unit: u32) {
+        if let Some(resident) = self.units.get_mut(unit as usize) {
+            *resident = None;
+        }
+    }
+
+    /// the unit that `texture` is bound to and whether it needs binding
+    fn allocate(&mut self, context: &WebGl2RenderingContext, texture: &WebGlTexture, target: u32) -> (u32, bool) {
+        if self.units.is_empty() {
+            let max_units = context.get_parameter(Gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS).ok()
+                .and_then(|value| value.as_f64())
+                // the minimum that webgl2 guarantees
+                .unwrap_or(32.0) as usize;
+            self.units.resize_with(max_units.max(1), || None);
+        }
+        self.clock += 1;
+
+        let resident = self.units.iter().position(|resident| resident.as_ref()
+            .is_some_and(|resident| resident.target == target && resident.texture == *texture));
+        if let Some(unit) = resident {
+            self.units[unit].as_mut().unwrap().last_used = self.clock;
+            return (unit as u32, false);
+        }
+
+        let unit = self.units.iter().position(Option::is_none).unwrap_or_else(|| {
+            self.units.iter().enumerate()
+                .min_by_key(|(_, resident)| resident.as_ref().map_or(0, |resident| resident.last_used))
+                .map_or(0, |(unit, _)| unit)
+        });
+        self.units[unit] = Some(ResidentTexture {
+            texture: texture.clone(),
+            target,
+            last_used: self.clock,
+        });
+        (unit as u32, true)
+    }
+}
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// binds `texture` to a texture unit chosen by the renderer and returns the unit, for setting a
+    /// sampler uniform to. If the texture is still bound from an earlier call its unit is reused without
+    /// rebinding, otherwise a free unit is used or the least recently used one is taken over
+    ///
+    /// textures bound directly (eg. with `Texture2d::bind`) aren't tracked, so either keep to units that
+    /// are never handed out here or call `reset_texture_units` afterwards
+    pub fn bind_texture(&mut self, texture: &impl TextureBinding) -> u32 {
+        let context = &self.renderer.context;
+        let (unit, needs_binding) = self.renderer.texture_units.allocate(context, texture.texture(), texture.target());
+        if needs_binding {
+            context.active_texture(Gl::TEXTURE0 + unit);
+            context.bind_texture(texture.target(), Some(texture.texture()));
+            stats::count(|stats| stats.texture_binds += 1);
+        }
+        unit
+    }
+
+    /// sets the filtering and wrapping of a 2d `texture` (eg. one uploaded with raw webgl calls) and
+    /// generates its mipmaps if `params.generate_mipmaps` is set, so call it after uploading:
+    /// ```ignore
+    /// render_info.configure_texture_2d(&texture, TextureParams { generate_mipmaps: true, min_filter: MinFilter::LinearMipmapLinear, ..Default::default() });
+    /// ```
+    ///
+    /// the texture is bound like `bind_texture` so it may stay bound to a unit afterwards
+    pub fn configure_texture_2d(&mut self, texture: &WebGlTexture, params: TextureParams) {
+        let context = &self.renderer.context;
+        let (unit, needs_binding) = self.renderer.texture_units.allocate(context, texture, Gl::TEXTURE_2D);
+        context.active_texture(Gl::TEXTURE0 + unit);
+        if needs_binding {
+            context.bind_texture(Gl::TEXTURE_2D, Some(texture));
+            stats::count(|stats| stats.texture_binds += 1);
+        }
+        params.apply(context);
+    }
+
+    /// binds `texture` with `bind_texture` and sets the sampler uniform `name` of `program` (which must
+    /// be in use) to its unit:
+    /// ```ignore
+    /// render_info.set_uniform_texture(&program, "u_albedo", &material.albedo);
+    /// render_info.set_uniform_texture(&program, "u_normal_map", &material.normal_map);
+    /// ```
+    ///
+    /// returns the unit, the uniform is skipped if `program` has no active uniform called `name`
+    pub fn set_uniform_texture(&mut self, program: &WebGlProgram, name: &str, texture: &impl TextureBinding) -> u32 {
+        let unit = self.bind_texture(texture);
+        let context = &self.renderer.context;
+        if let Some(location) = context.get_uniform_location(program, name) {
+            context.uniform1i(Some(&location), unit as i32);
+        }
+        unit
+    }
+
+    /// forgets which textures are bound to which units so that the next `bind_texture` calls hand out
+    /// units from the start again, eg. at the start of each `on_render` for the same units every frame
+    pub fn reset_texture_units(&mut self) {
+        self.renderer.texture_units.units.fill_with(|| None);
+    }
+}
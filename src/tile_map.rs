@@ -0,0 +1,310 @@
+use wasm_bindgen::JsCast;
+use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlProgram, WebGlTexture, WebGlUniformLocation, WebGlVertexArrayObject};
+
+use crate::{Camera2d, RenderInfo, RendererError, Texture2d, TextureAtlas, AtlasRegion, compile_shader, link_program};
+use crate::stats;
+
+type Gl = WebGl2RenderingContext;
+
+const TILE_VERTEX_SHADER: &str = "\
+#version 300 es
+layout(location = 0) in uint a_tile;
+uniform mat4 u_view_proj;
+uniform vec2 u_origin;
+uniform float u_tile_size;
+uniform int u_map_width;
+uniform int u_first_cell;
+uniform ivec2 u_visible_columns;
+uniform ivec2 u_tileset_size;
+uniform vec4 u_tileset_uv;
+out vec2 v_uv;
+
+void main() {
+    int cell = gl_InstanceID + u_first_cell;
+    ivec2 coord = ivec2(cell % u_map_width, cell / u_map_width);
+    uint index = a_tile & 0xffffu;
+    if (index == 0xffffu || coord.x < u_visible_columns.x || coord.x > u_visible_columns.y) {
+        // outside the clip volume so nothing is rasterized
+        gl_Position = vec4(2.0, 2.0, 2.0, 1.0);
+        return;
+    }
+    vec2 corner = vec2(gl_VertexID & 1, gl_VertexID >> 1);
+    vec2 uv_corner = corner;
+    if ((a_tile & 0x10000u) != 0u) uv_corner.x = 1.0 - uv_corner.x;
+    if ((a_tile & 0x20000u) != 0u) uv_corner.y = 1.0 - uv_corner.y;
+    // tiles are numbered from the top-left of the tileset but textures are bottom up
+    ivec2 tile = ivec2(int(index) % u_tileset_size.x, u_tileset_size.y - 1 - int(index) / u_tileset_size.x);
+    v_uv = mix(u_tileset_uv.xy, u_tileset_uv.zw, (vec2(tile) + uv_corner) / vec2(u_tileset_size));
+    gl_Position = u_view_proj * vec4(u_origin + (vec2(coord) + corner) * u_tile_size, 0.0, 1.0);
+}
+";
+
+const TILE_FRAGMENT_SHADER: &str = "\
+#version 300 es
+precision mediump float;
+uniform sampler2D u_tileset;
+in vec2 v_uv;
+out vec4 color;
+
+void main() {
+    color = texture(u_tileset, v_uv);
+    if (color.a == 0.0) discard;
+}
+";
+
+/// flips a tile horizontally, see `TileMap::set_tile_flipped`
+pub const FLIP_X: u8 = 1;
+/// flips a tile vertically, see `TileMap::set_tile_flipped`
+pub const FLIP_Y: u8 = 2;
+
+/// a grid of equally sized tiles in a texture (or a region of an atlas), numbered left to right from
+/// the top-left
+pub struct TileSet {
+    texture: WebGlTexture,
+    uv_rect: [f32; 4],
+    columns: u32,
+    rows: u32,
+}
+
+impl TileSet {
+    /// the whole of `texture` split into `columns` by `rows` tiles, the texture must outlive the tileset's draws
+    pub fn from_texture(texture: &Texture2d, columns: u32, rows: u32) -> Self {
+        TileSet { texture: texture.texture().clone(), uv_rect: [0.0, 0.0, 1.0, 1.0], columns: columns.max(1), rows: rows.max(1) }
+    }
+    /// `region` of `atlas` split into `columns` by `rows` tiles, the atlas must outlive the tileset's draws
+    pub fn from_region(atlas: &TextureAtlas, region: AtlasRegion, columns: u32, rows: u32) -> Self {
+        TileSet { texture: atlas.texture().texture().clone(), uv_rect: region.uv_rect, columns: columns.max(1), rows: rows.max(1) }
+    }
+}
+
+/// layers of tiles drawn with one instanced draw call per layer, with only the rows (and columns) of
+/// tiles in the visible rect being drawn:
+/// ```ignore
+/// let mut map = TileMap::new(context, TileSet::from_texture(&tileset, 8, 8), 256, 256, 2, 16.0)?;
+/// map.set_tile(0, 3, 4, 12);
+/// map.set_tile_flipped(1, 3, 5, 40, FLIP_X);
+/// ...
+/// // in `on_render`
+/// map.draw_with_camera(&mut render_info, &state.camera)?;
+/// ```
+///
+/// tile `(0, 0)` is at `origin` with `x` going right and `y` going up. Changes are uploaded by the next
+/// `draw`, only re-uploading the range of cells that changed in each layer
+pub struct TileMap {
+    context: WebGl2RenderingContext,
+    program: WebGlProgram,
+    locations: [Option<WebGlUniformLocation>; 9],
+    vertex_array: WebGlVertexArrayObject,
+    buffer: WebGlBuffer,
+    tileset: TileSet,
+    width: u32,
+    height: u32,
+    layers: u32,
+    tile_size: f32,
+    origin: [f32; 2],
+    /// every layer one after another, each packed as the tile index with the flip flags above it
+    tiles: Vec<u32>,
+    /// the range of changed cells in each layer
+    dirty: Vec<Option<(usize, usize)>>,
+}
+
+/// a cell without a tile
+const EMPTY: u32 = 0xffff;
+
+impl TileMap {
+    /// creates an empty map of `width` by `height` tiles in each of `layers` layers, with square tiles
+    /// `tile_size` world units across
+    ///
+    /// errors if the program, buffer or vertex array object could not be created
+    pub fn new(context: &WebGl2RenderingContext, tileset: TileSet, width: u32, height: u32, layers: u32, tile_size: f32) -> Result<TileMap, RendererError> {
+        let vert_shader = compile_shader(context, Gl::VERTEX_SHADER, TILE_VERTEX_SHADER)?;
+        let frag_shader = compile_shader(context, Gl::FRAGMENT_SHADER, TILE_FRAGMENT_SHADER)?;
+        let program = link_program(context, &vert_shader, &frag_shader);
+        context.delete_shader(Some(&vert_shader));
+        context.delete_shader(Some(&frag_shader));
+        let program = program?;
+        let locations = [
+            "u_view_proj", "u_origin", "u_tile_size", "u_map_width", "u_first_cell",
+            "u_visible_columns", "u_tileset_size", "u_tileset_uv", "u_tileset",
+        ].map(|name| context.get_uniform_location(&program, name));
+
+        let (width, height, layers) = (width.max(1), height.max(1), layers.max(1));
+        let tiles = vec![EMPTY; width as usize * height as usize * layers as usize];
+        let buffer = context.create_buffer().ok_or(RendererError::Create("buffer"))?;
+        context.bind_buffer(Gl::ARRAY_BUFFER, Some(&buffer));
+        context.buffer_data_with_u8_array(Gl::ARRAY_BUFFER, &to_bytes(&tiles), Gl::DYNAMIC_DRAW);
+
+        let vertex_array = context.create_vertex_array().ok_or(RendererError::Create("vertex array"))?;
+        let previous = context.get_parameter(Gl::VERTEX_ARRAY_BINDING).ok()
+            .and_then(|value| value.dyn_into::<WebGlVertexArrayObject>().ok());
+        context.bind_vertex_array(Some(&vertex_array));
+        context.enable_vertex_attrib_array(0);
+        context.vertex_attrib_divisor(0, 1);
+        context.bind_vertex_array(previous.as_ref());
+
+        Ok(TileMap {
+            context: context.clone(),
+            program,
+            locations,
+            vertex_array,
+            buffer,
+            tileset,
+            width,
+            height,
+            layers,
+            tile_size,
+            origin: [0.0, 0.0],
+            tiles,
+            dirty: vec![None; layers as usize],
+        })
+    }
+
+    /// sets the tile at `(x, y)` of `layer` to the tileset's tile `index`, does nothing if the cell is
+    /// outside the map
+    pub fn set_tile(&mut self, layer: u32, x: u32, y: u32, index: u16) {
+        self.set_tile_flipped(layer, x, y, index, 0);
+    }
+
+    /// like `set_tile` but flipped by `flags` (`FLIP_X` and/or `FLIP_Y`)
+    pub fn set_tile_flipped(&mut self, layer: u32, x: u32, y: u32, index: u16, flags: u8) {
+        self.set_cell(layer, x, y, index as u32 | ((flags as u32 & 3) << 16));
+    }
+
+    /// removes the tile at `(x, y)` of `layer`
+    pub fn clear_tile(&mut self, layer: u32, x: u32, y: u32) {
+        self.set_cell(layer, x, y, EMPTY);
+    }
+
+    fn set_cell(&mut self, layer: u32, x: u32, y: u32, value: u32) {
+        if layer >= self.layers || x >= self.width || y >= self.height {
+            return;
+        }
+        let cell = (y * self.width + x) as usize;
+        let index = layer as usize * self.layer_len() + cell;
+        if self.tiles[index] == value {
+            return;
+        }
+        self.tiles[index] = value;
+        let dirty = &mut self.dirty[layer as usize];
+        *dirty = Some(dirty.map_or((cell, cell), |(start, end)| (start.min(cell), end.max(cell))));
+    }
+
+    /// the tile index at `(x, y)` of `layer`, `None` if it is empty or outside the map
+    pub fn tile(&self, layer: u32, x: u32, y: u32) -> Option<u16> {
+        if layer >= self.layers || x >= self.width || y >= self.height {
+            return None;
+        }
+        let value = self.tiles[layer as usize * self.layer_len() + (y * self.width + x) as usize];
+        (value & 0xffff != EMPTY).then_some(value as u16)
+    }
+
+    /// moves the bottom-left corner of tile `(0, 0)`, `(0, 0)` by default
+    pub fn set_origin(&mut self, origin: [f32; 2]) {
+        self.origin = origin;
+    }
+    /// the tile at the world position `position`, if it is inside the map
+    pub fn world_to_tile(&self, position: [f32; 2]) -> Option<(u32, u32)> {
+        let x = ((position[0] - self.origin[0]) / self.tile_size).floor();
+        let y = ((position[1] - self.origin[1]) / self.tile_size).floor();
+        let inside = x >= 0.0 && y >= 0.0 && x < self.width as f32 && y < self.height as f32;
+        inside.then_some((x as u32, y as u32))
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    pub fn layers(&self) -> u32 {
+        self.layers
+    }
+    pub fn tile_size(&self) -> f32 {
+        self.tile_size
+    }
+
+    fn layer_len(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// uploads any changed tiles and draws every layer (in order) with one draw call each, skipping the
+    /// tiles outside `visible_rect` (`[min_x, min_y, max_x, max_y]` in world units, eg. the camera's
+    /// visible area)
+    ///
+    /// `view_proj` is the (column-major) view-projection matrix. The tileset is bound to unit 0,
+    /// transparent texels are discarded and the previously bound program and vertex array object are
+    /// rebound afterwards
+    pub fn draw<S>(&mut self, render_info: &mut RenderInfo<S>, view_proj: [[f32; 4]; 4], visible_rect: [f32; 4]) -> Result<(), RendererError> {
+        let context = &self.context;
+        context.bind_buffer(Gl::ARRAY_BUFFER, Some(&self.buffer));
+        let layer_len = self.layer_len();
+        for (layer, dirty) in self.dirty.iter_mut().enumerate() {
+            if let Some((start, end)) = dirty.take() {
+                let offset = layer * layer_len + start;
+                let bytes = to_bytes(&self.tiles[offset..=layer * layer_len + end]);
+                context.buffer_sub_data_with_i32_and_u8_array(Gl::ARRAY_BUFFER, (offset * size_of::<u32>()) as i32, &bytes);
+            }
+        }
+
+        let [min_x, min_y, max_x, max_y] = visible_rect;
+        let to_cell = |world: f32, origin: f32, max: u32| ((world - origin) / self.tile_size).floor().clamp(-1.0, max as f32) as i32;
+        let (first_column, last_column) = (to_cell(min_x, self.origin[0], self.width), to_cell(max_x, self.origin[0], self.width));
+        let (first_row, last_row) = (to_cell(min_y, self.origin[1], self.height).max(0), to_cell(max_y, self.origin[1], self.height - 1));
+        if last_row < first_row || last_column < 0 || first_column >= self.width as i32 {
+            return Ok(());
+        }
+
+        let previous_program = context.get_parameter(Gl::CURRENT_PROGRAM).ok()
+            .and_then(|value| value.dyn_into::<WebGlProgram>().ok());
+        let previous_vertex_array = context.get_parameter(Gl::VERTEX_ARRAY_BINDING).ok()
+            .and_then(|value| value.dyn_into::<WebGlVertexArrayObject>().ok());
+        context.use_program(Some(&self.program));
+        let [view_proj_location, origin, tile_size, map_width, first_cell, visible_columns, tileset_size, tileset_uv, tileset] = &self.locations;
+        context.uniform_matrix4fv_with_f32_array(view_proj_location.as_ref(), false, view_proj.as_flattened());
+        context.uniform2f(origin.as_ref(), self.origin[0], self.origin[1]);
+        context.uniform1f(tile_size.as_ref(), self.tile_size);
+        context.uniform1i(map_width.as_ref(), self.width as i32);
+        context.uniform2i(visible_columns.as_ref(), first_column, last_column);
+        context.uniform2i(tileset_size.as_ref(), self.tileset.columns as i32, self.tileset.rows as i32);
+        context.uniform4fv_with_f32_array(tileset_uv.as_ref(), &self.tileset.uv_rect);
+        context.uniform1i(tileset.as_ref(), 0);
+        context.active_texture(Gl::TEXTURE0);
+        context.bind_texture(Gl::TEXTURE_2D, Some(&self.tileset.texture));
+        stats::count(|stats| stats.texture_binds += 1);
+        render_info.renderer.texture_units.forget(0);
+
+        context.bind_vertex_array(Some(&self.vertex_array));
+        let first = first_row as usize * self.width as usize;
+        let count = (last_row - first_row + 1) as usize * self.width as usize;
+        context.uniform1i(first_cell.as_ref(), first as i32);
+        for layer in 0..self.layers as usize {
+            // webgl2 has no base instance so the attribute is pointed at the first visible cell instead
+            context.vertex_attrib_i_pointer_with_i32(0, 1, Gl::UNSIGNED_INT, 0, ((layer * layer_len + first) * size_of::<u32>()) as i32);
+            context.draw_arrays_instanced(Gl::TRIANGLE_STRIP, 0, 4, count as i32);
+            render_info.note_draw_call(count as u32 * 2);
+        }
+
+        context.bind_vertex_array(previous_vertex_array.as_ref());
+        context.use_program(previous_program.as_ref());
+        Ok(())
+    }
+}
+
+impl TileMap {
+    /// `draw` with `camera`'s view-projection matrix and visible rect
+    pub fn draw_with_camera<S>(&mut self, render_info: &mut RenderInfo<S>, camera: &Camera2d) -> Result<(), RendererError> {
+        self.draw(render_info, camera.view_proj(), camera.visible_rect())
+    }
+}
+
+impl Drop for TileMap {
+    fn drop(&mut self) {
+        self.context.delete_program(Some(&self.program));
+        self.context.delete_buffer(Some(&self.buffer));
+        self.context.delete_vertex_array(Some(&self.vertex_array));
+    }
+}
+
+fn to_bytes(tiles: &[u32]) -> Vec<u8> {
+    tiles.iter().flat_map(|tile| tile.to_ne_bytes()).collect()
+}
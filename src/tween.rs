@@ -0,0 +1,309 @@
+use crate::Color;
+
+/// values that can be moved towards each other, for `Tween`
+pub trait Lerp: Clone {
+    /// `self` moved towards `other` by `t`, where `t` is usually (but, eg. with `Easing::ElasticOut`,
+    /// not always) between 0 and 1
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+impl Lerp for f64 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t as f64
+    }
+}
+impl<const N: usize> Lerp for [f32; N] {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        std::array::from_fn(|i| self[i].lerp(&other[i], t))
+    }
+}
+impl Lerp for Color {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Color::lerp(*self, *other, t)
+    }
+}
+
+#[cfg(feature = "glam")]
+mod glam_lerp {
+    use super::Lerp;
+
+    macro_rules! impl_lerp {
+        ($($ty:ty),*) => {$(
+            impl Lerp for $ty {
+                fn lerp(&self, other: &Self, t: f32) -> Self {
+                    <$ty>::lerp(*self, *other, t)
+                }
+            }
+        )*};
+    }
+    impl_lerp!(glam::Vec2, glam::Vec3, glam::Vec4);
+
+    impl Lerp for glam::Quat {
+        fn lerp(&self, other: &Self, t: f32) -> Self {
+            self.slerp(*other, t)
+        }
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+mod nalgebra_lerp {
+    use super::Lerp;
+    use nalgebra::{UnitQuaternion, Vector2, Vector3, Vector4};
+
+    macro_rules! impl_lerp {
+        ($($ty:ty),*) => {$(
+            impl Lerp for $ty {
+                fn lerp(&self, other: &Self, t: f32) -> Self {
+                    <$ty>::lerp(self, other, t)
+                }
+            }
+        )*};
+    }
+    impl_lerp!(Vector2<f32>, Vector3<f32>, Vector4<f32>);
+
+    impl Lerp for UnitQuaternion<f32> {
+        fn lerp(&self, other: &Self, t: f32) -> Self {
+            self.slerp(other, t)
+        }
+    }
+}
+
+/// how a `Tween` moves between its values over time, see <https://easings.net>
+///
+/// `In` starts slowly, `Out` ends slowly and `InOut` does both
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    /// overshoots below the start before springing to the end
+    ElasticIn,
+    /// overshoots past the end before settling on it
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    /// bounces on the end like a dropped ball
+    BounceOut,
+    BounceInOut,
+}
+
+impl Easing {
+    /// the eased progress for the linear progress `t`, which is clamped between 0 and 1
+    pub fn apply(self, t: f32) -> f32 {
+        use std::f32::consts::PI;
+        let t = t.clamp(0.0, 1.0);
+        let in_out = |ease_in: fn(f32) -> f32| if t < 0.5 { ease_in(t * 2.0) / 2.0 } else { 1.0 - ease_in(2.0 - t * 2.0) / 2.0 };
+        let out = |ease_in: fn(f32) -> f32| 1.0 - ease_in(1.0 - t);
+        let expo_in = |t: f32| if t == 0.0 { 0.0 } else { 2f32.powf(10.0 * t - 10.0) };
+        let elastic_in = |t: f32| match t {
+            0.0 | 1.0 => t,
+            _ => -(2f32.powf(10.0 * t - 10.0)) * ((t * 10.0 - 10.75) * (2.0 * PI / 3.0)).sin(),
+        };
+        let bounce_in = |t: f32| 1.0 - bounce_out(1.0 - t);
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => out(|t| t * t),
+            Easing::QuadInOut => in_out(|t| t * t),
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => out(|t| t * t * t),
+            Easing::CubicInOut => in_out(|t| t * t * t),
+            Easing::ExpoIn => expo_in(t),
+            Easing::ExpoOut => out(expo_in),
+            Easing::ExpoInOut => in_out(expo_in),
+            Easing::ElasticIn => elastic_in(t),
+            Easing::ElasticOut => out(elastic_in),
+            Easing::ElasticInOut => in_out(elastic_in),
+            Easing::BounceIn => bounce_in(t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => in_out(bounce_in),
+        }
+    }
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+/// a value animated from `from` to `to` over `duration` seconds, advanced with the fixed time step in
+/// `on_update` and read (between the last two updates) in `on_render`:
+/// ```ignore
+/// // in `on_update`
+/// if clicked {
+///     state.camera_zoom = Tween::new(state.camera_zoom.value(), 2.0, 0.5, Easing::CubicOut);
+/// }
+/// state.camera_zoom.tick(update_info.fixed_time_step());
+/// ...
+/// // in `on_render`
+/// camera.zoom = state.camera_zoom.blended_value(render_info.blending_factor());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Tween<T: Lerp> {
+    from: T,
+    to: T,
+    duration: f64,
+    elapsed: f64,
+    previous_elapsed: f64,
+    easing: Easing,
+}
+
+impl<T: Lerp> Tween<T> {
+    /// a tween with a non-positive `duration` is already finished
+    pub fn new(from: T, to: T, duration: f64, easing: Easing) -> Self {
+        Tween { from, to, duration: duration.max(0.0), elapsed: 0.0, previous_elapsed: 0.0, easing }
+    }
+
+    /// advances the tween by `delta` seconds, usually `UpdateInfo::fixed_time_step`
+    pub fn tick(&mut self, delta: f64) {
+        self.previous_elapsed = self.elapsed;
+        self.elapsed = (self.elapsed + delta).min(self.duration);
+    }
+
+    /// the value as of the latest tick
+    pub fn value(&self) -> T {
+        self.value_at(self.elapsed)
+    }
+    /// the value between the previous tick and the latest one, with `blending_factor` from
+    /// `RenderInfo::blending_factor`, so that it moves smoothly when rendering more often than updating
+    pub fn blended_value(&self, blending_factor: f64) -> T {
+        let elapsed = self.previous_elapsed + (self.elapsed - self.previous_elapsed) * blending_factor.clamp(0.0, 1.0);
+        self.value_at(elapsed)
+    }
+    fn value_at(&self, elapsed: f64) -> T {
+        let progress = if self.duration > 0.0 { elapsed / self.duration } else { 1.0 };
+        self.from.lerp(&self.to, self.easing.apply(progress as f32))
+    }
+
+    /// how far through the tween the latest tick was, between 0 and 1 (before easing)
+    pub fn progress(&self) -> f64 {
+        if self.duration > 0.0 { self.elapsed / self.duration } else { 1.0 }
+    }
+    /// whether the tween has reached `to`, after which ticking it does nothing
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+    /// starts the tween again from `from`
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+        self.previous_elapsed = 0.0;
+    }
+
+    pub fn from(&self) -> &T {
+        &self.from
+    }
+    pub fn to(&self) -> &T {
+        &self.to
+    }
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+    pub fn easing(&self) -> Easing {
+        self.easing
+    }
+}
+
+/// identifies a tween in a `Tweens`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TweenId(u64);
+
+/// many tweens of the same type, eg. fading ui elements, ticked together and removed once finished:
+/// ```ignore
+/// // in `on_update`
+/// let fade = state.tweens.add(Tween::new(0.0, 1.0, 0.25, Easing::QuadOut));
+/// state.tweens.tick(update_info.fixed_time_step());
+/// ...
+/// // in `on_render`
+/// let alpha = state.tweens.blended_value(fade, render_info.blending_factor()).unwrap_or(1.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Tweens<T: Lerp> {
+    tweens: Vec<(TweenId, Tween<T>)>,
+    next_id: u64,
+}
+
+impl<T: Lerp> Default for Tweens<T> {
+    fn default() -> Self {
+        Tweens { tweens: Vec::new(), next_id: 0 }
+    }
+}
+
+impl<T: Lerp> Tweens<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, tween: Tween<T>) -> TweenId {
+        let id = TweenId(self.next_id);
+        self.next_id += 1;
+        self.tweens.push((id, tween));
+        id
+    }
+    /// returns the removed tween if it hadn't already been removed
+    pub fn remove(&mut self, id: TweenId) -> Option<Tween<T>> {
+        let index = self.tweens.iter().position(|(tween_id, _)| *tween_id == id)?;
+        Some(self.tweens.remove(index).1)
+    }
+    pub fn clear(&mut self) {
+        self.tweens.clear();
+    }
+
+    /// removes the tweens that finished on an earlier tick and then advances the rest by `delta`
+    /// seconds, so a tween can still be read (at its end value) until the tick after it finishes
+    pub fn tick(&mut self, delta: f64) {
+        self.tweens.retain(|(_, tween)| !tween.is_finished());
+        for (_, tween) in &mut self.tweens {
+            tween.tick(delta);
+        }
+    }
+
+    pub fn get(&self, id: TweenId) -> Option<&Tween<T>> {
+        self.tweens.iter().find(|(tween_id, _)| *tween_id == id).map(|(_, tween)| tween)
+    }
+    pub fn get_mut(&mut self, id: TweenId) -> Option<&mut Tween<T>> {
+        self.tweens.iter_mut().find(|(tween_id, _)| *tween_id == id).map(|(_, tween)| tween)
+    }
+    /// `Tween::value` of the tween, or `None` if it has been removed
+    pub fn value(&self, id: TweenId) -> Option<T> {
+        self.get(id).map(Tween::value)
+    }
+    /// `Tween::blended_value` of the tween, or `None` if it has been removed
+    pub fn blended_value(&self, id: TweenId, blending_factor: f64) -> Option<T> {
+        self.get(id).map(|tween| tween.blended_value(blending_factor))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (TweenId, &Tween<T>)> {
+        self.tweens.iter().map(|(id, tween)| (*id, tween))
+    }
+    pub fn len(&self) -> usize {
+        self.tweens.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.tweens.is_empty()
+    }
+}
@@ -0,0 +1,164 @@
+use web_sys::{WebGl2RenderingContext, WebGlUniformLocation};
+
+/// a value that can be uploaded to a uniform, matrices are column-major
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UniformValue {
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Int(i32),
+    IVec2([i32; 2]),
+    IVec3([i32; 3]),
+    IVec4([i32; 4]),
+    UInt(u32),
+    Bool(bool),
+    Mat2([f32; 4]),
+    Mat3([f32; 9]),
+    Mat4([f32; 16]),
+}
+
+impl UniformValue {
+    /// uploads the value to `location` of the program currently in use
+    pub fn upload(&self, context: &WebGl2RenderingContext, location: Option<&WebGlUniformLocation>) {
+        match *self {
+            UniformValue::Float(x) => context.uniform1f(location, x),
+            UniformValue::Vec2([x, y]) => context.uniform2f(location, x, y),
+            UniformValue::Vec3([x, y, z]) => context.uniform3f(location, x, y, z),
+            UniformValue::Vec4([x, y, z, w]) => context.uniform4f(location, x, y, z, w),
+            UniformValue::Int(x) => context.uniform1i(location, x),
+            UniformValue::IVec2([x, y]) => context.uniform2i(location, x, y),
+            UniformValue::IVec3([x, y, z]) => context.uniform3i(location, x, y, z),
+            UniformValue::IVec4([x, y, z, w]) => context.uniform4i(location, x, y, z, w),
+            UniformValue::UInt(x) => context.uniform1ui(location, x),
+            UniformValue::Bool(x) => context.uniform1i(location, x as i32),
+            UniformValue::Mat2(m) => context.uniform_matrix2fv_with_f32_array(location, false, &m),
+            UniformValue::Mat3(m) => context.uniform_matrix3fv_with_f32_array(location, false, &m),
+            UniformValue::Mat4(m) => context.uniform_matrix4fv_with_f32_array(location, false, &m),
+        }
+    }
+}
+
+/// something that can be uploaded to a uniform, see `RenderInfo::set_uniform_cached`
+///
+/// slices upload to uniform arrays, eg. `&bone_matrices[..]` for a `uniform mat4 u_bones[N]`
+pub trait Uniform {
+    /// uploads the value to `location` of the program currently in use
+    fn upload(&self, context: &WebGl2RenderingContext, location: Option<&WebGlUniformLocation>);
+}
+
+impl<T: Uniform + ?Sized> Uniform for &T {
+    fn upload(&self, context: &WebGl2RenderingContext, location: Option<&WebGlUniformLocation>) {
+        (**self).upload(context, location)
+    }
+}
+
+impl Uniform for UniformValue {
+    fn upload(&self, context: &WebGl2RenderingContext, location: Option<&WebGlUniformLocation>) {
+        UniformValue::upload(self, context, location)
+    }
+}
+
+impl Uniform for [[f32; 16]] {
+    fn upload(&self, context: &WebGl2RenderingContext, location: Option<&WebGlUniformLocation>) {
+        context.uniform_matrix4fv_with_f32_array(location, false, self.as_flattened())
+    }
+}
+
+#[cfg(feature = "glam")]
+mod glam_uniforms {
+    use web_sys::{WebGl2RenderingContext, WebGlUniformLocation};
+
+    use super::{Uniform, UniformValue};
+
+    impl From<glam::Vec2> for UniformValue {
+        fn from(v: glam::Vec2) -> Self { UniformValue::Vec2(v.to_array()) }
+    }
+    impl From<glam::Vec3> for UniformValue {
+        fn from(v: glam::Vec3) -> Self { UniformValue::Vec3(v.to_array()) }
+    }
+    impl From<glam::Vec4> for UniformValue {
+        fn from(v: glam::Vec4) -> Self { UniformValue::Vec4(v.to_array()) }
+    }
+    impl From<glam::Mat2> for UniformValue {
+        fn from(m: glam::Mat2) -> Self { UniformValue::Mat2(m.to_cols_array()) }
+    }
+    impl From<glam::Mat3> for UniformValue {
+        fn from(m: glam::Mat3) -> Self { UniformValue::Mat3(m.to_cols_array()) }
+    }
+    impl From<glam::Mat4> for UniformValue {
+        fn from(m: glam::Mat4) -> Self { UniformValue::Mat4(m.to_cols_array()) }
+    }
+    /// uploads as a `mat4` rotation matrix
+    impl From<glam::Quat> for UniformValue {
+        fn from(q: glam::Quat) -> Self { glam::Mat4::from_quat(q).into() }
+    }
+
+    macro_rules! impl_uniform {
+        ($($ty:ty),*) => {$(
+            impl Uniform for $ty {
+                fn upload(&self, context: &WebGl2RenderingContext, location: Option<&WebGlUniformLocation>) {
+                    UniformValue::from(*self).upload(context, location)
+                }
+            }
+        )*};
+    }
+    impl_uniform!(glam::Vec2, glam::Vec3, glam::Vec4, glam::Mat2, glam::Mat3, glam::Mat4, glam::Quat);
+
+    impl Uniform for [glam::Mat4] {
+        fn upload(&self, context: &WebGl2RenderingContext, location: Option<&WebGlUniformLocation>) {
+            let flattened: Vec<f32> = self.iter().flat_map(glam::Mat4::to_cols_array).collect();
+            context.uniform_matrix4fv_with_f32_array(location, false, &flattened)
+        }
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+mod nalgebra_uniforms {
+    use web_sys::{WebGl2RenderingContext, WebGlUniformLocation};
+    use nalgebra::{Matrix2, Matrix3, Matrix4, UnitQuaternion, Vector2, Vector3, Vector4};
+
+    use super::{Uniform, UniformValue};
+
+    // nalgebra stores matrices column-major, so their slices can be uploaded as is
+    impl From<Vector2<f32>> for UniformValue {
+        fn from(v: Vector2<f32>) -> Self { UniformValue::Vec2(v.into()) }
+    }
+    impl From<Vector3<f32>> for UniformValue {
+        fn from(v: Vector3<f32>) -> Self { UniformValue::Vec3(v.into()) }
+    }
+    impl From<Vector4<f32>> for UniformValue {
+        fn from(v: Vector4<f32>) -> Self { UniformValue::Vec4(v.into()) }
+    }
+    impl From<Matrix2<f32>> for UniformValue {
+        fn from(m: Matrix2<f32>) -> Self { UniformValue::Mat2(m.as_slice().try_into().unwrap()) }
+    }
+    impl From<Matrix3<f32>> for UniformValue {
+        fn from(m: Matrix3<f32>) -> Self { UniformValue::Mat3(m.as_slice().try_into().unwrap()) }
+    }
+    impl From<Matrix4<f32>> for UniformValue {
+        fn from(m: Matrix4<f32>) -> Self { UniformValue::Mat4(m.as_slice().try_into().unwrap()) }
+    }
+    /// uploads as a `mat4` rotation matrix
+    impl From<UnitQuaternion<f32>> for UniformValue {
+        fn from(q: UnitQuaternion<f32>) -> Self { q.to_homogeneous().into() }
+    }
+
+    macro_rules! impl_uniform {
+        ($($ty:ty),*) => {$(
+            impl Uniform for $ty {
+                fn upload(&self, context: &WebGl2RenderingContext, location: Option<&WebGlUniformLocation>) {
+                    UniformValue::from(*self).upload(context, location)
+                }
+            }
+        )*};
+    }
+    impl_uniform!(Vector2<f32>, Vector3<f32>, Vector4<f32>, Matrix2<f32>, Matrix3<f32>, Matrix4<f32>, UnitQuaternion<f32>);
+
+    impl Uniform for [Matrix4<f32>] {
+        fn upload(&self, context: &WebGl2RenderingContext, location: Option<&WebGlUniformLocation>) {
+            let flattened: Vec<f32> = self.iter().flat_map(|m| m.as_slice().iter().copied()).collect();
+            context.uniform_matrix4fv_with_f32_array(location, false, &flattened)
+        }
+    }
+}
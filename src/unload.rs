@@ -0,0 +1,71 @@
+use wasm_bindgen::{JsCast, closure::Closure};
+use web_sys::{PageTransitionEvent, window};
+
+use crate::{Renderer, RendererError};
+
+/// the `pagehide` and `pageshow` listeners on `window` added by `Renderer::with_on_unload`, removed
+/// when dropped
+pub(crate) struct UnloadListener {
+    on_pagehide: Closure<dyn FnMut(PageTransitionEvent)>,
+    on_pageshow: Closure<dyn FnMut(PageTransitionEvent)>,
+}
+
+impl Drop for UnloadListener {
+    fn drop(&mut self) {
+        if let Some(window) = window() {
+            let _ = window.remove_event_listener_with_callback("pagehide", self.on_pagehide.as_ref().unchecked_ref());
+            let _ = window.remove_event_listener_with_callback("pageshow", self.on_pageshow.as_ref().unchecked_ref());
+        }
+    }
+}
+
+impl<S> Renderer<S> {
+    /// calls `on_unload` with the state when the page is about to be unloaded (eg. the user navigates
+    /// away or closes the tab), eg. to save progress, and exits the game loop so that no more frames
+    /// are requested:
+    /// ```ignore
+    /// renderer = renderer.with_on_unload(|state| save_to_local_storage(&state.progress))?;
+    /// ```
+    ///
+    /// this listens for `pagehide` rather than `beforeunload`, which mobile browsers often don't fire and
+    /// which stops pages from entering the back/forward cache. If the page is restored from that cache
+    /// (`pageshow` with `persisted`) before the game loop has seen the unload, it carries on running
+    ///
+    /// `on_unload` is skipped if the state is borrowed (ie. the page is unloaded from inside a callback)
+    /// and the listeners are removed when the renderer is dropped
+    ///
+    /// returns self for chaining
+    ///
+    /// errors if `on_unload` has already been set or there is no `window`
+    pub fn with_on_unload(mut self, on_unload: fn(&mut S)) -> Result<Self, RendererError> where S: 'static {
+        if self.unload_listener.is_some() {
+            return Err(RendererError::AlreadySet("on_unload"));
+        }
+        let window = window().ok_or(RendererError::Unsupported("window"))?;
+        let state = self.state.clone();
+        let unloaded = self.unloaded.clone();
+        let on_pagehide = Closure::<dyn FnMut(PageTransitionEvent)>::new(move |_| {
+            unloaded.set(true);
+            if let Some(mut state) = state.get().and_then(|state| state.try_borrow_mut().ok()) {
+                on_unload(&mut state);
+            }
+        });
+        let unloaded = self.unloaded.clone();
+        let on_pageshow = Closure::<dyn FnMut(PageTransitionEvent)>::new(move |event: PageTransitionEvent| {
+            if event.persisted() {
+                unloaded.set(false);
+            }
+        });
+        // constructed before adding the listeners so that they are removed again if adding one fails
+        let listener = UnloadListener { on_pagehide, on_pageshow };
+        window.add_event_listener_with_callback("pagehide", listener.on_pagehide.as_ref().unchecked_ref())?;
+        window.add_event_listener_with_callback("pageshow", listener.on_pageshow.as_ref().unchecked_ref())?;
+        self.unload_listener = Some(listener);
+        Ok(self)
+    }
+
+    /// whether the game loop should stop, because of `exit` or the page unloading
+    pub(crate) fn is_exiting(&self) -> bool {
+        self.exit || self.unloaded.get()
+    }
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+
+use crate::{LogLevel, Renderer};
+
+/// how likely a `RendererWarning` is to be a mistake
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WarningLevel {
+    /// fine if intentional, eg. drawing only with helpers that have their own programs
+    Info,
+    /// almost certainly a mistake
+    Warning,
+}
+
+/// a likely misconfiguration found by `Renderer::validate`
+#[derive(Debug, Clone, PartialEq)]
+pub enum RendererWarning {
+    /// `with_on_render` wasn't called so nothing will be drawn
+    NoOnRender,
+    /// neither `with_shaders` nor `with_named_shader` was called
+    NoShaders,
+    /// the loop was given 0 updates per second so `on_update` will never be called
+    ZeroUpdatesPerSecond,
+    /// the max frame time is less than the fixed time step, so at most one update runs per frame
+    /// however far behind the loop is, contains the max frame time and the fixed time step
+    MaxFrameTimeBelowTimeStep(f64, f64),
+}
+
+impl RendererWarning {
+    pub fn level(&self) -> WarningLevel {
+        match self {
+            RendererWarning::NoShaders => WarningLevel::Info,
+            _ => WarningLevel::Warning,
+        }
+    }
+    /// a human-readable description of the warning, the same as its `Display`
+    pub fn message(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for RendererWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RendererWarning::NoOnRender => write!(f, "on_render isn't set, so nothing will be drawn"),
+            RendererWarning::NoShaders => write!(f, "no shaders have been linked, so only helpers with their own programs can draw"),
+            RendererWarning::ZeroUpdatesPerSecond => write!(f, "updates per second is 0, so on_update will never be called"),
+            RendererWarning::MaxFrameTimeBelowTimeStep(max_frame_time, fixed_time_step) => write!(
+                f,
+                "the max frame time of {} seconds is less than the fixed time step of {} seconds, so at most one update will run per frame",
+                max_frame_time, fixed_time_step,
+            ),
+        }
+    }
+}
+
+impl<S> Renderer<S> {
+    /// checks for common misconfigurations that aren't errors but usually are mistakes, eg. forgetting
+    /// `with_on_render`, which would otherwise silently leave the canvas blank
+    ///
+    /// the loop's updates per second and max frame time are only checked once they are known, ie. after
+    /// `RendererBuilder::build` or from `start`, which calls this and logs the warnings to
+    /// `console.warn` (and the infos to `console.log` with `LogLevel::Verbose`)
+    pub fn validate(&self) -> Vec<RendererWarning> {
+        let mut warnings = Vec::new();
+        if self.on_render.get().is_none() {
+            warnings.push(RendererWarning::NoOnRender);
+        }
+        if self.program.is_none() && self.named_shaders.is_empty() {
+            warnings.push(RendererWarning::NoShaders);
+        }
+        if self.state.get().is_some() {
+            if self.updates_per_second == 0 {
+                warnings.push(RendererWarning::ZeroUpdatesPerSecond);
+            } else if self.max_frame_time < self.fixed_time_step {
+                warnings.push(RendererWarning::MaxFrameTimeBelowTimeStep(self.max_frame_time, self.fixed_time_step));
+            }
+        }
+        warnings
+    }
+
+    /// logs the warnings from `validate`, skipping the checks if they wouldn't be logged
+    pub(crate) fn log_validation(&self) {
+        if self.log_level.get() < LogLevel::Warnings {
+            return;
+        }
+        for warning in self.validate() {
+            let level = match warning.level() {
+                WarningLevel::Info => LogLevel::Verbose,
+                WarningLevel::Warning => LogLevel::Warnings,
+            };
+            self.log(level, || warning.message());
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlProgram};
+
+use crate::{RendererError, ReadbackToken};
+
+type Gl = WebGl2RenderingContext;
+
+/// the type of each component of a vertex attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeType {
+    Float,
+    Byte,
+    UnsignedByte,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+}
+
+impl AttributeType {
+    pub fn to_gl(self) -> u32 {
+        match self {
+            AttributeType::Float => Gl::FLOAT,
+            AttributeType::Byte => Gl::BYTE,
+            AttributeType::UnsignedByte => Gl::UNSIGNED_BYTE,
+            AttributeType::Short => Gl::SHORT,
+            AttributeType::UnsignedShort => Gl::UNSIGNED_SHORT,
+            AttributeType::Int => Gl::INT,
+            AttributeType::UnsignedInt => Gl::UNSIGNED_INT,
+        }
+    }
+}
+
+/// a rust type that can be used as a vertex attribute, eg. `[f32; 3]` or `[u8; 4]`
+pub trait AttributeFormat {
+    const TYPE: AttributeType;
+    const COMPONENTS: i32;
+}
+
+macro_rules! impl_attribute_format {
+    ($($ty:ty => $attribute_type:ident),*) => {$(
+        impl AttributeFormat for $ty {
+            const TYPE: AttributeType = AttributeType::$attribute_type;
+            const COMPONENTS: i32 = 1;
+        }
+        impl<const N: usize> AttributeFormat for [$ty; N] {
+            const TYPE: AttributeType = AttributeType::$attribute_type;
+            const COMPONENTS: i32 = {
+                assert!(N >= 1 && N <= 4, "vertex attributes have 1 to 4 components");
+                N as i32
+            };
+        }
+    )*};
+}
+impl_attribute_format!(
+    f32 => Float,
+    i8 => Byte,
+    u8 => UnsignedByte,
+    i16 => Short,
+    u16 => UnsignedShort,
+    i32 => Int,
+    u32 => UnsignedInt
+);
+
+/// describes one attribute of a vertex
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttributeDesc {
+    /// the name of the attribute in the vertex shader
+    pub name: &'static str,
+    pub components: i32,
+    pub attribute_type: AttributeType,
+    /// whether integer components are mapped to `[0, 1]` (or `[-1, 1]` if signed) floats, integer
+    /// components that aren't normalized are passed to the shader as integers (eg. an `ivec4`)
+    pub normalized: bool,
+    /// the offset of the attribute in bytes from the start of the vertex
+    pub offset: usize,
+}
+
+/// a vertex struct whose layout is described by `LAYOUT`, usually implemented with `#[derive(Vertex)]`
+/// (behind the `derive` feature):
+/// ```ignore
+/// #[derive(Clone, Copy, Vertex)]
+/// #[repr(C)]
+/// struct MyVertex {
+///     #[vertex(name = "a_pos")]
+///     position: [f32; 3],
+///     #[vertex(normalized)]
+///     color: [u8; 4],
+/// }
+/// ```
+///
+/// # Safety
+///
+/// vertices are uploaded by reinterpreting them as bytes so implementors must be `#[repr(C)]`, contain
+/// no padding and only have fields that are plain old data (which the derive checks) and `LAYOUT` must
+/// describe fields that are within the struct
+pub unsafe trait Vertex: Copy + 'static {
+    const LAYOUT: &'static [AttributeDesc];
+}
+
+/// the attributes of a vertex buffer and the stride between vertices
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexLayout {
+    pub attributes: Vec<AttributeDesc>,
+    pub stride: usize,
+}
+
+impl VertexLayout {
+    /// the layout of tightly packed `V`s
+    pub fn of<V: Vertex>() -> VertexLayout {
+        VertexLayout {
+            attributes: V::LAYOUT.to_vec(),
+            stride: size_of::<V>(),
+        }
+    }
+
+    /// points each attribute that is active in `program` at the buffer bound to `ARRAY_BUFFER` (recording
+    /// it in the bound vertex array object, if there is one), attributes that aren't active are skipped
+    pub fn apply(&self, context: &WebGl2RenderingContext, program: &WebGlProgram) {
+        for attribute in &self.attributes {
+            let location = context.get_attrib_location(program, attribute.name);
+            if location < 0 { continue }
+            let location = location as u32;
+
+            context.enable_vertex_attrib_array(location);
+            if attribute.attribute_type == AttributeType::Float || attribute.normalized {
+                context.vertex_attrib_pointer_with_i32(
+                    location, attribute.components, attribute.attribute_type.to_gl(),
+                    attribute.normalized, self.stride as i32, attribute.offset as i32,
+                );
+            } else {
+                context.vertex_attrib_i_pointer_with_i32(
+                    location, attribute.components, attribute.attribute_type.to_gl(),
+                    self.stride as i32, attribute.offset as i32,
+                );
+            }
+        }
+    }
+}
+
+/// an `ARRAY_BUFFER` of vertices that is deleted when dropped
+pub struct VertexBuffer {
+    context: WebGl2RenderingContext,
+    buffer: WebGlBuffer,
+    layout: Option<VertexLayout>,
+    len: usize,
+}
+
+impl VertexBuffer {
+    pub fn new(context: &WebGl2RenderingContext) -> Result<VertexBuffer, RendererError> {
+        let buffer = context.create_buffer().ok_or(RendererError::Create("buffer"))?;
+        Ok(VertexBuffer {
+            context: context.clone(),
+            buffer,
+            layout: None,
+            len: 0,
+        })
+    }
+
+    /// replaces the contents of the buffer with `vertices` and sets the layout to `V::LAYOUT`
+    ///
+    /// `usage` is eg. `STATIC_DRAW` or `DYNAMIC_DRAW`, the buffer is left bound to `ARRAY_BUFFER`
+    pub fn upload_structs<V: Vertex>(&mut self, vertices: &[V], usage: u32) {
+        // SAFETY: `Vertex` guarantees that `V` has no padding and only plain old data fields so
+        // every byte of `vertices` is initialised
+        let bytes = unsafe { std::slice::from_raw_parts(vertices.as_ptr().cast::<u8>(), size_of_val(vertices)) };
+        self.bind();
+        self.context.buffer_data_with_u8_array(Gl::ARRAY_BUFFER, bytes, usage);
+        self.layout = Some(VertexLayout::of::<V>());
+        self.len = vertices.len();
+    }
+
+    /// binds the buffer to `ARRAY_BUFFER`
+    pub fn bind(&self) {
+        self.context.bind_buffer(Gl::ARRAY_BUFFER, Some(&self.buffer));
+    }
+
+    /// binds the buffer and points the attributes of `program` at it according to the layout of the
+    /// last upload, see `VertexLayout::apply`
+    pub fn apply_layout(&self, program: &WebGlProgram) {
+        if let Some(layout) = &self.layout {
+            self.bind();
+            layout.apply(&self.context, program);
+        }
+    }
+
+    /// starts copying the contents of the buffer back to the cpu without stalling, poll the result with
+    /// `ReadbackToken::poll` on later frames (or pass it to `RenderInfo::on_readback`)
+    ///
+    /// errors if the copy's buffer or the fence could not be created
+    pub fn begin_async_readback(&self) -> Result<ReadbackToken, RendererError> {
+        ReadbackToken::copy(&self.context, &self.buffer, self.size_in_bytes())
+    }
+
+    pub fn buffer(&self) -> &WebGlBuffer {
+        &self.buffer
+    }
+    /// the layout of the last upload
+    pub fn layout(&self) -> Option<&VertexLayout> {
+        self.layout.as_ref()
+    }
+    /// the number of vertices in the last upload
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// the size of the last upload in bytes
+    pub fn size_in_bytes(&self) -> usize {
+        self.layout.as_ref().map_or(0, |layout| layout.stride * self.len)
+    }
+}
+
+impl Drop for VertexBuffer {
+    fn drop(&mut self) {
+        self.context.delete_buffer(Some(&self.buffer));
+    }
+}
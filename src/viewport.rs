@@ -0,0 +1,122 @@
+use wasm_bindgen::JsCast;
+use js_sys::Int32Array;
+use web_sys::WebGl2RenderingContext;
+
+use crate::RenderInfo;
+
+type Gl = WebGl2RenderingContext;
+
+/// a rectangle in drawing-buffer pixels with a top-left origin (y increases downwards)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Rect { x, y, width, height }
+    }
+
+    /// converts to the bottom-left origin used by webgl, given the height of what is being drawn to
+    pub fn to_gl(self, buffer_height: i32) -> [i32; 4] {
+        [self.x, buffer_height - (self.y + self.height), self.width, self.height]
+    }
+}
+
+impl<'a, S> RenderInfo<'a, S> {
+    /// sets the viewport to `rect` while `f` runs, restoring the previous viewport afterwards
+    ///
+    /// `rect` has a top-left origin relative to the drawing buffer, calls can be nested:
+    /// ```ignore
+    /// render_info.with_viewport(Rect::new(0, 0, 200, 200), |render_info| {
+    ///     draw_minimap(render_info);
+    /// });
+    /// ```
+    pub fn with_viewport<R>(&mut self, rect: Rect, f: impl FnOnce(&mut Self) -> R) -> R {
+        let context = (*self.renderer.context).clone();
+        let [x, y, width, height] = rect.to_gl(context.drawing_buffer_height());
+
+        let _restore = RestoreBox {
+            previous: get_box(&context, Gl::VIEWPORT),
+            set_box: Gl::viewport,
+            context: context.clone(),
+        };
+        context.viewport(x, y, width, height);
+        f(self)
+    }
+
+    /// enables the scissor test with a scissor box of `rect` while `f` runs, restoring the previous
+    /// scissor box and whether the scissor test was enabled afterwards
+    ///
+    /// `rect` has a top-left origin relative to the drawing buffer, calls can be nested
+    pub fn with_scissor<R>(&mut self, rect: Rect, f: impl FnOnce(&mut Self) -> R) -> R {
+        let context = (*self.renderer.context).clone();
+        let [x, y, width, height] = rect.to_gl(context.drawing_buffer_height());
+
+        let _restore_enabled = RestoreEnabled {
+            was_enabled: context.is_enabled(Gl::SCISSOR_TEST),
+            capability: Gl::SCISSOR_TEST,
+            context: context.clone(),
+        };
+        let _restore_box = RestoreBox {
+            previous: get_box(&context, Gl::SCISSOR_BOX),
+            set_box: Gl::scissor,
+            context: context.clone(),
+        };
+        context.enable(Gl::SCISSOR_TEST);
+        context.scissor(x, y, width, height);
+        f(self)
+    }
+}
+
+pub(crate) fn get_box(context: &WebGl2RenderingContext, pname: u32) -> [i32; 4] {
+    let mut previous = [0; 4];
+    if let Ok(value) = context.get_parameter(pname) {
+        if let Some(array) = value.dyn_ref::<Int32Array>() {
+            array.copy_to(&mut previous);
+        }
+    }
+    previous
+}
+
+/// restores a viewport or scissor box when dropped (including when unwinding)
+struct RestoreBox {
+    context: WebGl2RenderingContext,
+    set_box: fn(&WebGl2RenderingContext, i32, i32, i32, i32),
+    previous: [i32; 4],
+}
+impl Drop for RestoreBox {
+    fn drop(&mut self) {
+        let [x, y, width, height] = self.previous;
+        (self.set_box)(&self.context, x, y, width, height);
+    }
+}
+
+/// restores whether a capability was enabled when dropped
+pub(crate) struct RestoreEnabled {
+    context: WebGl2RenderingContext,
+    capability: u32,
+    was_enabled: bool,
+}
+impl RestoreEnabled {
+    /// records whether `capability` is currently enabled
+    pub(crate) fn new(context: &WebGl2RenderingContext, capability: u32) -> Self {
+        RestoreEnabled {
+            was_enabled: context.is_enabled(capability),
+            capability,
+            context: context.clone(),
+        }
+    }
+}
+impl Drop for RestoreEnabled {
+    fn drop(&mut self) {
+        if self.was_enabled {
+            self.context.enable(self.capability);
+        } else {
+            self.context.disable(self.capability);
+        }
+    }
+}
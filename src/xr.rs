@@ -0,0 +1,234 @@
+use wasm_bindgen::{JsCast, JsValue, closure::Closure};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{WebGl2RenderingContext, XrFrame, XrReferenceSpace, XrReferenceSpaceType, XrRenderStateInit, XrSession, XrSessionMode, XrWebGlLayer, window};
+use std::cell::{Cell, OnceCell, RefCell};
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+use crate::{Renderer, RenderInfo, RendererError, current_instant};
+
+type Gl = WebGl2RenderingContext;
+
+/// the kind of session to request with `Renderer::with_webxr`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XrSessionType {
+    /// `immersive-vr`, rendering replaces the user's view
+    ImmersiveVr,
+    /// `immersive-ar`, rendering is composited over the user's view of the real world
+    ImmersiveAr,
+}
+
+impl XrSessionType {
+    fn mode(self) -> XrSessionMode {
+        match self {
+            XrSessionType::ImmersiveVr => XrSessionMode::ImmersiveVr,
+            XrSessionType::ImmersiveAr => XrSessionMode::ImmersiveAr,
+        }
+    }
+}
+
+/// a single view (usually one per eye) of an xr frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct XrView {
+    /// column-major
+    pub projection_matrix: [f32; 16],
+    /// column-major, the inverse of the view's pose in the reference space
+    pub view_matrix: [f32; 16],
+    /// the region of the xr framebuffer to render this view into as `[x, y, width, height]`
+    /// (with webgl's bottom-left origin)
+    pub viewport: [i32; 4],
+}
+
+/// the xr state of a renderer, `session` is `None` until `with_webxr` succeeds
+pub(crate) struct Xr<S: 'static> {
+    session: Option<XrSessionState>,
+    on_xr_render: OnceCell<fn(XrRenderInfo<S>)>,
+    /// set by the session's `end` event
+    ended: Rc<Cell<bool>>,
+    /// the renderer while it waits for the session's next frame, so that the `end` listener can exit
+    /// the game loop as an ended session won't call it
+    waiting: Rc<RefCell<Option<Renderer<S>>>>,
+}
+
+impl<S> Default for Xr<S> {
+    fn default() -> Self {
+        Xr {
+            session: None,
+            on_xr_render: OnceCell::new(),
+            ended: Rc::new(Cell::new(false)),
+            waiting: Rc::new(RefCell::new(None)),
+        }
+    }
+}
+
+struct XrSessionState {
+    session: XrSession,
+    reference_space: XrReferenceSpace,
+    layer: XrWebGlLayer,
+    on_end: Closure<dyn FnMut(JsValue)>,
+}
+
+impl Drop for XrSessionState {
+    fn drop(&mut self) {
+        let _ = self.session.remove_event_listener_with_callback("end", self.on_end.as_ref().unchecked_ref());
+    }
+}
+
+/// a `RenderInfo` for an xr frame, which also has the views that need to be rendered
+///
+/// the xr framebuffer is already bound, each view should be drawn with its own viewport:
+/// ```ignore
+/// fn on_xr_render(mut xr_render_info: XrRenderInfo<State>) {
+///     for view in xr_render_info.views().to_vec() {
+///         let [x, y, width, height] = view.viewport;
+///         xr_render_info.context().viewport(x, y, width, height);
+///         draw_scene(&mut xr_render_info, &view.projection_matrix, &view.view_matrix);
+///     }
+/// }
+/// ```
+pub struct XrRenderInfo<'a, S: 'static> {
+    render_info: RenderInfo<'a, S>,
+    views: Vec<XrView>,
+}
+
+impl<'a, S> XrRenderInfo<'a, S> {
+    pub fn views(&self) -> &[XrView] {
+        &self.views
+    }
+}
+
+impl<'a, S> Deref for XrRenderInfo<'a, S> {
+    type Target = RenderInfo<'a, S>;
+    fn deref(&self) -> &Self::Target {
+        &self.render_info
+    }
+}
+impl<S> DerefMut for XrRenderInfo<'_, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.render_info
+    }
+}
+
+impl<S> Renderer<S> {
+    /// requests a webxr session and, once the game loop is started, drives it with the session's
+    /// `requestAnimationFrame` instead of the window's, calling `on_xr_render` (see `with_on_xr_render`)
+    /// instead of `on_render`
+    ///
+    /// browsers only grant immersive sessions in response to a user gesture, so this should be
+    /// awaited from eg. a click handler. The game loop stops when the session ends
+    ///
+    /// returns self for chaining
+    ///
+    /// errors if webxr is unsupported, there is no `window` or the session could not be created
+    pub async fn with_webxr(mut self, session_type: XrSessionType) -> Result<Self, RendererError> {
+        let navigator = window().ok_or(RendererError::Unsupported("window"))?.navigator();
+        if !js_sys::Reflect::has(&navigator, &JsValue::from_str("xr"))? {
+            return Err(RendererError::Unsupported("webxr"));
+        }
+
+        let session: XrSession = JsFuture::from(navigator.xr().request_session(session_type.mode())).await?.unchecked_into();
+        JsFuture::from(self.context.make_xr_compatible()).await?;
+        let layer = XrWebGlLayer::new_with_web_gl2_rendering_context(&session, &self.context)?;
+        let render_state = XrRenderStateInit::new();
+        render_state.set_base_layer(Some(&layer));
+        session.update_render_state_with_state(&render_state);
+        let reference_space: XrReferenceSpace = JsFuture::from(session.request_reference_space(XrReferenceSpaceType::Local)).await?.unchecked_into();
+
+        let ended = self.xr.ended.clone();
+        let waiting = self.xr.waiting.clone();
+        let on_end = Closure::<dyn FnMut(JsValue)>::new(move |_| {
+            ended.set(true);
+            let renderer = waiting.borrow_mut().take();
+            if let Some(mut renderer) = renderer {
+                renderer.exit = true;
+                renderer.next_frame();
+            }
+        });
+        session.add_event_listener_with_callback("end", on_end.as_ref().unchecked_ref())?;
+
+        self.xr.session = Some(XrSessionState { session, reference_space, layer, on_end });
+        Ok(self)
+    }
+
+    /// adds an `on_xr_render` function that is called for each frame of the webxr session
+    ///
+    /// returns self for chaining
+    ///
+    /// errors if `on_xr_render` has already been set
+    pub fn with_on_xr_render(self, on_xr_render: fn(XrRenderInfo<S>)) -> Result<Self, RendererError> {
+        self.xr.on_xr_render.set(on_xr_render).map_err(|_| RendererError::AlreadySet("on_xr_render"))?;
+        Ok(self)
+    }
+
+    /// whether `with_webxr` has created a session
+    pub fn is_xr(&self) -> bool {
+        self.xr.session.is_some()
+    }
+
+    pub(crate) fn request_xr_frame(mut self) {
+        // the session ended before the game loop started
+        if self.xr.ended.get() {
+            self.exit = true;
+            return self.exit_loop();
+        }
+        let session = self.xr.session.as_ref().unwrap().session.clone();
+        let waiting = self.xr.waiting.clone();
+        *waiting.borrow_mut() = Some(self);
+        let closure = Closure::once_into_js(move |_time: f64, frame: XrFrame| {
+            let renderer = waiting.borrow_mut().take();
+            if let Some(renderer) = renderer {
+                renderer.next_xr_frame(frame);
+            }
+        });
+        session.request_animation_frame(closure.unchecked_ref());
+    }
+
+    fn next_xr_frame(mut self, frame: XrFrame) {
+        if self.is_exiting() {
+            let _ = self.xr.session.as_ref().unwrap().session.end();
+            return self.exit_loop();
+        }
+
+        let current_instant = current_instant();
+
+        self.accumulate(current_instant);
+        self.run_updates();
+        self.render_xr(&frame);
+        self.number_of_renders += 1;
+
+        self.previous_instant = current_instant;
+
+        self.request_xr_frame();
+    }
+
+    fn render_xr(&mut self, frame: &XrFrame) {
+        let Some(on_xr_render) = self.xr.on_xr_render.get().copied() else { return };
+        let XrSessionState { reference_space, layer, .. } = self.xr.session.as_ref().unwrap();
+        // there is no pose while tracking is lost
+        let Some(pose) = frame.get_viewer_pose(reference_space) else { return };
+
+        let views = pose.views().iter().filter_map(|view| {
+            let viewport = layer.get_viewport(&view)?;
+            Some(XrView {
+                projection_matrix: to_matrix(view.projection_matrix()),
+                view_matrix: to_matrix(view.transform().inverse().matrix()),
+                viewport: [viewport.x(), viewport.y(), viewport.width(), viewport.height()],
+            })
+        }).collect();
+        self.context.bind_framebuffer(Gl::FRAMEBUFFER, layer.framebuffer().as_ref());
+
+        on_xr_render(XrRenderInfo {
+            render_info: RenderInfo {
+                state: self.state.clone().get().unwrap().borrow_mut().deref_mut(),
+                renderer: self,
+            },
+            views,
+        });
+    }
+}
+
+fn to_matrix(values: Vec<f32>) -> [f32; 16] {
+    let mut matrix = [0.0; 16];
+    matrix.copy_from_slice(&values[..16]);
+    matrix
+}
@@ -0,0 +1,21 @@
+//! only needs to compile: the deprecated `from_canvas` chain must keep working with `?` in functions
+//! returning `Result<_, JsValue>`, as it did before `RendererError`
+#![allow(deprecated, dead_code)]
+
+use wasm_bindgen::JsValue;
+use web_sys::{Event, HtmlCanvasElement};
+use web_render_rs::{RenderInfo, Renderer, UpdateInfo};
+
+fn on_update(_update_info: UpdateInfo<()>) {}
+fn on_render(_render_info: RenderInfo<()>) {}
+fn on_keydown(_state: &mut (), _event: Event) {}
+
+fn start(canvas: HtmlCanvasElement) -> Result<(), JsValue> {
+    Renderer::from_canvas(canvas)?
+        .with_on_update(on_update)?
+        .with_on_render(on_render)?
+        .with_shaders("", "")?
+        .with_on_event("keydown", on_keydown)?
+        .start((), 60, 0.1);
+    Ok(())
+}
@@ -0,0 +1,50 @@
+//! run with `wasm-pack test --headless --chrome --features test-utils`
+#![cfg(all(target_arch = "wasm32", feature = "test-utils"))]
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+use web_sys::{WebGl2RenderingContext, WebGlFramebuffer};
+use web_render_rs::{ColorSpace, LoopMode, MagFilter, Rect, RenderInfo, RenderTarget, Renderer};
+
+type Gl = WebGl2RenderingContext;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+fn read_pixel(context: &WebGl2RenderingContext) -> [u8; 4] {
+    let mut pixel = [0; 4];
+    context.read_pixels_with_opt_u8_array(0, 0, 1, 1, Gl::RGBA, Gl::UNSIGNED_BYTE, Some(&mut pixel)).unwrap();
+    pixel
+}
+
+fn render(mut render_info: RenderInfo<()>) {
+    let context = render_info.context().clone();
+    let srgb = RenderTarget::new(&context, 1, 1, ColorSpace::Srgb).unwrap();
+    let linear = RenderTarget::new(&context, 1, 1, ColorSpace::Linear).unwrap();
+
+    // a linear 0.5 is encoded to 188 when written into the srgb target
+    render_info.bind_framebuffer(&srgb);
+    context.clear_color(0.5, 0.5, 0.5, 1.0);
+    context.clear(Gl::COLOR_BUFFER_BIT);
+    let [encoded, ..] = read_pixel(&context);
+    assert!(encoded.abs_diff(188) <= 1, "stored {} in the srgb target", encoded);
+
+    // building a render target keeps whatever was bound
+    let _other = RenderTarget::new(&context, 1, 1, ColorSpace::Linear).unwrap();
+    let bound = context.get_parameter(Gl::FRAMEBUFFER_BINDING).unwrap().dyn_into::<WebGlFramebuffer>().ok();
+    assert_eq!(bound.as_ref(), Some(srgb.framebuffer()));
+
+    // and decoded back to 0.5 when sampled
+    render_info.bind_framebuffer(&linear);
+    render_info.blit(srgb.color_texture(), None, Rect::new(0, 0, 1, 1), MagFilter::Nearest).unwrap();
+    let [decoded, ..] = read_pixel(&context);
+    assert!(decoded.abs_diff(128) <= 1, "sampled {} from the srgb target", decoded);
+}
+
+#[wasm_bindgen_test]
+fn srgb_render_target_decodes_when_sampled() {
+    let mut renderer = Renderer::builder()
+        .with_on_render(render)
+        .build_with_optional_canvas(None, (), LoopMode { updates_per_second: 60, max_frame_time: 0.1 })
+        .unwrap();
+    renderer.tick_once(1.0 / 60.0);
+}